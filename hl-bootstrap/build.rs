@@ -0,0 +1,36 @@
+use std::{path::Path, process::Command};
+
+/// Embeds the source commit as HL_BOOTSTRAP_COMMIT. Docker builds don't see .git, so the commit can be passed in
+/// through the environment instead. Also compiles the gRPC control API with $PROTOC, falling back to a vendored protoc
+fn main() {
+    println!("cargo:rerun-if-env-changed=PROTOC");
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc =
+            protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this platform");
+        // SAFETY: build scripts are single-threaded
+        unsafe { std::env::set_var("PROTOC", protoc) };
+    }
+    tonic_build::compile_protos("proto/hl_bootstrap/v1/control.proto")
+        .expect("failed to compile protos");
+
+    println!("cargo:rerun-if-env-changed=HL_BOOTSTRAP_COMMIT");
+    for path in ["../.git/HEAD", "../.git/refs/heads"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={path}");
+        }
+    }
+
+    let commit = std::env::var("HL_BOOTSTRAP_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short=12", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=HL_BOOTSTRAP_COMMIT={commit}");
+}
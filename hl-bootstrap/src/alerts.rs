@@ -0,0 +1,340 @@
+use std::{
+    sync::LazyLock,
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use clap::{Args, ValueEnum};
+use eyre::ContextCompat;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, header::ContentType},
+    transport::smtp::authentication::Credentials,
+};
+use reqwest::{Client, ClientBuilder, Url};
+use serde::Serialize;
+use serde_json::json;
+use tracing::{debug, warn};
+
+use crate::{hl_gossip_config::HyperliquidChain, procfs, status::unix_ms_now};
+
+#[derive(Clone, Debug, Args)]
+pub struct AlertArgs {
+    /// Generic webhook URLs receiving alerts as JSON (`event`, `message`, `host`, `network`, `timestamp_ms`)
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_WEBHOOK_URL", value_delimiter = ',')]
+    pub alert_webhook_url: Vec<Url>,
+
+    /// Slack incoming webhook URL
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_SLACK_WEBHOOK_URL")]
+    pub alert_slack_webhook_url: Option<Url>,
+
+    /// Discord webhook URL
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_DISCORD_WEBHOOK_URL")]
+    pub alert_discord_webhook_url: Option<Url>,
+
+    /// Telegram bot token, requires --alert-telegram-chat-id
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_ALERT_TELEGRAM_BOT_TOKEN",
+        requires = "alert_telegram_chat_id"
+    )]
+    pub alert_telegram_bot_token: Option<String>,
+
+    /// Telegram chat id to send alerts to
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_TELEGRAM_CHAT_ID")]
+    pub alert_telegram_chat_id: Option<String>,
+
+    /// SMTP server to send alerts as email through, requires --alert-email-from and --alert-email-to
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_SMTP_HOST")]
+    pub alert_smtp_host: Option<String>,
+
+    /// SMTP port, defaults to 465 for tls, 587 for starttls and 25 for none
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_SMTP_PORT")]
+    pub alert_smtp_port: Option<u16>,
+
+    /// How to secure the SMTP connection
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_SMTP_TLS", default_value = "starttls")]
+    pub alert_smtp_tls: SmtpTls,
+
+    /// SMTP username, requires --alert-smtp-password
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_ALERT_SMTP_USERNAME",
+        requires = "alert_smtp_password"
+    )]
+    pub alert_smtp_username: Option<String>,
+
+    /// SMTP password
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_SMTP_PASSWORD")]
+    pub alert_smtp_password: Option<String>,
+
+    /// Sender of alert emails, e.g. `hl-bootstrap <alerts@example.com>`
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_EMAIL_FROM")]
+    pub alert_email_from: Option<Mailbox>,
+
+    /// Recipients of alert emails
+    #[arg(long, env = "HL_BOOTSTRAP_ALERT_EMAIL_TO", value_delimiter = ',')]
+    pub alert_email_to: Vec<Mailbox>,
+
+    /// Subject of alert emails. `{event}`, `{message}`, `{host}`, `{network}` and `{time}` are replaced
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_ALERT_EMAIL_SUBJECT",
+        default_value = "[{host} {network}] {event}"
+    )]
+    pub alert_email_subject: String,
+
+    /// Plain text body of alert emails, with the same placeholders as the subject
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_ALERT_EMAIL_BODY",
+        default_value = "{message}\\n\\nEvent: {event}\\nHost: {host}\\nNetwork: {network}\\nTime: {time}"
+    )]
+    pub alert_email_body: String,
+
+    /// Events to send alerts for
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_ALERT_EVENTS",
+        value_delimiter = ',',
+        default_value = "bootstrap-failed,child-crashed,child-restarted,watchdog-triggered,prune-failed,binary-updated,gossip-config-stale,preflight-failed,backup-failed,validator-jailed,validator-uptime-low"
+    )]
+    pub alert_events: Vec<AlertEvent>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEvent {
+    BootstrapFailed,
+    ChildCrashed,
+    ChildRestarted,
+    WatchdogTriggered,
+    PruneFailed,
+    BinaryUpdated,
+    GossipConfigStale,
+    PreflightFailed,
+    BackupFailed,
+    ValidatorJailed,
+    ValidatorUptimeLow,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum SmtpTls {
+    /// TLS from the start of the connection (SMTPS)
+    Tls,
+    /// Upgrade a plain connection with STARTTLS, failing when the server doesn't offer it
+    Starttls,
+    /// Unencrypted, e.g. for a relay on localhost
+    None,
+}
+
+struct Alerts {
+    args: AlertArgs,
+    host: String,
+    network: HyperliquidChain,
+    smtp: Option<AsyncSmtpTransport<Tokio1Executor>>,
+}
+
+static ALERTS: OnceLock<Alerts> = OnceLock::new();
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    ClientBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap()
+});
+
+pub fn init(args: &AlertArgs, network: HyperliquidChain) {
+    let host = procfs::hostname().unwrap_or_else(|_| "unknown".to_string());
+    let smtp = args.alert_smtp_host.as_deref().and_then(|smtp_host| {
+        match smtp_transport(smtp_host, args) {
+            Ok(transport) => Some(transport),
+            Err(err) => {
+                warn!(
+                    ?err,
+                    smtp_host, "failed to set up SMTP, not sending alert emails"
+                );
+                None
+            }
+        }
+    });
+    let _ = ALERTS.set(Alerts {
+        args: args.clone(),
+        host,
+        network,
+        smtp,
+    });
+}
+
+fn smtp_transport(
+    host: &str,
+    args: &AlertArgs,
+) -> eyre::Result<AsyncSmtpTransport<Tokio1Executor>> {
+    let mut builder = match args.alert_smtp_tls {
+        SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+        SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+        SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+    }
+    .timeout(Some(Duration::from_secs(10)));
+    if let Some(port) = args.alert_smtp_port {
+        builder = builder.port(port);
+    }
+    if let (Some(username), Some(password)) = (&args.alert_smtp_username, &args.alert_smtp_password)
+    {
+        builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    Ok(builder.build())
+}
+
+/// Sends the alert in the background. Must be called from within the tokio runtime
+pub fn alert(event: AlertEvent, message: impl Into<String>) {
+    let message = message.into();
+    tokio::spawn(async move { send_alert(event, &message).await });
+}
+
+/// Sends the alert to all configured destinations, failures are only logged
+pub async fn send_alert(event: AlertEvent, message: &str) {
+    let Some(alerts) = ALERTS.get() else {
+        return;
+    };
+    if !alerts.args.alert_events.contains(&event) {
+        return;
+    }
+
+    let text = format!("[{} {}] {message}", alerts.host, alerts.network.to_string());
+    debug!(?event, text, "sending alert");
+
+    let mut requests = Vec::new();
+    for url in &alerts.args.alert_webhook_url {
+        requests.push(CLIENT.post(url.clone()).json(&json!({
+            "event": event,
+            "message": message,
+            "host": alerts.host,
+            "network": alerts.network,
+            "timestamp_ms": unix_ms_now(),
+        })));
+    }
+    if let Some(url) = &alerts.args.alert_slack_webhook_url {
+        requests.push(CLIENT.post(url.clone()).json(&json!({ "text": text })));
+    }
+    if let Some(url) = &alerts.args.alert_discord_webhook_url {
+        requests.push(CLIENT.post(url.clone()).json(&json!({ "content": text })));
+    }
+    if let (Some(token), Some(chat_id)) = (
+        &alerts.args.alert_telegram_bot_token,
+        &alerts.args.alert_telegram_chat_id,
+    ) {
+        requests.push(
+            CLIENT
+                .post(format!("https://api.telegram.org/bot{token}/sendMessage"))
+                .json(&json!({ "chat_id": chat_id, "text": text })),
+        );
+    }
+
+    for request in requests {
+        if let Err(err) = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            // Telegram token is part of the URL
+            warn!(?event, err = %err.without_url(), "failed to send alert");
+        }
+    }
+
+    if let Some(smtp) = &alerts.smtp
+        && let Err(err) = send_email(smtp, alerts, event, message).await
+    {
+        warn!(?event, ?err, "failed to send alert email");
+    }
+}
+
+async fn send_email(
+    smtp: &AsyncSmtpTransport<Tokio1Executor>,
+    alerts: &Alerts,
+    event: AlertEvent,
+    message: &str,
+) -> eyre::Result<()> {
+    let placeholders = Placeholders {
+        event,
+        message,
+        host: &alerts.host,
+        network: alerts.network,
+        time: DateTime::<Utc>::from(SystemTime::now()),
+    };
+
+    let mut email = Message::builder()
+        .from(
+            alerts
+                .args
+                .alert_email_from
+                .clone()
+                .wrap_err("--alert-email-from is not set")?,
+        )
+        .subject(placeholders.render(&alerts.args.alert_email_subject))
+        .header(ContentType::TEXT_PLAIN);
+    for to in &alerts.args.alert_email_to {
+        email = email.to(to.clone());
+    }
+    let email = email.body(placeholders.render(&alerts.args.alert_email_body))?;
+
+    smtp.send(email).await?;
+    Ok(())
+}
+
+struct Placeholders<'a> {
+    event: AlertEvent,
+    message: &'a str,
+    host: &'a str,
+    network: HyperliquidChain,
+    time: DateTime<Utc>,
+}
+
+impl Placeholders<'_> {
+    /// Replaces `{event}`, `{message}`, `{host}`, `{network}` and `{time}`, and turns `\n` into newlines so templates
+    /// fit on one line in the environment
+    fn render(&self, template: &str) -> String {
+        let event = self
+            .event
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default();
+        // {message} goes last, so placeholders in it are kept as they are
+        template
+            .replace("\\n", "\n")
+            .replace("{event}", &event)
+            .replace("{host}", self.host)
+            .replace("{network}", &self.network.to_string())
+            .replace(
+                "{time}",
+                &self.time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            )
+            .replace("{message}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let placeholders = Placeholders {
+            event: AlertEvent::ChildCrashed,
+            message: "hl-visor exited with {code} 1",
+            host: "node-1",
+            network: HyperliquidChain::Mainnet,
+            time: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        };
+
+        assert_eq!(
+            placeholders.render("[{host} {network}] {event}"),
+            "[node-1 Mainnet] child-crashed"
+        );
+        assert_eq!(
+            placeholders.render("{message}\\nTime: {time}"),
+            "hl-visor exited with {code} 1\nTime: 2023-11-14 22:13:20 UTC"
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::{net::SocketAddr, sync::OnceLock, time::Duration};
+
+use clap::{Args, ValueEnum};
+use duration_string::DurationString;
+use tracing::{info, warn};
+
+use crate::speedtest::measure_latency;
+
+/// Connections measured to the anchor, the fastest one counts
+const SAMPLES: usize = 3;
+
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Args)]
+pub struct AnchorArgs {
+    /// Host of known location accepting TCP connections, e.g. `<Tokyo server ip>:4001`, measured before the seed
+    /// peers. Most peers are near Tokyo, so its latency tells how far away this node is & whether
+    /// --seed-peers-max-latency fits
+    #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_ANCHOR")]
+    pub seed_peers_anchor: Option<SocketAddr>,
+
+    /// What to do with the anchor latency
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_SEED_PEERS_ANCHOR_MODE",
+        default_value = "check"
+    )]
+    pub seed_peers_anchor_mode: AnchorMode,
+
+    /// Latency seed peers may have on top of the anchor's
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_SEED_PEERS_ANCHOR_MARGIN",
+        default_value = "20ms"
+    )]
+    pub seed_peers_anchor_margin: DurationString,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum AnchorMode {
+    /// Warn when --seed-peers-max-latency is below the anchor latency plus the margin, as the peers near the anchor
+    /// would be rejected
+    Check,
+    /// Raise --seed-peers-max-latency to the anchor latency plus the margin when it's lower
+    Adjust,
+}
+
+/// Lowest max latency found by adjusting, unset without an anchor, in check mode or when it was unreachable
+static FLOOR: OnceLock<Option<Duration>> = OnceLock::new();
+
+/// Max seed peer latency to use, the configured one raised by the calibration
+pub fn max_latency(configured: Duration) -> Duration {
+    match FLOOR.get() {
+        Some(Some(floor)) => configured.max(*floor),
+        _ => configured,
+    }
+}
+
+/// Measures the anchor once per process, then returns the max seed peer latency to use
+pub async fn calibrate(args: &AnchorArgs, configured: Duration) -> Duration {
+    if FLOOR.get().is_none() {
+        let floor = match args.seed_peers_anchor {
+            Some(anchor) => measure_floor(args, anchor, configured).await,
+            None => None,
+        };
+        let _ = FLOOR.set(floor);
+    }
+    max_latency(configured)
+}
+
+async fn measure_floor(
+    args: &AnchorArgs,
+    anchor: SocketAddr,
+    configured: Duration,
+) -> Option<Duration> {
+    let mut latency: Option<Duration> = None;
+    for _ in 0..SAMPLES {
+        match measure_latency(anchor, TIMEOUT).await {
+            Ok(sample) => latency = Some(latency.map_or(sample, |latency| latency.min(sample))),
+            Err(err) => warn!(%anchor, %err, "failed to measure latency to anchor"),
+        }
+    }
+    let Some(latency) = latency else {
+        warn!(
+            %anchor,
+            max_latency = ?configured,
+            "anchor is unreachable, keeping --seed-peers-max-latency"
+        );
+        return None;
+    };
+
+    let required = required_max_latency(latency, args.seed_peers_anchor_margin.into());
+    info!(%anchor, ?latency, ?required, "measured latency to anchor");
+    if required <= configured {
+        return None;
+    }
+    match args.seed_peers_anchor_mode {
+        AnchorMode::Check => {
+            warn!(
+                %anchor,
+                ?latency,
+                max_latency = ?configured,
+                "--seed-peers-max-latency rejects the seed peers near the anchor, raise it to at least {required:?} or \
+                 use --seed-peers-anchor-mode adjust"
+            );
+            None
+        }
+        AnchorMode::Adjust => {
+            info!(
+                before = ?configured,
+                after = ?required,
+                "raised --seed-peers-max-latency to the anchor latency"
+            );
+            Some(required)
+        }
+    }
+}
+
+/// Rounded up to whole milliseconds, like the configured latencies
+fn required_max_latency(anchor_latency: Duration, margin: Duration) -> Duration {
+    let required = anchor_latency + margin;
+    Duration::from_millis(required.as_micros().div_ceil(1000) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_max_latency() {
+        assert_eq!(
+            required_max_latency(Duration::from_micros(142_300), Duration::from_millis(20)),
+            Duration::from_millis(163)
+        );
+        assert_eq!(
+            required_max_latency(Duration::from_millis(2), Duration::from_millis(20)),
+            Duration::from_millis(22)
+        );
+    }
+}
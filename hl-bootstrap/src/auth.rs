@@ -0,0 +1,604 @@
+use std::{
+    collections::HashMap,
+    fs,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use axum::{
+    Router,
+    extract::{ConnectInfo, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::{Next, from_fn_with_state},
+    response::{IntoResponse, Response},
+};
+use clap::{Args, ValueEnum};
+use eyre::{Context, ContextCompat, bail, eyre};
+use hmac_sha256::Hash;
+use tracing::{debug, info};
+
+#[derive(Clone, Debug, Args)]
+pub struct AuthArgs {
+    /// API keys the HTTP & gRPC servers require as `Authorization: Bearer <key>` or `X-API-Key: <key>`, granting
+    /// every scope. Servers are open when neither these nor --api-keys-file are set
+    #[arg(long, env = "HL_BOOTSTRAP_API_KEYS", value_delimiter = ',')]
+    pub api_keys: Vec<String>,
+
+    /// File with one API key per line, followed by the scopes it grants (`data`, `stream`, `info`, `control`,
+    /// `metrics`, `health`), e.g. `0f3a... data,stream`. Keys without scopes grant every scope, `#` starts a comment
+    #[arg(long, env = "HL_BOOTSTRAP_API_KEYS_FILE")]
+    pub api_keys_file: Option<PathBuf>,
+
+    /// Require API keys on the metrics & health servers as well. Off by default, as Kubernetes probes and most
+    /// scrapers can't send them
+    #[arg(long, env = "HL_BOOTSTRAP_API_KEYS_HEALTH")]
+    pub api_keys_health: bool,
+
+    /// Networks allowed to reach the HTTP & gRPC servers, e.g. `10.0.0.0/8,203.0.113.7`. Other clients are refused
+    /// before any handler runs, everyone is allowed when unset
+    #[arg(long, env = "HL_BOOTSTRAP_ALLOW_IPS", value_delimiter = ',', value_parser = parse_cidr)]
+    pub allow_ips: Vec<Cidr>,
+
+    /// Reverse proxies trusted to name the client in X-Forwarded-For, which is then checked against --allow-ips
+    /// instead of the proxy itself
+    #[arg(long, env = "HL_BOOTSTRAP_TRUSTED_PROXIES", value_delimiter = ',', value_parser = parse_cidr)]
+    pub trusted_proxies: Vec<Cidr>,
+}
+
+/// Network in CIDR notation, single addresses are taken as /32 or /128
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // Dual-stack listeners see IPv4 clients as IPv4-mapped IPv6 addresses
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+fn parse_cidr(value: &str) -> eyre::Result<Cidr> {
+    let (network, prefix) = match value.split_once('/') {
+        Some((network, prefix)) => (network, Some(prefix)),
+        None => (value, None),
+    };
+    let network: IpAddr = network
+        .parse()
+        .wrap_err_with(|| format!("invalid address {network:?}"))?;
+    let max_prefix = if network.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(prefix) => prefix
+            .parse()
+            .ok()
+            .filter(|prefix| *prefix <= max_prefix)
+            .wrap_err_with(|| format!("invalid prefix length {prefix:?}"))?,
+        None => max_prefix,
+    };
+
+    Ok(Cidr {
+        network: network.to_canonical(),
+        prefix,
+    })
+}
+
+/// API keys & IP allowlist, shared by all servers
+#[derive(Clone, Debug, Default)]
+pub struct Access {
+    pub api_keys: Option<ApiKeys>,
+    pub allowlist: Option<Allowlist>,
+    /// Whether --trusted-proxies are set, clients of a reverse proxy on this host then look local
+    pub behind_proxy: bool,
+}
+
+impl Access {
+    pub fn from_args(args: &AuthArgs) -> eyre::Result<Self> {
+        let allowlist = (!args.allow_ips.is_empty()).then(|| Allowlist {
+            allowed: args.allow_ips.clone().into(),
+            trusted_proxies: args.trusted_proxies.clone().into(),
+        });
+        if let Some(allowlist) = &allowlist {
+            info!(allowed = ?allowlist.allowed, "restricting clients to allowlist");
+        }
+
+        Ok(Self {
+            api_keys: ApiKeys::from_args(args)?,
+            allowlist,
+            behind_proxy: !args.trusted_proxies.is_empty(),
+        })
+    }
+
+    /// Access to the metrics & health servers, which only require API keys with --api-keys-health
+    pub fn health(&self) -> Self {
+        Self {
+            api_keys: self.api_keys.clone().filter(|api_keys| api_keys.health),
+            allowlist: self.allowlist.clone(),
+            behind_proxy: self.behind_proxy,
+        }
+    }
+}
+
+/// What an API key grants access to, one per server
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Scope {
+    /// Fills, trades & blocks of the data API
+    Data,
+    /// replica_cmds WebSocket stream
+    Stream,
+    /// Info endpoint proxy
+    Info,
+    /// gRPC control API
+    Control,
+    /// /metrics
+    Metrics,
+    /// Health, probe & maintenance endpoints
+    Health,
+}
+
+#[derive(Debug)]
+struct ApiKey {
+    key: String,
+    /// Unset for keys granting every scope
+    scopes: Option<Vec<Scope>>,
+}
+
+/// Accepted API keys, looked up by their sha256 so lookups don't leak how much of a key matched through timing
+#[derive(Clone, Debug)]
+pub struct ApiKeys {
+    keys: Arc<HashMap<[u8; 32], ApiKey>>,
+    pub health: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Denied {
+    Missing,
+    Invalid,
+    OutOfScope,
+}
+
+impl ApiKeys {
+    /// Keys from the flags & key file, unset when authentication isn't enabled
+    pub fn from_args(args: &AuthArgs) -> eyre::Result<Option<Self>> {
+        let mut keys: Vec<_> = args
+            .api_keys
+            .iter()
+            .map(|key| ApiKey {
+                key: key.clone(),
+                scopes: None,
+            })
+            .collect();
+        if let Some(path) = &args.api_keys_file {
+            let contents = fs::read_to_string(path)
+                .wrap_err_with(|| format!("failed to read API keys file {path:?}"))?;
+            keys.extend(
+                parse_keys(&contents)
+                    .wrap_err_with(|| format!("invalid API keys file {path:?}"))?,
+            );
+        }
+        if keys.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            keys: Arc::new(
+                keys.into_iter()
+                    .map(|key| (Hash::hash(key.key.as_bytes()), key))
+                    .collect(),
+            ),
+            health: args.api_keys_health,
+        }))
+    }
+
+    pub fn check(&self, key: Option<&str>, scope: Scope) -> Result<(), Denied> {
+        let key = key.ok_or(Denied::Missing)?;
+        let api_key = self
+            .keys
+            .get(&Hash::hash(key.as_bytes()))
+            .ok_or(Denied::Invalid)?;
+        match &api_key.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(Denied::OutOfScope),
+            _ => Ok(()),
+        }
+    }
+
+    /// A key granting the scope, for querying the own servers
+    pub fn client_key(&self, scope: Scope) -> Option<String> {
+        self.keys
+            .values()
+            .find(|api_key| {
+                api_key
+                    .scopes
+                    .as_ref()
+                    .is_none_or(|scopes| scopes.contains(&scope))
+            })
+            .map(|api_key| api_key.key.clone())
+    }
+}
+
+/// Key for querying the own health server, unset when it doesn't require one
+pub fn health_client_key(args: &AuthArgs) -> eyre::Result<Option<String>> {
+    Ok(ApiKeys::from_args(args)?
+        .filter(|api_keys| api_keys.health)
+        .and_then(|api_keys| api_keys.client_key(Scope::Health)))
+}
+
+/// Requires a key granting the scope for every route of the router, when authentication is enabled
+pub fn require<S: Clone + Send + Sync + 'static>(
+    access: &Access,
+    router: Router<S>,
+    scope: Scope,
+) -> Router<S> {
+    match &access.api_keys {
+        Some(api_keys) => {
+            router.route_layer(from_fn_with_state((api_keys.clone(), scope), authenticate))
+        }
+        None => router,
+    }
+}
+
+/// Requires a key granting the scope even when the server is otherwise open, e.g. for routes changing state. Without
+/// authentication only clients on the same host are let through, and nobody behind --trusted-proxies
+pub fn require_or_local<S: Clone + Send + Sync + 'static>(
+    access: &Access,
+    router: Router<S>,
+    scope: Scope,
+) -> Router<S> {
+    match &access.api_keys {
+        Some(_) => require(access, router, scope),
+        None => router.route_layer(from_fn_with_state(access.behind_proxy, local)),
+    }
+}
+
+/// Refuses clients outside the allowlist, unknown paths included. The router must be served with connect info
+pub fn restrict<S: Clone + Send + Sync + 'static>(access: &Access, router: Router<S>) -> Router<S> {
+    match &access.allowlist {
+        Some(allowlist) => router.layer(from_fn_with_state(allowlist.clone(), allow)),
+        None => router,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Allowlist {
+    allowed: Arc<[Cidr]>,
+    trusted_proxies: Arc<[Cidr]>,
+}
+
+impl Allowlist {
+    /// Client of a connection from the peer. Behind trusted proxies that's the last X-Forwarded-For hop not added by
+    /// one of them, as anything before it may be made up by the client
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let trusted = |ip: IpAddr| self.trusted_proxies.iter().any(|cidr| cidr.contains(ip));
+        if !trusted(peer) {
+            return peer;
+        }
+
+        let hops: Vec<&str> = headers
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .collect();
+        let mut client = peer;
+        for hop in hops.iter().rev() {
+            match hop.trim().parse() {
+                Ok(ip) => client = ip,
+                // Obfuscated hops like `unknown` end the chain at the last proxy
+                Err(_) => break,
+            }
+            if !trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+
+    pub fn allows(&self, client: IpAddr) -> bool {
+        self.allowed.iter().any(|cidr| cidr.contains(client))
+    }
+}
+
+fn parse_keys(contents: &str) -> eyre::Result<Vec<ApiKey>> {
+    let mut keys = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let key = fields.next().unwrap_or_default().to_string();
+        let scopes = fields
+            .next()
+            .map(|scopes| {
+                scopes
+                    .split(',')
+                    .map(|scope| {
+                        Scope::from_str(scope, true).map_err(|err| {
+                            eyre!("line {}: invalid scope {scope:?}: {err}", index + 1)
+                        })
+                    })
+                    .collect::<eyre::Result<Vec<_>>>()
+            })
+            .transpose()?;
+        if fields.next().is_some() {
+            bail!("line {}: expected `<key> [scope,...]`", index + 1);
+        }
+        keys.push(ApiKey { key, scopes });
+    }
+    Ok(keys)
+}
+
+/// Key from `Authorization: Bearer <key>` or `X-API-Key: <key>`
+pub fn request_key(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| {
+            headers
+                .get("x-api-key")
+                .and_then(|value| value.to_str().ok())
+        })
+        .map(str::trim)
+}
+
+async fn allow(
+    State(allowlist): State<Allowlist>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = allowlist.client_ip(peer.ip(), request.headers());
+    if !allowlist.allows(client) {
+        debug!(%client, %peer, path = request.uri().path(), "refused client outside allowlist");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
+
+/// Refuses clients on other hosts, and requests a reverse proxy on this host forwarded for them
+async fn local(
+    State(behind_proxy): State<bool>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let forwarded = ["forwarded", "x-forwarded-for", "x-real-ip"]
+        .iter()
+        .any(|name| request.headers().contains_key(*name));
+    if behind_proxy || forwarded || !peer.ip().to_canonical().is_loopback() {
+        debug!(%peer, path = request.uri().path(), "refused remote client without API keys");
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
+
+async fn authenticate(
+    State((api_keys, scope)): State<(ApiKeys, Scope)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match api_keys.check(request_key(request.headers()), scope) {
+        Ok(()) => next.run(request).await,
+        Err(denied) => {
+            debug!(
+                ?denied,
+                ?scope,
+                path = request.uri().path(),
+                "denied request"
+            );
+            match denied {
+                Denied::Missing | Denied::Invalid => (
+                    StatusCode::UNAUTHORIZED,
+                    [(header::WWW_AUTHENTICATE, "Bearer")],
+                )
+                    .into_response(),
+                Denied::OutOfScope => StatusCode::FORBIDDEN.into_response(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let keys = parse_keys(
+            "# readers\n\
+             reader data,stream\n\
+             admin # every scope\n",
+        )
+        .unwrap();
+        let api_keys = ApiKeys {
+            keys: Arc::new(
+                keys.into_iter()
+                    .map(|key| (Hash::hash(key.key.as_bytes()), key))
+                    .collect(),
+            ),
+            health: false,
+        };
+
+        assert_eq!(api_keys.check(Some("reader"), Scope::Data), Ok(()));
+        assert_eq!(
+            api_keys.check(Some("reader"), Scope::Control),
+            Err(Denied::OutOfScope)
+        );
+        assert_eq!(api_keys.check(Some("admin"), Scope::Control), Ok(()));
+        assert_eq!(
+            api_keys.check(Some("guess"), Scope::Data),
+            Err(Denied::Invalid)
+        );
+        assert_eq!(api_keys.check(None, Scope::Data), Err(Denied::Missing));
+        assert_eq!(api_keys.client_key(Scope::Health).as_deref(), Some("admin"));
+
+        assert!(parse_keys("reader data,everything").is_err());
+        assert!(parse_keys("reader data stream").is_err());
+    }
+
+    #[test]
+    fn test_request_key() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(request_key(&headers), None);
+        headers.insert("x-api-key", "abc".parse().unwrap());
+        assert_eq!(request_key(&headers), Some("abc"));
+        headers.insert(header::AUTHORIZATION, "Bearer def".parse().unwrap());
+        assert_eq!(request_key(&headers), Some("def"));
+    }
+
+    #[test]
+    fn test_cidr() {
+        let cidr = parse_cidr("10.1.0.0/16").unwrap();
+        assert!(cidr.contains("10.1.2.3".parse().unwrap()));
+        assert!(cidr.contains("::ffff:10.1.2.3".parse().unwrap()));
+        assert!(!cidr.contains("10.2.0.1".parse().unwrap()));
+        assert!(
+            parse_cidr("0.0.0.0/0")
+                .unwrap()
+                .contains("203.0.113.7".parse().unwrap())
+        );
+        assert!(
+            parse_cidr("2001:db8::/32")
+                .unwrap()
+                .contains("2001:db8::1".parse().unwrap())
+        );
+        assert!(
+            parse_cidr("203.0.113.7")
+                .unwrap()
+                .contains("203.0.113.7".parse().unwrap())
+        );
+        assert!(parse_cidr("10.0.0.0/33").is_err());
+        assert!(parse_cidr("example.com").is_err());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let allowlist = Allowlist {
+            allowed: [parse_cidr("203.0.113.0/24").unwrap()].into(),
+            trusted_proxies: [parse_cidr("10.0.0.0/8").unwrap()].into(),
+        };
+        let proxy: IpAddr = "10.0.0.5".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "1.2.3.4, 203.0.113.7, 10.0.0.9".parse().unwrap(),
+        );
+
+        // Spoofed first hop is skipped, the hop added by the first trusted proxy counts
+        assert_eq!(
+            allowlist.client_ip(proxy, &headers),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+        // Untrusted peers can't pick their address
+        let peer = "198.51.100.1".parse().unwrap();
+        assert_eq!(allowlist.client_ip(peer, &headers), peer);
+        assert_eq!(allowlist.client_ip(proxy, &HeaderMap::new()), proxy);
+        assert!(!allowlist.allows(proxy));
+    }
+
+    #[tokio::test]
+    async fn test_require_or_local() {
+        use axum::{body::Body, routing::get};
+        use tower::ServiceExt;
+
+        let request = |method: &str, peer: &str, headers: &[(&str, &str)]| {
+            let mut request = Request::builder().method(method).uri("/maintenance");
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            request
+                .extension(ConnectInfo(peer.parse::<SocketAddr>().unwrap()))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let router = |access: &Access| {
+            let toggle = Router::new().route("/maintenance", axum::routing::post(|| async {}));
+            Router::new()
+                .route("/maintenance", get(|| async {}))
+                .merge(require_or_local(access, toggle, Scope::Health))
+        };
+        let status = |router: Router, request: Request| async move {
+            router.oneshot(request).await.unwrap().status()
+        };
+
+        let open = router(&Access::default());
+        assert_eq!(
+            status(open.clone(), request("GET", "203.0.113.7:1000", &[])).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(open.clone(), request("POST", "203.0.113.7:1000", &[])).await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(open.clone(), request("POST", "127.0.0.1:1000", &[])).await,
+            StatusCode::OK
+        );
+        // Forwarded by a reverse proxy on this host
+        assert_eq!(
+            status(
+                open,
+                request(
+                    "POST",
+                    "127.0.0.1:1000",
+                    &[("x-forwarded-for", "203.0.113.7")]
+                )
+            )
+            .await,
+            StatusCode::FORBIDDEN
+        );
+        // Proxies don't always name the client, so nothing is local behind trusted ones
+        let proxied = router(&Access {
+            behind_proxy: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            status(proxied, request("POST", "127.0.0.1:1000", &[])).await,
+            StatusCode::FORBIDDEN
+        );
+
+        // Keys are required without --api-keys-health, from localhost as well
+        let keyed = router(&Access {
+            api_keys: Some(ApiKeys {
+                keys: Arc::new(
+                    parse_keys("admin")
+                        .unwrap()
+                        .into_iter()
+                        .map(|key| (Hash::hash(key.key.as_bytes()), key))
+                        .collect(),
+                ),
+                health: false,
+            }),
+            ..Default::default()
+        });
+        assert_eq!(
+            status(keyed.clone(), request("POST", "127.0.0.1:1000", &[])).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(
+                keyed,
+                request("POST", "203.0.113.7:1000", &[("x-api-key", "admin")])
+            )
+            .await,
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use eyre::{Context, ContextCompat};
+use hmac_sha256::{HMAC, Hash};
+use object_store::aws::{AmazonS3Builder, AwsCredential, AwsCredentialProvider};
+use reqwest::{Client, Response, Url};
+
+use crate::storage::hex;
+
+/// Region from AWS_REGION or AWS_DEFAULT_REGION
+pub fn env_region() -> Option<String> {
+    std::env::var("AWS_REGION")
+        .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+        .ok()
+}
+
+/// Credentials resolved like the AWS SDKs do (environment, web identity, ECS task role, instance metadata), through
+/// object_store. The bucket only satisfies its builder and is never accessed
+pub fn credentials(region: &str) -> eyre::Result<AwsCredentialProvider> {
+    Ok(AmazonS3Builder::from_env()
+        .with_region(region)
+        .with_bucket_name("unused")
+        .build()
+        .wrap_err("failed to set up AWS credentials")?
+        .credentials()
+        .clone())
+}
+
+/// Service of an AWS API reached with POST requests, like the query & JSON protocols
+pub struct Service {
+    pub client: Client,
+    pub credentials: AwsCredentialProvider,
+    /// Signing name, e.g. `monitoring` for CloudWatch
+    pub name: &'static str,
+    pub region: String,
+    pub endpoint: Url,
+}
+
+impl Service {
+    /// Sends a request signed with Signature Version 4, returning failures with the error the API responded with
+    pub async fn post(
+        &self,
+        headers: Vec<(&'static str, String)>,
+        body: String,
+    ) -> eyre::Result<Response> {
+        let credential = self
+            .credentials
+            .get_credential()
+            .await
+            .wrap_err("failed to get AWS credentials")?;
+        let headers = sign(
+            &credential,
+            self.name,
+            &self.region,
+            &self.endpoint,
+            headers,
+            &body,
+            SystemTime::now().into(),
+        )?;
+
+        let mut request = self.client.post(self.endpoint.clone());
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request.body(body).send().await?;
+        if let Err(err) = response.error_for_status_ref() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(err).wrap_err(text);
+        }
+        Ok(response)
+    }
+}
+
+/// Adds the AWS Signature Version 4 headers to the lowercase request headers of a POST to the endpoint
+fn sign(
+    credential: &AwsCredential,
+    service: &str,
+    region: &str,
+    endpoint: &Url,
+    mut headers: Vec<(&'static str, String)>,
+    body: &str,
+    now: DateTime<Utc>,
+) -> eyre::Result<Vec<(&'static str, String)>> {
+    let mut host = endpoint
+        .host_str()
+        .wrap_err("AWS endpoint has no host")?
+        .to_string();
+    if let Some(port) = endpoint.port() {
+        host = format!("{host}:{port}");
+    }
+    let date_time = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let scope = format!("{date}/{region}/{service}/aws4_request");
+
+    headers.push(("host", host));
+    headers.push(("x-amz-date", date_time.clone()));
+    if let Some(token) = &credential.token {
+        headers.push(("x-amz-security-token", token.clone()));
+    }
+    headers.sort_unstable();
+
+    let signed_headers = headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect();
+    let canonical_request = format!(
+        "POST\n{}\n\n{canonical_headers}\n{signed_headers}\n{}",
+        endpoint.path(),
+        hex(&Hash::hash(body.as_bytes())),
+    );
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{date_time}\n{scope}\n{}",
+        hex(&Hash::hash(canonical_request.as_bytes())),
+    );
+
+    let key = HMAC::mac(date, format!("AWS4{}", credential.secret_key));
+    let key = HMAC::mac(region, key);
+    let key = HMAC::mac(service, key);
+    let key = HMAC::mac("aws4_request", key);
+    let signature = hex(&HMAC::mac(string_to_sign, key));
+
+    // host is set by reqwest from the URL
+    headers.retain(|(name, _)| *name != "host");
+    headers.push((
+        "authorization",
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+            credential.key_id
+        ),
+    ));
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign() {
+        let credential = AwsCredential {
+            key_id: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            token: None,
+        };
+        let endpoint = Url::parse("https://monitoring.us-east-1.amazonaws.com/").unwrap();
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        let headers = sign(
+            &credential,
+            "monitoring",
+            "us-east-1",
+            &endpoint,
+            vec![(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+            )],
+            "Action=PutMetricData&Version=2010-08-01&Namespace=Test",
+            now,
+        )
+        .unwrap();
+        assert_eq!(headers[1], ("x-amz-date", "20231114T221320Z".to_string()));
+        assert_eq!(
+            headers[2].1,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20231114/us-east-1/monitoring/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=7880da6a230955eaca8be77c426966ea77e03e9a250c0d277ed5dd57422a7074"
+        );
+    }
+}
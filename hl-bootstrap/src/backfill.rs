@@ -0,0 +1,329 @@
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::{self, BufWriter},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use bytesize::ByteSize;
+use chrono::NaiveDate;
+use clap::Args;
+use eyre::{Context, bail};
+use lz4_flex::frame::FrameDecoder;
+use reqwest::Url;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use crate::{
+    compress::COMPRESSED_SUFFIX,
+    hl_gossip_config::HyperliquidChain,
+    storage::{Bucket, Object, StorageArgs},
+};
+
+/// Suffix of objects Hyperliquid compresses with LZ4 (frame format)
+const LZ4_SUFFIX: &str = ".lz4";
+
+/// Suffix of files being downloaded, renamed into place once complete
+const STAGING_SUFFIX: &str = ".hl-backfill.tmp";
+
+#[derive(Clone, Debug, Args)]
+pub struct BackfillArgs {
+    /// First day to download, e.g. 2025-06-15
+    #[arg(long)]
+    pub from: NaiveDate,
+
+    /// Last day to download, inclusive. Defaults to --from
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+
+    /// hl/data subdirectories to download, as named in the bucket
+    #[arg(long, value_delimiter = ',', default_value = "node_fills_by_block")]
+    pub datasets: Vec<String>,
+
+    /// Bucket to download from. Defaults to hl-mainnet-node-data on mainnet
+    #[arg(long, env = "HL_BOOTSTRAP_BACKFILL_BUCKET")]
+    pub bucket: Option<String>,
+
+    /// Region the bucket is in
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_BACKFILL_REGION",
+        default_value = "ap-northeast-1"
+    )]
+    pub region: String,
+
+    /// Pay for requests & transfer with the --s3-access-key-id (or AWS_ACCESS_KEY_ID) account, which Hyperliquid's
+    /// buckets require
+    #[arg(long, env = "HL_BOOTSTRAP_BACKFILL_REQUESTER_PAYS", default_value_t = true, action = clap::ArgAction::Set)]
+    pub requester_pays: bool,
+
+    /// Files downloaded at once
+    #[arg(long, default_value_t = 4)]
+    pub parallel: usize,
+
+    /// Only list what would be downloaded
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl BackfillArgs {
+    fn bucket(&self, chain: HyperliquidChain, storage: &StorageArgs) -> eyre::Result<Bucket> {
+        let name = match (&self.bucket, chain) {
+            (Some(name), _) => name.clone(),
+            (None, HyperliquidChain::Mainnet) => "hl-mainnet-node-data".to_string(),
+            (None, HyperliquidChain::Testnet) => {
+                bail!("there's no known public node data bucket for testnet, pass --bucket")
+            }
+        };
+        let endpoint: Url = format!("https://s3.{}.amazonaws.com", self.region).parse()?;
+        let args = StorageArgs {
+            storage_url: None,
+            s3_endpoint: endpoint,
+            s3_region: self.region.clone(),
+            s3_bucket: Some(name),
+            s3_prefix: String::new(),
+            ..storage.clone()
+        };
+        if self.requester_pays {
+            Bucket::requester_pays(&args)
+        } else {
+            Bucket::from_args(&args)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BackfillReport {
+    pub files: usize,
+    pub existing: usize,
+    pub downloaded: usize,
+    /// Transferred, before decompression
+    pub bytes: u64,
+    /// Planned downloads, only listed with --dry-run
+    pub actions: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl BackfillReport {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for action in &self.actions {
+            let _ = writeln!(out, "{action}");
+        }
+        let _ = writeln!(
+            out,
+            "{} files: {} already present, {} downloaded ({})",
+            self.files,
+            self.existing,
+            self.downloaded,
+            ByteSize::b(self.bytes)
+        );
+        for (key, err) in &self.failed {
+            let _ = writeln!(out, "[FAIL] {key}: {err}");
+        }
+        out
+    }
+}
+
+/// Downloads the days' files of the datasets into ${base}/hl/data, decompressed like hl-node writes them. Files
+/// already present, also compressed by --compress-data-interval, are skipped, so an interrupted run resumes
+pub async fn backfill(
+    data_directory: &Path,
+    args: &BackfillArgs,
+    chain: HyperliquidChain,
+    storage: &StorageArgs,
+) -> eyre::Result<BackfillReport> {
+    let to = args.to.unwrap_or(args.from);
+    if to < args.from {
+        bail!("--to {to} is before --from {}", args.from);
+    }
+    let bucket = Arc::new(args.bucket(chain, storage)?);
+    let data_path = data_directory.join("hl/data");
+
+    let mut report = BackfillReport::default();
+    let mut jobs = JoinSet::new();
+    for dataset in &args.datasets {
+        let objects = bucket
+            .list(&format!("{}/", dataset.trim_matches('/')))
+            .await
+            .wrap_err_with(|| format!("failed to list {dataset}"))?;
+        let objects: Vec<_> = objects
+            .into_iter()
+            .filter(|object| {
+                !object.key.ends_with('/')
+                    && key_date(&object.key).is_some_and(|date| (args.from..=to).contains(&date))
+            })
+            .collect();
+        info!(dataset, files = objects.len(), from = %args.from, %to, "backfilling data");
+
+        for object in objects {
+            report.files += 1;
+            let Some(destination) = local_path(&data_path, &object.key) else {
+                report
+                    .failed
+                    .push((object.key, "not a file path".to_string()));
+                continue;
+            };
+            if present(&destination) {
+                report.existing += 1;
+                continue;
+            }
+            if args.dry_run {
+                report.actions.push(format!(
+                    "download {} ({})",
+                    object.key,
+                    ByteSize::b(object.size)
+                ));
+                continue;
+            }
+
+            while jobs.len() >= args.parallel.max(1) {
+                collect(jobs.join_next().await, &mut report);
+            }
+            jobs.spawn(download(Arc::clone(&bucket), object, destination));
+        }
+    }
+    while let Some(result) = jobs.join_next().await {
+        collect(Some(result), &mut report);
+    }
+    Ok(report)
+}
+
+fn collect(
+    result: Option<Result<(Object, eyre::Result<()>), tokio::task::JoinError>>,
+    report: &mut BackfillReport,
+) {
+    let Some(result) = result else {
+        return;
+    };
+    match result {
+        Ok((object, Ok(()))) => {
+            debug!(key = object.key, size = object.size, "downloaded");
+            report.downloaded += 1;
+            report.bytes += object.size;
+        }
+        Ok((object, Err(err))) => {
+            warn!(?err, key = object.key, "failed to download file");
+            report.failed.push((object.key, format!("{err:#}")));
+        }
+        Err(err) => warn!(?err, "backfill job failed"),
+    }
+}
+
+/// Downloads next to the destination, so nothing half-written ever appears under the final name
+async fn download(
+    bucket: Arc<Bucket>,
+    object: Object,
+    destination: PathBuf,
+) -> (Object, eyre::Result<()>) {
+    let result = async {
+        let parent = destination.parent().unwrap_or(Path::new("."));
+        tokio::fs::create_dir_all(parent).await?;
+        let compressed = object.key.ends_with(LZ4_SUFFIX);
+        let download_path = staging_path(&destination, if compressed { LZ4_SUFFIX } else { "" });
+        bucket.download_file(&object.key, &download_path).await?;
+
+        if !compressed {
+            return tokio::fs::rename(&download_path, &destination)
+                .await
+                .wrap_err_with(|| format!("failed to rename into {destination:?}"));
+        }
+        let destination = destination.clone();
+        tokio::task::spawn_blocking(move || {
+            let result = decompress(&download_path, &destination);
+            let _ = fs::remove_file(&download_path);
+            result
+        })
+        .await?
+    }
+    .await;
+    (object, result)
+}
+
+fn decompress(source: &Path, destination: &Path) -> eyre::Result<()> {
+    let staging = staging_path(destination, "");
+    let result = (|| {
+        let mut decoder = FrameDecoder::new(File::open(source)?);
+        let mut output = BufWriter::new(File::create(&staging)?);
+        io::copy(&mut decoder, &mut output)?;
+        output
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        fs::rename(&staging, destination)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result.wrap_err_with(|| format!("failed to decompress into {destination:?}"))
+}
+
+fn staging_path(destination: &Path, suffix: &str) -> PathBuf {
+    let name = destination
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    destination.with_file_name(format!(".{name}{suffix}{STAGING_SUFFIX}"))
+}
+
+fn present(destination: &Path) -> bool {
+    let mut compressed = destination.as_os_str().to_owned();
+    compressed.push(COMPRESSED_SUFFIX);
+    destination.exists() || Path::new(&compressed).exists()
+}
+
+/// Where hl-node would have written the object, without the .lz4 suffix
+fn local_path(data_path: &Path, key: &str) -> Option<PathBuf> {
+    if key.ends_with('/') {
+        return None;
+    }
+    let relative = key.strip_suffix(LZ4_SUFFIX).unwrap_or(key);
+    let relative = Path::new(relative);
+    let normal = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    (normal && relative.file_name().is_some()).then(|| data_path.join(relative))
+}
+
+/// Day of the `YYYYMMDD` directory the object is in, e.g. node_fills_by_block/hourly/20250615/15.lz4
+fn key_date(key: &str) -> Option<NaiveDate> {
+    let (directories, _) = key.rsplit_once('/')?;
+    directories.split('/').find_map(|component| {
+        (component.len() == 8 && component.bytes().all(|byte| byte.is_ascii_digit()))
+            .then(|| NaiveDate::parse_from_str(component, "%Y%m%d").ok())
+            .flatten()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_date() {
+        assert_eq!(
+            key_date("node_fills_by_block/hourly/20250615/15.lz4"),
+            NaiveDate::from_ymd_opt(2025, 6, 15)
+        );
+        assert_eq!(
+            key_date("replica_cmds/2025-06-14T08:00:00Z/20250616/628160000.lz4"),
+            NaiveDate::from_ymd_opt(2025, 6, 16)
+        );
+        assert_eq!(key_date("node_fills_by_block/hourly/20251315/1.lz4"), None);
+        assert_eq!(key_date("20250615"), None);
+    }
+
+    #[test]
+    fn test_local_path() {
+        let data_path = Path::new("/data/hl/data");
+        assert_eq!(
+            local_path(data_path, "node_fills_by_block/hourly/20250615/15.lz4"),
+            Some(PathBuf::from(
+                "/data/hl/data/node_fills_by_block/hourly/20250615/15"
+            ))
+        );
+        assert_eq!(local_path(data_path, "node_fills/../../etc/passwd"), None);
+        assert_eq!(local_path(data_path, "node_fills/hourly/"), None);
+    }
+}
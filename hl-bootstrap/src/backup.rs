@@ -0,0 +1,465 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytesize::ByteSize;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, ContextCompat, bail};
+use tempfile::NamedTempFile;
+use tokio::{
+    sync::Mutex,
+    time::{Instant, MissedTickBehavior, interval_at},
+};
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    alerts::{AlertEvent, alert},
+    metrics::{COUNTER_BACKUPS, GAUGE_LAST_BACKUP_TIMESTAMP},
+    procfs,
+    self_update::sha256sum,
+    storage::{Bucket, Object},
+    supervisor::privileges::RunAs,
+};
+
+/// Backups are stored as `<prefix>hl-backup-<timestamp>.tar.zst`, next to a `.sha256` file
+pub const BACKUP_KEY_PREFIX: &str = "hl-backup-";
+pub const BACKUP_KEY_SUFFIX: &str = ".tar.zst";
+
+#[derive(Clone, Debug, Args)]
+pub struct BackupArgs {
+    /// How often to back up the newest periodic ABCI state to object storage (see --s3-bucket or
+    /// --storage-url). Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_BACKUP_INTERVAL")]
+    pub backup_interval: Option<DurationString>,
+
+    /// Other paths within the data directory to include in backups, e.g. `hl/hyperliquid_data/node_config.json`
+    #[arg(long, env = "HL_BOOTSTRAP_BACKUP_EXTRA_PATHS", value_delimiter = ',')]
+    pub backup_extra_paths: Vec<PathBuf>,
+
+    /// How many newest backups to keep, older ones are deleted after each successful backup
+    #[arg(long, env = "HL_BOOTSTRAP_BACKUP_RETENTION", default_value_t = 7)]
+    pub backup_retention: usize,
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct RestoreArgs {
+    /// Only list the available backups, oldest first
+    #[arg(long)]
+    pub list: bool,
+
+    /// Backup to restore, as listed by --list. Defaults to the newest one
+    pub backup: Option<String>,
+}
+
+/// Worker task that periodically uploads backups, starting one interval after startup as the node needs to catch up
+/// before its state is worth keeping
+pub async fn backup_worker_task(
+    data_directory: PathBuf,
+    args: BackupArgs,
+    bucket: Bucket,
+    backup_interval: Duration,
+) {
+    let mut interval = interval_at(Instant::now() + backup_interval, backup_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        ?backup_interval,
+        retention = args.backup_retention,
+        "backing up node state"
+    );
+    loop {
+        interval.tick().await;
+
+        if let Err(err) = run_backup(&data_directory, &args, &bucket).await {
+            warn!(?err, "backup failed");
+            alert(AlertEvent::BackupFailed, format!("backup failed: {err:#}"));
+        }
+    }
+}
+
+/// Uploads a backup & applies retention, returning its key. Also used for backups on request, which wait for one in
+/// progress, so two backups within the same second can't overwrite each other
+pub async fn run_backup(
+    data_directory: &Path,
+    args: &BackupArgs,
+    bucket: &Bucket,
+) -> eyre::Result<String> {
+    static RUNNING: Mutex<()> = Mutex::const_new(());
+    let _running = RUNNING.lock().await;
+
+    match backup(data_directory, &args.backup_extra_paths, bucket).await {
+        Ok(key) => {
+            COUNTER_BACKUPS.with_label_values(&["success"]).inc();
+            GAUGE_LAST_BACKUP_TIMESTAMP.set(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs() as i64,
+            );
+            info!(key, "backup uploaded");
+
+            if let Err(err) = apply_retention(bucket, args.backup_retention).await {
+                warn!(?err, "failed to delete old backups");
+            }
+            Ok(key)
+        }
+        Err(err) => {
+            COUNTER_BACKUPS.with_label_values(&["failed"]).inc();
+            Err(err)
+        }
+    }
+}
+
+/// Archives & uploads the backup, returning its key
+#[instrument(skip_all)]
+async fn backup(
+    data_directory: &Path,
+    extra_paths: &[PathBuf],
+    bucket: &Bucket,
+) -> eyre::Result<String> {
+    let data_directory = data_directory.to_path_buf();
+    let extra_paths = extra_paths.to_vec();
+    let (archive, checksum) = tokio::task::spawn_blocking(move || {
+        let archive = write_archive(&data_directory, &extra_paths)?;
+        let checksum = sha256sum(archive.path())?;
+        eyre::Ok((archive, checksum))
+    })
+    .await??;
+
+    let timestamp = DateTime::<Utc>::from(SystemTime::now()).format("%Y%m%dT%H%M%SZ");
+    let name = format!("{BACKUP_KEY_PREFIX}{timestamp}{BACKUP_KEY_SUFFIX}");
+    let key = format!("{}{name}", bucket.prefix);
+
+    bucket.upload_file(&key, archive.path()).await?;
+    // Same format as sha256sum output, so backups can be used as --snapshot-url as well
+    bucket
+        .put(
+            &format!("{key}.sha256"),
+            format!("{checksum}  {name}\n").into_bytes(),
+        )
+        .await?;
+
+    Ok(key)
+}
+
+/// Writes a zstd compressed tarball of the newest periodic ABCI state & the extra paths, relative to the data
+/// directory so it unpacks into the hl home directly
+fn write_archive(data_directory: &Path, extra_paths: &[PathBuf]) -> eyre::Result<NamedTempFile> {
+    let abci_states = data_directory.join("hl/data/periodic_abci_states");
+    let newest_state = newest_file(&abci_states)
+        .wrap_err_with(|| format!("no periodic ABCI state in {abci_states:?} yet"))?;
+    debug!(?newest_state, "archiving newest periodic ABCI state");
+
+    let archive = NamedTempFile::with_prefix_in(".hl-backup-", data_directory)
+        .wrap_err("failed to create backup archive")?;
+    let encoder = zstd::Encoder::new(archive.reopen()?, 3)?;
+    let mut tar = tar::Builder::new(encoder);
+
+    let mut paths = vec![newest_state];
+    paths.extend(extra_paths.iter().map(|path| data_directory.join(path)));
+    for path in &paths {
+        let name = path
+            .strip_prefix(data_directory)
+            .wrap_err_with(|| format!("{path:?} is outside of the data directory"))?;
+        if path.is_dir() {
+            tar.append_dir_all(name, path)
+        } else {
+            tar.append_path_with_name(path, name)
+        }
+        .wrap_err_with(|| format!("failed to archive {path:?}"))?;
+    }
+    tar.into_inner()?.finish()?;
+
+    Ok(archive)
+}
+
+/// Files modified more recently than this may still be written by hl-node
+const SETTLE_TIME: Duration = Duration::from_secs(10);
+
+/// Most recently modified file anywhere under the directory, skipping files modified within the [SETTLE_TIME]
+fn newest_file(directory: &Path) -> Option<PathBuf> {
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if let Ok(modified) = metadata.modified()
+                && modified
+                    .elapsed()
+                    .is_ok_and(|elapsed| elapsed >= SETTLE_TIME)
+                && newest.as_ref().is_none_or(|(newest, _)| modified > *newest)
+            {
+                newest = Some((modified, entry.path()));
+            }
+        }
+    }
+    newest.map(|(_, path)| path)
+}
+
+/// Backups under the bucket prefix, oldest first. Timestamps in the names sort chronologically
+pub async fn list_backups(bucket: &Bucket) -> eyre::Result<Vec<Object>> {
+    let mut backups: Vec<_> = bucket
+        .list(&format!("{}{BACKUP_KEY_PREFIX}", bucket.prefix))
+        .await?
+        .into_iter()
+        .filter(|object| object.key.ends_with(BACKUP_KEY_SUFFIX))
+        .collect();
+    backups.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+    Ok(backups)
+}
+
+async fn apply_retention(bucket: &Bucket, retention: usize) -> eyre::Result<()> {
+    let backups = list_backups(bucket).await?;
+    let expired = backups.len().saturating_sub(retention);
+    for backup in &backups[..expired] {
+        info!(key = backup.key, "deleting old backup");
+        bucket.delete(&backup.key).await?;
+        bucket.delete(&format!("{}.sha256", backup.key)).await?;
+    }
+    Ok(())
+}
+
+/// Downloads, verifies and unpacks a backup over the data directory, returning its key. Files from the backup
+/// replace existing ones, everything else is left alone. The node must not be running
+#[instrument(skip(bucket, data_directory, owner))]
+pub async fn restore_backup(
+    bucket: &Bucket,
+    name: Option<&str>,
+    data_directory: &Path,
+    owner: Option<&RunAs>,
+) -> eyre::Result<String> {
+    let backups = list_backups(bucket).await?;
+    let backup = match name {
+        Some(name) => backups
+            .iter()
+            .find(|backup| {
+                backup.key == name || backup.key.strip_prefix(&bucket.prefix) == Some(name)
+            })
+            .wrap_err_with(|| format!("backup {name} not found, see `restore --list`"))?,
+        None => backups.last().wrap_err("no backups found")?,
+    };
+    let key = backup.key.clone();
+
+    let checksum = bucket.get_text(&format!("{key}.sha256")).await?;
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .wrap_err("empty backup checksum file")?;
+
+    info!(key, size = %ByteSize::b(backup.size), "downloading backup");
+    let archive = NamedTempFile::with_prefix_in(".hl-restore-", data_directory)
+        .wrap_err("failed to create backup download file")?;
+    bucket.download_file(&key, archive.path()).await?;
+
+    let actual = sha256sum(archive.path())?;
+    debug!(actual, expected, "comparing checksums");
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!("checksum mismatch for backup {key}, expected {expected}, got {actual}");
+    }
+
+    let data_directory = data_directory.to_path_buf();
+    let owner = owner.cloned();
+    let restored = tokio::task::spawn_blocking(move || {
+        unpack(archive.path(), &data_directory, owner.as_ref())
+    })
+    .await??;
+    info!(key, files = restored, "backup restored");
+
+    Ok(key)
+}
+
+/// Returns the amount of restored entries
+fn unpack(archive: &Path, data_directory: &Path, owner: Option<&RunAs>) -> eyre::Result<usize> {
+    let mut tar = tar::Archive::new(zstd::Decoder::new(File::open(archive)?)?);
+    let mut restored = 0;
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = data_directory.join(entry.path()?);
+        if !entry.unpack_in(data_directory)? {
+            warn!(?path, "skipped backup entry outside of the data directory");
+            continue;
+        }
+        restored += 1;
+
+        // Parent directories created along the way are handed over too
+        if let Some(owner) = owner {
+            for ancestor in path.ancestors() {
+                if ancestor == data_directory {
+                    break;
+                }
+                owner.chown(ancestor)?;
+            }
+        }
+    }
+    Ok(restored)
+}
+
+/// hl-node & hl-visor processes, which must not be running while restoring over their data
+pub fn running_node_processes() -> Vec<(u32, String)> {
+    procfs::processes()
+        .into_iter()
+        .filter_map(|process| {
+            let comm = procfs::comm(process.pid)?;
+            (comm == "hl-node" || comm == "hl-visor").then_some((process.pid, comm))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::FileTimes;
+
+    use reqwest::Url;
+
+    use super::*;
+    use crate::storage::StorageArgs;
+
+    fn write_file(path: &Path, contents: &str, age: Duration) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+        let modified = SystemTime::now() - age;
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(modified))
+            .unwrap();
+    }
+
+    fn file_bucket(directory: &Path) -> Bucket {
+        Bucket::from_args(&StorageArgs {
+            storage_url: Some(Url::from_directory_path(directory).unwrap()),
+            s3_endpoint: "https://s3.amazonaws.com".parse().unwrap(),
+            s3_region: "us-east-1".to_string(),
+            s3_bucket: None,
+            s3_prefix: String::new(),
+            s3_access_key_id: None,
+            s3_secret_access_key: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_newest_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let hour = Duration::from_secs(3600);
+        write_file(&directory.path().join("20260101/100"), "older", 2 * hour);
+        write_file(&directory.path().join("20260101/200"), "newest", hour);
+        // Still being written
+        write_file(
+            &directory.path().join("20260101/300"),
+            "partial",
+            Duration::ZERO,
+        );
+
+        assert_eq!(
+            newest_file(directory.path()),
+            Some(directory.path().join("20260101/200"))
+        );
+        assert_eq!(newest_file(&directory.path().join("missing")), None);
+    }
+
+    #[tokio::test]
+    async fn test_backup_round_trip() {
+        let data_directory = tempfile::tempdir().unwrap();
+        let storage = tempfile::tempdir().unwrap();
+        let bucket = file_bucket(storage.path());
+        let hour = Duration::from_secs(3600);
+        write_file(
+            &data_directory
+                .path()
+                .join("hl/data/periodic_abci_states/20260101/100"),
+            "state",
+            hour,
+        );
+        write_file(
+            &data_directory
+                .path()
+                .join("hl/hyperliquid_data/node_config.json"),
+            "{}",
+            hour,
+        );
+
+        let args = BackupArgs {
+            backup_interval: None,
+            backup_extra_paths: vec![PathBuf::from("hl/hyperliquid_data/node_config.json")],
+            backup_retention: 7,
+        };
+        let key = run_backup(data_directory.path(), &args, &bucket)
+            .await
+            .unwrap();
+        let backups = list_backups(&bucket).await.unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].key, key);
+
+        let restore_directory = tempfile::tempdir().unwrap();
+        assert_eq!(
+            restore_backup(&bucket, None, restore_directory.path(), None)
+                .await
+                .unwrap(),
+            key
+        );
+        assert_eq!(
+            fs::read_to_string(
+                restore_directory
+                    .path()
+                    .join("hl/data/periodic_abci_states/20260101/100")
+            )
+            .unwrap(),
+            "state"
+        );
+        assert_eq!(
+            fs::read_to_string(
+                restore_directory
+                    .path()
+                    .join("hl/hyperliquid_data/node_config.json")
+            )
+            .unwrap(),
+            "{}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_apply_retention() {
+        let storage = tempfile::tempdir().unwrap();
+        let bucket = file_bucket(storage.path());
+        for timestamp in ["20260101T000000Z", "20260102T000000Z", "20260103T000000Z"] {
+            let key = format!(
+                "{}{BACKUP_KEY_PREFIX}{timestamp}{BACKUP_KEY_SUFFIX}",
+                bucket.prefix
+            );
+            bucket.put(&key, Vec::new()).await.unwrap();
+            bucket
+                .put(&format!("{key}.sha256"), Vec::new())
+                .await
+                .unwrap();
+        }
+
+        apply_retention(&bucket, 2).await.unwrap();
+        let backups = list_backups(&bucket).await.unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(
+            backups[0]
+                .key
+                .ends_with("hl-backup-20260102T000000Z.tar.zst")
+        );
+        assert!(
+            bucket
+                .list(&format!("{}{BACKUP_KEY_PREFIX}20260101", bucket.prefix))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,335 @@
+use std::{collections::HashMap, time::Duration};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, ContextCompat, bail};
+use prometheus::proto::{MetricFamily, MetricType};
+use reqwest::{Client, Url};
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, warn};
+
+use crate::aws;
+
+const API_VERSION: &str = "2010-08-01";
+
+/// PutMetricData takes up to 1000 metrics per request, smaller batches stay clear of its 1 MB payload limit
+const BATCH_SIZE: usize = 500;
+
+/// Dimensions CloudWatch allows per metric
+const MAX_DIMENSIONS: usize = 30;
+
+#[derive(Clone, Debug, Args)]
+pub struct CloudWatchArgs {
+    /// CloudWatch namespace to publish metrics to, e.g. `HyperliquidNode`. Publishes the same metrics as /metrics,
+    /// disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_CLOUDWATCH_NAMESPACE")]
+    pub cloudwatch_namespace: Option<String>,
+
+    /// Region to publish metrics in. Defaults to AWS_REGION or AWS_DEFAULT_REGION
+    #[arg(long, env = "HL_BOOTSTRAP_CLOUDWATCH_REGION")]
+    pub cloudwatch_region: Option<String>,
+
+    /// CloudWatch endpoint, e.g. for VPC endpoints or LocalStack. Defaults to the regional endpoint
+    #[arg(long, env = "HL_BOOTSTRAP_CLOUDWATCH_ENDPOINT")]
+    pub cloudwatch_endpoint: Option<Url>,
+
+    /// Dimensions added to every metric as Name=Value, e.g. `InstanceId=i-0123456789abcdef0`. Metric labels are added
+    /// as dimensions as well
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CLOUDWATCH_DIMENSIONS",
+        value_delimiter = ',',
+        value_parser = parse_dimension
+    )]
+    pub cloudwatch_dimensions: Vec<(String, String)>,
+
+    /// How often metrics are published to CloudWatch. Every publish is billed per metric, see CloudWatch pricing
+    #[arg(long, env = "HL_BOOTSTRAP_CLOUDWATCH_INTERVAL", default_value = "60s")]
+    pub cloudwatch_interval: DurationString,
+}
+
+fn parse_dimension(value: &str) -> eyre::Result<(String, String)> {
+    let (name, value) = value
+        .split_once('=')
+        .wrap_err("expected dimension in Name=Value format")?;
+    if name.is_empty() || value.is_empty() {
+        bail!("dimension name and value can't be empty");
+    }
+
+    Ok((name.to_string(), value.to_string()))
+}
+
+impl CloudWatchArgs {
+    pub fn region(&self) -> Option<String> {
+        self.cloudwatch_region.clone().or_else(aws::env_region)
+    }
+}
+
+/// Worker task periodically publishing the Prometheus registry to CloudWatch.
+/// Counters are sent as deltas since the previous publish, everything else as their current value.
+pub async fn cloudwatch_task(namespace: String, args: CloudWatchArgs) {
+    let cloudwatch = match CloudWatch::new(namespace, &args) {
+        Ok(cloudwatch) => cloudwatch,
+        Err(err) => {
+            warn!(
+                ?err,
+                "failed to set up CloudWatch client, not publishing metrics"
+            );
+            return;
+        }
+    };
+
+    let mut encoder = Encoder {
+        dimensions: args.cloudwatch_dimensions,
+        counters: HashMap::new(),
+    };
+
+    let mut interval = interval(args.cloudwatch_interval.into());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        namespace = cloudwatch.namespace,
+        region = cloudwatch.service.region,
+        "publishing metrics to CloudWatch"
+    );
+    loop {
+        interval.tick().await;
+
+        crate::metrics::refresh();
+        let data = encoder.encode(&prometheus::default_registry().gather());
+
+        for batch in data.chunks(BATCH_SIZE) {
+            if let Err(err) = cloudwatch.put_metric_data(batch).await {
+                warn!(?err, "failed to publish metrics to CloudWatch");
+            }
+        }
+    }
+}
+
+struct CloudWatch {
+    service: aws::Service,
+    namespace: String,
+}
+
+impl CloudWatch {
+    fn new(namespace: String, args: &CloudWatchArgs) -> eyre::Result<Self> {
+        let region = args
+            .region()
+            .wrap_err("no region set, see --cloudwatch-region")?;
+        let endpoint = match &args.cloudwatch_endpoint {
+            Some(endpoint) => endpoint.clone(),
+            None => format!("https://monitoring.{region}.amazonaws.com/").parse()?,
+        };
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .wrap_err("failed to build CloudWatch client")?;
+
+        Ok(Self {
+            service: aws::Service {
+                client,
+                credentials: aws::credentials(&region)?,
+                name: "monitoring",
+                region,
+                endpoint,
+            },
+            namespace,
+        })
+    }
+
+    async fn put_metric_data(&self, data: &[Datum]) -> eyre::Result<()> {
+        let headers = vec![(
+            "content-type",
+            "application/x-www-form-urlencoded; charset=utf-8".to_string(),
+        )];
+        self.service
+            .post(headers, put_metric_data_body(&self.namespace, data))
+            .await?;
+
+        debug!(metrics = data.len(), "published metrics to CloudWatch");
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Datum {
+    name: String,
+    dimensions: Vec<(String, String)>,
+    value: f64,
+    unit: &'static str,
+}
+
+struct Encoder {
+    dimensions: Vec<(String, String)>,
+    /// Last seen counter values, keyed by metric name & label values
+    counters: HashMap<(String, Vec<String>), f64>,
+}
+
+impl Encoder {
+    fn encode(&mut self, families: &[MetricFamily]) -> Vec<Datum> {
+        let mut data = Vec::new();
+        for family in families {
+            let name = family.name();
+            for metric in family.get_metric() {
+                let dimensions: Vec<_> = self
+                    .dimensions
+                    .iter()
+                    .cloned()
+                    .chain(
+                        metric
+                            .get_label()
+                            .iter()
+                            // CloudWatch rejects empty dimension values
+                            .filter(|label| !label.value().is_empty())
+                            .map(|label| (label.name().to_string(), label.value().to_string())),
+                    )
+                    .take(MAX_DIMENSIONS)
+                    .collect();
+                let datum = |name: String, value: f64, unit| Datum {
+                    name,
+                    dimensions: dimensions.clone(),
+                    value,
+                    unit,
+                };
+
+                match family.get_field_type() {
+                    MetricType::COUNTER => {
+                        let key = (
+                            name.to_string(),
+                            metric
+                                .get_label()
+                                .iter()
+                                .map(|label| label.value().to_string())
+                                .collect(),
+                        );
+                        let value = metric.get_counter().get_value();
+                        let previous = self.counters.insert(key, value).unwrap_or(0.0);
+                        // Counter was reset, e.g. metric got removed and recreated
+                        let delta = if value >= previous {
+                            value - previous
+                        } else {
+                            value
+                        };
+                        data.push(datum(name.to_string(), delta, "Count"));
+                    }
+                    MetricType::GAUGE => {
+                        data.push(datum(
+                            name.to_string(),
+                            metric.get_gauge().get_value(),
+                            "None",
+                        ));
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        data.push(datum(
+                            format!("{name}_sum"),
+                            histogram.get_sample_sum(),
+                            "None",
+                        ));
+                        data.push(datum(
+                            format!("{name}_count"),
+                            histogram.get_sample_count() as f64,
+                            "None",
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // CloudWatch rejects NaN & infinite values
+        data.retain(|datum| datum.value.is_finite());
+        data
+    }
+}
+
+/// Form encoded PutMetricData request of the query API
+fn put_metric_data_body(namespace: &str, data: &[Datum]) -> String {
+    let mut form = Url::parse("http://localhost/").unwrap();
+    {
+        let mut pairs = form.query_pairs_mut();
+        pairs
+            .append_pair("Action", "PutMetricData")
+            .append_pair("Version", API_VERSION)
+            .append_pair("Namespace", namespace);
+        for (index, datum) in data.iter().enumerate() {
+            let member = format!("MetricData.member.{}", index + 1);
+            pairs
+                .append_pair(&format!("{member}.MetricName"), &datum.name)
+                .append_pair(&format!("{member}.Value"), &datum.value.to_string())
+                .append_pair(&format!("{member}.Unit"), datum.unit);
+            for (index, (name, value)) in datum.dimensions.iter().enumerate() {
+                let dimension = format!("{member}.Dimensions.member.{}", index + 1);
+                pairs
+                    .append_pair(&format!("{dimension}.Name"), name)
+                    .append_pair(&format!("{dimension}.Value"), value);
+            }
+        }
+    }
+    form.query().unwrap_or_default().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{IntCounterVec, IntGauge, Opts, Registry};
+
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let registry = Registry::new();
+        let counter = IntCounterVec::new(Opts::new("test_runs", "runs"), &["result"]).unwrap();
+        let gauge = IntGauge::new("test_peers", "peers").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(gauge.clone())).unwrap();
+
+        counter.with_label_values(&["ok"]).inc_by(3);
+        gauge.set(7);
+
+        let mut encoder = Encoder {
+            dimensions: vec![("Network".to_string(), "Mainnet".to_string())],
+            counters: HashMap::new(),
+        };
+        let network = ("Network".to_string(), "Mainnet".to_string());
+        let result = ("result".to_string(), "ok".to_string());
+
+        assert_eq!(
+            encoder.encode(&registry.gather()),
+            [
+                Datum {
+                    name: "test_peers".to_string(),
+                    dimensions: vec![network.clone()],
+                    value: 7.0,
+                    unit: "None",
+                },
+                Datum {
+                    name: "test_runs".to_string(),
+                    dimensions: vec![network.clone(), result.clone()],
+                    value: 3.0,
+                    unit: "Count",
+                },
+            ]
+        );
+
+        counter.with_label_values(&["ok"]).inc();
+        assert_eq!(encoder.encode(&registry.gather())[1].value, 1.0);
+    }
+
+    #[test]
+    fn test_put_metric_data_body() {
+        let data = [Datum {
+            name: "test_runs".to_string(),
+            dimensions: vec![("result".to_string(), "ok".to_string())],
+            value: 3.0,
+            unit: "Count",
+        }];
+
+        assert_eq!(
+            put_metric_data_body("Hyperliquid Node", &data),
+            "Action=PutMetricData&Version=2010-08-01&Namespace=Hyperliquid+Node\
+             &MetricData.member.1.MetricName=test_runs&MetricData.member.1.Value=3\
+             &MetricData.member.1.Unit=Count&MetricData.member.1.Dimensions.member.1.Name=result\
+             &MetricData.member.1.Dimensions.member.1.Value=ok"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    os::unix::fs::{MetadataExt, fchown},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytesize::ByteSize;
+use clap::Args;
+use duration_string::DurationString;
+use eyre::Context;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, instrument, warn};
+
+use crate::metrics::{COUNTER_COMPRESSED_BYTES_SAVED, COUNTER_COMPRESSED_FILES};
+
+/// Suffix of files compressed by hl-bootstrap
+pub const COMPRESSED_SUFFIX: &str = ".zst";
+
+#[derive(Clone, Debug, Args)]
+pub struct CompressArgs {
+    /// How often to compress aged data files to .zst in place. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_COMPRESS_DATA_INTERVAL")]
+    pub compress_data_interval: Option<DurationString>,
+
+    /// Files not modified for this long are compressed, hl-node must be done writing them
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_COMPRESS_DATA_OLDER_THAN",
+        default_value = "2h"
+    )]
+    pub compress_data_older_than: DurationString,
+
+    /// Subdirectories of hl/data to compress. Only outputs hl-node itself doesn't read back belong here
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_COMPRESS_DATA_DIRECTORIES",
+        value_delimiter = ',',
+        default_value = "replica_cmds,node_fills,node_fills_by_block,node_trades,node_trades_by_block,node_order_statuses,node_order_statuses_by_block,misc_events,misc_events_by_block"
+    )]
+    pub compress_data_directories: Vec<PathBuf>,
+
+    /// zstd compression level, higher saves more disk for more CPU time
+    #[arg(long, env = "HL_BOOTSTRAP_COMPRESS_DATA_LEVEL", default_value_t = 3)]
+    pub compress_data_level: i32,
+}
+
+/// Name of a data file with the compression suffix removed, e.g. for parsing hours & heights
+pub fn uncompressed_name(name: &str) -> &str {
+    name.strip_suffix(COMPRESSED_SUFFIX).unwrap_or(name)
+}
+
+/// Opens a data file for reading, decompressing it if it was compressed
+pub fn open(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)?;
+    if path.to_string_lossy().ends_with(COMPRESSED_SUFFIX) {
+        Ok(Box::new(BufReader::new(zstd::Decoder::new(file)?)))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Worker task that periodically compresses aged files in the configured ${base}/hl/data subdirectories
+pub async fn compress_worker_task(
+    data_directory: PathBuf,
+    args: CompressArgs,
+    compress_interval: Duration,
+) {
+    let data_path = data_directory.join("hl/data");
+    let older_than = args.compress_data_older_than.into();
+
+    let mut interval = interval(compress_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        ?data_path,
+        directories = ?args.compress_data_directories,
+        ?older_than,
+        "compressing aged data files"
+    );
+    loop {
+        interval.tick().await;
+
+        let directories: Vec<_> = args
+            .compress_data_directories
+            .iter()
+            .map(|directory| data_path.join(directory))
+            .collect();
+        let level = args.compress_data_level;
+        let result = tokio::task::spawn_blocking(move || {
+            compress_directories(&directories, older_than, level)
+        })
+        .await;
+        if let Err(err) = result {
+            warn!(?err, "data compression failed");
+        }
+    }
+}
+
+#[instrument(skip_all)]
+fn compress_directories(directories: &[PathBuf], older_than: Duration, level: i32) {
+    let now = SystemTime::now();
+    let (mut compressed, mut failed, mut saved) = (0_u64, 0_u64, 0_u64);
+
+    let mut pending = directories.to_vec();
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let aged = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| now.duration_since(modified).ok())
+                .is_some_and(|age| age > older_than);
+            if !metadata.is_file()
+                || !aged
+                || name.starts_with('.')
+                || name.ends_with(COMPRESSED_SUFFIX)
+            {
+                continue;
+            }
+
+            match compress_file(&path, &metadata, level) {
+                Ok(size) => {
+                    debug!(
+                        ?path,
+                        before = metadata.len(),
+                        after = size,
+                        "compressed data file"
+                    );
+                    compressed += 1;
+                    saved += metadata.len().saturating_sub(size);
+                }
+                Err(err) => {
+                    warn!(?err, ?path, "failed to compress data file");
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    COUNTER_COMPRESSED_FILES
+        .with_label_values(&["compressed"])
+        .inc_by(compressed);
+    COUNTER_COMPRESSED_FILES
+        .with_label_values(&["failed"])
+        .inc_by(failed);
+    COUNTER_COMPRESSED_BYTES_SAVED.inc_by(saved);
+    info!(compressed, failed, saved = %ByteSize::b(saved), "data compression complete");
+}
+
+/// Compresses next to the file and swaps it in, keeping the owner & modification time so pruning still sees the
+/// original age. Returns the compressed size
+fn compress_file(path: &Path, metadata: &fs::Metadata, level: i32) -> eyre::Result<u64> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let compressed_path = path.with_file_name(format!("{name}{COMPRESSED_SUFFIX}"));
+    let staging_path = path.with_file_name(format!(".{name}{COMPRESSED_SUFFIX}.tmp"));
+
+    let result = (|| {
+        let mut source = File::open(path)?;
+        let staging = File::create(&staging_path)?;
+        fchown(&staging, Some(metadata.uid()), Some(metadata.gid()))?;
+
+        let mut encoder = zstd::Encoder::new(staging, level)?;
+        io::copy(&mut source, &mut encoder)?;
+        let staging = encoder.finish()?;
+        staging.set_modified(metadata.modified()?)?;
+        staging.sync_all()?;
+        let size = staging.metadata()?.len();
+
+        fs::rename(&staging_path, &compressed_path)?;
+        eyre::Ok(size)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&staging_path);
+    }
+    let size = result.wrap_err_with(|| format!("failed to compress {path:?}"))?;
+
+    fs::remove_file(path).wrap_err_with(|| format!("failed to remove {path:?}"))?;
+    Ok(size)
+}
@@ -0,0 +1,528 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::{Command, Parser, ValueEnum};
+use eyre::{Context, bail, eyre};
+use toml::{Table, Value};
+
+use crate::{role::NodeRole, secrets::SecretRef};
+
+/// Parses command line arguments, layered on top of the configuration file given with `--config`.
+///
+/// Precedence, highest first:
+/// 1. command line flags
+/// 2. `HL_BOOTSTRAP_*` environment variables
+/// 3. configuration file, keys named after the long flags (`metrics-listen-address` or `metrics_listen_address`)
+/// 4. defaults of the `--node-role` preset, see [NodeRole::defaults]
+/// 5. built-in defaults
+///
+/// Configuration file values are applied as argument defaults, so clap resolves the precedence itself.
+///
+/// The file can define named profiles in `[profiles.<name>]` tables, e.g. one per network. The profile selected with
+/// `--profile` (or a top level `profile` key) overrides the top level keys.
+///
+/// Configuration file and environment values can reference secrets in Vault or AWS Secrets Manager instead of holding
+/// them, see [SecretRef]. They're fetched here, so a reload fetches them again.
+///
+/// Every `HL_BOOTSTRAP_*` variable can also be given as `HL_BOOTSTRAP_*_FILE`, naming a file to read the value from,
+/// e.g. a mounted Kubernetes or Docker secret. They're read here as well, so a reload picks up rotated files.
+pub fn parse<P: Parser>() -> eyre::Result<P> {
+    try_parse().map_err(|err| match err.downcast::<clap::Error>() {
+        Ok(err) => err.exit(),
+        Err(err) => err,
+    })
+}
+
+/// Same as [parse], but returns invalid arguments as an error instead of exiting. Used for reloading the configuration
+pub fn try_parse<P: Parser>() -> eyre::Result<P> {
+    // Only looking for --config, --profile & --node-role here, required args might come from the configuration file
+    let matches = P::command().ignore_errors(true).try_get_matches().ok();
+    let config_path = matches
+        .as_ref()
+        .and_then(|matches| matches.get_one::<PathBuf>("config").cloned());
+    let profile = matches
+        .as_ref()
+        .and_then(|matches| matches.get_one::<String>("profile").cloned());
+    let role = matches.as_ref().and_then(|matches| {
+        matches
+            .try_get_one::<NodeRole>("node_role")
+            .ok()
+            .flatten()
+            .copied()
+    });
+
+    let mut table = match config_path {
+        Some(path) => Some(
+            select_profile(load(&path)?, profile.as_deref())
+                .wrap_err_with(|| format!("invalid configuration file {path:?}"))?,
+        ),
+        None => {
+            if let Some(profile) = profile {
+                bail!(
+                    "profile {profile:?} selected, but no configuration file given with --config"
+                );
+            }
+            None
+        }
+    };
+
+    let mut command = P::command();
+    let role = match (role, &table) {
+        (Some(role), _) => Some(role),
+        (None, Some(table)) => table_role(table)?,
+        (None, None) => None,
+    };
+    if let Some(role) = role {
+        command = apply_role(command, role);
+    }
+    let environment = secret_environment(&command)?;
+    let mut references: BTreeSet<_> = environment
+        .iter()
+        .map(|(_, reference)| reference.clone())
+        .collect();
+    if let Some(table) = &table {
+        references.extend(table_secrets(table)?);
+    }
+    let secrets = if references.is_empty() {
+        HashMap::new()
+    } else {
+        crate::secrets::resolve(references)?
+    };
+
+    if let Some(table) = &mut table {
+        replace_secrets(table, &secrets);
+        command = apply_defaults(command, table)?;
+    }
+    // Environment variables take precedence over the file, so their secrets replace its defaults
+    let mut environment: Vec<_> = environment
+        .into_iter()
+        .map(|(id, reference)| (id, secrets[&reference].clone()))
+        .collect();
+    environment.extend(file_environment(&command, |name| std::env::var_os(name))?);
+    for (id, value) in environment {
+        command = command.mut_arg(id, |arg| {
+            arg.env(None::<&str>).default_value(value).required(false)
+        });
+    }
+
+    let matches = command.try_get_matches()?;
+    Ok(P::from_arg_matches(&matches)?)
+}
+
+/// Checks that configuration file contents, with each of the profiles applied, parse into valid arguments
+pub fn validate<P: Parser>(contents: &str) -> eyre::Result<()> {
+    let table: Table = contents.parse().wrap_err("failed to parse configuration")?;
+
+    let mut profiles: Vec<_> = match table.get(PROFILES_KEY) {
+        Some(Value::Table(profiles)) => profiles.keys().cloned().map(Some).collect(),
+        _ => Vec::new(),
+    };
+    if profiles.is_empty() || !table.contains_key("profile") {
+        profiles.push(None);
+    }
+
+    for profile in profiles {
+        let table = select_profile(table.clone(), profile.as_deref())?;
+        apply_defaults(P::command(), &table)?
+            .try_get_matches_from([env!("CARGO_PKG_NAME")])
+            .wrap_err_with(|| match &profile {
+                Some(profile) => format!("invalid profile {profile:?}"),
+                None => "invalid configuration".to_string(),
+            })?;
+    }
+    Ok(())
+}
+
+/// Ids of args whose environment variable holds a secret reference, with the reference
+fn secret_environment(command: &Command) -> eyre::Result<Vec<(String, String)>> {
+    let mut environment = Vec::new();
+    for arg in command.get_arguments() {
+        let Some(value) = arg
+            .get_env()
+            .and_then(std::env::var_os)
+            .and_then(|value| value.into_string().ok())
+        else {
+            continue;
+        };
+        if SecretRef::parse(&value)
+            .wrap_err_with(|| format!("invalid secret reference in {:?}", arg.get_env()))?
+            .is_some()
+        {
+            environment.push((arg.get_id().to_string(), value));
+        }
+    }
+    Ok(environment)
+}
+
+/// Ids of args whose environment variable is given as `<name>_FILE`, with the contents of the file. Names that are
+/// variables of other args themselves, like `HL_BOOTSTRAP_API_KEYS_FILE`, stay theirs
+fn file_environment(
+    command: &Command,
+    var: impl Fn(&str) -> Option<OsString>,
+) -> eyre::Result<Vec<(String, String)>> {
+    let names: BTreeSet<_> = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_env()?.to_str())
+        .collect();
+
+    let mut environment = Vec::new();
+    for arg in command.get_arguments() {
+        let Some(name) = arg.get_env().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let file_name = format!("{name}_FILE");
+        if names.contains(file_name.as_str()) {
+            continue;
+        }
+        let Some(path) = var(&file_name) else {
+            continue;
+        };
+        if var(name).is_some() {
+            bail!("both {name} and {file_name} are set, keep one of them");
+        }
+
+        let contents = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read {file_name} {path:?}"))?;
+        // Files written by editors and `echo` end with a newline that isn't part of the value
+        let value = contents.trim_end_matches(['\n', '\r']).to_string();
+        environment.push((arg.get_id().to_string(), value));
+    }
+    Ok(environment)
+}
+
+/// Secret references among the configuration file values
+fn table_secrets(table: &Table) -> eyre::Result<Vec<String>> {
+    let mut references = Vec::new();
+    for (key, value) in table {
+        let values = match value {
+            Value::Array(items) => items.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            if let Value::String(value) = value
+                && SecretRef::parse(value)
+                    .wrap_err_with(|| {
+                        format!("invalid secret reference in configuration key {key:?}")
+                    })?
+                    .is_some()
+            {
+                references.push(value.clone());
+            }
+        }
+    }
+    Ok(references)
+}
+
+fn replace_secrets(table: &mut Table, secrets: &HashMap<String, String>) {
+    for value in table.iter_mut().map(|(_, value)| value) {
+        let values: Vec<&mut Value> = match value {
+            Value::Array(items) => items.iter_mut().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            if let Value::String(string) = value
+                && let Some(secret) = secrets.get(string.as_str())
+            {
+                *string = secret.clone();
+            }
+        }
+    }
+}
+
+fn load(path: &Path) -> eyre::Result<Table> {
+    let contents = fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read configuration file {path:?}"))?;
+    contents
+        .parse()
+        .wrap_err_with(|| format!("failed to parse configuration file {path:?}"))
+}
+
+const PROFILES_KEY: &str = "profiles";
+
+/// Merges the selected profile over the top level keys. Without an explicit selection, falls back to the top level
+/// `profile` key
+fn select_profile(mut table: Table, profile: Option<&str>) -> eyre::Result<Table> {
+    let mut profiles = match table.remove(PROFILES_KEY) {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => bail!("{PROFILES_KEY:?} must be a table of profiles"),
+        None => Table::new(),
+    };
+
+    let profile = match profile {
+        Some(profile) => profile.to_string(),
+        None => match table.get("profile") {
+            Some(Value::String(profile)) => profile.clone(),
+            Some(_) => bail!("\"profile\" must be a string"),
+            None => return Ok(table),
+        },
+    };
+
+    match profiles.remove(&profile) {
+        Some(Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                if key == PROFILES_KEY || key == "profile" {
+                    bail!("profile {profile:?} can't contain {key:?}");
+                }
+                // Same key can be spelled with - or _, the profile one wins
+                let id = key.replace('-', "_");
+                table.retain(|existing, _| existing.replace('-', "_") != id);
+                table.insert(key, value);
+            }
+        }
+        Some(_) => bail!("profile {profile:?} must be a table"),
+        None => {
+            let available: Vec<_> = profiles.keys().map(String::as_str).collect();
+            bail!(
+                "unknown profile {profile:?}, available profiles: {}",
+                if available.is_empty() {
+                    "none".to_string()
+                } else {
+                    available.join(", ")
+                }
+            );
+        }
+    }
+    // Keep the selection visible to the parsed arguments
+    table.insert("profile".to_string(), Value::String(profile));
+
+    Ok(table)
+}
+
+/// Role set in the configuration file
+fn table_role(table: &Table) -> eyre::Result<Option<NodeRole>> {
+    let Some((key, value)) = table
+        .iter()
+        .find(|(key, _)| key.replace('-', "_") == "node_role")
+    else {
+        return Ok(None);
+    };
+
+    match value {
+        Value::String(role) => NodeRole::from_str(role, true)
+            .map(Some)
+            .map_err(|err| eyre!("invalid value for configuration key {key:?}: {err}")),
+        _ => bail!("configuration key {key:?} must be a string"),
+    }
+}
+
+fn apply_role(mut command: Command, role: NodeRole) -> Command {
+    for &(id, values) in role.defaults() {
+        if command.get_arguments().any(|arg| arg.get_id() == id) {
+            command = command.mut_arg(id, |arg| arg.default_values(values));
+        }
+    }
+    command
+}
+
+fn apply_defaults(mut command: Command, table: &Table) -> eyre::Result<Command> {
+    for (key, value) in table {
+        let id = key.replace('-', "_");
+        if id == "config"
+            || !command
+                .get_arguments()
+                .any(|arg| arg.get_id() == id.as_str())
+        {
+            bail!("unknown configuration key {key:?}");
+        }
+
+        let values = match value {
+            Value::Array(items) => items
+                .iter()
+                .map(|item| scalar(key, item))
+                .collect::<eyre::Result<Vec<_>>>()?,
+            value => vec![scalar(key, value)?],
+        };
+        // Required args are satisfied by the file, clap doesn't count defaults for that
+        command = command.mut_arg(id, |arg| arg.default_values(values).required(false));
+    }
+
+    Ok(command)
+}
+
+fn scalar(key: &str, value: &Value) -> eyre::Result<String> {
+    Ok(match value {
+        Value::String(value) => value.clone(),
+        Value::Integer(value) => value.to_string(),
+        Value::Float(value) => value.to_string(),
+        Value::Boolean(value) => value.to_string(),
+        _ => bail!("unsupported value for configuration key {key:?}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Arg, CommandFactory, FromArgMatches};
+
+    use super::*;
+
+    #[derive(Debug, Parser)]
+    struct TestCli {
+        #[arg(long)]
+        config: Option<PathBuf>,
+
+        #[arg(long)]
+        profile: Option<String>,
+
+        #[arg(long, default_value = "15m")]
+        max_age: String,
+
+        #[arg(long, default_value_t = 5)]
+        amount: usize,
+
+        #[arg(long)]
+        try_new_peers: bool,
+
+        #[arg(long, value_delimiter = ',')]
+        peers: Vec<String>,
+    }
+
+    fn parse(table: &str, args: &[&str]) -> eyre::Result<TestCli> {
+        let command = apply_defaults(TestCli::command(), &table.parse()?)?;
+        let matches =
+            command.try_get_matches_from(std::iter::once("test").chain(args.iter().copied()))?;
+        Ok(TestCli::from_arg_matches(&matches)?)
+    }
+
+    #[test]
+    fn test_config_layering() {
+        let config = r#"
+            max-age = "1h"
+            amount = 8
+            try_new_peers = true
+            peers = ["1.1.1.1", "8.8.8.8"]
+        "#;
+
+        let cli = parse(config, &[]).unwrap();
+        assert_eq!(cli.max_age, "1h");
+        assert_eq!(cli.amount, 8);
+        assert!(cli.try_new_peers);
+        assert_eq!(cli.peers, ["1.1.1.1", "8.8.8.8"]);
+
+        let cli = parse(config, &["--amount", "3", "--peers", "9.9.9.9"]).unwrap();
+        assert_eq!(cli.max_age, "1h");
+        assert_eq!(cli.amount, 3);
+        assert_eq!(cli.peers, ["9.9.9.9"]);
+
+        assert!(parse("unknown = 1", &[]).is_err());
+    }
+
+    #[test]
+    fn test_select_profile() {
+        let config: Table = r#"
+            max-age = "1h"
+            amount = 8
+
+            [profiles.testnet]
+            max_age = "5m"
+
+            [profiles.mainnet]
+            amount = 10
+        "#
+        .parse()
+        .unwrap();
+
+        let table = select_profile(config.clone(), None).unwrap();
+        assert_eq!(table.get("amount"), Some(&Value::Integer(8)));
+        assert!(!table.contains_key("profiles"));
+
+        let table = select_profile(config.clone(), Some("testnet")).unwrap();
+        assert_eq!(table.get("max_age"), Some(&Value::String("5m".into())));
+        assert!(!table.contains_key("max-age"));
+        assert_eq!(table.get("amount"), Some(&Value::Integer(8)));
+
+        let mut with_default = config.clone();
+        with_default.insert("profile".into(), Value::String("mainnet".into()));
+        let table = select_profile(with_default, None).unwrap();
+        assert_eq!(table.get("amount"), Some(&Value::Integer(10)));
+
+        assert!(select_profile(config, Some("devnet")).is_err());
+    }
+
+    #[test]
+    fn test_replace_secrets() {
+        let mut table: Table = r#"
+            max-age = "1h"
+            token = "vault://secret/data/hl-node#token"
+            peers = ["1.1.1.1", "aws-sm://hl-node#peer"]
+        "#
+        .parse()
+        .unwrap();
+
+        assert_eq!(
+            table_secrets(&table).unwrap(),
+            ["aws-sm://hl-node#peer", "vault://secret/data/hl-node#token"]
+        );
+
+        let secrets = HashMap::from([
+            (
+                "vault://secret/data/hl-node#token".to_string(),
+                "s3cret".to_string(),
+            ),
+            ("aws-sm://hl-node#peer".to_string(), "8.8.8.8".to_string()),
+        ]);
+        replace_secrets(&mut table, &secrets);
+        assert_eq!(table.get("token"), Some(&Value::String("s3cret".into())));
+        assert_eq!(
+            table.get("peers"),
+            Some(&Value::Array(vec![
+                Value::String("1.1.1.1".into()),
+                Value::String("8.8.8.8".into())
+            ]))
+        );
+        assert_eq!(table.get("max-age"), Some(&Value::String("1h".into())));
+    }
+
+    #[test]
+    fn test_file_environment() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        std::io::Write::write_all(&mut file, b"s3cret\n").unwrap();
+        let path = file.path().as_os_str().to_owned();
+
+        let command = Command::new("test")
+            .arg(Arg::new("token").long("token").env("TEST_TOKEN"))
+            .arg(Arg::new("keys").long("keys").env("TEST_KEYS"))
+            .arg(
+                Arg::new("keys_file")
+                    .long("keys-file")
+                    .env("TEST_KEYS_FILE"),
+            );
+        let environment = file_environment(&command, |name| {
+            matches!(name, "TEST_TOKEN_FILE" | "TEST_KEYS_FILE").then(|| path.clone())
+        })
+        .unwrap();
+        // TEST_KEYS_FILE belongs to --keys-file
+        assert_eq!(environment, [("token".to_string(), "s3cret".to_string())]);
+
+        assert!(
+            file_environment(&command, |name| {
+                matches!(name, "TEST_TOKEN" | "TEST_TOKEN_FILE").then(|| path.clone())
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_role_defaults() {
+        for role in NodeRole::value_variants() {
+            // Catches preset ids that don't exist or values that don't parse
+            let command = apply_role(crate::Cli::command(), *role);
+            for (id, _) in role.defaults() {
+                assert!(
+                    command.get_arguments().any(|arg| arg.get_id() == *id),
+                    "{id}"
+                );
+            }
+            let matches = command
+                .try_get_matches_from(["test", "--visor-binary-directory", "/opt/hl"])
+                .unwrap();
+            let cli = crate::Cli::from_arg_matches(&matches).unwrap();
+            if *role == NodeRole::Validator {
+                assert_eq!(cli.seed_peers_amount, 8);
+            }
+        }
+    }
+}
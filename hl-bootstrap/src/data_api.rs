@@ -0,0 +1,454 @@
+use std::{
+    fs,
+    io::BufRead,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use clap::Args;
+use duration_string::DurationString;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::net::TcpListener;
+use tracing::{debug, info, warn};
+
+use crate::{
+    auth::{self, Access, Scope},
+    compress::{self, uncompressed_name},
+    explorer,
+    http_server::{self, HttpServerArgs},
+    tls::{self, TlsListener},
+};
+
+const HOUR_MS: i64 = 60 * 60 * 1000;
+
+#[derive(Clone, Debug, Args)]
+pub struct DataApiArgs {
+    /// Address to serve fills & trades written by hl-node (--write-fills, --write-trades) on, as /fills and /trades,
+    /// and blocks from replica_cmds as /blocks, /blocks/{height} and /txs/{hash}. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_API_LISTEN_ADDRESS")]
+    pub data_api_listen_address: Option<SocketAddr>,
+
+    /// Most records returned per page
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_API_MAX_LIMIT", default_value_t = 1000)]
+    pub data_api_max_limit: usize,
+
+    /// Longest time range a single query may cover, as every hourly file in it is read
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_API_MAX_RANGE", default_value = "24h")]
+    pub data_api_max_range: DurationString,
+
+    /// PEM certificate chain to serve the data API over TLS with, together with --data-api-tls-key
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_API_TLS_CERT")]
+    pub data_api_tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --data-api-tls-cert
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_API_TLS_KEY")]
+    pub data_api_tls_key: Option<PathBuf>,
+
+    /// PEM CA certificates client certificates must be issued by. Clients without one are refused during the TLS
+    /// handshake, before any request is read
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_API_TLS_CLIENT_CA")]
+    pub data_api_tls_client_ca: Option<PathBuf>,
+}
+
+#[derive(Clone)]
+struct DataApi {
+    data_directory: PathBuf,
+    max_limit: usize,
+    max_range: Duration,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Dataset {
+    Fills,
+    Trades,
+}
+
+impl Dataset {
+    fn directory(&self) -> &'static str {
+        match self {
+            Self::Fills => "node_fills",
+            Self::Trades => "node_trades",
+        }
+    }
+
+    /// hl-node writes into `<directory>_by_block` instead with --batch-by-block
+    fn hourly_root(&self, data_directory: &Path) -> PathBuf {
+        let data = data_directory.join("hl/data");
+        let by_block = data.join(format!("{}_by_block/hourly", self.directory()));
+        if by_block.is_dir() {
+            by_block
+        } else {
+            data.join(self.directory()).join("hourly")
+        }
+    }
+
+    /// Records of a line, which is a block with the records in `events` when batched by block. Fills are written as
+    /// `[user, fill]`, the user is moved into the fill so both datasets are plain objects
+    fn records(&self, line: Value) -> Vec<Value> {
+        let events = match line {
+            Value::Object(mut block) if block.contains_key("events") => {
+                match block.remove("events") {
+                    Some(Value::Array(events)) => events,
+                    _ => return Vec::new(),
+                }
+            }
+            event => vec![event],
+        };
+
+        events
+            .into_iter()
+            .filter_map(|event| match (self, event) {
+                (Self::Fills, Value::Array(pair)) => {
+                    let [user, Value::Object(mut fill)] = <[Value; 2]>::try_from(pair).ok()? else {
+                        return None;
+                    };
+                    fill.insert("user".to_string(), user);
+                    Some(Value::Object(fill))
+                }
+                (Self::Trades, trade @ Value::Object(_)) => Some(trade),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Fill times are unix milliseconds, trade times naive UTC timestamps with nanoseconds
+    fn time_ms(&self, record: &Value) -> Option<i64> {
+        match self {
+            Self::Fills => record.get("time")?.as_i64(),
+            Self::Trades => parse_node_time(record.get("time")?.as_str()?),
+        }
+    }
+
+    fn has_user(&self, record: &Value, user: &str) -> bool {
+        let matches = |value: &Value| value.as_str().is_some_and(|v| v.eq_ignore_ascii_case(user));
+        match self {
+            Self::Fills => record.get("user").is_some_and(matches),
+            Self::Trades => record
+                .get("side_info")
+                .and_then(Value::as_array)
+                .is_some_and(|sides| {
+                    sides
+                        .iter()
+                        .any(|side| side.get("user").is_some_and(matches))
+                }),
+        }
+    }
+}
+
+/// Parses the naive UTC timestamps with nanoseconds hl-node writes, e.g. `2025-06-15T15:06:40.123456789`, into unix
+/// milliseconds
+pub fn parse_node_time(time: &str) -> Option<i64> {
+    let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M:%S%.f").ok()?;
+    Some(time.and_utc().timestamp_millis())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordQuery {
+    coin: Option<String>,
+    user: Option<String>,
+    /// Unix milliseconds, defaults to an hour before the end time
+    start_time: Option<i64>,
+    /// Unix milliseconds, defaults to now
+    end_time: Option<i64>,
+    limit: Option<usize>,
+    /// `nextCursor` of the previous page
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordPage {
+    data: Vec<Value>,
+    next_cursor: Option<String>,
+}
+
+/// Position after the last returned record: hourly file, line within it & record within the line
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+struct Cursor {
+    hour_ms: i64,
+    line: usize,
+    record: usize,
+}
+
+impl Cursor {
+    fn parse(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.split('-').map(str::parse::<i64>);
+        let cursor = Self {
+            hour_ms: parts.next()?.ok()?,
+            line: usize::try_from(parts.next()?.ok()?).ok()?,
+            record: usize::try_from(parts.next()?.ok()?).ok()?,
+        };
+        parts.next().is_none().then_some(cursor)
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}-{}", self.hour_ms, self.line, self.record)
+    }
+}
+
+pub async fn run_data_api_server(
+    listen_address: SocketAddr,
+    data_directory: PathBuf,
+    args: DataApiArgs,
+    access: Access,
+    http: HttpServerArgs,
+) -> eyre::Result<()> {
+    let explorer = explorer::router(
+        &data_directory,
+        args.data_api_max_limit,
+        args.data_api_max_range.into(),
+    );
+    let state = DataApi {
+        data_directory,
+        max_limit: args.data_api_max_limit,
+        max_range: args.data_api_max_range.into(),
+    };
+    let router = Router::new()
+        .route("/fills", get(fills))
+        .route("/trades", get(trades))
+        .with_state(state)
+        .merge(explorer);
+    let router = auth::restrict(&access, auth::require(&access, router, Scope::Data));
+    let tls = match (&args.data_api_tls_cert, &args.data_api_tls_key) {
+        (Some(cert), Some(key)) => Some(tls::server_config(
+            cert,
+            key,
+            args.data_api_tls_client_ca.as_deref(),
+        )?),
+        _ => None,
+    };
+
+    let listener = TcpListener::bind(listen_address).await?;
+    info!(
+        %listen_address,
+        tls = tls.is_some(),
+        client_certificates = args.data_api_tls_client_ca.is_some(),
+        "serving fills, trades & blocks"
+    );
+    match tls {
+        Some(config) => {
+            http_server::serve(TlsListener::new(listener, config)?, router, &http).await
+        }
+        None => http_server::serve(listener, router, &http).await,
+    }
+
+    Ok(())
+}
+
+async fn fills(State(api): State<DataApi>, Query(query): Query<RecordQuery>) -> Response {
+    api.query(Dataset::Fills, query).await
+}
+
+async fn trades(State(api): State<DataApi>, Query(query): Query<RecordQuery>) -> Response {
+    api.query(Dataset::Trades, query).await
+}
+
+impl DataApi {
+    async fn query(self, dataset: Dataset, query: RecordQuery) -> Response {
+        let end_ms = query
+            .end_time
+            .unwrap_or_else(|| DateTime::<Utc>::from(SystemTime::now()).timestamp_millis());
+        let start_ms = query.start_time.unwrap_or(end_ms - HOUR_MS);
+        if start_ms > end_ms {
+            return (StatusCode::BAD_REQUEST, "startTime is after endTime").into_response();
+        }
+        if (end_ms - start_ms) as u128 > self.max_range.as_millis() {
+            return (
+                StatusCode::BAD_REQUEST,
+                format!("time range is longer than {:?}", self.max_range),
+            )
+                .into_response();
+        }
+        let after = match query.cursor.as_deref().map(Cursor::parse) {
+            Some(None) => return (StatusCode::BAD_REQUEST, "invalid cursor").into_response(),
+            Some(cursor) => cursor,
+            None => None,
+        };
+        let limit = query
+            .limit
+            .unwrap_or(self.max_limit)
+            .clamp(1, self.max_limit);
+
+        let root = dataset.hourly_root(&self.data_directory);
+        let result = tokio::task::spawn_blocking(move || {
+            let filter = |record: &Value| {
+                query.coin.as_ref().is_none_or(|coin| {
+                    record.get("coin").and_then(Value::as_str) == Some(coin.as_str())
+                }) && query
+                    .user
+                    .as_ref()
+                    .is_none_or(|user| dataset.has_user(record, user))
+                    && dataset
+                        .time_ms(record)
+                        .is_some_and(|time| (start_ms..=end_ms).contains(&time))
+            };
+            read_page(dataset, &root, start_ms, end_ms, after, limit, filter)
+        })
+        .await;
+
+        match result {
+            Ok(page) => Json(page).into_response(),
+            Err(err) => {
+                warn!(?err, ?dataset, "failed to read records");
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+}
+
+/// Reads matching records in file order, starting after the cursor. The next cursor is only set when the limit was
+/// hit, so an exhausted range has none
+fn read_page(
+    dataset: Dataset,
+    root: &Path,
+    start_ms: i64,
+    end_ms: i64,
+    after: Option<Cursor>,
+    limit: usize,
+    filter: impl Fn(&Value) -> bool,
+) -> RecordPage {
+    let mut data = Vec::new();
+    // Files are named after the hour they were written in, records can be from slightly before that
+    for (hour_ms, path) in hourly_files(root, start_ms - HOUR_MS, end_ms) {
+        if after.is_some_and(|after| hour_ms < after.hour_ms) {
+            continue;
+        }
+        let Ok(file) = compress::open(&path) else {
+            // Pruned in the meantime
+            continue;
+        };
+        debug!(?path, "reading records");
+
+        for (line_number, line) in file.lines().enumerate() {
+            let Ok(line) = line else {
+                break;
+            };
+            let Ok(line) = serde_json::from_str(&line) else {
+                // Last line may still be being written
+                continue;
+            };
+            for (record_number, record) in dataset.records(line).into_iter().enumerate() {
+                let position = Cursor {
+                    hour_ms,
+                    line: line_number,
+                    record: record_number,
+                };
+                if after.is_some_and(|after| position <= after) || !filter(&record) {
+                    continue;
+                }
+                data.push(record);
+                if data.len() == limit {
+                    return RecordPage {
+                        data,
+                        next_cursor: Some(position.to_string()),
+                    };
+                }
+            }
+        }
+    }
+    RecordPage {
+        data,
+        next_cursor: None,
+    }
+}
+
+/// Hourly files as `<root>/<YYYYMMDD>/<hour>`, compressed or not, starting within the range, oldest first
+fn hourly_files(root: &Path, start_ms: i64, end_ms: i64) -> Vec<(i64, PathBuf)> {
+    let mut files = Vec::new();
+    let Ok(days) = fs::read_dir(root) else {
+        return files;
+    };
+    for day in days.flatten() {
+        let Some(date) = day
+            .file_name()
+            .to_str()
+            .and_then(|name| NaiveDate::parse_from_str(name, "%Y%m%d").ok())
+        else {
+            continue;
+        };
+        let day_ms = date
+            .and_time(Default::default())
+            .and_utc()
+            .timestamp_millis();
+        if day_ms > end_ms || day_ms + 24 * HOUR_MS <= start_ms {
+            continue;
+        }
+        let Ok(hours) = fs::read_dir(day.path()) else {
+            continue;
+        };
+        for hour in hours.flatten() {
+            let Some(hour_ms) = hour
+                .file_name()
+                .to_str()
+                .and_then(|name| uncompressed_name(name).parse::<i64>().ok())
+                .filter(|hour| (0..24).contains(hour))
+                .map(|hour| day_ms + hour * HOUR_MS)
+            else {
+                continue;
+            };
+            if hour_ms <= end_ms && hour_ms + HOUR_MS > start_ms {
+                files.push((hour_ms, hour.path()));
+            }
+        }
+    }
+    files.sort_unstable();
+    // Both exist for a moment while a file is being compressed
+    files.dedup_by_key(|(hour_ms, _)| *hour_ms);
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_records() {
+        let pair = serde_json::json!(["0xabc", {"coin": "BTC", "time": 1750000000000_i64}]);
+        let block = serde_json::json!({"block_number": 1, "events": [pair.clone(), pair.clone()]});
+
+        let records = Dataset::Fills.records(pair);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["user"], "0xabc");
+        assert!(Dataset::Fills.has_user(&records[0], "0xABC"));
+        assert_eq!(Dataset::Fills.time_ms(&records[0]), Some(1750000000000));
+        assert_eq!(Dataset::Fills.records(block).len(), 2);
+    }
+
+    #[test]
+    fn test_trade_time() {
+        let trade = serde_json::json!({
+            "coin": "ETH",
+            "time": "2025-06-15T15:06:40.123456789",
+            "side_info": [{"user": "0xabc"}, {"user": "0xdef"}],
+        });
+        assert_eq!(Dataset::Trades.time_ms(&trade), Some(1750000000123));
+        assert!(Dataset::Trades.has_user(&trade, "0xdef"));
+
+        let block = serde_json::json!({"block_number": 1, "events": [trade.clone(), trade]});
+        assert_eq!(Dataset::Trades.records(block).len(), 2);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let cursor = Cursor {
+            hour_ms: 1750000000000,
+            line: 12,
+            record: 3,
+        };
+        assert_eq!(Cursor::parse(&cursor.to_string()), Some(cursor));
+        assert_eq!(Cursor::parse("1-2"), None);
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, bail};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HlHome {
+    /// Contains hl/ from a previous run
+    Existing,
+    /// Empty directory, hl-node creates hl/ on the first start
+    Fresh,
+    /// Has other files but no hl/, likely the wrong directory
+    Unrecognized,
+}
+
+/// Resolves the data directory to an absolute path, so the child, pruning and disk checks agree regardless of where
+/// hl-bootstrap is started from. Defaults to the current working directory
+pub fn resolve(data_dir: Option<&Path>) -> eyre::Result<PathBuf> {
+    let cwd = current_dir().wrap_err("failed to get current working directory")?;
+    Ok(match data_dir {
+        Some(data_dir) => cwd.join(data_dir),
+        None => cwd,
+    })
+}
+
+/// Checks that the directory can serve as the hl home, i.e. the directory hl-node keeps hl/data &
+/// hl/hyperliquid_data in
+pub fn inspect(path: &Path) -> eyre::Result<HlHome> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) => {
+            bail!("data directory {path:?} is not accessible ({err}), create it or pass --data-dir")
+        }
+    };
+    if !metadata.is_dir() {
+        bail!("data directory {path:?} is not a directory");
+    }
+
+    // Easy mistake to point at hl/ or hl/data instead of the directory containing them
+    if path.join("hyperliquid_data").is_dir()
+        || path.file_name().is_some_and(|name| name == "data")
+            && path
+                .parent()
+                .and_then(Path::file_name)
+                .is_some_and(|name| name == "hl")
+    {
+        bail!(
+            "data directory {path:?} looks like the inside of an hl home, pass the directory containing hl/ as --data-dir"
+        );
+    }
+
+    let hl = path.join("hl");
+    if hl.exists() {
+        if !hl.is_dir() {
+            bail!("{hl:?} exists but is not a directory");
+        }
+        return Ok(HlHome::Existing);
+    }
+
+    let mut entries =
+        fs::read_dir(path).wrap_err_with(|| format!("failed to list data directory {path:?}"))?;
+    Ok(if entries.next().is_none() {
+        HlHome::Fresh
+    } else {
+        HlHome::Unrecognized
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inspect() {
+        let home = tempfile::tempdir().unwrap();
+        assert_eq!(inspect(home.path()).unwrap(), HlHome::Fresh);
+
+        fs::write(home.path().join("override_gossip_config.json"), "{}").unwrap();
+        assert_eq!(inspect(home.path()).unwrap(), HlHome::Unrecognized);
+
+        fs::create_dir_all(home.path().join("hl/data")).unwrap();
+        fs::create_dir_all(home.path().join("hl/hyperliquid_data")).unwrap();
+        assert_eq!(inspect(home.path()).unwrap(), HlHome::Existing);
+
+        assert!(inspect(&home.path().join("hl")).is_err());
+        assert!(inspect(&home.path().join("hl/data")).is_err());
+        assert!(inspect(&home.path().join("missing")).is_err());
+    }
+}
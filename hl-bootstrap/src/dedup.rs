@@ -0,0 +1,224 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytesize::ByteSize;
+use clap::Args;
+use duration_string::DurationString;
+use eyre::Context;
+use hmac_sha256::Hash;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, instrument, warn};
+
+use crate::metrics::{COUNTER_DEDUP_BYTES_SAVED, COUNTER_DEDUP_LINKED_FILES};
+
+/// Files modified this recently may still be written by hl-node
+const MIN_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Clone, Debug, Args)]
+pub struct DedupArgs {
+    /// How often to replace byte-identical files with hardlinks to one copy. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_DEDUP_DATA_INTERVAL")]
+    pub dedup_data_interval: Option<DurationString>,
+
+    /// Subdirectories of hl/data to deduplicate
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_DEDUP_DATA_DIRECTORIES",
+        value_delimiter = ',',
+        default_value = "periodic_abci_states"
+    )]
+    pub dedup_data_directories: Vec<PathBuf>,
+}
+
+/// Inode identity plus what changes when it's rewritten, so unchanged files aren't hashed again
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+struct FileId {
+    device: u64,
+    inode: u64,
+    size: u64,
+    modified: i64,
+}
+
+#[derive(Debug)]
+struct Candidate {
+    path: PathBuf,
+    id: FileId,
+    modified: SystemTime,
+    links: u64,
+}
+
+/// Worker task that periodically hardlinks identical files in the configured ${base}/hl/data subdirectories
+pub async fn dedup_worker_task(data_directory: PathBuf, args: DedupArgs, dedup_interval: Duration) {
+    let data_path = data_directory.join("hl/data");
+    let mut hashes = HashMap::new();
+
+    let mut interval = interval(dedup_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        ?data_path,
+        directories = ?args.dedup_data_directories,
+        "deduplicating data files"
+    );
+    loop {
+        interval.tick().await;
+
+        let directories: Vec<_> = args
+            .dedup_data_directories
+            .iter()
+            .map(|directory| data_path.join(directory))
+            .collect();
+        let result = tokio::task::spawn_blocking(move || {
+            dedup_directories(&directories, &mut hashes);
+            hashes
+        })
+        .await;
+        match result {
+            Ok(cached) => hashes = cached,
+            Err(err) => {
+                warn!(?err, "data deduplication failed");
+                hashes = HashMap::new();
+            }
+        }
+    }
+}
+
+#[instrument(skip_all)]
+fn dedup_directories(directories: &[PathBuf], hashes: &mut HashMap<FileId, [u8; 32]>) {
+    let now = SystemTime::now();
+
+    // Only files of the same size can be identical, so most are never hashed
+    let mut by_size: HashMap<u64, Vec<Candidate>> = HashMap::new();
+    let mut pending = directories.to_vec();
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+                continue;
+            }
+            let Ok(modified) = metadata.modified() else {
+                continue;
+            };
+            let settled = now.duration_since(modified).is_ok_and(|age| age > MIN_AGE);
+            if !metadata.is_file()
+                || metadata.len() == 0
+                || !settled
+                || entry.file_name().to_string_lossy().starts_with('.')
+            {
+                continue;
+            }
+            by_size.entry(metadata.len()).or_default().push(Candidate {
+                path: entry.path(),
+                id: FileId {
+                    device: metadata.dev(),
+                    inode: metadata.ino(),
+                    size: metadata.len(),
+                    modified: metadata.mtime(),
+                },
+                modified,
+                links: metadata.nlink(),
+            });
+        }
+    }
+
+    let mut seen = HashMap::new();
+    let (mut linked, mut failed, mut saved) = (0_u64, 0_u64, 0_u64);
+    for candidates in by_size.into_values().filter(|group| group.len() > 1) {
+        let mut by_hash: HashMap<[u8; 32], Vec<Candidate>> = HashMap::new();
+        for candidate in candidates {
+            let hash = match hashes.get(&candidate.id) {
+                Some(hash) => *hash,
+                None => match sha256(&candidate.path) {
+                    Ok(hash) => *hashes.entry(candidate.id).or_insert(hash),
+                    Err(err) => {
+                        debug!(?err, path = ?candidate.path, "failed to hash file");
+                        continue;
+                    }
+                },
+            };
+            seen.insert(candidate.id, hash);
+            by_hash.entry(hash).or_default().push(candidate);
+        }
+
+        for mut duplicates in by_hash.into_values().filter(|group| group.len() > 1) {
+            // The newest copy is kept, so pruning by modification time never removes data earlier than it would have
+            duplicates.sort_by_key(|candidate| candidate.modified);
+            let Some(keep) = duplicates.pop() else {
+                continue;
+            };
+            for duplicate in duplicates {
+                if duplicate.id.device != keep.id.device || duplicate.id.inode == keep.id.inode {
+                    continue;
+                }
+                match link(&keep.path, &duplicate.path) {
+                    Ok(()) => {
+                        debug!(path = ?duplicate.path, target = ?keep.path, "hardlinked identical file");
+                        linked += 1;
+                        // Space is only freed once no other name refers to the duplicate's inode
+                        if duplicate.links == 1 {
+                            saved += duplicate.id.size;
+                        }
+                    }
+                    Err(err) => {
+                        warn!(?err, path = ?duplicate.path, "failed to hardlink identical file");
+                        failed += 1;
+                    }
+                }
+            }
+        }
+    }
+    // Files gone since the previous pass aren't kept around
+    *hashes = seen;
+
+    COUNTER_DEDUP_LINKED_FILES
+        .with_label_values(&["linked"])
+        .inc_by(linked);
+    COUNTER_DEDUP_LINKED_FILES
+        .with_label_values(&["failed"])
+        .inc_by(failed);
+    COUNTER_DEDUP_BYTES_SAVED.inc_by(saved);
+    info!(linked, failed, saved = %ByteSize::b(saved), "data deduplication complete");
+}
+
+/// Swaps the duplicate for a hardlink to the kept file, through a rename so it never goes missing
+fn link(keep: &Path, duplicate: &Path) -> eyre::Result<()> {
+    let name = duplicate
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let staging = duplicate.with_file_name(format!(".{name}.link.tmp"));
+    let _ = fs::remove_file(&staging);
+
+    fs::hard_link(keep, &staging).wrap_err_with(|| format!("failed to link {keep:?}"))?;
+    if let Err(err) = fs::rename(&staging, duplicate) {
+        let _ = fs::remove_file(&staging);
+        return Err(err).wrap_err_with(|| format!("failed to replace {duplicate:?}"));
+    }
+    Ok(())
+}
+
+fn sha256(path: &Path) -> io::Result<[u8; 32]> {
+    let mut file = File::open(path)?;
+    let mut hash = Hash::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hash.update(&buffer[..read]);
+    }
+    Ok(hash.finalize())
+}
@@ -0,0 +1,186 @@
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    time::SystemTime,
+};
+
+use clap::Args;
+use eyre::Context;
+use flate2::{Compression, write::GzEncoder};
+use nix::sys::statvfs::statvfs;
+use tracing::{info, instrument, warn};
+
+use crate::{status::unix_ms_now, supervisor::output::recent_output};
+
+/// How many newest visor_child_stderr files to include
+const STDERR_FILES: usize = 3;
+/// How much of the end of each visor_child_stderr file to include
+const STDERR_TAIL_BYTES: u64 = 1024 * 1024;
+
+#[derive(Clone, Debug, Args)]
+pub struct DiagnosticsArgs {
+    /// Directory where to write a diagnostics tarball every time the child exits with a failure. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_CRASH_DIAGNOSTICS_DIR")]
+    pub crash_diagnostics_dir: Option<PathBuf>,
+
+    /// How many last lines of captured child output to include in the diagnostics (requires --child-capture-output)
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CRASH_DIAGNOSTICS_OUTPUT_LINES",
+        default_value_t = 1000
+    )]
+    pub crash_diagnostics_output_lines: usize,
+}
+
+#[derive(Clone, Debug)]
+pub struct CrashDiagnostics {
+    pub output_dir: PathBuf,
+    pub data_directory: PathBuf,
+    pub gossip_config_path: PathBuf,
+}
+
+impl CrashDiagnostics {
+    /// Collects the bundle in a blocking task, logging instead of failing as diagnostics are best effort
+    #[instrument(skip(self))]
+    pub async fn collect(&self, pid: u32, status: ExitStatus) {
+        let diagnostics = self.clone();
+        let result =
+            tokio::task::spawn_blocking(move || diagnostics.write_bundle(pid, status)).await;
+
+        match result {
+            Ok(Ok(path)) => info!(?path, "crash diagnostics written"),
+            Ok(Err(err)) => warn!(?err, "failed to write crash diagnostics"),
+            Err(err) => warn!(?err, "crash diagnostics task failed"),
+        }
+    }
+
+    fn write_bundle(&self, pid: u32, status: ExitStatus) -> eyre::Result<PathBuf> {
+        fs::create_dir_all(&self.output_dir)
+            .wrap_err("failed to create crash diagnostics directory")?;
+
+        let name = format!("hl-crash-{}-{pid}", unix_ms_now());
+        let path = self.output_dir.join(format!("{name}.tar.gz"));
+        let file = File::create(&path).wrap_err("failed to create crash diagnostics file")?;
+        let mut tar = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        let summary = format!(
+            "pid: {pid}\nstatus: {status}\nexit_code: {:?}\n",
+            status.code()
+        );
+        append_bytes(&mut tar, &format!("{name}/summary.txt"), summary.as_bytes())?;
+
+        let output = recent_output();
+        let output = if output.is_empty() {
+            "no captured output, is --child-capture-output enabled?\n".to_string()
+        } else {
+            output.join("\n") + "\n"
+        };
+        append_bytes(&mut tar, &format!("{name}/output.log"), output.as_bytes())?;
+
+        match fs::read(&self.gossip_config_path) {
+            Ok(config) => append_bytes(
+                &mut tar,
+                &format!("{name}/override_gossip_config.json"),
+                &config,
+            )?,
+            Err(err) => {
+                warn!(?err, path = ?self.gossip_config_path, "failed to read gossip config")
+            }
+        }
+
+        append_bytes(
+            &mut tar,
+            &format!("{name}/disk_usage.txt"),
+            disk_usage(&self.data_directory).as_bytes(),
+        )?;
+
+        let stderr_dir = self.data_directory.join("hl/data/visor_child_stderr");
+        for (idx, stderr_file) in newest_files(&stderr_dir, STDERR_FILES).iter().enumerate() {
+            let Ok(tail) = read_tail(stderr_file, STDERR_TAIL_BYTES) else {
+                continue;
+            };
+            let relative = stderr_file.strip_prefix(&stderr_dir).unwrap_or(stderr_file);
+            let entry_name = format!(
+                "{name}/visor_child_stderr/{idx}-{}",
+                relative.to_string_lossy().replace('/', "_")
+            );
+            append_bytes(&mut tar, &entry_name, &tail)?;
+        }
+
+        tar.into_inner()?.finish()?;
+
+        Ok(path)
+    }
+}
+
+fn append_bytes<W: std::io::Write>(
+    tar: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+) -> eyre::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(unix_ms_now() / 1000);
+    header.set_cksum();
+    tar.append_data(&mut header, path, data)
+        .wrap_err_with(|| format!("failed to append {path} to crash diagnostics"))?;
+
+    Ok(())
+}
+
+fn disk_usage(path: &Path) -> String {
+    match statvfs(path) {
+        Ok(stat) => {
+            let fragment_size = stat.fragment_size();
+            format!(
+                "path: {}\ntotal_bytes: {}\navailable_bytes: {}\ntotal_inodes: {}\navailable_inodes: {}\n",
+                path.display(),
+                stat.blocks() * fragment_size,
+                stat.blocks_available() * fragment_size,
+                stat.files(),
+                stat.files_available(),
+            )
+        }
+        Err(err) => format!("failed to stat {}: {err}\n", path.display()),
+    }
+}
+
+/// Newest files by modification time, recursively
+fn newest_files(dir: &Path, n: usize) -> Vec<PathBuf> {
+    fn walk(dir: &Path, files: &mut Vec<(SystemTime, PathBuf)>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                walk(&entry.path(), files);
+            } else if metadata.is_file()
+                && let Ok(modified) = metadata.modified()
+            {
+                files.push((modified, entry.path()));
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(dir, &mut files);
+    files.sort_by(|a, b| b.0.cmp(&a.0));
+    files.into_iter().take(n).map(|(_, path)| path).collect()
+}
+
+fn read_tail(path: &Path, max_bytes: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(max_bytes)))?;
+
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf)?;
+    Ok(buf)
+}
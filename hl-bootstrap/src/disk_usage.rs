@@ -0,0 +1,87 @@
+use std::{
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Args;
+use duration_string::DurationString;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, warn};
+
+use crate::metrics::GAUGE_DATA_DIRECTORY_BYTES;
+
+#[derive(Clone, Debug, Args)]
+pub struct DiskUsageArgs {
+    /// How often to measure disk usage of the data subdirectories. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_DISK_USAGE_INTERVAL")]
+    pub disk_usage_interval: Option<DurationString>,
+
+    /// Subdirectories of hl/data to measure
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_DISK_USAGE_DIRECTORIES",
+        value_delimiter = ',',
+        default_value = "replica_cmds,periodic_abci_states,evm_block_and_receipts,node_order_statuses"
+    )]
+    pub disk_usage_directories: Vec<PathBuf>,
+}
+
+/// Worker task that periodically measures disk usage of the configured ${base}/hl/data subdirectories
+pub async fn disk_usage_task(
+    base_path: PathBuf,
+    directories: Vec<PathBuf>,
+    measure_interval: Duration,
+) {
+    let data_path = base_path.join("hl/data");
+
+    let mut interval = interval(measure_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        ?data_path,
+        ?directories,
+        "measuring data directory disk usage"
+    );
+    loop {
+        interval.tick().await;
+
+        for directory in &directories {
+            let path = data_path.join(directory);
+            let usage = match tokio::task::spawn_blocking({
+                let path = path.clone();
+                move || directory_size(&path)
+            })
+            .await
+            {
+                Ok(usage) => usage,
+                Err(err) => {
+                    warn!(?err, ?path, "disk usage measurement failed");
+                    continue;
+                }
+            };
+
+            debug!(?path, usage, "data directory disk usage");
+            GAUGE_DATA_DIRECTORY_BYTES
+                .with_label_values(&[&*directory.to_string_lossy()])
+                .set(usage as i64);
+        }
+    }
+}
+
+/// Allocated size of all files under the path, like du(1). Missing directory is 0 bytes.
+pub fn directory_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => directory_size(&entry.path()),
+            Ok(metadata) => metadata.blocks() * 512,
+            Err(_) => 0,
+        })
+        .sum()
+}
@@ -0,0 +1,613 @@
+use std::{
+    collections::HashSet,
+    fmt::Write,
+    fs,
+    path::PathBuf,
+    process::Command,
+    time::{Duration, SystemTime},
+};
+
+use nix::sys::resource::{Resource, getrlimit};
+use tokio::task::JoinSet;
+
+use crate::{
+    data_dir::{self, HlHome},
+    hl_gossip_config::{HyperliquidChain, fetch_hyperliquid_seed_peers},
+    hl_visor::download::binary_url,
+    monitor::{NETWORK_CLIENT, request_network_exchange_time},
+    preflight::{
+        PreflightArgs, check_clock, check_cpu_governor, check_disk, check_filesystem,
+        check_resources, check_swap, check_transparent_hugepages,
+    },
+    reachability::check_reachability,
+    reaper,
+    speedtest::{GOSSIP_PORT, measure_node_latency},
+    sysctl::{network_tuning_findings, read_sysctl},
+};
+
+/// Fingerprint of the key Hyperliquid binaries are signed with, shipped as etc/hl-pubkey.asc
+const HL_SIGNING_KEY_FINGERPRINT: &str = "CF2C2EA3DC3E8F042A55FB6503254A9349F1820B";
+
+/// hl-node keeps a lot of files and sockets open
+const MIN_OPEN_FILES: u64 = 65536;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pass => "PASS",
+            Self::Warn => "WARN",
+            Self::Fail => "FAIL",
+        }
+    }
+}
+
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// How to fix the problem, shown for warnings & failures
+    pub hint: Option<&'static str>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            hint: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, hint: &'static str) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            hint: Some(hint),
+        }
+    }
+}
+
+/// Related checks, rendered under a common title
+pub struct Section {
+    pub title: &'static str,
+    pub results: Vec<CheckResult>,
+}
+
+/// Preflight checks for the environment hl-node is about to run in
+pub struct Doctor {
+    pub network: HyperliquidChain,
+    pub data_directory: PathBuf,
+    /// Thresholds shared with the startup preflight checks
+    pub preflight: PreflightArgs,
+    /// IPv6 is tolerated when the public IP is set explicitly
+    pub ipv6_allowed: bool,
+}
+
+impl Doctor {
+    pub async fn run(&self) -> Vec<Section> {
+        let (ntp, clock, egress, binaries) = tokio::join!(
+            self.check_ntp(),
+            self.check_clock(),
+            self.check_egress(),
+            self.check_binaries()
+        );
+
+        let mut network = vec![self.check_ipv6_addresses(), egress];
+        if let Some(url) = &self.preflight.preflight_reachability_url {
+            network.push(match check_reachability(url).await {
+                Ok(detail) => CheckResult::pass("reachability", detail),
+                Err(err) => CheckResult::fail(
+                    "reachability",
+                    format!("{err:#}"),
+                    "allow inbound TCP 4000-4010 and forward the ports when behind NAT",
+                ),
+            });
+        }
+
+        vec![
+            Section {
+                title: "kernel tuning",
+                results: vec![
+                    self.check_sysctl(),
+                    check_network_tuning(),
+                    check_open_files(),
+                    check_governor(),
+                    self.check_swap(),
+                    check_thp(),
+                ],
+            },
+            Section {
+                title: "machine",
+                results: vec![
+                    self.check_data_directory(),
+                    self.check_disk(),
+                    self.check_filesystem(),
+                    self.check_resources(),
+                ],
+            },
+            Section {
+                title: "time",
+                results: vec![ntp, clock],
+            },
+            Section {
+                title: "network",
+                results: network,
+            },
+            Section {
+                title: "binaries",
+                results: vec![binaries, check_gpg()],
+            },
+        ]
+    }
+
+    fn check_sysctl(&self) -> CheckResult {
+        const NAME: &str = "sysctl";
+        const HINT: &str = "set sysctl net.ipv6.conf.all.disable_ipv6=1 (or run with --disable-ipv6), or pass --external-ipv4";
+
+        match read_sysctl("net.ipv6.conf.all.disable_ipv6").as_deref() {
+            Ok("1") => CheckResult::pass(NAME, "net.ipv6.conf.all.disable_ipv6 = 1"),
+            Ok(value) if self.ipv6_allowed => CheckResult::warn(
+                NAME,
+                format!("net.ipv6.conf.all.disable_ipv6 = {value}, ignored"),
+                HINT,
+            ),
+            Ok(value) => CheckResult::fail(
+                NAME,
+                format!("net.ipv6.conf.all.disable_ipv6 = {value}"),
+                HINT,
+            ),
+            // No IPv6 support in the kernel at all
+            Err(_) => CheckResult::pass(NAME, "IPv6 is not available"),
+        }
+    }
+
+    fn check_ipv6_addresses(&self) -> CheckResult {
+        const NAME: &str = "ipv6";
+
+        // Address, interface index, prefix length, scope (00 is global), flags, interface name
+        let global_addresses = fs::read_to_string("/proc/net/if_inet6")
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| line.split_whitespace().nth(3) == Some("00"))
+            .count();
+        if global_addresses == 0 {
+            CheckResult::pass(NAME, "no global IPv6 addresses")
+        } else {
+            CheckResult::warn(
+                NAME,
+                format!("{global_addresses} global IPv6 addresses configured"),
+                "hl-node can pick up an IPv6 address as its public IP, remove them or pass --external-ipv4",
+            )
+        }
+    }
+
+    fn check_data_directory(&self) -> CheckResult {
+        const NAME: &str = "data-dir";
+        const HINT: &str = "pass the directory containing hl/ as --data-dir";
+
+        let path = self.data_directory.display();
+        match data_dir::inspect(&self.data_directory) {
+            Ok(HlHome::Existing) => CheckResult::pass(NAME, format!("{path} contains hl/")),
+            Ok(HlHome::Fresh) => {
+                CheckResult::pass(NAME, format!("{path} is empty, hl-node will initialize it"))
+            }
+            Ok(HlHome::Unrecognized) => {
+                CheckResult::warn(NAME, format!("{path} has files but no hl/"), HINT)
+            }
+            Err(err) => CheckResult::fail(NAME, format!("{err:#}"), HINT),
+        }
+    }
+
+    fn check_disk(&self) -> CheckResult {
+        const NAME: &str = "disk";
+
+        match check_disk(
+            &self.data_directory,
+            self.preflight.preflight_disk_min_free,
+            self.preflight.preflight_disk_min_inodes,
+        ) {
+            Ok(detail) => CheckResult::pass(NAME, detail),
+            Err(err) => CheckResult::fail(
+                NAME,
+                format!("{err:#}"),
+                "free up space or enable pruning with --prune-data-interval",
+            ),
+        }
+    }
+
+    fn check_resources(&self) -> CheckResult {
+        const NAME: &str = "resources";
+
+        match check_resources(
+            self.preflight.preflight_min_cpus,
+            self.preflight.preflight_min_memory,
+            &self.preflight.preflight_cpu_features,
+        ) {
+            Ok(detail) => CheckResult::pass(NAME, detail),
+            Err(err) => CheckResult::warn(
+                NAME,
+                format!("{err:#}"),
+                "use a bigger machine, or pass --node-role matching the node",
+            ),
+        }
+    }
+
+    fn check_filesystem(&self) -> CheckResult {
+        const NAME: &str = "filesystem";
+
+        match check_filesystem(&self.data_directory) {
+            Ok(detail) => CheckResult::pass(NAME, detail),
+            Err(err) => CheckResult::warn(
+                NAME,
+                format!("{err:#}"),
+                "move the data directory to local storage",
+            ),
+        }
+    }
+
+    fn check_swap(&self) -> CheckResult {
+        const NAME: &str = "swap";
+
+        match check_swap(self.preflight.preflight_max_swappiness, false) {
+            Ok(detail) => CheckResult::pass(NAME, detail),
+            Err(err) => CheckResult::warn(
+                NAME,
+                format!("{err:#}"),
+                "remove swap from /etc/fstab and persist vm.swappiness in /etc/sysctl.d",
+            ),
+        }
+    }
+
+    async fn check_ntp(&self) -> CheckResult {
+        const NAME: &str = "ntp";
+
+        match check_clock(
+            &self.preflight.preflight_ntp_server,
+            self.preflight.preflight_clock_max_skew.into(),
+        )
+        .await
+        {
+            Ok(detail) => CheckResult::pass(NAME, detail),
+            Err(err) => CheckResult::fail(
+                NAME,
+                format!("{err:#}"),
+                "enable time synchronization, e.g. chrony or systemd-timesyncd",
+            ),
+        }
+    }
+
+    async fn check_clock(&self) -> CheckResult {
+        const NAME: &str = "clock";
+        const HINT: &str = "enable time synchronization, e.g. chrony or systemd-timesyncd";
+
+        let network_ms = match request_network_exchange_time(self.network).await {
+            Ok(time) => time,
+            Err(err) => {
+                return CheckResult::warn(
+                    NAME,
+                    format!("failed to request exchange time: {err}"),
+                    "check connectivity to the Hyperliquid API",
+                );
+            }
+        };
+        let local_ms = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let offset = Duration::from_millis(local_ms.abs_diff(network_ms));
+        let detail = format!("{offset:?} off the exchange time");
+        // Exchange time trails wall time a bit by itself
+        if offset < Duration::from_secs(2) {
+            CheckResult::pass(NAME, detail)
+        } else if offset < Duration::from_secs(10) {
+            CheckResult::warn(NAME, detail, HINT)
+        } else {
+            CheckResult::fail(NAME, detail, HINT)
+        }
+    }
+
+    async fn check_egress(&self) -> CheckResult {
+        const NAME: &str = "egress";
+
+        let seed_peers = match fetch_hyperliquid_seed_peers(self.network, &HashSet::new()).await {
+            Ok(seed_peers) => seed_peers,
+            Err(err) => {
+                return CheckResult::fail(
+                    NAME,
+                    format!("failed to fetch seed peers: {err:#}"),
+                    "allow outbound HTTPS to the Hyperliquid API",
+                );
+            }
+        };
+
+        let mut tasks = JoinSet::new();
+        for seed_peer in seed_peers.iter().take(10) {
+            let ip = seed_peer.ip;
+            tasks.spawn(async move {
+                measure_node_latency(ip, GOSSIP_PORT, Duration::from_secs(2)).await
+            });
+        }
+        let attempted = tasks.len();
+        let reachable = tasks
+            .join_all()
+            .await
+            .into_iter()
+            .filter(Result::is_ok)
+            .count();
+
+        let detail = format!("{reachable}/{attempted} seed peers reachable on port {GOSSIP_PORT}");
+        if reachable > 0 {
+            CheckResult::pass(NAME, detail)
+        } else {
+            CheckResult::fail(NAME, detail, "allow outbound TCP to ports 4000-4010")
+        }
+    }
+
+    async fn check_binaries(&self) -> CheckResult {
+        const NAME: &str = "binaries";
+
+        let url = binary_url(self.network, "hl-visor");
+        match NETWORK_CLIENT
+            .head(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+        {
+            Ok(_) => CheckResult::pass(NAME, format!("{url} is reachable")),
+            Err(err) => CheckResult::fail(
+                NAME,
+                format!("failed to reach {url}: {err}"),
+                "allow outbound HTTPS to the Hyperliquid binaries host",
+            ),
+        }
+    }
+}
+
+fn check_open_files() -> CheckResult {
+    const NAME: &str = "ulimit";
+    const HINT: &str =
+        "raise the hard open files limit, e.g. LimitNOFILE=1048576 or --ulimit nofile=1048576";
+
+    match getrlimit(Resource::RLIMIT_NOFILE) {
+        Ok((soft, hard)) => {
+            let detail =
+                format!("open files: {soft} (hard: {hard}), recommended: {MIN_OPEN_FILES}");
+            // hl-bootstrap raises the soft limit up to the hard limit before starting the child
+            if soft >= MIN_OPEN_FILES || hard >= MIN_OPEN_FILES {
+                CheckResult::pass(NAME, detail)
+            } else {
+                CheckResult::warn(NAME, detail, HINT)
+            }
+        }
+        Err(err) => CheckResult::warn(NAME, format!("failed to get open files limit: {err}"), HINT),
+    }
+}
+
+fn check_governor() -> CheckResult {
+    const NAME: &str = "cpu-governor";
+
+    match check_cpu_governor() {
+        Ok(detail) => CheckResult::pass(NAME, detail),
+        Err(err) => CheckResult::warn(
+            NAME,
+            format!("{err:#}"),
+            "set the performance governor, especially on validators",
+        ),
+    }
+}
+
+fn check_thp() -> CheckResult {
+    const NAME: &str = "thp";
+
+    match check_transparent_hugepages(false) {
+        Ok(detail) => CheckResult::pass(NAME, detail),
+        Err(err) => CheckResult::warn(
+            NAME,
+            format!("{err:#}"),
+            "persist the setting with transparent_hugepage=madvise on the kernel command line",
+        ),
+    }
+}
+
+fn check_network_tuning() -> CheckResult {
+    const NAME: &str = "network";
+
+    let findings = network_tuning_findings();
+    if findings.is_empty() {
+        CheckResult::pass(NAME, "conntrack, socket buffers & backlogs are tuned")
+    } else {
+        CheckResult::warn(
+            NAME,
+            findings.join(", "),
+            "raise the values with sysctl, or run with --apply-sysctls",
+        )
+    }
+}
+
+fn check_gpg() -> CheckResult {
+    const NAME: &str = "gpg";
+    const HINT: &str = "install gpg and import the signing key: gpg --import etc/hl-pubkey.asc";
+
+    if which::which("gpg").is_err() {
+        return CheckResult::fail(NAME, "gpg not found in PATH", HINT);
+    }
+
+    match reaper::output(Command::new("gpg").args([
+        "--batch",
+        "--list-keys",
+        HL_SIGNING_KEY_FINGERPRINT,
+    ])) {
+        Ok(output) if output.status.success() => {
+            CheckResult::pass(NAME, "Hyperliquid signing key is imported")
+        }
+        Ok(_) => CheckResult::fail(NAME, "Hyperliquid signing key is not imported", HINT),
+        Err(err) => CheckResult::fail(NAME, format!("failed to run gpg: {err}"), HINT),
+    }
+}
+
+/// Failures exit with 1, warnings alone don't affect the exit code
+pub fn exit_code(sections: &[Section]) -> i32 {
+    let failed = sections
+        .iter()
+        .flat_map(|section| &section.results)
+        .any(|result| result.status == CheckStatus::Fail);
+    if failed { 1 } else { 0 }
+}
+
+pub fn render(sections: &[Section]) -> String {
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{}:", section.title);
+        render_results(&mut out, &section.results);
+    }
+    out
+}
+
+fn render_results(out: &mut String, results: &[CheckResult]) {
+    for result in results {
+        let _ = writeln!(
+            out,
+            "[{}] {}: {}",
+            result.status.as_str(),
+            result.name,
+            result.detail
+        );
+        if result.status != CheckStatus::Pass
+            && let Some(hint) = result.hint
+        {
+            let _ = writeln!(out, "       hint: {hint}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytesize::ByteSize;
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        preflight: PreflightArgs,
+    }
+
+    fn doctor(data_directory: PathBuf) -> Doctor {
+        Doctor {
+            network: HyperliquidChain::Mainnet,
+            data_directory,
+            preflight: TestCli::parse_from(["test"]).preflight,
+            ipv6_allowed: false,
+        }
+    }
+
+    #[test]
+    fn test_check_data_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let missing = doctor(directory.path().join("missing"));
+        assert_eq!(missing.check_data_directory().status, CheckStatus::Fail);
+
+        let doctor = doctor(directory.path().to_path_buf());
+        assert_eq!(doctor.check_data_directory().status, CheckStatus::Pass);
+
+        fs::write(directory.path().join("stray"), "").unwrap();
+        let result = doctor.check_data_directory();
+        assert_eq!(result.status, CheckStatus::Warn);
+        assert!(result.hint.is_some());
+
+        fs::create_dir(directory.path().join("hl")).unwrap();
+        assert_eq!(doctor.check_data_directory().status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_check_disk() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut doctor = doctor(directory.path().to_path_buf());
+        doctor.preflight.preflight_disk_min_free = ByteSize::b(0);
+        doctor.preflight.preflight_disk_min_inodes = 0;
+        assert_eq!(doctor.check_disk().status, CheckStatus::Pass);
+
+        doctor.preflight.preflight_disk_min_free = ByteSize::pib(1024);
+        assert_eq!(doctor.check_disk().status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn test_check_resources() {
+        let mut doctor = doctor(PathBuf::from("/"));
+        doctor.preflight.preflight_min_cpus = 0;
+        doctor.preflight.preflight_min_memory = ByteSize::b(0);
+        assert_eq!(doctor.check_resources().status, CheckStatus::Pass);
+
+        // Too small a machine is only a warning
+        doctor.preflight.preflight_min_cpus = usize::MAX;
+        assert_eq!(doctor.check_resources().status, CheckStatus::Warn);
+    }
+
+    #[test]
+    fn test_exit_code() {
+        let section = |results| Section {
+            title: "test",
+            results,
+        };
+
+        assert_eq!(exit_code(&[]), 0);
+        assert_eq!(
+            exit_code(&[
+                section(vec![CheckResult::pass("a", "ok")]),
+                section(vec![CheckResult::warn("b", "meh", "hint")]),
+            ]),
+            0
+        );
+        assert_eq!(
+            exit_code(&[
+                section(vec![CheckResult::pass("a", "ok")]),
+                section(vec![
+                    CheckResult::warn("b", "meh", "hint"),
+                    CheckResult::fail("c", "broken", "hint"),
+                ]),
+            ]),
+            1
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let out = render(&[
+            Section {
+                title: "first",
+                results: vec![CheckResult::pass("a", "ok")],
+            },
+            Section {
+                title: "second",
+                results: vec![CheckResult::fail("b", "broken", "fix it")],
+            },
+        ]);
+        assert_eq!(
+            out,
+            "first:\n[PASS] a: ok\n\nsecond:\n[FAIL] b: broken\n       hint: fix it\n"
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Json, Router,
+    extract::{self, Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{
+    compress::{self, COMPRESSED_SUFFIX, uncompressed_name},
+    data_api::parse_node_time,
+    monitor::height::last_lines,
+};
+
+/// Blocks returned by /blocks without a limit
+const DEFAULT_RECENT_BLOCKS: usize = 10;
+
+/// Read-only views of the blocks hl-node applied, from replica_cmds, for debugging what the own node did
+#[derive(Clone)]
+struct Explorer {
+    replica_cmds: PathBuf,
+    max_limit: usize,
+    max_range: Duration,
+}
+
+/// Served next to fills & trades by the data API
+pub fn router(data_directory: &Path, max_limit: usize, max_range: Duration) -> Router {
+    Router::new()
+        .route("/blocks", get(recent_blocks))
+        .route("/blocks/{height}", get(block))
+        .route("/txs/{hash}", get(tx))
+        .with_state(Explorer {
+            replica_cmds: data_directory.join("hl/data/replica_cmds"),
+            max_limit,
+            max_range,
+        })
+}
+
+#[derive(Debug, Deserialize)]
+struct RecentQuery {
+    limit: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct BlockSummary {
+    height: Option<u64>,
+    /// Unix milliseconds
+    time: Option<i64>,
+    proposer: Option<String>,
+    bundles: usize,
+    actions: usize,
+}
+
+impl BlockSummary {
+    fn new(block: &Value) -> Self {
+        let abci_block = &block["abci_block"];
+        let bundles = abci_block["signed_action_bundles"].as_array();
+        Self {
+            height: abci_block["round"].as_u64(),
+            time: abci_block["time"].as_str().and_then(parse_node_time),
+            proposer: abci_block["proposer"].as_str().map(str::to_string),
+            bundles: bundles.map_or(0, Vec::len),
+            actions: bundles.into_iter().flatten().map(actions).sum(),
+        }
+    }
+}
+
+fn actions(bundle: &Value) -> usize {
+    bundle[1]["signed_actions"].as_array().map_or(0, Vec::len)
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockHeight {
+    abci_block: RoundOnly,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoundOnly {
+    round: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct Tx {
+    hash: String,
+    height: Option<u64>,
+    /// Unix milliseconds
+    time: Option<i64>,
+    bundle: Value,
+}
+
+async fn recent_blocks(
+    State(explorer): State<Explorer>,
+    Query(query): Query<RecentQuery>,
+) -> Response {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_RECENT_BLOCKS)
+        .clamp(1, explorer.max_limit);
+    let result = tokio::task::spawn_blocking(move || {
+        let files = replica_files(&explorer.replica_cmds);
+        let mut blocks = Vec::new();
+        // The newest file may have just been started, in which case the rest comes from the one before
+        for path in files.iter().rev() {
+            let lines = match tail(path, limit - blocks.len()) {
+                Ok(lines) => lines,
+                Err(err) => {
+                    debug!(?err, ?path, "failed to read replica_cmds file");
+                    continue;
+                }
+            };
+            for line in lines.iter().rev() {
+                if let Ok(block) = serde_json::from_slice::<Value>(line) {
+                    blocks.push(BlockSummary::new(&block));
+                }
+            }
+            if blocks.len() >= limit {
+                break;
+            }
+        }
+        blocks
+    })
+    .await;
+
+    match result {
+        Ok(blocks) => Json(blocks).into_response(),
+        Err(err) => {
+            warn!(?err, "failed to read recent blocks");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+async fn block(
+    State(explorer): State<Explorer>,
+    extract::Path(height): extract::Path<u64>,
+) -> Response {
+    let result = tokio::task::spawn_blocking(move || {
+        let files = replica_files(&explorer.replica_cmds);
+        // Files are named after their first block
+        let index = files.partition_point(|path| file_height(path).is_some_and(|h| h <= height));
+        let path = files.get(index.checked_sub(1)?)?;
+        let file = compress::open(path).ok()?;
+        debug!(?path, height, "looking up block");
+        file.lines().map_while(Result::ok).find(|line| {
+            serde_json::from_str::<BlockHeight>(line)
+                .is_ok_and(|block| block.abci_block.round == height)
+        })
+    })
+    .await;
+
+    match result {
+        // Lines are passed through as written by hl-node
+        Ok(Some(line)) => ([(CONTENT_TYPE, "application/json")], line).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "block not found in replica_cmds").into_response(),
+        Err(err) => {
+            warn!(?err, height, "failed to read block");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Looks the hash up among signed action bundles, newest first, in files written within --data-api-max-range
+async fn tx(
+    State(explorer): State<Explorer>,
+    extract::Path(hash): extract::Path<String>,
+) -> Response {
+    let hash = hash.to_ascii_lowercase();
+    if hash.len() != 66 || !hash.starts_with("0x") {
+        return (
+            StatusCode::BAD_REQUEST,
+            "hash has to be 0x followed by 64 hex digits",
+        )
+            .into_response();
+    }
+    let needle = hash.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        let (hash, files) = (needle, replica_files(&explorer.replica_cmds));
+        let now = SystemTime::now();
+        for path in files.iter().rev() {
+            let recent = fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .is_ok_and(|modified| {
+                    now.duration_since(modified).unwrap_or_default() <= explorer.max_range
+                });
+            if !recent {
+                break;
+            }
+            let Ok(file) = compress::open(path) else {
+                continue;
+            };
+            debug!(?path, hash, "looking up tx");
+            for line in file.lines().map_while(Result::ok) {
+                // Most blocks don't contain it, which is cheaper to rule out than to parse them
+                if !line.contains(&hash) {
+                    continue;
+                }
+                if let Some(tx) = find_tx(&line, &hash) {
+                    return Some(tx);
+                }
+            }
+        }
+        None
+    })
+    .await;
+
+    match result {
+        Ok(Some(tx)) => Json(tx).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, "tx not found in recent replica_cmds").into_response(),
+        Err(err) => {
+            warn!(?err, hash, "failed to look up tx");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn find_tx(line: &str, hash: &str) -> Option<Tx> {
+    let block: Value = serde_json::from_str(line).ok()?;
+    let summary = BlockSummary::new(&block);
+    let bundle = block["abci_block"]["signed_action_bundles"]
+        .as_array()?
+        .iter()
+        .find(|bundle| {
+            bundle[0]
+                .as_str()
+                .is_some_and(|bundle_hash| bundle_hash.eq_ignore_ascii_case(hash))
+        })?;
+    Some(Tx {
+        hash: hash.to_string(),
+        height: summary.height,
+        time: summary.time,
+        bundle: bundle[1].clone(),
+    })
+}
+
+/// Last complete lines of the file, oldest first. Compressed files can't be read backwards, so they're read whole
+fn tail(path: &Path, count: usize) -> std::io::Result<Vec<Vec<u8>>> {
+    if !path.to_string_lossy().ends_with(COMPRESSED_SUFFIX) {
+        return last_lines(path, count);
+    }
+    let mut lines = VecDeque::with_capacity(count + 1);
+    for line in compress::open(path)?.split(b'\n') {
+        lines.push_back(line?);
+        if lines.len() > count {
+            lines.pop_front();
+        }
+    }
+    Ok(lines.into())
+}
+
+/// replica_cmds/<session start>/<YYYYMMDD>/<height> files, compressed or not, by height
+fn replica_files(replica_cmds: &Path) -> Vec<PathBuf> {
+    let entries = |path: &Path| {
+        fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+    };
+    let mut files: Vec<_> = entries(replica_cmds)
+        .flat_map(|session| entries(&session).collect::<Vec<_>>())
+        .flat_map(|day| entries(&day).collect::<Vec<_>>())
+        .filter_map(|path| Some((file_height(&path)?, path)))
+        .collect();
+    files.sort_unstable();
+    // Both exist for a moment while a file is being compressed
+    files.dedup_by_key(|(height, _)| *height);
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+fn file_height(path: &Path) -> Option<u64> {
+    uncompressed_name(path.file_name()?.to_str()?).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_tx() {
+        let hash = format!("0x{}", "ab".repeat(32));
+        let line = format!(
+            r#"{{"abci_block":{{"time":"2025-06-15T15:06:40.123456789","round":628178000,"proposer":"0x01","signed_action_bundles":[["0x{}",{{"signed_actions":[{{}}]}}],["{hash}",{{"signed_actions":[{{}},{{}}]}}]]}},"resps":null}}"#,
+            "cd".repeat(32)
+        );
+        let block: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            BlockSummary::new(&block),
+            BlockSummary {
+                height: Some(628178000),
+                time: Some(1750000000123),
+                proposer: Some("0x01".to_string()),
+                bundles: 2,
+                actions: 3,
+            }
+        );
+
+        let tx = find_tx(&line, &hash).unwrap();
+        assert_eq!(tx.height, Some(628178000));
+        assert_eq!(tx.bundle["signed_actions"].as_array().unwrap().len(), 2);
+        assert!(find_tx(&line, &format!("0x{}", "ef".repeat(32))).is_none());
+    }
+}
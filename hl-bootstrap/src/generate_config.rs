@@ -0,0 +1,107 @@
+use std::{
+    fs,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use eyre::{Context, bail};
+use serde::Serialize;
+
+use crate::{
+    hl_gossip_config::{HyperliquidChain, NodeIp, OverrideGossipConfig},
+    hl_visor::config::VisorConfig,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct GenerateConfigArgs {
+    /// Directory to write visor.json, hl-bootstrap.toml and with --with-seed-peers override_gossip_config.json into
+    #[arg(long, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Overwrite existing files
+    #[arg(long)]
+    pub force: bool,
+
+    /// Measure seed peers of the network and fill them into override_gossip_config.json, instead of leaving it for
+    /// hl-bootstrap to set up on the first start
+    #[arg(long)]
+    pub with_seed_peers: bool,
+}
+
+/// hl-bootstrap configuration file contents, see [crate::config]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct StarterConfig {
+    pub network: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visor_binary_directory: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_dir: Option<PathBuf>,
+    pub override_gossip_config_path: PathBuf,
+    pub override_gossip_config_max_age: String,
+    pub seed_peers_amount: usize,
+    pub seed_peers_max_latency: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reserved_peers: Vec<Ipv4Addr>,
+    pub try_new_peers: bool,
+}
+
+pub struct GeneratedConfig {
+    pub visor_config: String,
+    /// Only with seed peers, startup keeps a fresh config without peers instead of measuring them
+    pub gossip_config: Option<String>,
+    pub bootstrap_config: String,
+}
+
+impl GeneratedConfig {
+    pub fn new(
+        network: HyperliquidChain,
+        bootstrap_config: &StarterConfig,
+        seed_peers: &[Ipv4Addr],
+    ) -> eyre::Result<Self> {
+        let gossip_config = if seed_peers.is_empty() {
+            None
+        } else {
+            let mut gossip_config =
+                OverrideGossipConfig::new(network, bootstrap_config.try_new_peers);
+            gossip_config.root_node_ips = seed_peers.iter().map(|&ip| NodeIp { ip }).collect();
+            gossip_config.reserved_peer_ips = bootstrap_config.reserved_peers.clone();
+            Some(serde_json::to_string_pretty(&gossip_config)?)
+        };
+
+        Ok(Self {
+            visor_config: serde_json::to_string_pretty(&VisorConfig { chain: network })?,
+            gossip_config,
+            bootstrap_config: toml::to_string(bootstrap_config)
+                .wrap_err("failed to serialize hl-bootstrap configuration")?,
+        })
+    }
+
+    /// Writes all files into the directory, refusing to overwrite anything unless forced
+    pub fn write(&self, directory: &Path, force: bool) -> eyre::Result<Vec<PathBuf>> {
+        let files: Vec<_> = [
+            Some((directory.join("visor.json"), &self.visor_config)),
+            self.gossip_config
+                .as_ref()
+                .map(|config| (directory.join("override_gossip_config.json"), config)),
+            Some((directory.join("hl-bootstrap.toml"), &self.bootstrap_config)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        if !force && let Some((path, _)) = files.iter().find(|(path, _)| path.exists()) {
+            bail!("{path:?} already exists, pass --force to overwrite");
+        }
+
+        fs::create_dir_all(directory)
+            .wrap_err_with(|| format!("failed to create directory {directory:?}"))?;
+        for (path, contents) in &files {
+            fs::write(path, format!("{}\n", contents.trim_end()))
+                .wrap_err_with(|| format!("failed to write {path:?}"))?;
+        }
+
+        Ok(files.into_iter().map(|(path, _)| path).collect())
+    }
+}
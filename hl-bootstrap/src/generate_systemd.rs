@@ -0,0 +1,246 @@
+use std::{
+    collections::BTreeSet,
+    ffi::OsString,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, bail};
+
+/// Environment variables with these in their name aren't written into the world-readable unit file
+const SECRET_MARKERS: [&str; 6] = [
+    "SECRET",
+    "TOKEN",
+    "PASSWORD",
+    "CREDENTIALS",
+    "API_KEYS",
+    "SIGNER_KEY",
+];
+
+#[derive(Clone, Debug, Args)]
+pub struct GenerateSystemdArgs {
+    /// Path to write the unit to, e.g. `/etc/systemd/system/hl-bootstrap.service`. Printed when unset
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Overwrite an existing unit
+    #[arg(long)]
+    pub force: bool,
+
+    /// User to run the service as. Ignored with --run-as-user, which needs hl-bootstrap to start as root
+    #[arg(long, default_value = "hyperliquid")]
+    pub user: String,
+
+    /// Restart the service when hl-bootstrap doesn't report healthy to systemd for this long
+    #[arg(long, default_value = "5m")]
+    pub watchdog: DurationString,
+
+    /// Args for the child, as given to `hl-bootstrap run`. Defaults to the --node-role preset
+    #[arg(last = true)]
+    pub args: Vec<OsString>,
+}
+
+/// Everything the generated unit depends on, resolved from the current flags & configuration
+#[derive(Debug)]
+pub struct SystemdUnit {
+    pub description: String,
+    /// hl-bootstrap binary, global flags & child args
+    pub exec_start: Vec<String>,
+    /// Unset when hl-bootstrap has to start as root
+    pub user: Option<String>,
+    pub working_directory: PathBuf,
+    pub read_write_paths: BTreeSet<PathBuf>,
+    pub environment: Vec<(String, String)>,
+    pub watchdog: Duration,
+    pub open_files_limit: u64,
+    /// --apply-sysctls & --disable-ipv6 write to /proc/sys
+    pub writes_sysctls: bool,
+    /// Child cgroup limits need a delegated cgroup subtree
+    pub delegate_cgroup: bool,
+}
+
+impl SystemdUnit {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Generated by `hl-bootstrap generate-systemd`");
+        let _ = writeln!(out, "[Unit]");
+        let _ = writeln!(out, "Description={}", self.description);
+        let _ = writeln!(out, "Wants=network-online.target");
+        let _ = writeln!(out, "After=network-online.target");
+
+        let _ = writeln!(out, "\n[Service]");
+        // hl-bootstrap reports ready once the child runs, and pings the watchdog while it's healthy
+        let _ = writeln!(out, "Type=notify");
+        let _ = writeln!(out, "NotifyAccess=main");
+        // Unlike other settings, command lines expand $VARIABLES
+        let exec_start: Vec<_> = self
+            .exec_start
+            .iter()
+            .map(|arg| quote(&arg.replace('$', "$$")))
+            .collect();
+        let _ = writeln!(out, "ExecStart={}", exec_start.join(" "));
+        let _ = writeln!(out, "ExecReload=/bin/kill -HUP $MAINPID");
+        let _ = writeln!(
+            out,
+            "WorkingDirectory={}",
+            escape_specifiers(&self.working_directory.to_string_lossy())
+        );
+        for (key, value) in &self.environment {
+            let _ = writeln!(out, "Environment={}", quote(&format!("{key}={value}")));
+        }
+        let _ = writeln!(out, "EnvironmentFile=-/etc/default/hl-bootstrap");
+        if let Some(user) = &self.user {
+            let _ = writeln!(out, "User={user}");
+            let _ = writeln!(out, "Group={user}");
+        }
+
+        // Snapshot restores & the initial sync take a while, hl-bootstrap stops hl-visor itself
+        let _ = writeln!(out, "Restart=on-failure");
+        let _ = writeln!(out, "RestartSec=10s");
+        let _ = writeln!(out, "TimeoutStartSec=infinity");
+        let _ = writeln!(out, "TimeoutStopSec=90s");
+        let _ = writeln!(out, "KillMode=mixed");
+        let _ = writeln!(out, "WatchdogSec={}", self.watchdog.as_secs().max(1));
+        let _ = writeln!(out, "LimitNOFILE={}", self.open_files_limit);
+
+        let _ = writeln!(out, "\nNoNewPrivileges=yes");
+        let _ = writeln!(out, "ProtectSystem=strict");
+        // Paths under /home stay writable through ReadWritePaths only when it isn't hidden entirely
+        let under_home = self
+            .read_write_paths
+            .iter()
+            .any(|path| path.starts_with("/home") || path.starts_with("/root"));
+        let _ = writeln!(
+            out,
+            "ProtectHome={}",
+            if under_home { "read-only" } else { "yes" }
+        );
+        for path in &self.read_write_paths {
+            let _ = writeln!(
+                out,
+                "ReadWritePaths={}",
+                escape_specifiers(&path.to_string_lossy())
+            );
+        }
+        let _ = writeln!(out, "PrivateTmp=yes");
+        let _ = writeln!(out, "PrivateDevices=yes");
+        if !self.writes_sysctls {
+            let _ = writeln!(out, "ProtectKernelTunables=yes");
+        }
+        let _ = writeln!(out, "ProtectKernelModules=yes");
+        let _ = writeln!(out, "ProtectKernelLogs=yes");
+        if self.delegate_cgroup {
+            let _ = writeln!(out, "Delegate=yes");
+        } else {
+            let _ = writeln!(out, "ProtectControlGroups=yes");
+        }
+        let _ = writeln!(out, "ProtectClock=yes");
+        let _ = writeln!(out, "ProtectHostname=yes");
+        let _ = writeln!(out, "RestrictSUIDSGID=yes");
+        let _ = writeln!(out, "RestrictRealtime=yes");
+        let _ = writeln!(out, "RestrictNamespaces=yes");
+        let _ = writeln!(out, "LockPersonality=yes");
+        let _ = writeln!(
+            out,
+            "RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6 AF_NETLINK"
+        );
+        let _ = writeln!(out, "SystemCallArchitectures=native");
+        if self.user.is_some() {
+            let _ = writeln!(out, "CapabilityBoundingSet=");
+        }
+
+        let _ = writeln!(out, "\n[Install]");
+        let _ = writeln!(out, "WantedBy=multi-user.target");
+        out
+    }
+
+    pub fn write(&self, path: &Path, force: bool) -> eyre::Result<()> {
+        if !force && path.exists() {
+            bail!("{path:?} already exists, pass --force to overwrite");
+        }
+        fs::write(path, self.render()).wrap_err_with(|| format!("failed to write {path:?}"))
+    }
+}
+
+/// HL_BOOTSTRAP_* variables of the current environment, and the names of secrets left out of them
+pub fn bootstrap_environment() -> (Vec<(String, String)>, Vec<String>) {
+    let mut environment: Vec<_> = std::env::vars()
+        .filter(|(key, _)| key.starts_with("HL_BOOTSTRAP_"))
+        .collect();
+    environment.sort_unstable();
+    let (secrets, environment) = environment
+        .into_iter()
+        .partition(|(key, _)| SECRET_MARKERS.iter().any(|marker| key.contains(marker)));
+    (
+        environment,
+        secrets
+            .into_iter()
+            .map(|(key, _): (String, _)| key)
+            .collect(),
+    )
+}
+
+/// Global flags hl-bootstrap was invoked with, i.e. the args before the subcommand
+pub fn global_args(subcommand: &str) -> Vec<String> {
+    std::env::args()
+        .skip(1)
+        .take_while(|arg| arg != subcommand)
+        .collect()
+}
+
+/// Quotes the value for systemd's command line & environment parsing when needed
+fn quote(value: &str) -> String {
+    let escaped = escape_specifiers(value);
+    if !escaped.is_empty()
+        && !escaped
+            .chars()
+            .any(|char| char.is_whitespace() || matches!(char, '"' | '\'' | '\\' | ';'))
+    {
+        return escaped;
+    }
+    format!("\"{}\"", escaped.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `%` starts a specifier like `%h` in unit files
+fn escape_specifiers(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let unit = SystemdUnit {
+            description: "Hyperliquid node (Mainnet)".to_string(),
+            exec_start: vec![
+                "/usr/local/bin/hl-bootstrap".to_string(),
+                "--config=/etc/hl bootstrap.toml".to_string(),
+                "--".to_string(),
+                "run-non-validator".to_string(),
+            ],
+            user: Some("hyperliquid".to_string()),
+            working_directory: PathBuf::from("/home/hyperliquid"),
+            read_write_paths: BTreeSet::from([PathBuf::from("/home/hyperliquid")]),
+            environment: vec![("HL_BOOTSTRAP_CHILD_ENV".to_string(), "A=1;B=%x".to_string())],
+            watchdog: Duration::from_secs(300),
+            open_files_limit: 1048576,
+            writes_sysctls: true,
+            delegate_cgroup: false,
+        };
+        let rendered = unit.render();
+        assert!(rendered.contains(
+            "ExecStart=/usr/local/bin/hl-bootstrap \"--config=/etc/hl bootstrap.toml\" -- run-non-validator\n"
+        ));
+        assert!(rendered.contains("Environment=\"HL_BOOTSTRAP_CHILD_ENV=A=1;B=%%x\"\n"));
+        assert!(rendered.contains("WatchdogSec=300\n"));
+        assert!(rendered.contains("ProtectHome=read-only\n"));
+        assert!(!rendered.contains("ProtectKernelTunables"));
+        assert!(rendered.contains("ProtectControlGroups=yes\n"));
+    }
+}
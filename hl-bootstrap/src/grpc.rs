@@ -0,0 +1,258 @@
+use std::{
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use tokio::{sync::watch, task::JoinSet};
+use tonic::{Request, Response, Status, transport::Server};
+use tracing::{info, warn};
+
+use crate::{
+    auth::{self, Access, Denied, Scope},
+    backup::{BackupArgs, run_backup},
+    hl_gossip_config::OverrideGossipConfig,
+    prune::prune_data,
+    reload::Tunables,
+    speedtest::{GOSSIP_PORT, measure_node_latency},
+    status::{self, BootstrapStatus},
+    storage::Bucket,
+};
+
+pub mod proto {
+    tonic::include_proto!("hl_bootstrap.v1");
+}
+
+use proto::control_server::{Control, ControlServer};
+
+#[derive(Clone, Debug, Args)]
+pub struct GrpcArgs {
+    /// Address to serve the gRPC control API on (status, peers, snapshots & pruning), as defined in
+    /// `proto/hl_bootstrap/v1/control.proto`. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_GRPC_LISTEN_ADDRESS")]
+    pub grpc_listen_address: Option<SocketAddr>,
+}
+
+struct ControlService {
+    data_directory: PathBuf,
+    gossip_config_path: PathBuf,
+    backup: BackupArgs,
+    /// Unset without object storage, snapshots are refused then
+    bucket: Option<Bucket>,
+    tunables: watch::Receiver<Tunables>,
+}
+
+pub async fn run_grpc_server(
+    listen_address: SocketAddr,
+    data_directory: PathBuf,
+    gossip_config_path: PathBuf,
+    backup: BackupArgs,
+    bucket: Option<Bucket>,
+    tunables: watch::Receiver<Tunables>,
+    access: Access,
+) -> eyre::Result<()> {
+    let service = ControlService {
+        data_directory,
+        gossip_config_path,
+        backup,
+        bucket,
+        tunables,
+    };
+
+    info!(%listen_address, "serving gRPC control API");
+    Server::builder()
+        .add_service(ControlServer::with_interceptor(
+            service,
+            move |request: Request<()>| authenticate(&access, request),
+        ))
+        .serve(listen_address)
+        .await?;
+
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl Control for ControlService {
+    async fn get_status(
+        &self,
+        _request: Request<proto::GetStatusRequest>,
+    ) -> Result<Response<proto::GetStatusResponse>, Status> {
+        Ok(Response::new(status_response(&status::snapshot())))
+    }
+
+    async fn list_peers(
+        &self,
+        _request: Request<proto::ListPeersRequest>,
+    ) -> Result<Response<proto::ListPeersResponse>, Status> {
+        let contents = tokio::fs::read(&self.gossip_config_path)
+            .await
+            .map_err(|err| Status::unavailable(format!("failed to read gossip config: {err}")))?;
+        let config: OverrideGossipConfig = serde_json::from_slice(&contents)
+            .map_err(|err| Status::internal(format!("failed to parse gossip config: {err}")))?;
+
+        let (timeout_duration, ignored_peers) = {
+            let tunables = self.tunables.borrow();
+            (
+                tunables.seed_peers_max_latency,
+                tunables.seed_peers_ignored.clone(),
+            )
+        };
+        let mut measurements = JoinSet::new();
+        for (index, peer) in config.root_node_ips.iter().enumerate() {
+            let ip = peer.ip;
+            if ignored_peers.contains(&ip) {
+                continue;
+            }
+            measurements.spawn(async move {
+                let latency = measure_node_latency(ip, GOSSIP_PORT, timeout_duration).await;
+                (index, latency.ok())
+            });
+        }
+
+        let mut peers: Vec<_> = config
+            .root_node_ips
+            .iter()
+            .map(|peer| proto::Peer {
+                ip: peer.ip.to_string(),
+                ignored: ignored_peers.contains(&peer.ip),
+                latency_seconds: None,
+            })
+            .collect();
+        while let Some(Ok((index, latency))) = measurements.join_next().await {
+            peers[index].latency_seconds = latency.map(|latency| latency.as_secs_f64());
+        }
+
+        Ok(Response::new(proto::ListPeersResponse {
+            peers,
+            try_new_peers: config.try_new_peers,
+        }))
+    }
+
+    async fn create_snapshot(
+        &self,
+        _request: Request<proto::CreateSnapshotRequest>,
+    ) -> Result<Response<proto::CreateSnapshotResponse>, Status> {
+        let bucket = self.bucket.as_ref().ok_or_else(|| {
+            Status::failed_precondition("object storage is not configured, see --storage-url")
+        })?;
+
+        let start = Instant::now();
+        match run_backup(&self.data_directory, &self.backup, bucket).await {
+            Ok(key) => {
+                info!(key, elapsed = ?start.elapsed(), "snapshot created on request");
+                Ok(Response::new(proto::CreateSnapshotResponse { key }))
+            }
+            Err(err) => {
+                warn!(?err, "failed to create snapshot on request");
+                Err(Status::internal(format!("{err:#}")))
+            }
+        }
+    }
+
+    async fn prune(
+        &self,
+        request: Request<proto::PruneRequest>,
+    ) -> Result<Response<proto::PruneResponse>, Status> {
+        let older_than = match request.into_inner().older_than_seconds {
+            Some(0) => return Err(Status::invalid_argument("older_than_seconds is 0")),
+            Some(seconds) => Duration::from_secs(seconds),
+            None => self.tunables.borrow().prune_data_older_than,
+        };
+
+        prune_data(&self.data_directory, older_than)
+            .await
+            .map_err(|err| Status::internal(format!("{err:#}")))?;
+        Ok(Response::new(proto::PruneResponse {
+            result: status::snapshot().last_prune.map(prune_result),
+        }))
+    }
+}
+
+/// Same allowlist & keys as the HTTP servers, keys sent as `authorization: Bearer <key>` or `x-api-key: <key>`
+/// metadata
+#[allow(clippy::result_large_err)]
+fn authenticate(access: &Access, request: Request<()>) -> Result<Request<()>, Status> {
+    let headers = request.metadata().clone().into_headers();
+    if let Some(allowlist) = &access.allowlist
+        && let Some(peer) = request.remote_addr()
+        && !allowlist.allows(allowlist.client_ip(peer.ip(), &headers))
+    {
+        return Err(Status::permission_denied("client is not in the allowlist"));
+    }
+
+    let Some(api_keys) = &access.api_keys else {
+        return Ok(request);
+    };
+    match api_keys.check(auth::request_key(&headers), Scope::Control) {
+        Ok(()) => Ok(request),
+        Err(Denied::OutOfScope) => {
+            Err(Status::permission_denied("API key lacks the control scope"))
+        }
+        Err(_) => Err(Status::unauthenticated("missing or invalid API key")),
+    }
+}
+
+fn status_response(status: &BootstrapStatus) -> proto::GetStatusResponse {
+    let phase = match status.phase {
+        status::Phase::Starting => proto::Phase::Starting,
+        status::Phase::DownloadingVisor => proto::Phase::DownloadingVisor,
+        status::Phase::RestoringSnapshot => proto::Phase::RestoringSnapshot,
+        status::Phase::ConfiguringPeers => proto::Phase::ConfiguringPeers,
+        status::Phase::SetupDone => proto::Phase::SetupDone,
+        status::Phase::Stopping => proto::Phase::Stopping,
+    };
+    let state = match status.child.state {
+        status::ChildState::NotStarted => proto::ChildState::NotStarted,
+        status::ChildState::Running => proto::ChildState::Running,
+        status::ChildState::Restarting => proto::ChildState::Restarting,
+        status::ChildState::Exited => proto::ChildState::Exited,
+    };
+
+    proto::GetStatusResponse {
+        phase: phase.into(),
+        healthy: status.is_healthy(),
+        maintenance: status.maintenance,
+        child: Some(proto::ChildStatus {
+            pid: status.child.pid,
+            state: state.into(),
+            restarts: status.child.restarts,
+            last_exit_code: status.child.last_exit_code,
+            node_responding: status.child.node_responding,
+            down_since_ms: status.child.down_since_ms,
+        }),
+        gossip_config_written_at_ms: status.gossip_config_written_at_ms,
+        last_prune: status.last_prune.clone().map(prune_result),
+    }
+}
+
+fn prune_result(prune: status::PruneStatus) -> proto::PruneResult {
+    proto::PruneResult {
+        finished_at_ms: prune.finished_at_ms,
+        removed: prune.removed as u64,
+        failed: prune.failed as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_response() {
+        let mut status = BootstrapStatus {
+            phase: status::Phase::SetupDone,
+            ..Default::default()
+        };
+        status.child.state = status::ChildState::Running;
+        status.child.pid = Some(42);
+
+        let response = status_response(&status);
+        assert_eq!(response.phase(), proto::Phase::SetupDone);
+        assert!(response.healthy);
+        let child = response.child.unwrap();
+        assert_eq!(child.state(), proto::ChildState::Running);
+        assert_eq!(child.pid, Some(42));
+        assert_eq!(response.last_prune, None);
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    fmt::Write,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use bytesize::ByteSize;
+use clap::{Args, ValueEnum};
+use duration_string::DurationString;
+use eyre::Context;
+use nix::sys::statvfs::statvfs;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hl_gossip_config::HyperliquidChain,
+    maintenance,
+    monitor::{
+        NETWORK_CLIENT, evm::EvmSync, height::local_height, request_exchange_time, sync_lag,
+    },
+    status::{self, BootstrapStatus, ChildState, Phase, unix_ms_now},
+};
+
+/// Set once /startupz succeeded, after which it doesn't fail anymore
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, Args)]
+pub struct HealthArgs {
+    /// Checks which have to pass for /healthz to report healthy. `sync` queries the public Hyperliquid API and is only
+    /// evaluated when required
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_HEALTH_REQUIRED_CHECKS",
+        value_delimiter = ',',
+        default_value = "child"
+    )]
+    pub health_required_checks: Vec<HealthCheck>,
+
+    /// Minimum free space in the data directory for the disk check
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_HEALTH_DISK_MIN_FREE",
+        default_value = "10GiB"
+    )]
+    pub health_disk_min_free: ByteSize,
+
+    /// How long the child may be down, e.g. while hl-bootstrap restarts it, before /livez fails
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_LIVEZ_CHILD_DOWN_GRACE",
+        default_value = "2m"
+    )]
+    pub livez_child_down_grace: DurationString,
+
+    /// How far behind the system time the node may be for the initial sync to be done (/startupz)
+    #[arg(long, env = "HL_BOOTSTRAP_STARTUPZ_MAX_DRIFT", default_value = "1m")]
+    pub startupz_max_drift: DurationString,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthCheck {
+    /// Bootstrap setup is progressing and the child is running
+    Child,
+    /// Local info endpoint is responding
+    Info,
+    /// Node is within the sync lag threshold of the network
+    Sync,
+    /// Data directory has enough free space
+    Disk,
+    /// Last block hl-node wrote into hl/data is within the sync lag threshold of now, without the info endpoint
+    Height,
+    /// Local EVM RPC is within the block lag threshold of the reference RPC, evaluated when hl-node serves it
+    Evm,
+}
+
+/// Configuration of the aggregated health check served on /healthz
+#[derive(Clone, Debug)]
+pub struct HealthChecks {
+    pub required: Vec<HealthCheck>,
+    pub data_directory: PathBuf,
+    pub disk_min_free: ByteSize,
+    pub network: HyperliquidChain,
+    pub sync_max_lag: Duration,
+    pub evm: Option<EvmSync>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CheckResult {
+    pub check: HealthCheck,
+    pub ok: bool,
+    pub required: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub checks: Vec<CheckResult>,
+    #[serde(flatten)]
+    pub status: BootstrapStatus,
+}
+
+impl HealthReport {
+    /// Fetches /healthz of the running instance, served on the health or metrics address
+    pub async fn fetch(address: SocketAddr, api_key: Option<&str>) -> eyre::Result<Self> {
+        let url = format!("http://{}/healthz", local_address(address));
+        let mut request = NETWORK_CLIENT.get(&url);
+        if let Some(api_key) = api_key {
+            request = request.bearer_auth(api_key);
+        }
+        // Unhealthy reports come with 503, so the status isn't an error here
+        request
+            .send()
+            .await
+            .wrap_err_with(|| format!("failed to query {url}"))?
+            .json()
+            .await
+            .wrap_err("failed to parse health report")
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for check in &self.checks {
+            let status = match (check.ok, check.required) {
+                (true, _) => "PASS",
+                (false, true) => "FAIL",
+                (false, false) => "WARN",
+            };
+            let name = check
+                .check
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default();
+            let _ = writeln!(out, "[{status}] {name}: {}", check.detail);
+        }
+        let _ = writeln!(out, "healthy: {}", self.healthy);
+        out
+    }
+}
+
+/// Listen addresses on all interfaces are reached through localhost
+pub fn local_address(mut address: SocketAddr) -> SocketAddr {
+    if address.ip().is_unspecified() {
+        address.set_ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+    }
+    address
+}
+
+impl HealthChecks {
+    pub async fn evaluate(&self) -> HealthReport {
+        let status = status::snapshot();
+
+        let mut checks = vec![
+            self.check_child(&status),
+            self.check_info().await,
+            self.check_disk(),
+            self.check_height().await,
+        ];
+        if self.required.contains(&HealthCheck::Sync) {
+            checks.push(self.check_sync().await);
+        }
+        if let Some(evm) = &self.evm {
+            checks.push(self.check_evm(evm).await);
+        }
+
+        HealthReport {
+            healthy: checks.iter().all(|check| check.ok || !check.required),
+            checks,
+            status,
+        }
+    }
+
+    fn result(&self, check: HealthCheck, ok: bool, detail: String) -> CheckResult {
+        CheckResult {
+            check,
+            ok,
+            required: self.required.contains(&check),
+            detail,
+        }
+    }
+
+    fn check_child(&self, status: &BootstrapStatus) -> CheckResult {
+        let detail = format!(
+            "phase: {:?}, child: {:?}, node responding: {:?}",
+            status.phase, status.child.state, status.child.node_responding
+        );
+        self.result(HealthCheck::Child, status.is_healthy(), detail)
+    }
+
+    async fn check_info(&self) -> CheckResult {
+        match request_exchange_time().await {
+            Ok(time) => self.result(HealthCheck::Info, true, format!("exchange time: {time}")),
+            Err(err) => self.result(HealthCheck::Info, false, err.to_string()),
+        }
+    }
+
+    async fn check_sync(&self) -> CheckResult {
+        match sync_lag(self.network).await {
+            Ok(lag) => self.result(
+                HealthCheck::Sync,
+                lag < self.sync_max_lag,
+                format!("lag: {lag:?}, max lag: {:?}", self.sync_max_lag),
+            ),
+            Err(err) => self.result(HealthCheck::Sync, false, format!("{err:#}")),
+        }
+    }
+
+    async fn check_evm(&self, evm: &EvmSync) -> CheckResult {
+        match evm.lag().await {
+            Ok(lag) => self.result(
+                HealthCheck::Evm,
+                lag.blocks() <= evm.max_lag_blocks,
+                format!(
+                    "block: {}, reference block: {}, max lag: {} blocks",
+                    lag.local, lag.reference, evm.max_lag_blocks
+                ),
+            ),
+            Err(err) => self.result(HealthCheck::Evm, false, format!("{err:#}")),
+        }
+    }
+
+    fn check_disk(&self) -> CheckResult {
+        match statvfs(&self.data_directory) {
+            Ok(stat) => {
+                let available = ByteSize::b(stat.blocks_available() * stat.fragment_size());
+                self.result(
+                    HealthCheck::Disk,
+                    available >= self.disk_min_free,
+                    format!("available: {available}, min free: {}", self.disk_min_free),
+                )
+            }
+            Err(err) => self.result(HealthCheck::Disk, false, err.to_string()),
+        }
+    }
+
+    /// Periodic ABCI states carry no block time, so finding one only shows hl-node has written state at some point
+    async fn check_height(&self) -> CheckResult {
+        let data_directory = self.data_directory.clone();
+        let height = match tokio::task::spawn_blocking(move || local_height(&data_directory)).await
+        {
+            Ok(Some(height)) => height,
+            Ok(None) => {
+                return self.result(
+                    HealthCheck::Height,
+                    false,
+                    "no replica_cmds or periodic ABCI states yet".to_string(),
+                );
+            }
+            Err(err) => return self.result(HealthCheck::Height, false, err.to_string()),
+        };
+
+        let source = height.source.as_str();
+        match height.time_ms {
+            Some(time_ms) => {
+                let lag = Duration::from_millis((unix_ms_now() as i64 - time_ms).max(0) as u64);
+                self.result(
+                    HealthCheck::Height,
+                    lag < self.sync_max_lag,
+                    format!(
+                        "height: {} ({source}), block time lag: {lag:?}, max lag: {:?}",
+                        height.height, self.sync_max_lag
+                    ),
+                )
+            }
+            None => self.result(
+                HealthCheck::Height,
+                true,
+                format!("height: {} ({source}), block time unknown", height.height),
+            ),
+        }
+    }
+}
+
+/// Thresholds of the Kubernetes style probes: /livez (process alive), /readyz (synced) and /startupz (setup done and
+/// caught up once)
+#[derive(Clone, Debug)]
+pub struct Probes {
+    pub child_down_grace: Duration,
+    pub ready_max_drift: Duration,
+    pub startup_max_drift: Duration,
+}
+
+impl Probes {
+    /// The child is running, or not for longer than hl-bootstrap takes to restart it. A hanging node is left to the
+    /// stall watchdog, restarting the container would only throw away its progress
+    pub fn live(&self) -> bool {
+        child_alive(&status::snapshot(), unix_ms_now(), self.child_down_grace)
+    }
+
+    pub async fn ready(&self) -> bool {
+        !maintenance::is_enabled()
+            && node_drift()
+                .await
+                .is_some_and(|drift| drift < self.ready_max_drift)
+    }
+
+    pub async fn started(&self) -> bool {
+        if STARTED.load(Ordering::Relaxed) {
+            return true;
+        }
+        let status = status::snapshot();
+        let started = status.phase == Phase::SetupDone
+            && status.child.state == ChildState::Running
+            && node_drift()
+                .await
+                .is_some_and(|drift| drift < self.startup_max_drift);
+        if started {
+            STARTED.store(true, Ordering::Relaxed);
+        }
+        started
+    }
+}
+
+fn child_alive(status: &BootstrapStatus, now_ms: u64, grace: Duration) -> bool {
+    match status.child.state {
+        ChildState::NotStarted | ChildState::Running => true,
+        ChildState::Restarting | ChildState::Exited => {
+            status.child.down_since_ms.is_none_or(|down_since_ms| {
+                now_ms.saturating_sub(down_since_ms) < grace.as_millis() as u64
+            })
+        }
+    }
+}
+
+/// How far the node's exchange time is behind the system time, unset while it isn't responding
+async fn node_drift() -> Option<Duration> {
+    let time = request_exchange_time().await.ok()?;
+    Some(Duration::from_millis(unix_ms_now().saturating_sub(time)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_alive() {
+        let grace = Duration::from_secs(120);
+        let mut status = BootstrapStatus::default();
+        assert!(child_alive(&status, 1_000_000, grace));
+
+        status.child.state = ChildState::Restarting;
+        status.child.down_since_ms = Some(1_000_000);
+        assert!(child_alive(&status, 1_060_000, grace));
+        assert!(!child_alive(&status, 1_120_000, grace));
+
+        status.child.state = ChildState::Running;
+        assert!(child_alive(&status, 2_000_000, grace));
+    }
+}
@@ -3,7 +3,7 @@ use std::{collections::HashSet, net::Ipv4Addr, str::FromStr};
 use eyre::{Context, ContextCompat, bail};
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
-use tracing::{debug, warn};
+use tracing::{debug, instrument, warn};
 
 structstruck::strike! {
     #[structstruck::each[derive(Clone, Debug, Deserialize, Serialize)]]
@@ -70,7 +70,6 @@ impl ToString for HyperliquidChain {
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct HyperliquidSeedPeer {
-    #[allow(dead_code)] // Keeping due to its value in logs
     pub operator_name: String,
     pub ip: Ipv4Addr,
 }
@@ -81,6 +80,7 @@ impl From<HyperliquidSeedPeer> for NodeIp {
     }
 }
 
+#[instrument(skip(ignored_peers))]
 pub async fn fetch_hyperliquid_seed_peers(
     chain: HyperliquidChain,
     ignored_peers: &HashSet<Ipv4Addr>,
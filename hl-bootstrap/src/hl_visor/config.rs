@@ -11,6 +11,12 @@ pub struct VisorConfig {
     pub chain: HyperliquidChain,
 }
 
+/// Whether the hl-visor config at path differs from what would be written
+pub fn hl_visor_config_changed(path: impl AsRef<Path>, network: HyperliquidChain) -> bool {
+    let expected = serde_json::to_string(&VisorConfig { chain: network }).ok();
+    std::fs::read_to_string(path).ok() != expected
+}
+
 pub fn write_hl_visor_config(
     path: impl AsRef<Path>,
     network: HyperliquidChain,
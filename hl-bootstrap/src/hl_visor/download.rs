@@ -10,68 +10,87 @@ use eyre::{Context, ContextCompat, bail};
 use http::header::ETAG;
 use tempfile::NamedTempFile;
 use tokio::fs::{read_to_string, set_permissions};
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, info, instrument, trace, warn};
 
-use crate::hl_gossip_config::HyperliquidChain;
+use crate::{
+    alerts::{AlertEvent, send_alert},
+    hl_gossip_config::HyperliquidChain,
+    metrics::{COUNTER_BINARY_DOWNLOAD_BYTES, COUNTER_BINARY_DOWNLOADS},
+    reaper,
+};
 
-pub async fn download_hl_visor(
+pub fn binary_url(network: HyperliquidChain, name: &str) -> String {
+    match network {
+        HyperliquidChain::Mainnet => format!("https://binaries.hyperliquid.xyz/Mainnet/{name}"),
+        HyperliquidChain::Testnet => {
+            format!("https://binaries.hyperliquid-testnet.xyz/Testnet/{name}")
+        }
+    }
+}
+
+/// Downloads a signed Hyperliquid binary (hl-visor or hl-node) into base path, if it has changed since the last download
+#[instrument(skip(base_path))]
+pub async fn download_hl_binary(
     base_path: impl AsRef<Path>,
     network: HyperliquidChain,
+    name: &str,
 ) -> eyre::Result<()> {
-    let base_path = base_path.as_ref();
-
-    debug!(?network, "checking for hl-visor updates");
-
-    let binary_url = match network {
-        HyperliquidChain::Mainnet => "https://binaries.hyperliquid.xyz/Mainnet/hl-visor",
-        HyperliquidChain::Testnet => "https://binaries.hyperliquid-testnet.xyz/Testnet/hl-visor",
+    let result = update_hl_binary(base_path.as_ref(), network, name).await;
+    let label = match &result {
+        Ok(true) => "downloaded",
+        Ok(false) => "up_to_date",
+        Err(_) => "failed",
     };
+    COUNTER_BINARY_DOWNLOADS
+        .with_label_values(&[name, label])
+        .inc();
+    if let Ok(true) = result {
+        send_alert(
+            AlertEvent::BinaryUpdated,
+            &format!("{name} updated to a new version"),
+        )
+        .await;
+    }
 
-    let hl_visor_path = base_path.join("hl-visor");
-    let etag_file_path = base_path.join(".hl-visor.etag");
-
-    let new_etag_value = fetch_etag(binary_url)
-        .await
-        .wrap_err("failed to obtain etag for hl-visor")?;
+    result.map(|_| ())
+}
 
-    let current_etag_value = match read_to_string(&etag_file_path).await {
-        Ok(value) => Some(value.trim().to_string()),
-        Err(err) if matches!(err.kind(), ErrorKind::NotFound) => None,
-        Err(err) => {
-            warn!(?err, ?etag_file_path, "failed to read last stored etag");
-            None
-        }
+/// Returns whether a new binary was downloaded
+async fn update_hl_binary(
+    base_path: &Path,
+    network: HyperliquidChain,
+    name: &str,
+) -> eyre::Result<bool> {
+    let Some(new_etag_value) = check_hl_binary_update(base_path, network, name).await? else {
+        return Ok(false);
     };
 
-    trace!(
-        ?network,
-        ?new_etag_value,
-        ?current_etag_value,
-        "comparing hl-visor etag values"
-    );
-    if matches!(&current_etag_value, Some(value) if *value == new_etag_value) {
-        debug!(?network, etag = ?current_etag_value.unwrap(), "hl-visor appears up to date");
-        return Ok(());
-    }
+    let binary_url = binary_url(network, name);
+    let binary_path = base_path.join(name);
+    let etag_file_path = base_path.join(format!(".{name}.etag"));
 
-    info!(?network, new_etag_value, "downloading new hl-visor binary");
+    info!(?network, name, new_etag_value, "downloading new binary");
 
     let mut new_binary = NamedTempFile::new_in(base_path)?;
     let mut new_sig_file = NamedTempFile::new_in(base_path)?;
     let mut new_etag_file = NamedTempFile::new_in(base_path)?;
 
     let binary_sig_url = format!("{binary_url}.asc");
-    tokio::try_join!(
-        download_file(binary_url, new_binary.as_file_mut()),
+    let (binary_bytes, sig_bytes) = tokio::try_join!(
+        download_file(&binary_url, new_binary.as_file_mut()),
         download_file(&binary_sig_url, new_sig_file.as_file_mut())
     )?;
-
-    // Verify hl-visor signature
-    let gpg_result = Command::new("gpg")
-        .arg("--verify")
-        .arg(new_sig_file.path())
-        .arg(new_binary.path())
-        .output()?;
+    COUNTER_BINARY_DOWNLOAD_BYTES
+        .with_label_values(&[name])
+        .inc_by(binary_bytes + sig_bytes);
+
+    // Verify binary signature
+    let gpg_result = reaper::output(
+        Command::new("gpg")
+            .arg("--verify")
+            .arg(new_sig_file.path())
+            .arg(new_binary.path()),
+    )?;
     if !gpg_result.status.success() {
         let stderr_str = str::from_utf8(&gpg_result.stderr);
         let stderr = match stderr_str {
@@ -80,26 +99,65 @@ pub async fn download_hl_visor(
         };
 
         bail!(
-            "gpg verification for hl-visor failed with status {:?}:\n{}",
+            "gpg verification for {name} failed with status {:?}:\n{}",
             gpg_result.status,
             stderr,
         );
     }
 
-    // Persist hl-visor
+    // Persist binary
     set_permissions(new_binary.path(), Permissions::from_mode(0o755)).await?;
     new_binary.flush()?;
-    new_binary.persist(&hl_visor_path)?;
+    new_binary.persist(&binary_path)?;
 
     // Store etag for future comparisons
     writeln!(&mut new_etag_file, "{new_etag_value}")?;
     new_etag_file.flush()?;
     new_etag_file.persist(etag_file_path)?;
 
-    Ok(())
+    Ok(true)
+}
+
+/// Returns the new etag when the binary in base path differs from the published one
+pub async fn check_hl_binary_update(
+    base_path: &Path,
+    network: HyperliquidChain,
+    name: &str,
+) -> eyre::Result<Option<String>> {
+    debug!(?network, name, "checking for binary updates");
+
+    let binary_url = binary_url(network, name);
+    let etag_file_path = base_path.join(format!(".{name}.etag"));
+
+    let new_etag_value = fetch_etag(&binary_url)
+        .await
+        .wrap_err_with(|| format!("failed to obtain etag for {name}"))?;
+
+    let current_etag_value = match read_to_string(&etag_file_path).await {
+        Ok(value) => Some(value.trim().to_string()),
+        Err(err) if matches!(err.kind(), ErrorKind::NotFound) => None,
+        Err(err) => {
+            warn!(?err, ?etag_file_path, "failed to read last stored etag");
+            None
+        }
+    };
+
+    trace!(
+        ?network,
+        ?new_etag_value,
+        ?current_etag_value,
+        name,
+        "comparing etag values"
+    );
+    if matches!(&current_etag_value, Some(value) if *value == new_etag_value) {
+        debug!(?network, name, etag = ?current_etag_value.unwrap(), "binary appears up to date");
+        return Ok(None);
+    }
+
+    Ok(Some(new_etag_value))
 }
 
-async fn fetch_etag(url: &str) -> eyre::Result<String> {
+pub async fn fetch_etag(url: &str) -> eyre::Result<String> {
     trace!(?url, "fetching etag");
 
     let response = reqwest::Client::new()
@@ -119,16 +177,19 @@ async fn fetch_etag(url: &str) -> eyre::Result<String> {
     Ok(value.trim().to_string())
 }
 
-async fn download_file(url: &str, target: &mut File) -> eyre::Result<()> {
+/// Returns the amount of bytes downloaded
+async fn download_file(url: &str, target: &mut File) -> eyre::Result<u64> {
     let mut response = reqwest::get(url)
         .await?
         .error_for_status()
         .wrap_err_with(|| format!("failed to send GET request to {url}"))?;
 
+    let mut downloaded = 0;
     while let Some(chunk) = response.chunk().await? {
         target.write_all(&chunk)?;
+        downloaded += chunk.len() as u64;
     }
     target.flush()?;
 
-    Ok(())
+    Ok(downloaded)
 }
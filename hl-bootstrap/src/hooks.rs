@@ -0,0 +1,118 @@
+use std::{path::Path, process::ExitStatus, time::Duration};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, bail};
+use tokio::{process::Command, time::timeout};
+use tracing::{info, instrument, warn};
+
+use crate::{hl_gossip_config::HyperliquidChain, reaper};
+
+#[derive(Clone, Debug, Args)]
+pub struct HookArgs {
+    /// Shell command to run before the setup starts. Failure aborts the startup
+    #[arg(long, env = "HL_BOOTSTRAP_PRE_START_HOOK")]
+    pub pre_start_hook: Option<String>,
+
+    /// Shell command to run after the setup is done, before starting the child. Failure aborts the startup
+    #[arg(long, env = "HL_BOOTSTRAP_POST_SETUP_HOOK")]
+    pub post_setup_hook: Option<String>,
+
+    /// Shell command to run every time the child exits. Failures are only logged
+    #[arg(long, env = "HL_BOOTSTRAP_POST_EXIT_HOOK")]
+    pub post_exit_hook: Option<String>,
+
+    /// How long a hook is allowed to run before it gets killed
+    #[arg(long, env = "HL_BOOTSTRAP_HOOK_TIMEOUT", default_value = "5m")]
+    pub hook_timeout: DurationString,
+}
+
+/// Lifecycle hooks along with the context passed to them via environment variables
+#[derive(Clone, Debug)]
+pub struct Hooks {
+    args: HookArgs,
+    env: Vec<(&'static str, String)>,
+}
+
+impl Hooks {
+    pub fn new(args: &HookArgs, network: HyperliquidChain, data_directory: &Path) -> Self {
+        Self {
+            args: args.clone(),
+            env: vec![
+                ("HL_BOOTSTRAP_NETWORK", network.to_string()),
+                (
+                    "HL_BOOTSTRAP_DATA_DIR",
+                    data_directory.to_string_lossy().into_owned(),
+                ),
+            ],
+        }
+    }
+
+    pub async fn pre_start(&self) -> eyre::Result<()> {
+        match &self.args.pre_start_hook {
+            Some(hook) => self.run("pre-start", hook, &[]).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn post_setup(&self) -> eyre::Result<()> {
+        match &self.args.post_setup_hook {
+            Some(hook) => self.run("post-setup", hook, &[]).await,
+            None => Ok(()),
+        }
+    }
+
+    pub async fn post_exit(&self, pid: u32, status: ExitStatus) {
+        let Some(hook) = &self.args.post_exit_hook else {
+            return;
+        };
+
+        let env = [
+            ("HL_BOOTSTRAP_CHILD_PID", pid.to_string()),
+            (
+                "HL_BOOTSTRAP_CHILD_EXIT_CODE",
+                status
+                    .code()
+                    .map(|code| code.to_string())
+                    .unwrap_or_default(),
+            ),
+        ];
+        if let Err(err) = self.run("post-exit", hook, &env).await {
+            warn!(?err, "post-exit hook failed");
+        }
+    }
+
+    #[instrument(skip(self, extra_env))]
+    async fn run(&self, name: &str, hook: &str, extra_env: &[(&str, String)]) -> eyre::Result<()> {
+        let hook_timeout: Duration = self.args.hook_timeout.into();
+        info!(name, hook, "running hook");
+
+        let mut child = reaper::spawn(
+            Command::new("sh")
+                .arg("-c")
+                .arg(hook)
+                .env("HL_BOOTSTRAP_HOOK", name)
+                .envs(self.env.iter().map(|(key, value)| (key, value)))
+                .envs(extra_env.iter().map(|(key, value)| (key, value)))
+                .kill_on_drop(true),
+        )
+        .wrap_err_with(|| format!("failed to spawn {name} hook"))?;
+
+        let pid = child.id();
+        let result = timeout(hook_timeout, child.wait()).await;
+        if let Some(pid) = pid {
+            reaper::unmanage(pid);
+        }
+
+        let status = match result {
+            Ok(status) => status.wrap_err_with(|| format!("failed to wait for {name} hook"))?,
+            Err(_) => bail!("{name} hook did not finish within {hook_timeout:?}"),
+        };
+
+        if !status.success() {
+            bail!("{name} hook failed with {status}");
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{
+    Router,
+    extract::{ConnectInfo, DefaultBodyLimit, Request, State},
+    http::StatusCode,
+    middleware::{Next, from_fn_with_state},
+    response::{IntoResponse, Response},
+    serve::Listener,
+};
+use bytesize::ByteSize;
+use clap::Args;
+use duration_string::DurationString;
+use hyper::server::conn::http1;
+use hyper_util::{
+    rt::{TokioIo, TokioTimer},
+    service::TowerToHyperService,
+};
+use tokio::sync::Semaphore;
+use tower::ServiceExt;
+use tracing::debug;
+
+/// Limits of the HTTP servers, so a misbehaving client can't exhaust the host running the node. Each server applies
+/// them on its own
+#[derive(Clone, Debug, Args)]
+pub struct HttpServerArgs {
+    /// Largest request body accepted, larger ones are refused with 413
+    #[arg(long, env = "HL_BOOTSTRAP_HTTP_MAX_BODY_SIZE", default_value = "1MiB")]
+    pub http_max_body_size: ByteSize,
+
+    /// How long clients get to send the request headers, idle keep-alive connections included, before they're
+    /// disconnected
+    #[arg(long, env = "HL_BOOTSTRAP_HTTP_HEADER_TIMEOUT", default_value = "10s")]
+    pub http_header_timeout: DurationString,
+
+    /// How long a request may take to be answered, reading its body included, before it's answered with 408.
+    /// WebSocket streams aren't limited once upgraded
+    #[arg(long, env = "HL_BOOTSTRAP_HTTP_REQUEST_TIMEOUT", default_value = "60s")]
+    pub http_request_timeout: DurationString,
+
+    /// Most connections open at once per server. Further clients wait in the listen backlog until one closes
+    #[arg(long, env = "HL_BOOTSTRAP_HTTP_MAX_CONNECTIONS", default_value_t = 512)]
+    pub http_max_connections: usize,
+}
+
+/// Serves the router like `axum::serve` with connect info, within the limits
+pub async fn serve<L>(mut listener: L, router: Router, args: &HttpServerArgs)
+where
+    L: Listener<Addr = SocketAddr>,
+{
+    let router = router
+        .layer(from_fn_with_state(
+            Duration::from(args.http_request_timeout),
+            request_timeout,
+        ))
+        .layer(DefaultBodyLimit::max(
+            args.http_max_body_size.as_u64() as usize
+        ));
+    let header_timeout: Duration = args.http_header_timeout.into();
+    let connections = Arc::new(Semaphore::new(args.http_max_connections));
+
+    loop {
+        // Not accepting while at the limit, rather than accepting & dropping, keeps clients queued in the backlog
+        let permit = connections
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let (io, peer) = listener.accept().await;
+
+        let service =
+            router
+                .clone()
+                .map_request(move |mut request: Request<hyper::body::Incoming>| {
+                    request.extensions_mut().insert(ConnectInfo(peer));
+                    request
+                });
+        tokio::spawn(async move {
+            let connection = http1::Builder::new()
+                .timer(TokioTimer::new())
+                .header_read_timeout(header_timeout)
+                .serve_connection(TokioIo::new(io), TowerToHyperService::new(service))
+                .with_upgrades();
+            if let Err(err) = connection.await {
+                debug!(%peer, ?err, "connection closed with error");
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn request_timeout(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            debug!(path, ?timeout, "request timed out");
+            StatusCode::REQUEST_TIMEOUT.into_response()
+        }
+    }
+}
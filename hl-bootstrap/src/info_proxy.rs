@@ -0,0 +1,242 @@
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderValue, header::CONTENT_TYPE},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use clap::Args;
+use duration_string::DurationString;
+use reqwest::{Client, ClientBuilder, StatusCode};
+use serde_json::Value;
+use tokio::{net::TcpListener, sync::OnceCell};
+use tracing::{debug, info};
+
+use crate::{
+    auth::{self, Access, Scope},
+    http_server::{self, HttpServerArgs},
+    metrics::COUNTER_INFO_PROXY_REQUESTS,
+    monitor::INFO_PORT,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct InfoProxyArgs {
+    /// Address to serve a caching proxy of the local info endpoint on, as POST /info. Identical queries within the
+    /// TTL are answered from the cache, and ones arriving while hl-node is answering wait for that answer. Disabled
+    /// when unset
+    #[arg(long, env = "HL_BOOTSTRAP_INFO_PROXY_LISTEN_ADDRESS")]
+    pub info_proxy_listen_address: Option<SocketAddr>,
+
+    /// Info request types forwarded to hl-node, others are rejected
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_INFO_PROXY_TYPES",
+        value_delimiter = ',',
+        default_value = "meta,spotMeta,clearinghouseState,spotClearinghouseState,openOrders,frontendOpenOrders,exchangeStatus"
+    )]
+    pub info_proxy_types: Vec<String>,
+
+    /// How long responses are served from the cache
+    #[arg(long, env = "HL_BOOTSTRAP_INFO_PROXY_CACHE_TTL", default_value = "1s")]
+    pub info_proxy_cache_ttl: DurationString,
+
+    /// How long hl-node may take to answer a query
+    #[arg(long, env = "HL_BOOTSTRAP_INFO_PROXY_TIMEOUT", default_value = "5s")]
+    pub info_proxy_timeout: DurationString,
+}
+
+#[derive(Clone, Debug)]
+struct Answer {
+    status: StatusCode,
+    body: Bytes,
+    received: Instant,
+}
+
+/// Answer of a query, empty while the first request for it waits for hl-node
+#[derive(Default)]
+struct Slot(OnceCell<Answer>);
+
+#[derive(Clone)]
+struct InfoProxy {
+    client: Client,
+    url: String,
+    types: Arc<[String]>,
+    ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, Arc<Slot>>>>,
+}
+
+pub async fn run_info_proxy_server(
+    listen_address: SocketAddr,
+    args: InfoProxyArgs,
+    access: Access,
+    http: HttpServerArgs,
+) -> eyre::Result<()> {
+    let state = InfoProxy {
+        client: ClientBuilder::new()
+            .timeout(args.info_proxy_timeout.into())
+            .build()?,
+        url: format!("http://127.0.0.1:{INFO_PORT}/info"),
+        types: args.info_proxy_types.into(),
+        ttl: args.info_proxy_cache_ttl.into(),
+        cache: Default::default(),
+    };
+    let router = Router::new().route("/info", post(info)).with_state(state);
+    let router = auth::restrict(&access, auth::require(&access, router, Scope::Info));
+
+    let listener = TcpListener::bind(listen_address).await?;
+    info!(%listen_address, "serving info endpoint proxy");
+    http_server::serve(listener, router, &http).await;
+
+    Ok(())
+}
+
+async fn info(State(proxy): State<InfoProxy>, body: Bytes) -> Response {
+    let (key, kind) = match cache_key(&body, &proxy.types) {
+        Ok(key) => key,
+        Err(err) => {
+            COUNTER_INFO_PROXY_REQUESTS
+                .with_label_values(&["", "rejected"])
+                .inc();
+            return (StatusCode::BAD_REQUEST, err).into_response();
+        }
+    };
+
+    let (slot, fresh) = proxy.slot(&key);
+    let mut fetched = false;
+    let answer = slot
+        .0
+        .get_or_init(|| {
+            fetched = true;
+            proxy.fetch(key)
+        })
+        .await;
+    let result = match (fetched, fresh) {
+        (true, _) => "miss",
+        (false, true) => "collapsed",
+        (false, false) => "hit",
+    };
+    COUNTER_INFO_PROXY_REQUESTS
+        .with_label_values(&[kind.as_str(), result])
+        .inc();
+
+    let mut response = (answer.status, answer.body.clone()).into_response();
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+        .headers_mut()
+        .insert("x-cache", HeaderValue::from_static(result));
+    response
+}
+
+impl InfoProxy {
+    /// Slot to wait on for the query, replaced once its answer is stale. Also whether the slot is new or still
+    /// pending, to tell collapsed requests from cache hits
+    fn slot(&self, key: &str) -> (Arc<Slot>, bool) {
+        let mut cache = self.cache.lock().unwrap();
+        if let Some(slot) = cache.get(key) {
+            match slot.0.get() {
+                None => return (Arc::clone(slot), true),
+                Some(answer) if self.reusable(answer) => return (Arc::clone(slot), false),
+                Some(_) => {}
+            }
+        }
+
+        // Stale answers are dropped whenever a query is added, so the cache only holds recently asked queries
+        cache.retain(|_, slot| slot.0.get().is_none_or(|answer| self.reusable(answer)));
+        let slot = Arc::new(Slot::default());
+        cache.insert(key.to_string(), Arc::clone(&slot));
+        (slot, true)
+    }
+
+    /// Errors are only shared with requests that arrived while waiting for them, not cached
+    fn reusable(&self, answer: &Answer) -> bool {
+        answer.status.is_success() && answer.received.elapsed() < self.ttl
+    }
+
+    async fn fetch(&self, body: String) -> Answer {
+        let response = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await;
+        let (status, body) = match response {
+            Ok(response) => {
+                let status = response.status();
+                match response.bytes().await {
+                    Ok(body) => (status, body),
+                    Err(err) => {
+                        debug!(?err, "failed to read info response from hl-node");
+                        (StatusCode::BAD_GATEWAY, Bytes::new())
+                    }
+                }
+            }
+            Err(err) => {
+                debug!(?err, "failed to forward info request to hl-node");
+                let status = if err.is_timeout() {
+                    StatusCode::GATEWAY_TIMEOUT
+                } else {
+                    StatusCode::BAD_GATEWAY
+                };
+                (status, Bytes::new())
+            }
+        };
+        Answer {
+            status,
+            body,
+            received: Instant::now(),
+        }
+    }
+}
+
+/// Normalized query, so queries differing only in key order or whitespace share a cache entry, and its type
+fn cache_key(body: &[u8], types: &[String]) -> Result<(String, String), String> {
+    let query: Value =
+        serde_json::from_slice(body).map_err(|err| format!("invalid JSON body: {err}"))?;
+    let kind = query
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or("query has no type")?
+        .to_string();
+    if !types.contains(&kind) {
+        return Err(format!("query type {kind} is not served by this proxy"));
+    }
+    // Object keys are kept sorted, so serializing gives the same key for equal queries
+    Ok((query.to_string(), kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key() {
+        let types = ["clearinghouseState".to_string()];
+        let (a, kind) = cache_key(
+            br#"{"type": "clearinghouseState", "user": "0x0000000000000000000000000000000000000001"}"#,
+            &types,
+        )
+        .unwrap();
+        let (b, _) = cache_key(
+            br#"{"user":"0x0000000000000000000000000000000000000001","type":"clearinghouseState"}"#,
+            &types,
+        )
+        .unwrap();
+        assert_eq!(a, b);
+        assert_eq!(kind, "clearinghouseState");
+
+        assert!(cache_key(br#"{"type":"userFills","user":"0x1"}"#, &types).is_err());
+        assert!(cache_key(br#"{"user":"0x1"}"#, &types).is_err());
+        assert!(cache_key(b"not json", &types).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, bail};
+use nix::{
+    errno::Errno,
+    fcntl::{FcntlArg, FdFlag, Flock, FlockArg, fcntl},
+};
+use tracing::{debug, warn};
+
+/// Exclusive lock on the PID file, preventing multiple hl-bootstrap instances from using the same data directory.
+/// As flock(2) locks are released by the kernel once the process exits, leftover PID files are never stale locks.
+pub struct InstanceLock {
+    file: Flock<File>,
+    path: PathBuf,
+}
+
+impl InstanceLock {
+    pub fn acquire(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .wrap_err_with(|| format!("failed to open pid file {path:?}"))?;
+
+        let mut file = match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+            Ok(file) => file,
+            Err((mut file, Errno::EWOULDBLOCK)) => {
+                let mut pid = String::new();
+                let _ = file.read_to_string(&mut pid);
+                bail!(
+                    "another hl-bootstrap instance (pid {}) is already running, pid file {path:?} is locked",
+                    pid.trim()
+                );
+            }
+            Err((_, errno)) => {
+                return Err(errno).wrap_err_with(|| format!("failed to lock pid file {path:?}"));
+            }
+        };
+
+        file.set_len(0)?;
+        file.rewind()?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+
+        debug!(?path, "acquired instance lock");
+        Ok(Self { file, path })
+    }
+
+    /// Keeps the lock held by the process after exec(2), as process id stays the same
+    pub fn inherit_on_exec(&self) -> eyre::Result<()> {
+        fcntl(&*self.file, FcntlArg::F_SETFD(FdFlag::empty()))
+            .wrap_err("failed to clear FD_CLOEXEC on pid file")?;
+
+        Ok(())
+    }
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if let Err(err) = self.file.set_len(0) {
+            warn!(?err, path = ?self.path, "failed to clear pid file");
+        }
+    }
+}
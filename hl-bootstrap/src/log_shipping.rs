@@ -0,0 +1,332 @@
+use std::{
+    fmt::Write as _,
+    io,
+    os::unix::net::UnixDatagram,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use clap::Args;
+use eyre::Context;
+use reqwest::{Url, blocking::Client};
+use serde_json::json;
+use tracing::{Event, Level, Subscriber, field::Field};
+use tracing_subscriber::{Layer, field::Visit, layer};
+
+use crate::{hl_gossip_config::HyperliquidChain, procfs};
+
+const THREAD_NAME: &str = "log-shipper";
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const LOKI_BATCH_SIZE: usize = 1000;
+const LOKI_BATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, Args)]
+pub struct LogShippingArgs {
+    /// Loki push API URL to ship logs to, e.g. `http://loki:3100/loki/api/v1/push`.
+    /// Captured child output is shipped as well
+    #[arg(long, env = "HL_BOOTSTRAP_LOG_LOKI_URL")]
+    pub log_loki_url: Option<Url>,
+
+    /// Write logs to the systemd journal using its native protocol
+    #[arg(long, env = "HL_BOOTSTRAP_LOG_JOURNALD")]
+    pub log_journald: bool,
+}
+
+impl LogShippingArgs {
+    pub fn enabled(&self) -> bool {
+        self.log_loki_url.is_some() || self.log_journald
+    }
+}
+
+struct LogRecord {
+    timestamp: SystemTime,
+    level: Level,
+    component: &'static str,
+    target: String,
+    message: String,
+}
+
+enum Message {
+    Record(LogRecord),
+    Flush(SyncSender<()>),
+}
+
+/// Ships log events to Loki and/or journald from a dedicated thread, so it works before the tokio runtime is up and
+/// logging never blocks on a slow sink. Events are dropped if the sinks can't keep up.
+/// Pending logs are flushed when this gets dropped.
+pub struct LogShipper {
+    sender: SyncSender<Message>,
+}
+
+impl LogShipper {
+    pub fn new(args: &LogShippingArgs, network: HyperliquidChain) -> eyre::Result<Self> {
+        let host = procfs::hostname()?;
+        let loki = args
+            .log_loki_url
+            .clone()
+            .map(|url| Loki::new(url, host, network.to_string()))
+            .transpose()?;
+        let journald = args
+            .log_journald
+            .then(|| Journald::new(network))
+            .transpose()?;
+
+        let (sender, receiver) = mpsc::sync_channel(10_000);
+        thread::Builder::new()
+            .name(THREAD_NAME.to_string())
+            .spawn(move || run_worker(receiver, loki, journald))
+            .wrap_err("failed to spawn log shipper thread")?;
+
+        Ok(Self { sender })
+    }
+
+    pub fn layer(&self) -> LogShipperLayer {
+        LogShipperLayer {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Waits until logs sent so far are shipped
+    pub fn flush(&self) {
+        let (done, wait) = mpsc::sync_channel(1);
+        if self.sender.send(Message::Flush(done)).is_ok() {
+            let _ = wait.recv_timeout(Duration::from_secs(10));
+        }
+    }
+}
+
+impl Drop for LogShipper {
+    fn drop(&mut self) {
+        // The layer keeps the worker alive for the rest of the process, so flush instead of waiting for it to exit
+        self.flush();
+    }
+}
+
+pub struct LogShipperLayer {
+    sender: SyncSender<Message>,
+}
+
+impl<S: Subscriber> Layer<S> for LogShipperLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: layer::Context<'_, S>) {
+        // Don't ship the sinks' own (e.g. HTTP client) logs, that would feed back into itself
+        if thread::current().name() == Some(THREAD_NAME) {
+            return;
+        }
+
+        let metadata = event.metadata();
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let record = LogRecord {
+            timestamp: SystemTime::now(),
+            level: *metadata.level(),
+            component: if metadata.target().starts_with("hl_visor") {
+                "child"
+            } else {
+                "bootstrap"
+            },
+            target: metadata.target().to_string(),
+            message: visitor.message,
+        };
+        // Dropped when the sinks can't keep up, can't log about it here as it would end up in this layer again
+        let _ = self.sender.try_send(Message::Record(record));
+    }
+}
+
+/// Formats an event the same way as the fmt layer does: the message followed by `key=value` fields
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.insert_str(0, value);
+        } else {
+            let _ = write!(self.message, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message.insert_str(0, &format!("{value:?}"));
+        } else {
+            let _ = write!(self.message, " {}={value:?}", field.name());
+        }
+    }
+}
+
+fn run_worker(receiver: Receiver<Message>, loki: Option<Loki>, journald: Option<Journald>) {
+    let mut batch = Vec::new();
+    let mut last_push = Instant::now();
+    loop {
+        let flush = match receiver.recv_timeout(LOKI_BATCH_INTERVAL) {
+            Ok(Message::Record(record)) => {
+                if let Some(journald) = &journald
+                    && let Err(err) = journald.send(&record)
+                {
+                    eprintln!("failed to write log to journald: {err}");
+                }
+                if loki.is_some() {
+                    batch.push(record);
+                }
+                None
+            }
+            Ok(Message::Flush(done)) => Some(done),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let batch_full = batch.len() >= LOKI_BATCH_SIZE;
+        let batch_due = last_push.elapsed() >= LOKI_BATCH_INTERVAL;
+        if let Some(loki) = &loki
+            && !batch.is_empty()
+            && (batch_full || batch_due || flush.is_some())
+        {
+            if let Err(err) = loki.push(&batch) {
+                eprintln!("failed to push logs to Loki: {err:#}");
+            }
+            batch.clear();
+            last_push = Instant::now();
+        }
+
+        if let Some(done) = flush {
+            let _ = done.send(());
+        }
+    }
+
+    if let Some(loki) = &loki
+        && !batch.is_empty()
+        && let Err(err) = loki.push(&batch)
+    {
+        eprintln!("failed to push logs to Loki: {err:#}");
+    }
+}
+
+struct Loki {
+    client: Client,
+    url: Url,
+    host: String,
+    network: String,
+}
+
+impl Loki {
+    fn new(url: Url, host: String, network: String) -> eyre::Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .wrap_err("failed to build Loki client")?;
+
+        Ok(Self {
+            client,
+            url,
+            host,
+            network,
+        })
+    }
+
+    fn push(&self, records: &[LogRecord]) -> eyre::Result<()> {
+        let streams: Vec<_> = ["bootstrap", "child"]
+            .into_iter()
+            .filter_map(|component| {
+                let values: Vec<_> = records
+                    .iter()
+                    .filter(|record| record.component == component)
+                    .map(|record| {
+                        let timestamp_ns = record
+                            .timestamp
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_nanos();
+                        json!([
+                            timestamp_ns.to_string(),
+                            format!("{} {}: {}", record.level, record.target, record.message)
+                        ])
+                    })
+                    .collect();
+                (!values.is_empty()).then(|| {
+                    json!({
+                        "stream": {
+                            "network": self.network,
+                            "host": self.host,
+                            "component": component,
+                        },
+                        "values": values,
+                    })
+                })
+            })
+            .collect();
+
+        self.client
+            .post(self.url.clone())
+            .json(&json!({ "streams": streams }))
+            .send()?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+struct Journald {
+    socket: UnixDatagram,
+    network: HyperliquidChain,
+}
+
+impl Journald {
+    fn new(network: HyperliquidChain) -> eyre::Result<Self> {
+        let socket = UnixDatagram::unbound().wrap_err("failed to create journald socket")?;
+        Ok(Self { socket, network })
+    }
+
+    fn send(&self, record: &LogRecord) -> io::Result<()> {
+        let priority = match record.level {
+            Level::ERROR => "3",
+            Level::WARN => "4",
+            Level::INFO => "6",
+            Level::DEBUG | Level::TRACE => "7",
+        };
+
+        let mut payload = Vec::new();
+        append_journal_field(&mut payload, "MESSAGE", &record.message);
+        append_journal_field(&mut payload, "PRIORITY", priority);
+        append_journal_field(&mut payload, "SYSLOG_IDENTIFIER", env!("CARGO_PKG_NAME"));
+        append_journal_field(&mut payload, "HL_NETWORK", &self.network.to_string());
+        append_journal_field(&mut payload, "HL_COMPONENT", record.component);
+        append_journal_field(&mut payload, "HL_TARGET", &record.target);
+
+        self.socket.send_to(&payload, JOURNALD_SOCKET)?;
+        Ok(())
+    }
+}
+
+/// Journal native protocol: `KEY=value\n`, or `KEY\n<u64 LE length><value>\n` for values containing newlines
+fn append_journal_field(payload: &mut Vec<u8>, key: &str, value: &str) {
+    payload.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+    payload.extend_from_slice(value.as_bytes());
+    payload.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_journal_field() {
+        let mut payload = Vec::new();
+        append_journal_field(&mut payload, "PRIORITY", "6");
+        append_journal_field(&mut payload, "MESSAGE", "a\nb");
+
+        let mut expected = b"PRIORITY=6\nMESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(payload, expected);
+    }
+}
@@ -1,50 +1,241 @@
 use std::{
-    collections::HashSet,
-    env::current_dir,
+    collections::{BTreeSet, HashSet},
     ffi::OsString,
     fs::{self},
     io::Write,
     net::{Ipv4Addr, SocketAddr},
     os::unix::process::CommandExt,
-    path::PathBuf,
-    process::Command,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
 
-use clap::Parser;
+use bytesize::ByteSize;
+use clap::{Parser, Subcommand, ValueEnum};
 use duration_string::DurationString;
 use eyre::{Context, ContextCompat, bail};
 use tempfile::NamedTempFile;
-use tokio::runtime::{Builder, Runtime};
-use tracing::{debug, error, info, level_filters::LevelFilter, trace};
+use tokio::{
+    runtime::{Builder, Runtime},
+    time::{Instant, MissedTickBehavior, interval_at},
+};
+use tracing::{debug, error, info, instrument, trace, warn};
 use tracing_subscriber::{
-    EnvFilter,
+    EnvFilter, Registry,
     fmt::{self, format::FmtSpan},
     layer::SubscriberExt,
     util::SubscriberInitExt,
 };
 
+mod alerts;
+mod anchor;
+mod auth;
+mod aws;
+mod backfill;
+mod backup;
+mod cloudwatch;
+mod compress;
+mod config;
+mod data_api;
+mod data_dir;
+mod dedup;
+mod diagnostics;
+mod disk_usage;
+mod doctor;
+mod explorer;
+mod generate_config;
+mod generate_systemd;
+mod grpc;
+mod health;
 mod hl_gossip_config;
 mod hl_visor;
+mod hooks;
+mod http_server;
+mod info_proxy;
+mod lock;
+mod log_shipping;
+mod maintenance;
+mod metrics;
+mod migrate;
 mod monitor;
+mod ntp;
+mod parquet_export;
+mod peer_list;
+mod preflight;
+mod procfs;
 mod prune;
+mod publish;
+mod pushgateway;
+mod reachability;
+mod reaper;
+mod registration;
+mod reload;
+mod replica_stream;
+mod restart_lock;
+mod role;
+mod run_flags;
+mod secrets;
+mod self_update;
+mod snapshot;
 mod speedtest;
+mod statsd;
+mod status;
+mod storage;
+mod supervisor;
 mod sysctl;
+mod systemd;
+mod telemetry;
+mod tls;
+mod validation;
+mod validator_config;
+mod verify_data;
+mod watchdog;
 
 use crate::{
+    alerts::{AlertArgs, AlertEvent, send_alert},
+    anchor::{AnchorArgs, AnchorMode},
+    auth::{Access, AuthArgs, health_client_key},
+    backfill::{BackfillArgs, backfill},
+    backup::{
+        BackupArgs, RestoreArgs, backup_worker_task, list_backups, restore_backup,
+        running_node_processes,
+    },
+    cloudwatch::{CloudWatchArgs, cloudwatch_task},
+    compress::{CompressArgs, compress_worker_task},
+    data_api::{DataApiArgs, run_data_api_server},
+    data_dir::HlHome,
+    dedup::{DedupArgs, dedup_worker_task},
+    diagnostics::{CrashDiagnostics, DiagnosticsArgs},
+    disk_usage::{DiskUsageArgs, disk_usage_task},
+    doctor::Doctor,
+    generate_config::{GenerateConfigArgs, GeneratedConfig, StarterConfig},
+    generate_systemd::{GenerateSystemdArgs, SystemdUnit, bootstrap_environment, global_args},
+    grpc::{GrpcArgs, run_grpc_server},
+    health::{HealthArgs, HealthChecks, HealthReport, Probes},
     hl_gossip_config::{
         HyperliquidChain, HyperliquidSeedPeer, OverrideGossipConfig, fetch_hyperliquid_seed_peers,
     },
-    hl_visor::{config::write_hl_visor_config, download::download_hl_visor},
-    prune::prune_worker_task,
+    hl_visor::{
+        config::{hl_visor_config_changed, write_hl_visor_config},
+        download::{check_hl_binary_update, download_hl_binary},
+    },
+    hooks::{HookArgs, Hooks},
+    http_server::HttpServerArgs,
+    info_proxy::{InfoProxyArgs, run_info_proxy_server},
+    lock::InstanceLock,
+    log_shipping::{LogShipper, LogShippingArgs},
+    migrate::{MigrateDataArgs, migrate_data},
+    monitor::{
+        evm::{EvmArgs, poll_evm},
+        validator::{ValidatorMonitorArgs, validator_monitor_task},
+    },
+    parquet_export::{ParquetExportArgs, parquet_export_task},
+    peer_list::{PeerListArgs, fetch_peer_list},
+    preflight::{PreflightArgs, check_disk_speed, run_preflight},
+    prune::{prune_data, prune_worker_task},
+    publish::{PublishArgs, publish_task},
+    pushgateway::{PushgatewayArgs, push_metrics},
+    registration::{Registration, RegistrationArgs},
+    reload::Tunables,
+    replica_stream::{ReplicaStreamArgs, run_replica_stream_server},
+    restart_lock::{RestartLock, RestartLockArgs},
+    role::NodeRole,
+    run_flags::RunFlagsArgs,
+    self_update::{SelfUpdateArgs, self_update},
+    snapshot::{SnapshotArgs, restore_snapshot},
     speedtest::speedtest_nodes,
-    sysctl::read_sysctl,
+    statsd::{StatsdArgs, statsd_task},
+    status::{Phase, report::StatusReport, version::VersionReport},
+    storage::{Bucket, StorageArgs},
+    supervisor::{
+        SupervisorArgs, exit_code,
+        limits::{ChildLimits, ResourceArgs, raise_open_files_limit},
+        log_file::{RotatingLog, set_child_log},
+        output::set_recent_output_capacity,
+        privileges::RunAs,
+        supervise,
+    },
+    sysctl::{apply_recommended_sysctls, disable_ipv6, pending_sysctls, read_sysctl},
+    telemetry::OtlpExporter,
+    validation::Problems,
+    validator_config::{ValidatorConfigArgs, write_node_config},
+    verify_data::{VerifyDataArgs, verify_data},
+    watchdog::{WatchdogArgs, liveness_probe_task, memory_watchdog_task, stall_watchdog_task},
 };
 
 #[derive(Clone, Debug, Parser)]
+#[command(version = status::version::VERSION)]
 struct Cli {
-    /// Path where to store hl-visor and its files
+    /// TOML configuration file with flags as keys (e.g. `metrics-listen-address = "0.0.0.0:9090"`).
+    /// Environment variables and command line flags take precedence over it
+    #[arg(long, env = "HL_BOOTSTRAP_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Configuration file profile to apply over the top level settings, e.g. `testnet`
+    #[arg(long, env = "HL_BOOTSTRAP_PROFILE")]
+    profile: Option<String>,
+
+    /// Preset of defaults for the kind of node: seed peers amount, pruning, required health checks and the hl-visor
+    /// command to run when no args are given. Anything set explicitly overrides the preset
+    #[arg(long, env = "HL_BOOTSTRAP_NODE_ROLE")]
+    node_role: Option<NodeRole>,
+
+    /// Do all fetches & measurements, but only print what would change instead of writing configuration & binaries
+    /// or running anything
+    #[arg(long, env = "HL_BOOTSTRAP_DRY_RUN")]
+    dry_run: bool,
+
+    /// Path where to store hl-visor (or hl-node in node run mode) and its files. Required to run the node and for
+    /// `download`
     #[arg(long, env = "HL_BOOTSTRAP_VISOR_BINARY_DIRECTORY")]
-    visor_binary_directory: PathBuf,
+    visor_binary_directory: Option<PathBuf>,
+
+    /// hl home directory (the one containing hl/data), used as the child working directory and for pruning, disk
+    /// usage & health checks. Defaults to current working directory
+    #[arg(long, env = "HL_BOOTSTRAP_DATA_DIR")]
+    data_dir: Option<PathBuf>,
+
+    /// Whether to run hl-node through hl-visor, or to run hl-node directly with hl-bootstrap managing the binary
+    #[arg(long, env = "HL_BOOTSTRAP_RUN_MODE", default_value = "visor")]
+    run_mode: RunMode,
+
+    /// Binary to execute after the setup, e.g. a wrapper around hl-visor. Defaults to hl-visor, or hl-node in node run mode
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_BINARY")]
+    child_binary: Option<OsString>,
+
+    /// Extra environment variables for the child as KEY=VALUE. Multiple entries in env var are separated by `;`
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_ENV",
+        value_delimiter = ';',
+        value_parser = parse_env_var
+    )]
+    child_env: Vec<(String, String)>,
+
+    /// Start the child with a clean environment, containing only --child-env-keep and --child-env variables
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_ENV_CLEAR", default_value_t = false)]
+    child_env_clear: bool,
+
+    /// Variables passed through to the child when --child-env-clear is set
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_ENV_KEEP",
+        value_delimiter = ',',
+        default_value = "PATH,HOME"
+    )]
+    child_env_keep: Vec<String>,
+
+    #[command(flatten)]
+    snapshot: SnapshotArgs,
+
+    #[command(flatten)]
+    backup: BackupArgs,
+
+    /// Unprivileged user to start the child as. hl-bootstrap has to be started as root then, which lets it apply
+    /// sysctls & limits before the node starts without root
+    #[arg(long, env = "HL_BOOTSTRAP_RUN_AS_USER")]
+    run_as_user: Option<String>,
 
     /// override_gossip_config.json path
     #[arg(
@@ -62,7 +253,16 @@ struct Cli {
     )]
     override_public_ip_address_file_path: PathBuf,
 
-    /// override_gossip_config.json max age when new peers will be checked & set up
+    /// PID file locked for the lifetime of hl-bootstrap (and hl-visor), preventing concurrent instances
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PID_FILE",
+        default_value = "./hl-bootstrap.pid"
+    )]
+    pid_file: PathBuf,
+
+    /// override_gossip_config.json max age when new peers will be checked & set up. While the node runs, it's refreshed
+    /// this often
     #[arg(
         long,
         env = "HL_BOOTSTRAP_OVERRIDE_GOSSIP_CONFIG_MAX_AGE",
@@ -70,6 +270,11 @@ struct Cli {
     )]
     override_gossip_config_max_age: DurationString,
 
+    /// Warn & alert when override_gossip_config.json gets older than this multiple of its max age, catching a broken
+    /// refresh (e.g. restarts or an external job failing to rewrite it). Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_OVERRIDE_GOSSIP_CONFIG_STALE_FACTOR")]
+    override_gossip_config_stale_factor: Option<u32>,
+
     /// How many seed peers to keep in the configuration
     #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_AMOUNT", default_value_t = 5)]
     seed_peers_amount: usize,
@@ -82,6 +287,18 @@ struct Cli {
     )]
     seed_peers_max_latency: DurationString,
 
+    /// How many seed peer speedtest runs to keep for /speedtest/history
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_SPEEDTEST_HISTORY_SIZE",
+        default_value_t = 20
+    )]
+    speedtest_history_size: usize,
+
+    /// File to persist speedtest history to, so runs from previous starts are kept as well
+    #[arg(long, env = "HL_BOOTSTRAP_SPEEDTEST_HISTORY_FILE")]
+    speedtest_history_file: Option<PathBuf>,
+
     /// Ignore known bad seed peers by IP
     #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_IGNORED", value_delimiter = ',')]
     seed_peers_ignored: Vec<Ipv4Addr>,
@@ -110,6 +327,16 @@ struct Cli {
     )]
     ignore_ipv6_enabled: bool,
 
+    /// Disable IPv6 on all interfaces through sysctls before starting, instead of failing when it's enabled.
+    /// Needs root
+    #[arg(long, env = "HL_BOOTSTRAP_DISABLE_IPV6")]
+    disable_ipv6: bool,
+
+    /// Write recommended sysctls (IPv6 disabled, larger net.core buffers & backlogs, somaxconn) before starting,
+    /// logging the values before and after. Needs root, failures only warn
+    #[arg(long, env = "HL_BOOTSTRAP_APPLY_SYSCTLS")]
+    apply_sysctls: bool,
+
     /// Whether to spawn data directory pruning task. This is used when hl-bootstrap has child process to execute
     #[arg(long, env = "HL_BOOTSTRAP_PRUNE_DATA_INTERVAL")]
     prune_data_interval: Option<DurationString>,
@@ -130,7 +357,31 @@ struct Cli {
     )]
     metrics_status_poll_interval: DurationString,
 
-    /// How much is node allowed to be behind the system time before reporting it unhealthy
+    /// How often should visor_abci_state.json and hl/data be read for block height & activity metrics
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_METRICS_ABCI_STATE_POLL_INTERVAL",
+        default_value = "1s"
+    )]
+    metrics_abci_state_poll_interval: DurationString,
+
+    /// How often should latency to the selected seed peers be measured
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_METRICS_PEER_LATENCY_POLL_INTERVAL",
+        default_value = "30s"
+    )]
+    metrics_peer_latency_poll_interval: DurationString,
+
+    /// How often should established gossip connections be counted
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_METRICS_GOSSIP_CONNECTIONS_POLL_INTERVAL",
+        default_value = "10s"
+    )]
+    metrics_gossip_connections_poll_interval: DurationString,
+
+    /// How much is node allowed to be behind the system time before reporting it unhealthy (/readyz)
     #[arg(
         long,
         env = "HL_BOOTSTRAP_METRICS_HEALTHY_DRIFT_THRESHOLD",
@@ -138,16 +389,628 @@ struct Cli {
     )]
     metrics_healthy_drift_threshold: DurationString,
 
+    /// How far behind the public Hyperliquid API the node may be to be considered synced (/ready, is-synced)
+    #[arg(long, env = "HL_BOOTSTRAP_SYNC_MAX_LAG", default_value = "5s")]
+    sync_max_lag: DurationString,
+
+    /// Address for the health server reporting bootstrap phase and child status on /healthz. Started before setup
+    #[arg(long, env = "HL_BOOTSTRAP_HEALTH_LISTEN_ADDRESS")]
+    health_listen_address: Option<SocketAddr>,
+
+    /// Flag file for maintenance mode. When set, maintenance mode is on while the file exists, and the /maintenance
+    /// endpoint creates/removes it
+    #[arg(long, env = "HL_BOOTSTRAP_MAINTENANCE_FILE")]
+    maintenance_file: Option<PathBuf>,
+
+    /// Log filter directives, e.g. `info,hl_bootstrap::prune=debug`. Defaults to RUST_LOG, reloaded on SIGHUP
+    #[arg(long, env = "HL_BOOTSTRAP_LOG_LEVEL")]
+    log_level: Option<String>,
+
+    /// OTLP/HTTP endpoint to export traces to, e.g. `http://otel-collector:4318/v1/traces`
+    #[arg(long, env = "HL_BOOTSTRAP_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
     /// Chain to set up configuration for
     #[arg(long, env = "HL_BOOTSTRAP_NETWORK", default_value_t = HyperliquidChain::Mainnet)]
     network: HyperliquidChain,
 
-    /// Free form args to execute after the setup
+    #[command(flatten)]
+    health: HealthArgs,
+
+    #[command(flatten)]
+    evm: EvmArgs,
+
+    #[command(flatten)]
+    validator_monitor: ValidatorMonitorArgs,
+
+    #[command(flatten)]
+    preflight: PreflightArgs,
+
+    #[command(flatten)]
+    alerts: AlertArgs,
+
+    #[command(flatten)]
+    log_shipping: LogShippingArgs,
+
+    #[command(flatten)]
+    statsd: StatsdArgs,
+
+    #[command(flatten)]
+    pushgateway: PushgatewayArgs,
+
+    #[command(flatten)]
+    cloudwatch: CloudWatchArgs,
+
+    #[command(flatten)]
+    supervisor: SupervisorArgs,
+
+    #[command(flatten)]
+    watchdog: WatchdogArgs,
+
+    #[command(flatten)]
+    restart_lock: RestartLockArgs,
+
+    #[command(flatten)]
+    registration: RegistrationArgs,
+
+    #[command(flatten)]
+    resources: ResourceArgs,
+
+    #[command(flatten)]
+    hooks: HookArgs,
+
+    #[command(flatten)]
+    diagnostics: DiagnosticsArgs,
+
+    #[command(flatten)]
+    disk_usage: DiskUsageArgs,
+
+    #[command(flatten)]
+    compress: CompressArgs,
+
+    #[command(flatten)]
+    dedup: DedupArgs,
+
+    #[command(flatten)]
+    storage: StorageArgs,
+
+    #[command(flatten)]
+    parquet_export: ParquetExportArgs,
+
+    #[command(flatten)]
+    replica_stream: ReplicaStreamArgs,
+
+    #[command(flatten)]
+    data_api: DataApiArgs,
+
+    #[command(flatten)]
+    info_proxy: InfoProxyArgs,
+
+    #[command(flatten)]
+    grpc: GrpcArgs,
+
+    #[command(flatten)]
+    peer_list: PeerListArgs,
+
+    #[command(flatten)]
+    anchor: AnchorArgs,
+
+    #[command(flatten)]
+    run_flags: RunFlagsArgs,
+
+    #[command(flatten)]
+    validator_config: ValidatorConfigArgs,
+
+    #[command(flatten)]
+    auth: AuthArgs,
+
+    #[command(flatten)]
+    http_server: HttpServerArgs,
+
+    #[command(flatten)]
+    publish: PublishArgs,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Free form args to execute after the setup, same as `run <ARGS>...`
     args: Vec<OsString>,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+enum RunMode {
+    /// Run hl-visor, which downloads and updates hl-node by itself
+    Visor,
+    /// Download & verify hl-node and run it directly, bypassing hl-visor and its updater
+    Node,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum Commands {
+    /// Download the binary, set up the configuration and exit
+    Bootstrap,
+    /// Set up everything, then run hl-visor (or hl-node) with the given args
+    Run {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        args: Vec<OsString>,
+    },
+    /// Prune old files from the data directory once, honoring --prune-data-older-than
+    Prune,
+    /// Measure latency to the seed peers and print the ones which would be picked, without writing anything
+    Speedtest,
+    /// Download & verify hl-visor (or hl-node in node run mode) without setting up anything else
+    Download,
+    /// Write a starter visor.json, override_gossip_config.json and hl-bootstrap.toml for the network, based on the
+    /// given flags
+    GenerateConfig(GenerateConfigArgs),
+    /// Write a hardened systemd unit (Type=notify with watchdog, sandboxing, restart policy) running hl-bootstrap with
+    /// the current global flags & HL_BOOTSTRAP_* environment. Secrets are left for /etc/default/hl-bootstrap
+    GenerateSystemd(GenerateSystemdArgs),
+    /// Run preflight checks of the host (sysctl, IPv6, network tuning, ulimits, disk, CPU & memory, NTP & clock,
+    /// egress, gpg) and print a report, exiting non-zero on failures
+    Doctor,
+    /// Benchmark sequential & fsync-heavy writes on the data directory filesystem against the
+    /// --preflight-disk-min-* thresholds, exiting non-zero when it's too slow for mainnet
+    BenchDisk,
+    /// Check whether the local node is in sync with the network, exiting non-zero when it's not
+    IsSynced,
+    /// Query the health checks of the running instance over its health or metrics address, exiting non-zero when
+    /// it's unhealthy or unreachable, e.g. as Docker HEALTHCHECK
+    Health,
+    /// Print a human readable summary of the node: gossip config, binaries, child, disk usage and sync lag
+    Status,
+    /// Print hl-bootstrap, hl-visor & hl-node versions and the network, for support requests
+    Version,
+    /// Replace the hl-bootstrap binary with the latest release after verifying its checksum
+    SelfUpdate(SelfUpdateArgs),
+    /// List backups in the configured bucket, or restore one into the data directory while the node is stopped
+    Restore(RestoreArgs),
+    /// Scan hl/data for empty & truncated files, unparseable JSON lines and missing hourly files, exiting non-zero
+    /// when any are found
+    VerifyData(VerifyDataArgs),
+    /// Sync the data directory to another directory or S3-compatible storage, transferring only what changed since
+    /// the previous pass, to move a node between machines
+    MigrateData(MigrateDataArgs),
+    /// Download days of historical node data (fills, replica_cmds, ...) from Hyperliquid's public requester-pays
+    /// bucket into hl/data, skipping files already present, to fill gaps in an archive without replaying
+    Backfill(BackfillArgs),
+}
+
+fn parse_env_var(value: &str) -> eyre::Result<(String, String)> {
+    let (key, value) = value
+        .split_once('=')
+        .wrap_err("expected environment variable in KEY=VALUE format")?;
+    if key.is_empty() {
+        bail!("environment variable name can't be empty");
+    }
+
+    Ok((key.to_string(), value.to_string()))
+}
+
+impl Cli {
+    fn child_binary(&self) -> OsString {
+        self.child_binary.clone().unwrap_or_else(|| {
+            match self.run_mode {
+                RunMode::Visor => "hl-visor",
+                RunMode::Node => "hl-node",
+            }
+            .into()
+        })
+    }
+
+    fn data_directory(&self) -> eyre::Result<PathBuf> {
+        data_dir::resolve(self.data_dir.as_deref())
+    }
+
+    fn visor_binary_directory(&self) -> eyre::Result<&Path> {
+        self.visor_binary_directory
+            .as_deref()
+            .wrap_err("--visor-binary-directory is not set")
+    }
+
+    /// Catches settings which parse fine, but contradict each other or can't work, reporting all of them at once
+    fn validate(&self) -> eyre::Result<()> {
+        let mut problems = Problems::default();
+
+        problems.check(
+            self.seed_peers_amount > 0,
+            "--seed-peers-amount is 0, no seed peers would be selected",
+            "set --seed-peers-amount to at least 1, e.g. 5",
+        );
+        problems.check(
+            !Duration::from(self.seed_peers_max_latency).is_zero(),
+            "--seed-peers-max-latency is 0, every seed peer would be rejected",
+            "set --seed-peers-max-latency to e.g. 200ms",
+        );
+        problems.check(
+            self.anchor.seed_peers_anchor.is_some()
+                || self.anchor.seed_peers_anchor_mode != AnchorMode::Adjust,
+            "--seed-peers-anchor-mode is adjust without --seed-peers-anchor",
+            "set --seed-peers-anchor to a host near the seed peers, e.g. in Tokyo",
+        );
+        problems.check(
+            self.visor_binary_directory.is_some()
+                || !matches!(self.command, None | Some(Commands::Download)),
+            "--visor-binary-directory is not set, but needed to download and run hl-visor",
+            "set --visor-binary-directory, e.g. /opt/hl",
+        );
+        problems.check(
+            self.backup.backup_retention >= 1,
+            "--backup-retention is 0, every backup would be deleted right after uploading",
+            "set --backup-retention to at least 1, e.g. 7",
+        );
+        problems.check(
+            self.override_gossip_config_stale_factor != Some(0),
+            "--override-gossip-config-stale-factor is 0, the gossip config would always be stale",
+            "set --override-gossip-config-stale-factor to at least 1, or unset it",
+        );
+
+        if self.prune_data_interval.is_some() {
+            problems.check(
+                self.node_role != Some(NodeRole::Archive),
+                "--prune-data-interval is set, but archive nodes keep all data",
+                "unset --prune-data-interval, or use another --node-role",
+            );
+            problems.check(
+                !Duration::from(self.prune_data_older_than).is_zero(),
+                "--prune-data-older-than is 0, pruning would remove files hl-node is still writing",
+                "set --prune-data-older-than to e.g. 4h",
+            );
+            problems.check(
+                self.data_directory()
+                    .is_ok_and(|path| path.parent().is_some()),
+                "--prune-data-interval is set without --data-dir, while running from /",
+                "pass the hl home as --data-dir",
+            );
+        }
+
+        if self.validator_config.enabled() {
+            problems.check(
+                matches!(self.node_role, None | Some(NodeRole::Validator)),
+                "the validator node config is set, but --node-role is not validator",
+                "unset --validator-signer-key & --validator-node-config, or use --node-role validator",
+            );
+            problems.check(
+                self.args
+                    .first()
+                    .is_none_or(|arg| arg != "run-non-validator"),
+                "the validator node config is set, but hl-visor runs run-non-validator",
+                "run hl-visor with run-validator",
+            );
+        }
+
+        problems.check(
+            self.preflight.preflight_disk_bench_size.as_u64() > 0,
+            "--preflight-disk-bench-size is 0, the disk benchmark would measure nothing",
+            "set --preflight-disk-bench-size to e.g. 256MiB",
+        );
+        problems.check(
+            (0.0..=1.0).contains(&self.validator_monitor.validator_min_uptime),
+            "--validator-min-uptime is not a fraction between 0 and 1",
+            "set --validator-min-uptime to e.g. 0.95",
+        );
+
+        let intervals = [
+            ("--prune-data-interval", self.prune_data_interval),
+            (
+                "--metrics-status-poll-interval",
+                Some(self.metrics_status_poll_interval),
+            ),
+            (
+                "--metrics-abci-state-poll-interval",
+                Some(self.metrics_abci_state_poll_interval),
+            ),
+            (
+                "--metrics-peer-latency-poll-interval",
+                Some(self.metrics_peer_latency_poll_interval),
+            ),
+            (
+                "--metrics-gossip-connections-poll-interval",
+                Some(self.metrics_gossip_connections_poll_interval),
+            ),
+            (
+                "--metrics-evm-poll-interval",
+                Some(self.evm.metrics_evm_poll_interval),
+            ),
+            (
+                "--validator-poll-interval",
+                Some(self.validator_monitor.validator_poll_interval),
+            ),
+            (
+                "--info-proxy-timeout",
+                Some(self.info_proxy.info_proxy_timeout),
+            ),
+            (
+                "--http-header-timeout",
+                Some(self.http_server.http_header_timeout),
+            ),
+            (
+                "--http-request-timeout",
+                Some(self.http_server.http_request_timeout),
+            ),
+            ("--statsd-interval", Some(self.statsd.statsd_interval)),
+            (
+                "--cloudwatch-interval",
+                Some(self.cloudwatch.cloudwatch_interval),
+            ),
+            ("--register-ttl", Some(self.registration.register_ttl)),
+            ("--disk-usage-interval", self.disk_usage.disk_usage_interval),
+            (
+                "--compress-data-interval",
+                self.compress.compress_data_interval,
+            ),
+            ("--dedup-data-interval", self.dedup.dedup_data_interval),
+            ("--backup-interval", self.backup.backup_interval),
+            (
+                "--parquet-export-interval",
+                self.parquet_export.parquet_export_interval,
+            ),
+            (
+                "--publish-poll-interval",
+                Some(self.publish.publish_poll_interval),
+            ),
+            (
+                "--replica-stream-poll-interval",
+                Some(self.replica_stream.replica_stream_poll_interval),
+            ),
+            (
+                "--stall-watchdog-timeout",
+                self.watchdog.stall_watchdog_timeout,
+            ),
+            (
+                "--liveness-probe-interval",
+                self.watchdog.liveness_probe_interval,
+            ),
+            (
+                "--memory-watchdog-interval",
+                Some(self.watchdog.memory_watchdog_interval),
+            ),
+        ];
+        for (name, interval) in intervals {
+            problems.check(
+                interval.is_none_or(|interval| !Duration::from(interval).is_zero()),
+                format!("{name} is 0"),
+                format!("set {name} to a positive duration, e.g. 10s"),
+            );
+        }
+
+        problems.check(
+            self.http_server.http_max_connections > 0,
+            "--http-max-connections is 0",
+            "set --http-max-connections to at least 1",
+        );
+        problems.check(
+            self.resources.open_files_min <= self.resources.open_files_limit,
+            format!(
+                "--open-files-min ({}) is above --open-files-limit ({})",
+                self.resources.open_files_min, self.resources.open_files_limit
+            ),
+            "raise --open-files-limit or lower --open-files-min",
+        );
+        problems.check(
+            self.resources
+                .child_cpu_max
+                .is_none_or(|cpu_max| cpu_max.is_finite() && cpu_max > 0.0),
+            "--child-cpu-max is not a positive number of cores",
+            "set --child-cpu-max to e.g. 7.5, or unset it",
+        );
+        problems.check(
+            self.health_listen_address.is_none()
+                || self.health_listen_address != self.metrics_listen_address,
+            "--health-listen-address and --metrics-listen-address are the same",
+            "drop --health-listen-address, the metrics server serves health endpoints too",
+        );
+        problems.check(
+            self.replica_stream.replica_stream_buffer > 0,
+            "--replica-stream-buffer is 0",
+            "set --replica-stream-buffer to e.g. 1024",
+        );
+        problems.check(
+            self.data_api.data_api_max_limit > 0,
+            "--data-api-max-limit is 0",
+            "set --data-api-max-limit to e.g. 1000",
+        );
+        problems.check(
+            !Duration::from(self.data_api.data_api_max_range).is_zero(),
+            "--data-api-max-range is 0, every query would be rejected",
+            "set --data-api-max-range to e.g. 24h",
+        );
+        problems.check(
+            !self.info_proxy.info_proxy_types.is_empty(),
+            "--info-proxy-types is empty, every query would be rejected",
+            "set --info-proxy-types to e.g. clearinghouseState,openOrders",
+        );
+        problems.check(
+            self.statsd.statsd_address.is_some()
+                || (!self.statsd.statsd_dogstatsd && self.statsd.statsd_tags.is_empty()),
+            "--statsd-dogstatsd or --statsd-tags is set without --statsd-address",
+            "set --statsd-address, or drop the StatsD settings",
+        );
+        if self.alerts.alert_smtp_host.is_some() {
+            problems.check(
+                self.alerts.alert_email_from.is_some() && !self.alerts.alert_email_to.is_empty(),
+                "--alert-smtp-host is set without --alert-email-from or --alert-email-to",
+                "set the sender with --alert-email-from and recipients with --alert-email-to",
+            );
+        }
+        if self.cloudwatch.cloudwatch_namespace.is_some() {
+            problems.check(
+                self.cloudwatch.region().is_some(),
+                "--cloudwatch-namespace is set without a region",
+                "set --cloudwatch-region or AWS_REGION",
+            );
+        }
+        problems.check(
+            self.data_api.data_api_tls_cert.is_some() == self.data_api.data_api_tls_key.is_some(),
+            "only one of --data-api-tls-cert and --data-api-tls-key is set",
+            "set both the certificate and its private key",
+        );
+        problems.check(
+            self.data_api.data_api_tls_client_ca.is_none()
+                || self.data_api.data_api_tls_cert.is_some(),
+            "--data-api-tls-client-ca is set without --data-api-tls-cert",
+            "client certificates need TLS, set --data-api-tls-cert and --data-api-tls-key",
+        );
+        problems.check(
+            self.peer_list.seed_peers_list.is_some()
+                || (self.peer_list.seed_peers_list_signature.is_none()
+                    && self.peer_list.seed_peers_list_ed25519_key.is_none()
+                    && self.peer_list.seed_peers_list_pgp_keyring.is_none()),
+            "peer list signature settings are set without --seed-peers-list",
+            "set the peer list location with --seed-peers-list",
+        );
+        problems.check(
+            self.peer_list.seed_peers_list_ed25519_key.is_none()
+                || self.peer_list.seed_peers_list_pgp_keyring.is_none(),
+            "both --seed-peers-list-ed25519-key and --seed-peers-list-pgp-keyring are set",
+            "keep the one the peer list is signed with",
+        );
+        if !self.auth.trusted_proxies.is_empty() {
+            problems.check(
+                !self.auth.allow_ips.is_empty(),
+                "--trusted-proxies is set without --allow-ips",
+                "set the allowed client networks with --allow-ips",
+            );
+        }
+        if self.auth.api_keys_health {
+            problems.check(
+                !self.auth.api_keys.is_empty() || self.auth.api_keys_file.is_some(),
+                "--api-keys-health is set without any API keys",
+                "set --api-keys or --api-keys-file",
+            );
+        }
+        if self.compress.compress_data_interval.is_some() {
+            problems.check(
+                Duration::from(self.compress.compress_data_older_than)
+                    >= Duration::from_secs(60 * 60),
+                "--compress-data-older-than is below 1h, hl-node may still be writing hourly files",
+                "set --compress-data-older-than to at least 1h, e.g. 2h",
+            );
+            problems.check(
+                zstd::compression_level_range().contains(&self.compress.compress_data_level),
+                format!(
+                    "--compress-data-level {} is not a valid zstd level",
+                    self.compress.compress_data_level
+                ),
+                "set --compress-data-level between 1 and 19",
+            );
+        }
+        if self.backup.backup_interval.is_some() {
+            problems.check(
+                self.storage.is_configured(),
+                "--backup-interval is set without object storage",
+                "set --s3-bucket or --storage-url to the bucket backups are uploaded to",
+            );
+            problems.check(
+                self.backup.backup_retention > 0,
+                "--backup-retention is 0, every backup would be deleted right after uploading it",
+                "set --backup-retention to at least 1",
+            );
+        }
+
+        if self.parquet_export.parquet_export_interval.is_some() {
+            problems.check(
+                self.parquet_export.parquet_export_directory.is_some()
+                    != self.parquet_export.parquet_export_s3_prefix.is_some(),
+                "--parquet-export-interval needs exactly one of --parquet-export-directory and --parquet-export-s3-prefix",
+                "set --parquet-export-directory to write locally, or --parquet-export-s3-prefix to upload",
+            );
+            if self.parquet_export.parquet_export_s3_prefix.is_some() {
+                problems.check(
+                    self.storage.is_configured(),
+                    "--parquet-export-s3-prefix is set without object storage",
+                    "set --s3-bucket or --storage-url",
+                );
+            }
+        }
+
+        if self.publish.enabled() {
+            problems.check(
+                self.publish.publish_nats_url.is_none()
+                    || self.publish.publish_kafka_brokers.is_none(),
+                "both --publish-nats-url and --publish-kafka-brokers are set",
+                "publish to either NATS or Kafka, run another hl-bootstrap for the other",
+            );
+            problems.check(
+                !self.publish.publish_datasets.is_empty(),
+                "--publish-datasets is empty",
+                "set --publish-datasets to hl/data subdirectories, e.g. replica_cmds",
+            );
+        }
+
+        problems.into_result()
+    }
+
+    /// Settings reloaded on SIGHUP
+    fn tunables(&self) -> Tunables {
+        Tunables {
+            prune_data_interval: self.prune_data_interval.map(Into::into),
+            prune_data_older_than: self.prune_data_older_than.into(),
+            seed_peers_max_latency: anchor::max_latency(self.seed_peers_max_latency.into()),
+            seed_peers_ignored: HashSet::from_iter(self.seed_peers_ignored.clone()),
+            log_level: self.log_level.clone(),
+        }
+    }
+
+    /// Whether node metrics need to be collected, for either Prometheus, StatsD or CloudWatch
+    fn metrics_enabled(&self) -> bool {
+        self.metrics_listen_address.is_some()
+            || self.statsd.statsd_address.is_some()
+            || self.cloudwatch.cloudwatch_namespace.is_some()
+    }
+
+    /// Whether hl-bootstrap needs to stay around as hl-visor parent process instead of exec-ing into it
+    fn supervise_child(&self) -> bool {
+        self.prune_data_interval.is_some()
+            || self.disk_usage.disk_usage_interval.is_some()
+            || self.compress.compress_data_interval.is_some()
+            || self.dedup.dedup_data_interval.is_some()
+            || self.backup.backup_interval.is_some()
+            || self.parquet_export.parquet_export_interval.is_some()
+            || self.replica_stream.replica_stream_listen_address.is_some()
+            || self.data_api.data_api_listen_address.is_some()
+            || self.info_proxy.info_proxy_listen_address.is_some()
+            || self.grpc.grpc_listen_address.is_some()
+            || self.publish.enabled()
+            || self.metrics_enabled()
+            || self.health_listen_address.is_some()
+            || self.registration.register_consul_url.is_some()
+            || self.registration.register_etcd_url.is_some()
+            || self.override_gossip_config_stale_factor.is_some()
+            || self.watchdog.stall_watchdog_timeout.is_some()
+            || self.watchdog.liveness_probe_interval.is_some()
+            || self.watchdog.memory_watchdog_limit.is_some()
+            || self.diagnostics.crash_diagnostics_dir.is_some()
+            || self.supervisor.capture_output()
+            || self.supervisor.child_subreaper
+            || reaper::is_init()
+            || systemd::notify_enabled()
+    }
+}
+
 fn main() -> eyre::Result<()> {
-    let args = Cli::parse();
+    let mut args: Cli = config::parse()?;
+
+    if args.command.is_none()
+        && args.args.is_empty()
+        && let Some(role) = args.node_role
+    {
+        args.args = role.child_args().iter().map(OsString::from).collect();
+    }
+
+    let setup_only = matches!(args.command, Some(Commands::Bootstrap));
+    // `bootstrap` and `run` are the same as invoking without a subcommand, which is kept for existing entrypoints
+    match &mut args.command {
+        Some(Commands::Bootstrap) => {
+            args.command = None;
+            args.args.clear();
+        }
+        Some(Commands::Run { args: child_args }) => {
+            args.args = std::mem::take(child_args);
+            args.command = None;
+        }
+        _ => {}
+    }
 
     // As hl-bootstrap is usually used in ENTRYPOINT, then bail out when first argument is
     // obviously not related to Hyperliquid (e.g. just running bash, for whatever purpose)
@@ -160,115 +1023,1044 @@ fn main() -> eyre::Result<()> {
         std::process::exit(1);
     }
 
+    if args.command.is_none() && !setup_only {
+        args.args = args.run_flags.apply(std::mem::take(&mut args.args))?;
+    }
+
+    args.validate()?;
+
+    let otlp_exporter = args
+        .otlp_endpoint
+        .as_deref()
+        .map(OtlpExporter::new)
+        .transpose()?;
+    let log_shipper = args
+        .log_shipping
+        .enabled()
+        .then(|| LogShipper::new(&args.log_shipping, args.network))
+        .transpose()?;
+
+    let (log_filter, log_filter_handle) =
+        tracing_subscriber::reload::Layer::new(reload::log_filter(args.log_level.as_deref()));
     tracing_subscriber::registry()
+        .with(log_filter)
+        .with(otlp_exporter.as_ref().map(OtlpExporter::layer))
+        .with(log_shipper.as_ref().map(LogShipper::layer))
         .with(
             fmt::layer()
                 .with_writer(|| Box::new(std::io::stderr()))
                 .with_target(true)
                 .with_span_events(FmtSpan::CLOSE),
         )
-        .with(
-            EnvFilter::builder()
-                .with_default_directive(LevelFilter::INFO.into())
-                .from_env_lossy(),
-        )
         .init();
 
     trace!(?args, "args");
 
-    let use_mt = args.prune_data_interval.is_some() || args.metrics_listen_address.is_some();
+    if let Some(command) = &args.command {
+        return run_command(command, &args);
+    }
+
+    // Returns before speedtest::history::configure, so the seed peer speedtest of a dry run isn't persisted either
+    if args.dry_run {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        runtime.block_on(prepare_hl_node(&args))?;
+        if !args.args.is_empty() {
+            println!("would run {:?} {:?}", args.child_binary(), args.args);
+        }
+        return Ok(());
+    }
+
+    let instance_lock = InstanceLock::acquire(&args.pid_file)?;
 
-    let runtime = if use_mt {
+    if let Some(maintenance_file) = &args.maintenance_file {
+        maintenance::set_flag_file(maintenance_file.clone());
+    }
+
+    crate::metrics::init();
+    speedtest::history::configure(
+        args.speedtest_history_size,
+        args.speedtest_history_file.clone(),
+    );
+    alerts::init(&args.alerts, args.network);
+
+    let runtime = if args.supervise_child() {
         Builder::new_multi_thread()
     } else {
         Builder::new_current_thread()
     }
     .enable_all()
     .build()?;
-    runtime.block_on(prepare_hl_node(&args))?;
+
+    // Before the tunables are taken, so they include the calibrated seed peer latency
+    runtime.block_on(anchor::calibrate(
+        &args.anchor,
+        args.seed_peers_max_latency.into(),
+    ));
+    reload::init(args.tunables());
+
+    let data_directory = args.data_directory()?;
+    match data_dir::inspect(&data_directory)? {
+        HlHome::Existing => {}
+        HlHome::Fresh => info!(
+            ?data_directory,
+            "data directory is empty, hl-node will initialize it"
+        ),
+        HlHome::Unrecognized => warn!(
+            ?data_directory,
+            "data directory has no hl/ directory, make sure --data-dir points at the hl home"
+        ),
+    }
+    let health_checks = HealthChecks {
+        required: args.health.health_required_checks.clone(),
+        data_directory: data_directory.clone(),
+        disk_min_free: args.health.health_disk_min_free,
+        network: args.network,
+        sync_max_lag: args.sync_max_lag.into(),
+        evm: args.evm.sync(args.network, &args.args),
+    };
+    let probes = Probes {
+        child_down_grace: args.health.livez_child_down_grace.into(),
+        ready_max_drift: args.metrics_healthy_drift_threshold.into(),
+        startup_max_drift: args.health.startupz_max_drift.into(),
+    };
+
+    let access = Access::from_args(&args.auth)?;
+
+    let _health_server = args.health_listen_address.map(|address| {
+        let health_checks = health_checks.clone();
+        let probes = probes.clone();
+        let access = access.clone();
+        let http = args.http_server.clone();
+        runtime.spawn(async move {
+            info!(%address, "starting health server");
+            if let Err(err) = crate::monitor::server::run_health_server(
+                address,
+                health_checks,
+                probes,
+                access,
+                http,
+            )
+            .await
+            {
+                error!(?err, "failed to start health server")
+            }
+        })
+    });
+
+    let _metrics_server = args.metrics_listen_address.map(|address| {
+        let network = args.network;
+        let sync_max_lag = args.sync_max_lag.into();
+        let health_checks = health_checks.clone();
+        let access = access.clone();
+        let http = args.http_server.clone();
+        runtime.spawn(async move {
+            info!(%address, "starting metrics server");
+            if let Err(err) = crate::monitor::server::run_metrics_server(
+                address,
+                network,
+                sync_max_lag,
+                health_checks,
+                probes,
+                access,
+                http,
+            )
+            .await
+            {
+                error!(?err, "failed to start metrics server")
+            }
+        })
+    });
+
+    let _statsd_task = args
+        .statsd
+        .statsd_address
+        .clone()
+        .map(|address| runtime.spawn(statsd_task(address, args.statsd.clone())));
+
+    let _cloudwatch_task = args
+        .cloudwatch
+        .cloudwatch_namespace
+        .clone()
+        .map(|namespace| runtime.spawn(cloudwatch_task(namespace, args.cloudwatch.clone())));
+
+    let hooks = Hooks::new(&args.hooks, args.network, &data_directory);
+
+    let setup = runtime.block_on(async {
+        let result = async {
+            hooks.pre_start().await?;
+            prepare_hl_node(&args).await?;
+            hooks.post_setup().await
+        }
+        .await;
+        if let Err(err) = &result {
+            send_alert(
+                AlertEvent::BootstrapFailed,
+                &format!("setup failed: {err:#}"),
+            )
+            .await;
+        }
+        result
+    });
+    if setup.is_ok() {
+        status::set_phase(Phase::SetupDone);
+    }
 
     if args.args.is_empty() {
+        // Nothing stays around to be scraped after setup-only runs, failed ones included
+        runtime.block_on(push_metrics(&args.pushgateway));
+        setup?;
         info!("setup done");
         return Ok(());
     }
+    setup?;
+
+    // Setup spans and logs would be lost when exec-ing into the child
+    if let Some(otlp_exporter) = &otlp_exporter {
+        otlp_exporter.flush();
+    }
+    if let Some(log_shipper) = &log_shipper {
+        log_shipper.flush();
+    }
 
-    run_node(runtime, &args)?;
+    let status = run_node(
+        runtime,
+        &args,
+        &data_directory,
+        &hooks,
+        &instance_lock,
+        log_filter_handle,
+        access,
+    )?;
+    if !status.success() {
+        // std::process::exit skips destructors, release the pid file and flush traces & logs first
+        drop(instance_lock);
+        drop(otlp_exporter);
+        drop(log_shipper);
+        std::process::exit(exit_code(status));
+    }
 
     Ok(())
 }
 
-fn run_node(rt: Runtime, args: &Cli) -> eyre::Result<()> {
-    info!(args = ?args.args, "setup done, executing hl-visor");
+fn run_command(command: &Commands, args: &Cli) -> eyre::Result<()> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+
+    match command {
+        Commands::Bootstrap | Commands::Run { .. } => unreachable!("handled in main"),
+        Commands::Prune => {
+            let data_directory = args.data_directory()?;
+            if data_dir::inspect(&data_directory)? != HlHome::Existing {
+                bail!("data directory {data_directory:?} has no hl/ directory, nothing to prune");
+            }
+            let result = runtime.block_on(prune_data(
+                &data_directory,
+                args.prune_data_older_than.into(),
+            ));
+            runtime.block_on(push_metrics(&args.pushgateway));
+            result?;
+        }
+        Commands::Speedtest => {
+            for seed_peer in runtime.block_on(select_seed_peers(args))? {
+                println!("{} ({})", seed_peer.ip, seed_peer.operator_name);
+            }
+        }
+        Commands::Download => {
+            let name = match args.run_mode {
+                RunMode::Visor => "hl-visor",
+                RunMode::Node => "hl-node",
+            };
+            runtime.block_on(download_hl_binary(
+                args.visor_binary_directory()?,
+                args.network,
+                name,
+            ))?;
+        }
+        Commands::Doctor => {
+            let doctor = Doctor {
+                network: args.network,
+                data_directory: args.data_directory()?,
+                preflight: args.preflight.clone(),
+                ipv6_allowed: args.ignore_ipv6_enabled || args.external_ipv4.is_some(),
+            };
+            let sections = runtime.block_on(doctor.run());
+            print!("{}", doctor::render(&sections));
+            std::process::exit(doctor::exit_code(&sections));
+        }
+        Commands::BenchDisk => {
+            let data_directory = args.data_directory()?;
+            if !data_directory.is_dir() {
+                bail!("data directory {data_directory:?} doesn't exist");
+            }
+            println!("{}", check_disk_speed(&data_directory, &args.preflight)?);
+        }
+        Commands::GenerateConfig(generate_args) => {
+            let starter_config = StarterConfig {
+                network: args.network.to_string(),
+                visor_binary_directory: args.visor_binary_directory.clone(),
+                data_dir: args.data_dir.clone(),
+                override_gossip_config_path: generate_args
+                    .output_dir
+                    .join("override_gossip_config.json"),
+                override_gossip_config_max_age: args.override_gossip_config_max_age.to_string(),
+                seed_peers_amount: args.seed_peers_amount,
+                seed_peers_max_latency: args.seed_peers_max_latency.to_string(),
+                reserved_peers: args.reserved_peers.clone(),
+                try_new_peers: args.try_new_peers,
+            };
+            let seed_peers: Vec<_> = if generate_args.with_seed_peers {
+                runtime
+                    .block_on(select_seed_peers(args))?
+                    .into_iter()
+                    .map(|seed_peer| seed_peer.ip)
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let generated = GeneratedConfig::new(args.network, &starter_config, &seed_peers)?;
+            config::validate::<Cli>(&generated.bootstrap_config)
+                .wrap_err("generated configuration is invalid")?;
+            for path in generated.write(&generate_args.output_dir, generate_args.force)? {
+                println!("wrote {}", path.display());
+            }
+        }
+        Commands::GenerateSystemd(systemd_args) => {
+            let data_directory = std::path::absolute(args.data_directory()?)?;
+            let mut read_write_paths = BTreeSet::from([data_directory.clone()]);
+            read_write_paths.extend(args.visor_binary_directory.clone());
+            let files = [
+                Some(&args.override_gossip_config_path),
+                Some(&args.override_public_ip_address_file_path),
+                Some(&args.pid_file),
+                args.maintenance_file.as_ref(),
+                args.speedtest_history_file.as_ref(),
+                args.supervisor.child_log_file.as_ref(),
+            ];
+            // Relative paths are relative to the working directory, which is the data directory in the unit
+            for file in files.into_iter().flatten() {
+                if let Some(parent) = data_directory.join(file).parent() {
+                    read_write_paths.insert(parent.to_path_buf());
+                }
+            }
+
+            let (environment, secrets) = bootstrap_environment();
+            for secret in secrets {
+                warn!(
+                    secret,
+                    "not writing secret into the unit, put it into /etc/default/hl-bootstrap"
+                );
+            }
+            let mut exec_start = vec![std::env::current_exe()?.to_string_lossy().into_owned()];
+            exec_start.extend(global_args("generate-systemd"));
+            if !systemd_args.args.is_empty() {
+                exec_start.push("--".to_string());
+                exec_start.extend(
+                    systemd_args
+                        .args
+                        .iter()
+                        .map(|arg| arg.to_string_lossy().into_owned()),
+                );
+            }
+
+            let unit = SystemdUnit {
+                description: format!("Hyperliquid node ({})", args.network.to_string()),
+                exec_start,
+                user: args
+                    .run_as_user
+                    .is_none()
+                    .then(|| systemd_args.user.clone()),
+                working_directory: data_directory,
+                read_write_paths,
+                environment,
+                watchdog: systemd_args.watchdog.into(),
+                open_files_limit: args.resources.open_files_limit,
+                writes_sysctls: args.apply_sysctls || args.disable_ipv6,
+                delegate_cgroup: args.resources.child_memory_max.is_some()
+                    || args.resources.child_cpu_max.is_some(),
+            };
+            match &systemd_args.output {
+                Some(path) => {
+                    unit.write(path, systemd_args.force)?;
+                    println!("wrote {}", path.display());
+                }
+                None => print!("{}", unit.render()),
+            }
+        }
+        Commands::IsSynced => {
+            let lag = runtime.block_on(crate::monitor::sync_lag(args.network))?;
+            let synced = lag < args.sync_max_lag;
+            println!(
+                "network: {}, lag: {lag:?}, max lag: {}, synced: {synced}",
+                args.network.to_string(),
+                args.sync_max_lag
+            );
+
+            if !synced {
+                std::process::exit(1);
+            }
+        }
+        Commands::Health => {
+            let address = args
+                .health_listen_address
+                .or(args.metrics_listen_address)
+                .wrap_err("neither --health-listen-address nor --metrics-listen-address is set")?;
+            let api_key = health_client_key(&args.auth)?;
+            let report = runtime.block_on(HealthReport::fetch(address, api_key.as_deref()))?;
+            print!("{}", report.render());
+
+            if !report.healthy {
+                std::process::exit(1);
+            }
+        }
+        Commands::Status => {
+            let report = StatusReport {
+                network: args.network,
+                data_directory: args.data_directory()?,
+                visor_binary_directory: args.visor_binary_directory.clone(),
+                gossip_config_path: args.override_gossip_config_path.clone(),
+                pid_file: args.pid_file.clone(),
+                disk_usage_directories: args.disk_usage.disk_usage_directories.clone(),
+                health_address: args.health_listen_address.or(args.metrics_listen_address),
+                health_api_key: health_client_key(&args.auth)?,
+            };
+            print!("{}", runtime.block_on(report.render()));
+        }
+        Commands::Version => {
+            let report = VersionReport {
+                network: args.network,
+                visor_binary_directory: args.visor_binary_directory.clone(),
+            };
+            print!("{}", runtime.block_on(report.render()));
+        }
+        Commands::SelfUpdate(self_update_args) => {
+            runtime.block_on(self_update(self_update_args))?;
+        }
+        Commands::Restore(restore_args) => {
+            let bucket = Bucket::from_args(&args.storage)?;
+            if restore_args.list {
+                for backup in runtime.block_on(list_backups(&bucket))? {
+                    println!(
+                        "{}\t{}\t{}",
+                        backup
+                            .key
+                            .strip_prefix(&bucket.prefix)
+                            .unwrap_or(&backup.key),
+                        ByteSize::b(backup.size),
+                        backup.last_modified
+                    );
+                }
+                return Ok(());
+            }
+
+            let data_directory = args.data_directory()?;
+            // Held until the restore is done, so hl-bootstrap can't start the node meanwhile
+            let _instance_lock = InstanceLock::acquire(&args.pid_file)
+                .wrap_err("hl-bootstrap is running, stop it before restoring")?;
+            let running = running_node_processes();
+            if !running.is_empty() {
+                bail!("node processes are running, stop them before restoring: {running:?}");
+            }
+            let owner = args
+                .run_as_user
+                .as_deref()
+                .map(RunAs::resolve)
+                .transpose()?;
+
+            let key = runtime.block_on(restore_backup(
+                &bucket,
+                restore_args.backup.as_deref(),
+                &data_directory,
+                owner.as_ref(),
+            ))?;
+            println!("restored {key} into {}", data_directory.display());
+        }
+        Commands::VerifyData(verify_data_args) => {
+            let report = verify_data(&args.data_directory()?, verify_data_args);
+            print!("{}", report.render());
+            if !report.issues.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::MigrateData(migrate_args) => {
+            let data_directory = args.data_directory()?;
+            // Pulling writes into the data directory, which the node must not be using
+            let _instance_lock = migrate_args
+                .pull
+                .then(|| {
+                    InstanceLock::acquire(&args.pid_file)
+                        .wrap_err("hl-bootstrap is running, stop it before pulling")
+                })
+                .transpose()?;
+            if migrate_args.pull {
+                let running = running_node_processes();
+                if !running.is_empty() {
+                    bail!("node processes are running, stop them before pulling: {running:?}");
+                }
+            }
+
+            let report =
+                runtime.block_on(migrate_data(&data_directory, migrate_args, &args.storage))?;
+            print!("{}", report.render());
+            if migrate_args.pull
+                && !migrate_args.dry_run
+                && let Some(user) = &args.run_as_user
+            {
+                RunAs::resolve(user)?.chown_recursive(&data_directory)?;
+            }
+            if !report.failed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Backfill(backfill_args) => {
+            let data_directory = args.data_directory()?;
+            let report = runtime.block_on(backfill(
+                &data_directory,
+                backfill_args,
+                args.network,
+                &args.storage,
+            ))?;
+            print!("{}", report.render());
+            if report.downloaded > 0
+                && let Some(user) = &args.run_as_user
+            {
+                let run_as = RunAs::resolve(user)?;
+                for dataset in &backfill_args.datasets {
+                    let path = data_directory.join("hl/data").join(dataset);
+                    if path.exists() {
+                        run_as.chown_recursive(&path)?;
+                    }
+                }
+            }
+            if !report.failed.is_empty() {
+                std::process::exit(1);
+            }
+        }
+    }
 
-    if args.prune_data_interval.is_none() && args.metrics_listen_address.is_none() {
+    Ok(())
+}
+
+/// Fetches seed peers of the network and picks the ones with the lowest latency
+async fn select_seed_peers(args: &Cli) -> eyre::Result<Vec<HyperliquidSeedPeer>> {
+    let ignored_seed_peers = HashSet::from_iter(args.seed_peers_ignored.clone());
+    let mut candidates = fetch_hyperliquid_seed_peers(args.network, &ignored_seed_peers).await?;
+    if let Some(peers) = fetch_peer_list(&args.peer_list, &ignored_seed_peers).await? {
+        candidates.extend(peers);
+    }
+    candidates.extend(args.seed_peers_extra.iter().map(|ip| HyperliquidSeedPeer {
+        operator_name: "manual".to_string(),
+        ip: *ip,
+    }));
+
+    let max_latency = anchor::calibrate(&args.anchor, args.seed_peers_max_latency.into()).await;
+    speedtest_nodes(candidates, args.seed_peers_amount, max_latency).await
+}
+
+fn run_node(
+    rt: Runtime,
+    args: &Cli,
+    data_directory: &Path,
+    hooks: &Hooks,
+    instance_lock: &InstanceLock,
+    log_filter_handle: tracing_subscriber::reload::Handle<EnvFilter, Registry>,
+    access: Access,
+) -> eyre::Result<ExitStatus> {
+    info!(
+        child_binary = ?args.child_binary(),
+        args = ?args.args,
+        ?data_directory,
+        "setup done, executing child"
+    );
+
+    rt.block_on(run_preflight(&args.preflight, data_directory, &args.args))?;
+    raise_open_files_limit(
+        args.resources.open_files_limit,
+        args.resources.open_files_min,
+    )?;
+    let limits = ChildLimits::prepare(&args.resources)?;
+    let run_as = args
+        .run_as_user
+        .as_deref()
+        .map(RunAs::resolve)
+        .transpose()?;
+    if let Some(run_as) = &run_as {
+        run_as.check_ownership(data_directory)?;
+    }
+
+    if !args.supervise_child() {
         drop(rt);
+        instance_lock.inherit_on_exec()?;
 
         // Just exec into the child
-        let err = Command::new("hl-visor").args(&args.args).exec();
+        let err = child_command(args, data_directory, limits, run_as).exec();
         error!(?err, ?args.args, "failed to exec");
         std::process::exit(1);
     }
 
-    // TODO: configurable in future
-    let data_directory = current_dir().wrap_err("failed to get current working directory")?;
+    let _reload_task = rt.spawn(reload::reload_task(
+        || {
+            let args = config::try_parse::<Cli>()?;
+            args.validate()?;
+            Ok(args.tunables())
+        },
+        log_filter_handle,
+    ));
 
-    let _prune_task = args.prune_data_interval.map(|prune_interval| {
-        rt.spawn({
-            let prune_data_older_than = args.prune_data_older_than;
+    // Pruning can be enabled by a reload as well
+    let _prune_task = rt.spawn(prune_worker_task(
+        data_directory.to_path_buf(),
+        reload::subscribe(),
+    ));
 
-            prune_worker_task(
-                data_directory,
-                prune_interval.into(),
-                prune_data_older_than.into(),
-            )
-        })
+    let _disk_usage_task = args.disk_usage.disk_usage_interval.map(|measure_interval| {
+        rt.spawn(disk_usage_task(
+            data_directory.to_path_buf(),
+            args.disk_usage.disk_usage_directories.clone(),
+            measure_interval.into(),
+        ))
     });
 
-    let _poll_task = args.metrics_listen_address.is_some().then(|| {
-        rt.spawn(crate::monitor::poll_node(
-            args.metrics_status_poll_interval.into(),
+    let _compress_task = args
+        .compress
+        .compress_data_interval
+        .map(|compress_interval| {
+            rt.spawn(compress_worker_task(
+                data_directory.to_path_buf(),
+                args.compress.clone(),
+                compress_interval.into(),
+            ))
+        });
+
+    let _dedup_task = args.dedup.dedup_data_interval.map(|dedup_interval| {
+        rt.spawn(dedup_worker_task(
+            data_directory.to_path_buf(),
+            args.dedup.clone(),
+            dedup_interval.into(),
         ))
     });
 
-    let _metrics_server = args.metrics_listen_address.map(|address| {
-        let metrics_healthy_drift_threshold = args.metrics_healthy_drift_threshold.into();
+    let _backup_task = args
+        .backup
+        .backup_interval
+        .map(|backup_interval| -> eyre::Result<_> {
+            let bucket = Bucket::from_args(&args.storage)?;
+            Ok(rt.spawn(backup_worker_task(
+                data_directory.to_path_buf(),
+                args.backup.clone(),
+                bucket,
+                backup_interval.into(),
+            )))
+        })
+        .transpose()?;
+
+    let _parquet_export_task = args
+        .parquet_export
+        .parquet_export_interval
+        .map(|export_interval| -> eyre::Result<_> {
+            let bucket = args
+                .parquet_export
+                .parquet_export_s3_prefix
+                .is_some()
+                .then(|| Bucket::from_args(&args.storage))
+                .transpose()?;
+            Ok(rt.spawn(parquet_export_task(
+                data_directory.to_path_buf(),
+                args.parquet_export.clone(),
+                bucket,
+                export_interval.into(),
+            )))
+        })
+        .transpose()?;
+
+    let _replica_stream_server = args
+        .replica_stream
+        .replica_stream_listen_address
+        .map(|address| {
+            let data_directory = data_directory.to_path_buf();
+            let replica_stream = args.replica_stream.clone();
+            let access = access.clone();
+            let http = args.http_server.clone();
+            rt.spawn(async move {
+                if let Err(err) =
+                    run_replica_stream_server(address, data_directory, replica_stream, access, http)
+                        .await
+                {
+                    error!(?err, "failed to start replica_cmds stream server")
+                }
+            })
+        });
+
+    let _data_api_server = args.data_api.data_api_listen_address.map(|address| {
+        let data_directory = data_directory.to_path_buf();
+        let data_api = args.data_api.clone();
+        let access = access.clone();
+        let http = args.http_server.clone();
         rt.spawn(async move {
-            info!(%address, "starting metrics server");
             if let Err(err) =
-                crate::monitor::server::run_metrics_server(address, metrics_healthy_drift_threshold)
-                    .await
+                run_data_api_server(address, data_directory, data_api, access, http).await
             {
-                error!(?err, "failed to start metrics server")
+                error!(?err, "failed to start data API server")
+            }
+        })
+    });
+
+    let _info_proxy_server = args.info_proxy.info_proxy_listen_address.map(|address| {
+        let info_proxy = args.info_proxy.clone();
+        let access = access.clone();
+        let http = args.http_server.clone();
+        rt.spawn(async move {
+            if let Err(err) = run_info_proxy_server(address, info_proxy, access, http).await {
+                error!(?err, "failed to start info endpoint proxy")
             }
         })
     });
 
-    let mut child = Command::new("hl-visor")
-        .args(&args.args)
-        .spawn()
-        .wrap_err("failed to spawn child")?;
+    let _grpc_server = args
+        .grpc
+        .grpc_listen_address
+        .map(|address| -> eyre::Result<_> {
+            let data_directory = data_directory.to_path_buf();
+            let gossip_config_path = args.override_gossip_config_path.clone();
+            let backup = args.backup.clone();
+            let access = access.clone();
+            let bucket = args
+                .storage
+                .is_configured()
+                .then(|| Bucket::from_args(&args.storage))
+                .transpose()?;
+            Ok(rt.spawn(async move {
+                if let Err(err) = run_grpc_server(
+                    address,
+                    data_directory,
+                    gossip_config_path,
+                    backup,
+                    bucket,
+                    reload::subscribe(),
+                    access,
+                )
+                .await
+                {
+                    error!(?err, "failed to start gRPC server")
+                }
+            }))
+        })
+        .transpose()?;
 
-    child.wait().wrap_err("failed to wait for child")?;
+    let _publish_task = args.publish.enabled().then(|| {
+        rt.spawn(publish_task(
+            data_directory.to_path_buf(),
+            args.publish.clone(),
+            args.publish.publish_poll_interval.into(),
+        ))
+    });
 
-    Ok(())
+    let _poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::poll_node(
+            args.metrics_status_poll_interval.into(),
+        ))
+    });
+
+    let _abci_state_poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::abci_state::poll_abci_state(
+            data_directory.to_path_buf(),
+            args.metrics_abci_state_poll_interval.into(),
+        ))
+    });
+
+    let _local_height_poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::height::poll_local_height(
+            data_directory.to_path_buf(),
+            args.metrics_abci_state_poll_interval.into(),
+        ))
+    });
+
+    let _activity_poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::activity::poll_activity(
+            data_directory.to_path_buf(),
+            args.metrics_abci_state_poll_interval.into(),
+        ))
+    });
+
+    let _consensus_poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::consensus::poll_consensus_status(
+            data_directory.to_path_buf(),
+            args.metrics_abci_state_poll_interval.into(),
+        ))
+    });
+
+    let _evm_poll_task = args
+        .evm
+        .sync(args.network, &args.args)
+        .filter(|_| args.metrics_enabled())
+        .map(|sync| rt.spawn(poll_evm(sync, args.evm.metrics_evm_poll_interval.into())));
+
+    let _gossip_connections_poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::gossip::poll_gossip_connections(
+            args.metrics_gossip_connections_poll_interval.into(),
+        ))
+    });
+
+    let _peer_latency_poll_task = args.metrics_enabled().then(|| {
+        rt.spawn(crate::monitor::peers::poll_peer_latency(
+            args.override_gossip_config_path.clone(),
+            args.metrics_peer_latency_poll_interval.into(),
+            reload::subscribe(),
+        ))
+    });
+
+    let _gossip_config_refresh_task = rt.spawn(gossip_config_refresh_task(args.clone()));
+
+    let _gossip_config_staleness_task =
+        args.override_gossip_config_stale_factor
+            .map(|stale_factor| {
+                let max_age: Duration = args.override_gossip_config_max_age.into();
+                rt.spawn(crate::monitor::gossip::gossip_config_staleness_task(
+                    args.override_gossip_config_path.clone(),
+                    max_age * stale_factor,
+                ))
+            });
+
+    let _validator_monitor_task = args
+        .validator_monitor
+        .validator_address
+        .clone()
+        .map(|address| {
+            rt.spawn(validator_monitor_task(
+                args.network,
+                address,
+                args.validator_monitor.validator_min_uptime,
+                args.validator_monitor.validator_poll_interval.into(),
+            ))
+        });
+
+    let _watchdog_task = args.watchdog.stall_watchdog_timeout.map(|stall_timeout| {
+        rt.spawn(stall_watchdog_task(
+            stall_timeout.into(),
+            args.watchdog.stall_watchdog_action,
+        ))
+    });
+
+    let _liveness_probe_task = args.watchdog.liveness_probe_interval.map(|probe_interval| {
+        rt.spawn(liveness_probe_task(
+            probe_interval.into(),
+            args.watchdog.liveness_probe_failure_threshold,
+        ))
+    });
+
+    let _memory_watchdog_task = args.watchdog.memory_watchdog_limit.map(|limit| {
+        rt.spawn(memory_watchdog_task(
+            limit,
+            args.watchdog.memory_watchdog_interval.into(),
+            args.watchdog.memory_watchdog_action,
+        ))
+    });
+
+    let _systemd_watchdog_task = rt.spawn(systemd::watchdog_task());
+
+    let _reaper_task = (args.supervisor.child_subreaper || reaper::is_init())
+        .then(|| {
+            if !reaper::is_init() {
+                reaper::enable_subreaper()?;
+            }
+            eyre::Ok(rt.spawn(reaper::reaper_task()))
+        })
+        .transpose()?;
+
+    let diagnostics = args
+        .diagnostics
+        .crash_diagnostics_dir
+        .as_ref()
+        .map(|output_dir| {
+            if args.supervisor.capture_output() {
+                set_recent_output_capacity(args.diagnostics.crash_diagnostics_output_lines);
+            }
+
+            CrashDiagnostics {
+                output_dir: output_dir.clone(),
+                data_directory: data_directory.to_path_buf(),
+                gossip_config_path: args.override_gossip_config_path.clone(),
+            }
+        });
+
+    if let Some(child_log_file) = &args.supervisor.child_log_file {
+        set_child_log(RotatingLog::open(
+            child_log_file.clone(),
+            args.supervisor.child_log_max_size,
+            args.supervisor.child_log_rotate_interval.map(Into::into),
+            args.supervisor.child_log_retention,
+        )?);
+    }
+
+    let restart_lock = RestartLock::from_args(&args.restart_lock)?;
+
+    let registration = Registration::from_args(
+        &args.registration,
+        args.network,
+        args.node_role,
+        args.health_listen_address.or(args.metrics_listen_address),
+    )?
+    .map(Arc::new);
+    let _registration_task = registration.clone().map(|registration| {
+        rt.spawn(async move {
+            registration.run().await;
+        })
+    });
+
+    let command = child_command(args, data_directory, limits, run_as).into();
+    let status = rt.block_on(supervise(
+        command,
+        &args.supervisor,
+        hooks,
+        diagnostics.as_ref(),
+        restart_lock.as_ref(),
+    ));
+    if let Some(registration) = &registration {
+        rt.block_on(registration.deregister());
+    }
+    let status = status?;
+    info!(%status, "child exited");
+
+    Ok(status)
 }
 
+fn child_command(
+    args: &Cli,
+    data_directory: &Path,
+    limits: ChildLimits,
+    run_as: Option<RunAs>,
+) -> Command {
+    let mut command = Command::new(args.child_binary());
+    if args.run_mode == RunMode::Node {
+        // hl-visor passes the chain to hl-node the same way
+        command.arg("--chain").arg(args.network.to_string());
+    }
+    command.args(&args.args).current_dir(data_directory);
+
+    if args.child_env_clear {
+        command.env_clear();
+        for key in &args.child_env_keep {
+            if let Some(value) = std::env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+    }
+    for var in systemd::NOTIFY_ENV_VARS {
+        command.env_remove(var);
+    }
+    command.envs(args.child_env.iter().map(|(key, value)| (key, value)));
+
+    limits.apply_to(&mut command);
+    if let Some(run_as) = run_as {
+        run_as.apply_to(&mut command);
+    }
+    command
+}
+
+#[instrument(skip_all, fields(network = ?args.network))]
 async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
-    if cfg!(target_os = "linux") && !args.ignore_ipv6_enabled && args.external_ipv4.is_none() {
+    if args.apply_sysctls {
+        if args.dry_run {
+            for (key, current, recommended) in pending_sysctls() {
+                println!("would set sysctl {key} = {recommended} (currently {current})");
+            }
+        } else {
+            apply_recommended_sysctls();
+        }
+    }
+
+    if args.disable_ipv6 {
+        if args.dry_run {
+            println!("would disable IPv6 on all interfaces");
+        } else {
+            disable_ipv6()?;
+        }
+    }
+
+    // A dry run with --disable-ipv6 only printed what it would do
+    if cfg!(target_os = "linux")
+        && !args.ignore_ipv6_enabled
+        && args.external_ipv4.is_none()
+        && !(args.dry_run && args.disable_ipv6)
+    {
         let key_ipv6_all = "net.ipv6.conf.all.disable_ipv6";
         if let Ok(value) = read_sysctl(key_ipv6_all)
             && value == "0"
         {
-            bail!("ipv6 appears to be enabled, set sysctl net.ipv6.conf.all.disable_ipv6=1");
+            bail!(
+                "ipv6 appears to be enabled, set sysctl net.ipv6.conf.all.disable_ipv6=1 or pass --disable-ipv6"
+            );
         }
     }
 
-    info!(network = ?args.network, "setting up hl-visor");
-    download_hl_visor(&args.visor_binary_directory, args.network).await?;
-    write_hl_visor_config(args.visor_binary_directory.join("visor.json"), args.network)?;
+    status::set_phase(Phase::DownloadingVisor);
+    let binary_name = match args.run_mode {
+        RunMode::Visor => "hl-visor",
+        RunMode::Node => "hl-node",
+    };
+    info!(network = ?args.network, "setting up {binary_name}");
+    if args.dry_run {
+        let update =
+            check_hl_binary_update(args.visor_binary_directory()?, args.network, binary_name)
+                .await?;
+        match update {
+            Some(etag) => println!("would download {binary_name} (etag {etag})"),
+            None => println!("{binary_name} is up to date"),
+        }
+    } else {
+        download_hl_binary(args.visor_binary_directory()?, args.network, binary_name).await?;
+    }
+
+    if args.run_mode == RunMode::Visor {
+        let visor_config_path = args.visor_binary_directory()?.join("visor.json");
+        if !args.dry_run {
+            write_hl_visor_config(visor_config_path, args.network)?;
+        } else if hl_visor_config_changed(&visor_config_path, args.network) {
+            println!("would write {}", visor_config_path.display());
+        }
+    }
+
+    let data_directory = args.data_directory()?;
+    if let Some(url) = &args.snapshot.snapshot_url
+        && !data_directory.join("hl").exists()
+    {
+        status::set_phase(Phase::RestoringSnapshot);
+        if args.dry_run {
+            println!(
+                "would restore snapshot {url} into {}",
+                data_directory.display()
+            );
+        } else {
+            let owner = args
+                .run_as_user
+                .as_deref()
+                .map(RunAs::resolve)
+                .transpose()?;
+            restore_snapshot(
+                url,
+                args.snapshot.snapshot_sha256.as_deref(),
+                &data_directory,
+                owner.as_ref(),
+            )
+            .await?;
+        }
+    }
+
+    if args.validator_config.enabled() {
+        let owner = args
+            .run_as_user
+            .as_deref()
+            .map(RunAs::resolve)
+            .transpose()?;
+        write_node_config(
+            &args.validator_config,
+            &data_directory,
+            owner.as_ref(),
+            args.dry_run,
+        )?;
+    }
 
+    status::set_phase(Phase::ConfiguringPeers);
     info!(network = ?args.network, "preparing hl-node configuration");
-    let ignored_seed_peers = HashSet::from_iter(args.seed_peers_ignored.clone());
 
     if let Ok(metadata) = fs::metadata(&args.override_gossip_config_path)
         && metadata.is_file()
@@ -282,21 +2074,57 @@ async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
             gossip_config_path = ?args.override_gossip_config_path,
             "gossip config last modified"
         );
-        if last_modified <= args.override_gossip_config_max_age {
+        if last_modified <= args.override_gossip_config_max_age && args.dry_run {
+            println!(
+                "{} is not older than {}, would keep it. Measuring seed peers anyway",
+                args.override_gossip_config_path.display(),
+                args.override_gossip_config_max_age
+            );
+        } else if last_modified <= args.override_gossip_config_max_age {
             debug!(
                 ?mtime,
                 gossip_config_path = ?args.override_gossip_config_path,
                 "gossip config modified recently, not updating seed peers"
             );
+            // Written by a previous run
+            let written_at_ms = mtime
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64;
+            status::update(|status| status.gossip_config_written_at_ms = Some(written_at_ms));
             return Ok(());
         }
     }
 
+    write_gossip_config(args).await
+}
+
+/// Rewrites override_gossip_config.json every --override-gossip-config-max-age while the node runs, so child restarts
+/// pick up current seed peers
+async fn gossip_config_refresh_task(args: Cli) {
+    let max_age: Duration = args.override_gossip_config_max_age.into();
+    let mut interval = interval_at(Instant::now() + max_age, max_age);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        interval.tick().await;
+
+        match write_gossip_config(&args).await {
+            Ok(()) => info!("refreshed gossip config"),
+            Err(err) => warn!(?err, "failed to refresh gossip config"),
+        }
+    }
+}
+
+/// Measures the seed peers and writes override_gossip_config.json with the fastest ones
+async fn write_gossip_config(args: &Cli) -> eyre::Result<()> {
     let config_path_directory = args
         .override_gossip_config_path
         .parent()
         .wrap_err("failed to determine override_gossip_config.json directory")?;
 
+    let ignored_seed_peers = HashSet::from_iter(args.seed_peers_ignored.clone());
+
     // TODO: load existing configuration
     let mut config = OverrideGossipConfig::new(args.network, args.try_new_peers);
     config
@@ -307,6 +2135,15 @@ async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
     let mut seed_nodes = fetch_hyperliquid_seed_peers(args.network, &ignored_seed_peers).await?;
     info!(network = ?args.network, count = seed_nodes.len(), "got seed nodes");
 
+    if let Some(peers) = fetch_peer_list(&args.peer_list, &ignored_seed_peers).await? {
+        info!(
+            network = ?args.network,
+            count = peers.len(),
+            "including seed peers from peer list"
+        );
+        seed_nodes.extend(peers);
+    }
+
     if !args.seed_peers_extra.is_empty() {
         info!(
             network = ?args.network,
@@ -322,24 +2159,21 @@ async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
     }
 
     if !seed_nodes.is_empty() {
-        let tested_seed_nodes = speedtest_nodes(
-            seed_nodes,
-            args.seed_peers_amount,
-            args.seed_peers_max_latency.into(),
-        )
-        .await
-        .wrap_err("failed to measure latency of seed nodes")?;
+        let max_latency = anchor::calibrate(&args.anchor, args.seed_peers_max_latency.into()).await;
+        let tested_seed_nodes = speedtest_nodes(seed_nodes, args.seed_peers_amount, max_latency)
+            .await
+            .wrap_err("failed to measure latency of seed nodes")?;
 
         if tested_seed_nodes.is_empty() {
             bail!(
-                "no seed nodes passed latency threshold, try increasing threshold (current: {})",
-                args.seed_peers_max_latency
+                "no seed nodes passed latency threshold, try increasing threshold (current: {max_latency:?})"
             );
         }
 
         for seed in tested_seed_nodes {
             config.root_node_ips.push(seed.into());
         }
+        crate::metrics::GAUGE_SEED_PEERS.set(config.root_node_ips.len() as i64);
 
         // Adjust n_gossip_peers
         // Allowed range is [1, 100]
@@ -350,6 +2184,35 @@ async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
         }
     }
 
+    if args.dry_run {
+        let new_config = serde_json::to_string_pretty(&config)?;
+        let current_config = fs::read(&args.override_gossip_config_path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<serde_json::Value>(&contents).ok())
+            .and_then(|current| serde_json::to_string_pretty(&current).ok());
+        match current_config {
+            Some(current) if current == new_config => println!(
+                "{} would stay the same",
+                args.override_gossip_config_path.display()
+            ),
+            Some(current) => println!(
+                "would replace {}\ncurrent:\n{current}\nnew:\n{new_config}",
+                args.override_gossip_config_path.display()
+            ),
+            None => println!(
+                "would write {}:\n{new_config}",
+                args.override_gossip_config_path.display()
+            ),
+        }
+        if let Some(external_ipv4) = args.external_ipv4 {
+            println!(
+                "would write {external_ipv4} to {}",
+                args.override_public_ip_address_file_path.display()
+            );
+        }
+        return Ok(());
+    }
+
     let mut new_config_file = NamedTempFile::new_in(config_path_directory)?;
     serde_json::to_writer(&mut new_config_file, &config)
         .wrap_err("failed to write new configuration")?;
@@ -357,6 +2220,7 @@ async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
     new_config_file
         .persist(&args.override_gossip_config_path)
         .wrap_err("failed to replace override_gossip_config.json")?;
+    status::update(|status| status.gossip_config_written_at_ms = Some(status::unix_ms_now()));
 
     if let Some(external_ipv4) = args.external_ipv4 {
         let mut new_override_public_ip_address = NamedTempFile::new_in(config_path_directory)?;
@@ -370,3 +2234,109 @@ async fn prepare_hl_node(args: &Cli) -> eyre::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(args: &[&str]) -> eyre::Result<()> {
+        Cli::try_parse_from(std::iter::once("hl-bootstrap").chain(args.iter().copied()))?.validate()
+    }
+
+    #[test]
+    fn test_validate_accepted() {
+        validate(&["--visor-binary-directory", "/opt/hl"]).unwrap();
+        validate(&[
+            "--visor-binary-directory",
+            "/opt/hl",
+            "--node-role",
+            "non-validator",
+            "--prune-data-interval",
+            "1h",
+            "--data-dir",
+            "/var/lib/hl",
+        ])
+        .unwrap();
+        // Only download and running the node need the binary directory
+        validate(&[
+            "--visor-binary-directory",
+            "/opt/hl",
+            "--child-cpu-max",
+            "7.5",
+        ])
+        .unwrap();
+        validate(&["doctor"]).unwrap();
+        validate(&["speedtest"]).unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejected() {
+        let err = validate(&[]).unwrap_err().to_string();
+        assert!(err.contains("--visor-binary-directory is not set"), "{err}");
+        let err = validate(&["download"]).unwrap_err().to_string();
+        assert!(err.contains("--visor-binary-directory is not set"), "{err}");
+
+        let err = validate(&[
+            "--visor-binary-directory",
+            "/opt/hl",
+            "--node-role",
+            "archive",
+            "--prune-data-interval",
+            "1h",
+            "--data-dir",
+            "/var/lib/hl",
+        ])
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("archive nodes keep all data"), "{err}");
+
+        let err = validate(&[
+            "--visor-binary-directory",
+            "/opt/hl",
+            "--open-files-min",
+            "100",
+            "--open-files-limit",
+            "10",
+        ])
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.contains("--open-files-min (100) is above --open-files-limit (10)"),
+            "{err}"
+        );
+
+        for cpu_max in ["0", "-1", "NaN", "inf"] {
+            let err = validate(&[
+                "--visor-binary-directory",
+                "/opt/hl",
+                &format!("--child-cpu-max={cpu_max}"),
+            ])
+            .unwrap_err()
+            .to_string();
+            assert!(err.contains("--child-cpu-max is not a positive"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let err = validate(&[
+            "--visor-binary-directory",
+            "/opt/hl",
+            "--seed-peers-amount",
+            "0",
+            "--backup-retention",
+            "0",
+            "--statsd-interval",
+            "0s",
+        ])
+        .unwrap_err()
+        .to_string();
+        assert!(
+            err.starts_with("invalid configuration, 3 problem(s):"),
+            "{err}"
+        );
+        assert!(err.contains("--seed-peers-amount is 0"), "{err}");
+        assert!(err.contains("--backup-retention is 0"), "{err}");
+        assert!(err.contains("--statsd-interval is 0"), "{err}");
+    }
+}
@@ -0,0 +1,49 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use eyre::Context;
+use tracing::info;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static FLAG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses the flag file as maintenance state instead of keeping it in memory, so it survives restarts and can be
+/// toggled with a plain `touch`/`rm`
+pub fn set_flag_file(path: PathBuf) {
+    let _ = FLAG_FILE.set(path);
+}
+
+/// Maintenance mode makes readiness report not-ready and pauses watchdog-driven restarts
+pub fn is_enabled() -> bool {
+    match FLAG_FILE.get() {
+        Some(path) => path.exists(),
+        None => ENABLED.load(Ordering::Relaxed),
+    }
+}
+
+pub fn set_enabled(enabled: bool) -> eyre::Result<()> {
+    if let Some(path) = FLAG_FILE.get() {
+        if enabled {
+            fs::write(path, "")
+                .wrap_err_with(|| format!("failed to create maintenance flag file {path:?}"))?;
+        } else if let Err(err) = fs::remove_file(path)
+            && err.kind() != ErrorKind::NotFound
+        {
+            return Err(err)
+                .wrap_err_with(|| format!("failed to remove maintenance flag file {path:?}"));
+        }
+    } else {
+        ENABLED.store(enabled, Ordering::Relaxed);
+    }
+
+    info!(enabled, "maintenance mode changed");
+    Ok(())
+}
@@ -0,0 +1,254 @@
+use std::sync::LazyLock;
+
+use prometheus::{
+    Gauge, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, register_gauge, register_int_counter,
+    register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+};
+
+use crate::status::{self, ChildState, Phase};
+
+// All metrics are registered into the default registry, which is served on /metrics along with the node metrics
+
+pub static GAUGE_PHASE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "hl_bootstrap_phase",
+        "Current hl-bootstrap phase, 1 for the active phase",
+        &["phase"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_SEED_PEERS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_seed_peers",
+        "Amount of seed peers selected into override_gossip_config.json"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_GOSSIP_CONFIG_AGE: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_bootstrap_gossip_config_age_seconds",
+        "Seconds since override_gossip_config.json was written by hl-bootstrap"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_PRUNE_RUNS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!("hl_bootstrap_prune_runs_total", "Data directory prune runs").unwrap()
+});
+
+pub static COUNTER_PRUNE_FILES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_prune_files_total",
+        "Files handled by data directory pruning",
+        &["result"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_BINARY_DOWNLOADS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_binary_downloads_total",
+        "Binary update checks by binary and result (up_to_date, downloaded, failed)",
+        &["binary", "result"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_BINARY_DOWNLOAD_BYTES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_binary_download_bytes_total",
+        "Bytes downloaded for binaries and their signatures",
+        &["binary"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_DATA_DIRECTORY_BYTES: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "hl_bootstrap_data_directory_bytes",
+        "Disk usage of hl/data subdirectories in bytes",
+        &["directory"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_BACKUPS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_backups_total",
+        "State backups by result (success, failed)",
+        &["result"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_LAST_BACKUP_TIMESTAMP: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_last_backup_timestamp_seconds",
+        "Unix timestamp of the last successful state backup"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_COMPRESSED_FILES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_compressed_files_total",
+        "Aged data files handled by compression, by result (compressed, failed)",
+        &["result"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_COMPRESSED_BYTES_SAVED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_bootstrap_compressed_bytes_saved_total",
+        "Disk space reclaimed by compressing aged data files"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_DEDUP_LINKED_FILES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_dedup_files_total",
+        "Identical data files replaced with hardlinks, by result (linked, failed)",
+        &["result"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_DEDUP_BYTES_SAVED: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_bootstrap_dedup_bytes_saved_total",
+        "Disk space reclaimed by hardlinking identical data files"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_PARQUET_EXPORTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_parquet_exports_total",
+        "replica_cmds files exported to Parquet by result (success, failed)",
+        &["result"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_PUBLISHED_RECORDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_published_records_total",
+        "Data file lines acknowledged by Kafka or NATS, by hl/data subdirectory",
+        &["dataset"]
+    )
+    .unwrap()
+});
+
+pub static COUNTER_PUBLISH_FAILURES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_bootstrap_publish_failures_total",
+        "Failed attempts to publish data files, retried on the next poll"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_REPLICA_STREAM_SUBSCRIBERS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_replica_stream_subscribers",
+        "Clients currently subscribed to the replica_cmds stream"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_REPLICA_STREAM_LINES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_bootstrap_replica_stream_lines_total",
+        "replica_cmds lines read for streaming to subscribers"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_INFO_PROXY_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "hl_bootstrap_info_proxy_requests_total",
+        "Requests to the info endpoint proxy by query type & result (hit, miss, collapsed, rejected)",
+        &["type", "result"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_CHILD_UP: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_child_up",
+        "Whether the child process is running"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_CHILD_RESTARTS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_child_restarts",
+        "How many times the child was restarted by hl-bootstrap"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_CHILD_LAST_EXIT_CODE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_child_last_exit_code",
+        "Exit code of the last child exit, -1 when killed by a signal or not exited yet"
+    )
+    .unwrap()
+});
+
+pub fn init() {
+    LazyLock::force(&GAUGE_PHASE);
+    LazyLock::force(&GAUGE_SEED_PEERS);
+    LazyLock::force(&GAUGE_GOSSIP_CONFIG_AGE);
+    LazyLock::force(&COUNTER_PRUNE_RUNS);
+    LazyLock::force(&COUNTER_PRUNE_FILES);
+    LazyLock::force(&COUNTER_BINARY_DOWNLOADS);
+    LazyLock::force(&COUNTER_BINARY_DOWNLOAD_BYTES);
+    LazyLock::force(&GAUGE_DATA_DIRECTORY_BYTES);
+    LazyLock::force(&COUNTER_BACKUPS);
+    LazyLock::force(&GAUGE_LAST_BACKUP_TIMESTAMP);
+    LazyLock::force(&GAUGE_REPLICA_STREAM_SUBSCRIBERS);
+    LazyLock::force(&COUNTER_REPLICA_STREAM_LINES);
+    LazyLock::force(&COUNTER_COMPRESSED_FILES);
+    LazyLock::force(&COUNTER_COMPRESSED_BYTES_SAVED);
+    LazyLock::force(&COUNTER_DEDUP_LINKED_FILES);
+    LazyLock::force(&COUNTER_DEDUP_BYTES_SAVED);
+    LazyLock::force(&COUNTER_PARQUET_EXPORTS);
+    LazyLock::force(&COUNTER_PUBLISHED_RECORDS);
+    LazyLock::force(&COUNTER_PUBLISH_FAILURES);
+    LazyLock::force(&COUNTER_INFO_PROXY_REQUESTS);
+    LazyLock::force(&GAUGE_CHILD_UP);
+    LazyLock::force(&GAUGE_CHILD_RESTARTS);
+    LazyLock::force(&GAUGE_CHILD_LAST_EXIT_CODE);
+}
+
+/// Updates metrics derived from the shared status, called before every scrape
+pub fn refresh() {
+    let status = status::snapshot();
+
+    for phase in [
+        Phase::Starting,
+        Phase::DownloadingVisor,
+        Phase::RestoringSnapshot,
+        Phase::ConfiguringPeers,
+        Phase::SetupDone,
+        Phase::Stopping,
+    ] {
+        GAUGE_PHASE
+            .with_label_values(&[phase.as_str()])
+            .set((phase == status.phase) as i64);
+    }
+
+    if let Some(written_at_ms) = status.gossip_config_written_at_ms {
+        let age_ms = status::unix_ms_now().saturating_sub(written_at_ms);
+        GAUGE_GOSSIP_CONFIG_AGE.set(age_ms as f64 / 1e3);
+    }
+
+    GAUGE_CHILD_UP.set((status.child.state == ChildState::Running) as i64);
+    GAUGE_CHILD_RESTARTS.set(status.child.restarts.into());
+    GAUGE_CHILD_LAST_EXIT_CODE.set(status.child.last_exit_code.unwrap_or(-1).into());
+}
@@ -0,0 +1,756 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use bytesize::ByteSize;
+use clap::Args;
+use eyre::{Context, ContextCompat, bail};
+use hmac_sha256::Hash;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use crate::storage::{Bucket, StorageArgs, hex};
+
+/// Object next to the uploaded files recording what was uploaded, as S3 listings lack modification times
+const MANIFEST_NAME: &str = ".hl-migrate-manifest.json";
+
+/// Suffix of files being copied into a destination directory, renamed into place once complete
+const STAGING_SUFFIX: &str = ".hl-migrate.tmp";
+
+/// How much of the end of a previously copied prefix is compared before appending to it
+const APPEND_CHECK_BYTES: u64 = 1024 * 1024;
+
+#[derive(Clone, Debug, Args)]
+pub struct MigrateDataArgs {
+    /// Directory to sync the data directory into, e.g. the new machine's disk mounted over NFS, or `s3://[prefix]`
+    /// for the configured object storage (--s3-bucket or --storage-url, under its prefix)
+    pub target: String,
+
+    /// Download from the s3:// target into the data directory instead, on the new machine
+    #[arg(long)]
+    pub pull: bool,
+
+    /// Files transferred at once
+    #[arg(long, default_value_t = 8)]
+    pub parallel: usize,
+
+    /// Compare sha256 checksums of transferred files against the source
+    #[arg(long)]
+    pub verify: bool,
+
+    /// Remove files missing from the source, e.g. ones pruned since the previous pass
+    #[arg(long)]
+    pub delete: bool,
+
+    /// Only list what would be transferred & removed
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+enum Target {
+    Directory(PathBuf),
+    Bucket { bucket: Box<Bucket>, prefix: String },
+}
+
+impl Target {
+    fn parse(target: &str, storage: &StorageArgs) -> eyre::Result<Self> {
+        let Some(prefix) = target.strip_prefix("s3://") else {
+            return Ok(Self::Directory(PathBuf::from(target)));
+        };
+        let bucket = Bucket::from_args(storage)?;
+        let mut prefix = format!("{}{prefix}", bucket.prefix);
+        if !prefix.is_empty() && !prefix.ends_with('/') {
+            prefix.push('/');
+        }
+        Ok(Self::Bucket {
+            bucket: Box::new(bucket),
+            prefix,
+        })
+    }
+}
+
+/// Size & modification time decide whether a file changed since the previous pass, like rsync's quick check
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+struct FileMeta {
+    size: u64,
+    /// Nanoseconds since Unix epoch
+    modified: u64,
+    /// Recorded when uploaded with --verify
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+impl FileMeta {
+    fn from_metadata(metadata: &fs::Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Self {
+            size: metadata.len(),
+            modified: modified.as_nanos() as u64,
+            sha256: None,
+        }
+    }
+
+    fn modified_time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_nanos(self.modified)
+    }
+
+    fn unchanged(&self, other: &Self) -> bool {
+        self.size == other.size && self.modified == other.modified
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Transfer {
+    Copy,
+    /// The destination holds an older, shorter version of an append-only file
+    Append {
+        offset: u64,
+    },
+}
+
+/// Files by path relative to the root
+type Listing = BTreeMap<String, FileMeta>;
+
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    pub files: usize,
+    pub unchanged: usize,
+    pub copied: usize,
+    pub appended: usize,
+    pub deleted: usize,
+    pub bytes: u64,
+    /// Planned transfers & removals, only listed with --dry-run
+    pub actions: Vec<String>,
+    pub failed: Vec<(String, String)>,
+}
+
+impl MigrationReport {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for action in &self.actions {
+            let _ = writeln!(out, "{action}");
+        }
+        let _ = writeln!(
+            out,
+            "{} files: {} unchanged, {} copied, {} appended ({}), {} deleted",
+            self.files,
+            self.unchanged,
+            self.copied,
+            self.appended,
+            ByteSize::b(self.bytes),
+            self.deleted
+        );
+        for (file, err) in &self.failed {
+            let _ = writeln!(out, "[FAIL] {file}: {err}");
+        }
+        out
+    }
+}
+
+/// Syncs the data directory into the target, transferring only files changed since the previous pass. Meant to be run
+/// while the node is still running, then once more after stopping it
+pub async fn migrate_data(
+    data_directory: &Path,
+    args: &MigrateDataArgs,
+    storage: &StorageArgs,
+) -> eyre::Result<MigrationReport> {
+    let target = Target::parse(&args.target, storage)?;
+    match (&target, args.pull) {
+        (Target::Directory(_), true) => {
+            bail!(
+                "--pull needs an s3:// target, run against the other directory with --data-dir instead"
+            )
+        }
+        (Target::Directory(directory), false) => {
+            let (source, directory) = (fs::canonicalize(data_directory)?, absolute(directory)?);
+            if directory.starts_with(&source) || source.starts_with(&directory) {
+                bail!("target {directory:?} overlaps with the data directory {source:?}");
+            }
+        }
+        (Target::Bucket { .. }, _) => {}
+    }
+
+    let local = {
+        let data_directory = data_directory.to_path_buf();
+        tokio::task::spawn_blocking(move || scan(&data_directory)).await?
+    };
+    let remote = match &target {
+        Target::Directory(directory) => {
+            let directory = directory.clone();
+            tokio::task::spawn_blocking(move || scan(&directory)).await?
+        }
+        Target::Bucket { bucket, prefix } => load_manifest(bucket, prefix).await?,
+    };
+    let (source, destination) = if args.pull {
+        (&remote, &local)
+    } else {
+        (&local, &remote)
+    };
+    // Manifest to upload afterwards, with what was transferred & deleted
+    let mut manifest = remote.clone();
+    info!(
+        files = source.len(),
+        target = args.target,
+        pull = args.pull,
+        "migrating data"
+    );
+
+    let mut report = MigrationReport {
+        files: source.len(),
+        ..Default::default()
+    };
+    let mut jobs = JoinSet::new();
+    let target = Arc::new(target);
+    for (file, meta) in source {
+        let transfer = match destination.get(file) {
+            Some(existing) if existing.unchanged(meta) => {
+                report.unchanged += 1;
+                continue;
+            }
+            // Objects can't be appended to
+            Some(existing)
+                if existing.size < meta.size && matches!(*target, Target::Directory(_)) =>
+            {
+                Transfer::Append {
+                    offset: existing.size,
+                }
+            }
+            _ => Transfer::Copy,
+        };
+        if args.dry_run {
+            report.actions.push(match transfer {
+                Transfer::Copy => format!("copy {file} ({})", ByteSize::b(meta.size)),
+                Transfer::Append { offset } => {
+                    format!("append {file} ({})", ByteSize::b(meta.size - offset))
+                }
+            });
+            continue;
+        }
+
+        while jobs.len() >= args.parallel.max(1) {
+            collect(jobs.join_next().await, &mut report, &mut manifest);
+        }
+        let job = Job {
+            data_directory: data_directory.to_path_buf(),
+            target: Arc::clone(&target),
+            file: file.clone(),
+            meta: meta.clone(),
+            transfer,
+            verify: args.verify,
+            pull: args.pull,
+        };
+        jobs.spawn(job.run());
+    }
+    while let Some(result) = jobs.join_next().await {
+        collect(Some(result), &mut report, &mut manifest);
+    }
+
+    if args.delete {
+        let extra: Vec<_> = destination
+            .keys()
+            .filter(|file| !source.contains_key(*file))
+            .cloned()
+            .collect();
+        for file in extra {
+            if args.dry_run {
+                report.actions.push(format!("delete {file}"));
+                continue;
+            }
+            let result = match (&*target, args.pull) {
+                (_, true) => fs::remove_file(data_directory.join(&file)).map_err(Into::into),
+                (Target::Directory(directory), false) => {
+                    fs::remove_file(directory.join(&file)).map_err(Into::into)
+                }
+                (Target::Bucket { bucket, prefix }, false) => {
+                    bucket.delete(&format!("{prefix}{file}")).await
+                }
+            };
+            match result {
+                Ok(()) => {
+                    debug!(file, "deleted");
+                    manifest.remove(&file);
+                    report.deleted += 1;
+                }
+                Err(err) => report.failed.push((file, format!("{err:#}"))),
+            }
+        }
+    }
+
+    if let Target::Bucket { bucket, prefix } = &*target
+        && !args.pull
+        && !args.dry_run
+    {
+        bucket
+            .put(
+                &format!("{prefix}{MANIFEST_NAME}"),
+                serde_json::to_vec(&manifest)?,
+            )
+            .await
+            .wrap_err("failed to upload migration manifest")?;
+    }
+    Ok(report)
+}
+
+fn collect(
+    result: Option<Result<(String, eyre::Result<Copied>), tokio::task::JoinError>>,
+    report: &mut MigrationReport,
+    manifest: &mut Listing,
+) {
+    let Some(result) = result else {
+        return;
+    };
+    let (file, result) = match result {
+        Ok(result) => result,
+        Err(err) => {
+            warn!(?err, "migration job failed");
+            return;
+        }
+    };
+    match result {
+        Ok(copied) => {
+            debug!(
+                file,
+                bytes = copied.bytes,
+                appended = copied.appended,
+                "transferred"
+            );
+            report.bytes += copied.bytes;
+            if copied.appended {
+                report.appended += 1;
+            } else {
+                report.copied += 1;
+            }
+            manifest.insert(file, copied.meta);
+        }
+        Err(err) => {
+            warn!(?err, file, "failed to transfer file");
+            report.failed.push((file, format!("{err:#}")));
+        }
+    }
+}
+
+struct Job {
+    data_directory: PathBuf,
+    target: Arc<Target>,
+    file: String,
+    meta: FileMeta,
+    transfer: Transfer,
+    verify: bool,
+    pull: bool,
+}
+
+#[derive(Debug)]
+struct Copied {
+    bytes: u64,
+    appended: bool,
+    /// What the destination holds now
+    meta: FileMeta,
+}
+
+impl Job {
+    async fn run(self) -> (String, eyre::Result<Copied>) {
+        let file = self.file.clone();
+        let result = match (&*self.target, self.pull) {
+            (Target::Directory(directory), _) => {
+                let source = self.data_directory.join(&self.file);
+                let destination = directory.join(&self.file);
+                tokio::task::spawn_blocking(move || {
+                    copy_file(&source, &destination, self.meta, self.transfer, self.verify)
+                })
+                .await
+                .map_err(Into::into)
+                .and_then(|result| result)
+            }
+            (Target::Bucket { bucket, prefix }, false) => {
+                let key = format!("{prefix}{}", self.file);
+                upload(
+                    bucket,
+                    &key,
+                    &self.data_directory.join(&self.file),
+                    self.meta,
+                    self.verify,
+                )
+                .await
+            }
+            (Target::Bucket { bucket, prefix }, true) => {
+                let key = format!("{prefix}{}", self.file);
+                download(
+                    bucket,
+                    &key,
+                    &self.data_directory.join(&self.file),
+                    self.meta,
+                    self.verify,
+                )
+                .await
+            }
+        };
+        (file, result)
+    }
+}
+
+/// Copies the file as it was when listed, so a file still being written is picked up by the next pass. Appends when
+/// the destination's end matches the source at the same offset
+fn copy_file(
+    source: &Path,
+    destination: &Path,
+    mut meta: FileMeta,
+    transfer: Transfer,
+    verify: bool,
+) -> eyre::Result<Copied> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut input = File::open(source).wrap_err_with(|| format!("failed to open {source:?}"))?;
+    let permissions = input.metadata()?.permissions();
+
+    let append_offset = match transfer {
+        Transfer::Append { offset } if same_tail(&mut input, destination, offset)? => Some(offset),
+        _ => None,
+    };
+    let (bytes, appended) = match append_offset {
+        Some(offset) => {
+            input.seek(SeekFrom::Start(offset))?;
+            let mut output = OpenOptions::new().append(true).open(destination)?;
+            let bytes = io::copy(&mut (&mut input).take(meta.size - offset), &mut output)?;
+            output.set_modified(meta.modified_time())?;
+            output.sync_all()?;
+            (bytes, true)
+        }
+        None => {
+            let name = destination
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let staging = destination.with_file_name(format!(".{name}{STAGING_SUFFIX}"));
+            let result = (|| {
+                // same_tail may have moved the position
+                input.seek(SeekFrom::Start(0))?;
+                let mut output = File::create(&staging)?;
+                let bytes = io::copy(&mut (&mut input).take(meta.size), &mut output)?;
+                output.set_permissions(permissions)?;
+                output.set_modified(meta.modified_time())?;
+                output.sync_all()?;
+                fs::rename(&staging, destination)?;
+                eyre::Ok(bytes)
+            })();
+            if result.is_err() {
+                let _ = fs::remove_file(&staging);
+            }
+            (result?, false)
+        }
+    };
+
+    if verify {
+        let expected = sha256(source, meta.size)?;
+        let actual = sha256(destination, u64::MAX)?;
+        if expected != actual {
+            bail!("checksum mismatch after copying: {actual}, expected {expected}");
+        }
+        meta.sha256 = Some(expected);
+    }
+    Ok(Copied {
+        bytes,
+        appended,
+        meta,
+    })
+}
+
+/// Whether the destination's last bytes before the offset match the source's
+fn same_tail(source: &mut File, destination: &Path, offset: u64) -> io::Result<bool> {
+    let start = offset.saturating_sub(APPEND_CHECK_BYTES);
+    let mut expected = vec![0; (offset - start) as usize];
+    source.seek(SeekFrom::Start(start))?;
+    source.read_exact(&mut expected)?;
+
+    let mut destination = File::open(destination)?;
+    if destination.metadata()?.len() != offset {
+        return Ok(false);
+    }
+    let mut actual = vec![0; expected.len()];
+    destination.seek(SeekFrom::Start(start))?;
+    destination.read_exact(&mut actual)?;
+    Ok(expected == actual)
+}
+
+async fn upload(
+    bucket: &Bucket,
+    key: &str,
+    path: &Path,
+    mut meta: FileMeta,
+    verify: bool,
+) -> eyre::Result<Copied> {
+    bucket.upload_file(key, path).await?;
+    if verify {
+        let path = path.to_path_buf();
+        let size = meta.size;
+        let expected = tokio::task::spawn_blocking(move || sha256(&path, size)).await??;
+        let actual = bucket.sha256(key).await?;
+        if expected != actual {
+            bail!(
+                "checksum mismatch after uploading: {actual}, expected {expected}, was the file still being written?"
+            );
+        }
+        meta.sha256 = Some(expected);
+    }
+    Ok(Copied {
+        bytes: meta.size,
+        appended: false,
+        meta,
+    })
+}
+
+async fn download(
+    bucket: &Bucket,
+    key: &str,
+    path: &Path,
+    meta: FileMeta,
+    verify: bool,
+) -> eyre::Result<Copied> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let staging = path.with_file_name(format!(".{name}{STAGING_SUFFIX}"));
+
+    let result = async {
+        bucket.download_file(key, &staging).await?;
+        if verify {
+            let expected = meta
+                .sha256
+                .as_deref()
+                .wrap_err("no checksum recorded, push with --verify to record one")?;
+            let staging = staging.clone();
+            let actual = tokio::task::spawn_blocking(move || sha256(&staging, u64::MAX)).await??;
+            if expected != actual {
+                bail!("checksum mismatch after downloading: {actual}, expected {expected}");
+            }
+        }
+        let file = File::options().write(true).open(&staging)?;
+        file.set_modified(meta.modified_time())?;
+        file.sync_all()?;
+        fs::rename(&staging, path)?;
+        eyre::Ok(())
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result?;
+
+    Ok(Copied {
+        bytes: meta.size,
+        appended: false,
+        meta,
+    })
+}
+
+async fn load_manifest(bucket: &Bucket, prefix: &str) -> eyre::Result<Listing> {
+    let key = format!("{prefix}{MANIFEST_NAME}");
+    let exists = bucket
+        .list(&key)
+        .await?
+        .iter()
+        .any(|object| object.key == key);
+    if !exists {
+        return Ok(Listing::new());
+    }
+    let manifest = bucket.get_text(&key).await?;
+    let listing: Listing =
+        serde_json::from_str(&manifest).wrap_err("failed to parse migration manifest")?;
+    // Pulling joins the files onto the data directory, so they must not lead out of it
+    if let Some(file) = listing.keys().find(|file| !is_relative_path(file)) {
+        bail!("migration manifest lists {file:?}, which is not a relative path");
+    }
+    Ok(listing)
+}
+
+/// Whether the path only consists of plain names, without `..`, `.` or a root
+fn is_relative_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Regular files under the root by relative path, leaving out partial copies
+fn scan(root: &Path) -> Listing {
+    let mut files = Listing::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let path = entry.path();
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !metadata.is_file()
+                || entry
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(STAGING_SUFFIX)
+            {
+                continue;
+            }
+            if let Ok(relative) = path.strip_prefix(root) {
+                files.insert(
+                    relative.to_string_lossy().into_owned(),
+                    FileMeta::from_metadata(&metadata),
+                );
+            }
+        }
+    }
+    files
+}
+
+/// Hex encoded sha256 of the first `limit` bytes of the file
+fn sha256(path: &Path, limit: u64) -> io::Result<String> {
+    let mut file = File::open(path)?.take(limit);
+    let mut hash = Hash::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hash.update(&buffer[..read]);
+    }
+    Ok(hex(&hash.finalize()))
+}
+
+/// Like canonicalize, but for directories that don't exist yet
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(path) => Ok(path),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => std::path::absolute(path),
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let report = MigrationReport {
+            files: 3,
+            unchanged: 1,
+            copied: 1,
+            appended: 1,
+            bytes: 2048,
+            failed: vec![("hl/data/x".to_string(), "gone".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(
+            report.render(),
+            "3 files: 1 unchanged, 1 copied, 1 appended (2.0 KiB), 0 deleted\n[FAIL] hl/data/x: gone\n"
+        );
+    }
+
+    #[test]
+    fn test_is_relative_path() {
+        assert!(is_relative_path("hl/data/replica_cmds/20250615/1"));
+        assert!(is_relative_path("node_config.json"));
+        assert!(!is_relative_path(""));
+        assert!(!is_relative_path("/etc/cron.d/x"));
+        assert!(!is_relative_path("hl/../../.ssh/authorized_keys"));
+        assert!(!is_relative_path("./hl/data"));
+    }
+
+    #[test]
+    fn test_scan() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("hl/data/node_fills/hourly")).unwrap();
+        fs::write(root.path().join("hl/data/node_fills/hourly/1"), b"fill").unwrap();
+        fs::write(root.path().join("visor.json"), b"{}").unwrap();
+        fs::write(
+            root.path()
+                .join(format!("hl/data/node_fills/hourly/.2{STAGING_SUFFIX}")),
+            b"partial",
+        )
+        .unwrap();
+
+        let listing = scan(root.path());
+        assert_eq!(
+            listing.keys().collect::<Vec<_>>(),
+            ["hl/data/node_fills/hourly/1", "visor.json"]
+        );
+        assert_eq!(listing["hl/data/node_fills/hourly/1"].size, 4);
+    }
+
+    #[test]
+    fn test_copy_file_append() {
+        let directory = tempfile::tempdir().unwrap();
+        let source = directory.path().join("source");
+        let destination = directory.path().join("target/destination");
+        fs::write(&source, b"block 1\nblock 2\n").unwrap();
+        let meta = FileMeta::from_metadata(&fs::metadata(&source).unwrap());
+
+        // Destination holds the earlier part of the file
+        fs::create_dir_all(destination.parent().unwrap()).unwrap();
+        fs::write(&destination, b"block 1\n").unwrap();
+        let copied = copy_file(
+            &source,
+            &destination,
+            meta.clone(),
+            Transfer::Append { offset: 8 },
+            true,
+        )
+        .unwrap();
+        assert!(copied.appended);
+        assert_eq!(copied.bytes, 8);
+        assert_eq!(fs::read(&destination).unwrap(), b"block 1\nblock 2\n");
+        assert!(copied.meta.sha256.is_some());
+        assert!(FileMeta::from_metadata(&fs::metadata(&destination).unwrap()).unchanged(&meta));
+
+        // Destination diverged from the source, so it's copied over instead
+        fs::write(&destination, b"block X\n").unwrap();
+        let copied = copy_file(
+            &source,
+            &destination,
+            meta.clone(),
+            Transfer::Append { offset: 8 },
+            false,
+        )
+        .unwrap();
+        assert!(!copied.appended);
+        assert_eq!(copied.bytes, 16);
+        assert_eq!(fs::read(&destination).unwrap(), b"block 1\nblock 2\n");
+    }
+
+    #[test]
+    fn test_same_tail() {
+        let directory = tempfile::tempdir().unwrap();
+        let source = directory.path().join("source");
+        let destination = directory.path().join("destination");
+        fs::write(&source, b"abcdef").unwrap();
+
+        fs::write(&destination, b"abc").unwrap();
+        let mut input = File::open(&source).unwrap();
+        assert!(same_tail(&mut input, &destination, 3).unwrap());
+        // Length must match the offset exactly
+        assert!(!same_tail(&mut input, &destination, 2).unwrap());
+        fs::write(&destination, b"abd").unwrap();
+        assert!(!same_tail(&mut input, &destination, 3).unwrap());
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use chrono::NaiveDateTime;
+use prometheus::{
+    Gauge, Histogram, IntGauge, exponential_buckets, histogram_opts, register_gauge,
+    register_histogram, register_int_gauge,
+};
+use serde::Deserialize;
+use tokio::time::{Instant, MissedTickBehavior, interval};
+use tracing::{debug, info, trace};
+
+pub static GAUGE_HL_NODE_BLOCK_HEIGHT: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_node_block_height",
+        "Last applied block height reported in visor_abci_state.json"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_CONSENSUS_TIME_MS: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_consensus_time",
+        "Consensus time of the last applied block in milliseconds since Unix epoch"
+    )
+    .unwrap()
+});
+
+pub static HISTOGRAM_HL_NODE_BLOCK_APPLY_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(histogram_opts!(
+        "hl_node_block_apply_duration_seconds",
+        "Average wall clock time per applied block between two height observations",
+        exponential_buckets(0.001, 1.5, 32).unwrap()
+    ))
+    .unwrap()
+});
+
+fn init_metrics() {
+    LazyLock::force(&GAUGE_HL_NODE_BLOCK_HEIGHT);
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_TIME_MS);
+    LazyLock::force(&HISTOGRAM_HL_NODE_BLOCK_APPLY_DURATION);
+}
+
+/// hl/hyperliquid_data/visor_abci_state.json, periodically written by hl-visor
+#[derive(Debug, Deserialize)]
+pub struct AbciState {
+    pub height: u64,
+    pub consensus_time: String,
+}
+
+impl AbciState {
+    pub fn path(data_directory: &Path) -> PathBuf {
+        data_directory.join("hl/hyperliquid_data/visor_abci_state.json")
+    }
+
+    /// Consensus time is in UTC, without a timezone and with nanosecond precision
+    pub fn consensus_time_ms(&self) -> Option<i64> {
+        NaiveDateTime::parse_from_str(&self.consensus_time, "%Y-%m-%dT%H:%M:%S%.f")
+            .ok()
+            .map(|time| time.and_utc().timestamp_millis())
+    }
+}
+
+pub async fn poll_abci_state(data_directory: PathBuf, poll_interval: Duration) {
+    init_metrics();
+
+    let path = AbciState::path(&data_directory);
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    // Height and time it was first observed at
+    let mut last_height: Option<(u64, Instant)> = None;
+
+    info!(?path, "polling block height");
+    loop {
+        interval.tick().await;
+
+        let state = match tokio::fs::read(&path).await {
+            Ok(contents) => match serde_json::from_slice::<AbciState>(&contents) {
+                Ok(state) => state,
+                // File could be in the middle of being written
+                Err(err) => {
+                    trace!(?err, "failed to parse abci state");
+                    continue;
+                }
+            },
+            Err(err) => {
+                trace!(?err, "failed to read abci state");
+                continue;
+            }
+        };
+
+        GAUGE_HL_NODE_BLOCK_HEIGHT.set(state.height as i64);
+        if let Some(consensus_time_ms) = state.consensus_time_ms() {
+            GAUGE_HL_NODE_CONSENSUS_TIME_MS.set(consensus_time_ms as f64);
+        }
+
+        let now = Instant::now();
+        match last_height {
+            Some((height, observed_at)) if state.height > height => {
+                let blocks = state.height - height;
+                let per_block = now.duration_since(observed_at).div_f64(blocks as f64);
+                debug!(height = state.height, blocks, ?per_block, "blocks applied");
+                HISTOGRAM_HL_NODE_BLOCK_APPLY_DURATION.observe(per_block.as_secs_f64());
+                last_height = Some((state.height, now));
+            }
+            Some((height, _)) if state.height == height => {}
+            // First observation, or height went backwards due to a state reset
+            _ => last_height = Some((state.height, now)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consensus_time_ms() {
+        let state = AbciState {
+            height: 628178000,
+            consensus_time: "2025-06-13T19:59:46.930709602".to_string(),
+        };
+        assert_eq!(state.consensus_time_ms(), Some(1749844786930));
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    collections::VecDeque,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use prometheus::{Gauge, IntCounter, register_gauge, register_int_counter};
+use serde::{Deserialize, de::IgnoredAny};
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, trace};
+
+use crate::replica_stream::{newest_file, split_lines};
+
+pub static COUNTER_HL_NODE_DATA_BLOCKS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_node_data_blocks_total",
+        "Blocks hl-node appended to replica_cmds"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_HL_NODE_DATA_ACTIONS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_node_data_actions_total",
+        "Signed actions (transactions) in the blocks hl-node appended to replica_cmds"
+    )
+    .unwrap()
+});
+
+pub static COUNTER_HL_NODE_DATA_REPLICA_CMDS_BYTES: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "hl_node_data_replica_cmds_bytes_total",
+        "Bytes hl-node appended to replica_cmds"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_DATA_BLOCKS_PER_SECOND: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_data_blocks_per_second",
+        "Blocks appended to replica_cmds per second over the last minute"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_DATA_ACTIONS_PER_BLOCK: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_data_actions_per_block",
+        "Signed actions per block appended to replica_cmds over the last minute"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_DATA_REPLICA_CMDS_BYTES_PER_SECOND: LazyLock<Gauge> =
+    LazyLock::new(|| {
+        register_gauge!(
+            "hl_node_data_replica_cmds_bytes_per_second",
+            "Bytes appended to replica_cmds per second over the last minute"
+        )
+        .unwrap()
+    });
+
+fn init_metrics() {
+    LazyLock::force(&COUNTER_HL_NODE_DATA_BLOCKS);
+    LazyLock::force(&COUNTER_HL_NODE_DATA_ACTIONS);
+    LazyLock::force(&COUNTER_HL_NODE_DATA_REPLICA_CMDS_BYTES);
+    LazyLock::force(&GAUGE_HL_NODE_DATA_BLOCKS_PER_SECOND);
+    LazyLock::force(&GAUGE_HL_NODE_DATA_ACTIONS_PER_BLOCK);
+    LazyLock::force(&GAUGE_HL_NODE_DATA_REPLICA_CMDS_BYTES_PER_SECOND);
+}
+
+/// Span the rate gauges are averaged over
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Only the actions of a block are counted, the rest of it is skipped while parsing
+#[derive(Deserialize)]
+struct ActivityBlock {
+    abci_block: ActivityAbciBlock,
+}
+
+#[derive(Deserialize)]
+struct ActivityAbciBlock {
+    signed_action_bundles: Vec<(IgnoredAny, ActionBundle)>,
+}
+
+#[derive(Deserialize)]
+struct ActionBundle {
+    signed_actions: Vec<IgnoredAny>,
+}
+
+/// Totals since the poller started
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Activity {
+    blocks: u64,
+    actions: u64,
+    bytes: u64,
+}
+
+/// Averages of the activity samples within the window
+#[derive(Debug, Default)]
+struct Rates {
+    samples: VecDeque<(Instant, Activity)>,
+}
+
+impl Rates {
+    /// Blocks per second, actions per block & bytes per second, once there are two samples
+    fn update(&mut self, now: Instant, activity: Activity) -> Option<(f64, f64, f64)> {
+        self.samples.push_back((now, activity));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(time, _)| now.duration_since(*time) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+
+        let (start, first) = self.samples.front()?;
+        let elapsed = now.duration_since(*start).as_secs_f64();
+        if elapsed == 0.0 {
+            return None;
+        }
+        let blocks = (activity.blocks - first.blocks) as f64;
+        let actions = (activity.actions - first.actions) as f64;
+        let bytes = (activity.bytes - first.bytes) as f64;
+        let actions_per_block = if blocks > 0.0 { actions / blocks } else { 0.0 };
+        Some((blocks / elapsed, actions_per_block, bytes / elapsed))
+    }
+}
+
+/// Position within the replica_cmds file being read
+struct Tail {
+    path: PathBuf,
+    offset: u64,
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    /// Counts the blocks appended since the last read, returning the amount of bytes read
+    fn read_new(&mut self, activity: &mut Activity) -> io::Result<u64> {
+        let mut file = File::open(&self.path)?;
+        let length = file.metadata()?.len();
+        if length <= self.offset {
+            return Ok(0);
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut chunk = Vec::new();
+        file.take(length - self.offset).read_to_end(&mut chunk)?;
+        self.offset += chunk.len() as u64;
+        activity.bytes += chunk.len() as u64;
+
+        for line in split_lines(&mut self.partial, &chunk) {
+            activity.blocks += 1;
+            match serde_json::from_str::<ActivityBlock>(&line) {
+                Ok(block) => activity.actions += actions(&block),
+                Err(err) => trace!(?err, "failed to parse replica_cmds block"),
+            }
+        }
+        Ok(chunk.len() as u64)
+    }
+}
+
+fn actions(block: &ActivityBlock) -> u64 {
+    block
+        .abci_block
+        .signed_action_bundles
+        .iter()
+        .map(|(_, bundle)| bundle.signed_actions.len() as u64)
+        .sum()
+}
+
+/// Moves on to the newest file once the current one is drained, like hl-node does. The first file is read from its
+/// end, so blocks written before hl-bootstrap started aren't counted
+fn poll(replica_cmds: &Path, tail: &mut Option<Tail>, activity: &mut Activity) {
+    if let Some(current) = tail {
+        match current.read_new(activity) {
+            Ok(0) => {}
+            Ok(_) => return,
+            Err(err) => {
+                debug!(?err, path = ?current.path, "failed to read replica_cmds file");
+                *tail = None;
+            }
+        }
+    }
+
+    let Some(newest) = newest_file(replica_cmds) else {
+        return;
+    };
+    if tail.as_ref().is_some_and(|current| current.path == newest) {
+        return;
+    }
+    let offset = match tail {
+        Some(_) => 0,
+        None => fs::metadata(&newest).map_or(0, |metadata| metadata.len()),
+    };
+    debug!(path = ?newest, offset, "counting replica_cmds blocks");
+    let mut next = Tail {
+        path: newest,
+        offset,
+        partial: Vec::new(),
+    };
+    if let Err(err) = next.read_new(activity) {
+        debug!(?err, path = ?next.path, "failed to read replica_cmds file");
+    }
+    *tail = Some(next);
+}
+
+pub async fn poll_activity(data_directory: PathBuf, poll_interval: Duration) {
+    init_metrics();
+
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let replica_cmds = data_directory.join("hl/data/replica_cmds");
+    let mut state = (None, Activity::default());
+    let mut rates = Rates::default();
+    info!(?replica_cmds, "measuring block activity from replica_cmds");
+    loop {
+        interval.tick().await;
+
+        let replica_cmds = replica_cmds.clone();
+        let (mut tail, mut activity) = state;
+        let previous = activity;
+        let Ok(polled) = tokio::task::spawn_blocking(move || {
+            poll(&replica_cmds, &mut tail, &mut activity);
+            (tail, activity)
+        })
+        .await
+        else {
+            state = (None, previous);
+            continue;
+        };
+        state = polled;
+
+        let activity = state.1;
+        COUNTER_HL_NODE_DATA_BLOCKS.inc_by(activity.blocks - previous.blocks);
+        COUNTER_HL_NODE_DATA_ACTIONS.inc_by(activity.actions - previous.actions);
+        COUNTER_HL_NODE_DATA_REPLICA_CMDS_BYTES.inc_by(activity.bytes - previous.bytes);
+        if let Some((blocks_per_second, actions_per_block, bytes_per_second)) =
+            rates.update(Instant::now(), activity)
+        {
+            GAUGE_HL_NODE_DATA_BLOCKS_PER_SECOND.set(blocks_per_second);
+            GAUGE_HL_NODE_DATA_ACTIONS_PER_BLOCK.set(actions_per_block);
+            GAUGE_HL_NODE_DATA_REPLICA_CMDS_BYTES_PER_SECOND.set(bytes_per_second);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions() {
+        let line = r#"{"abci_block":{"time":"2025-06-15T15:06:40.123456789","round":628178000,"signed_action_bundles":[["0xab",{"signed_actions":[{"action":{"type":"order"}},{"action":{"type":"cancel"}}],"broadcaster":"0x01"}],["0xcd",{"signed_actions":[{"action":{"type":"noop"}}]}]]},"resps":null}"#;
+        let block: ActivityBlock = serde_json::from_str(line).unwrap();
+        assert_eq!(actions(&block), 3);
+    }
+
+    #[test]
+    fn test_rates() {
+        let mut rates = Rates::default();
+        let start = Instant::now();
+        assert_eq!(rates.update(start, Activity::default()), None);
+
+        let activity = Activity {
+            blocks: 20,
+            actions: 100,
+            bytes: 4000,
+        };
+        assert_eq!(
+            rates.update(start + Duration::from_secs(2), activity),
+            Some((10.0, 5.0, 2000.0))
+        );
+
+        // Samples older than the window no longer count
+        let later = Activity {
+            blocks: 30,
+            actions: 100,
+            bytes: 5000,
+        };
+        assert_eq!(
+            rates.update(start + Duration::from_secs(62), later),
+            Some((10.0 / 60.0, 0.0, 1000.0 / 60.0))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use prometheus::{Gauge, IntGauge, register_gauge, register_int_gauge};
+use serde::Deserialize;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{info, trace};
+
+use crate::{data_api::parse_node_time, monitor::height::last_lines, replica_stream::newest_file};
+
+pub static GAUGE_HL_NODE_CONSENSUS_ROUND: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_node_consensus_round",
+        "Consensus round in the last node status line"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_CONSENSUS_ROUNDS_PER_SECOND: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_consensus_rounds_per_second",
+        "Consensus rounds progressed per second since the previous poll"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_CONSENSUS_ROUND_AGE_SECONDS: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_consensus_round_age_seconds",
+        "Seconds since the consensus round last progressed"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_CONSENSUS_VOTE_PARTICIPATION: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_consensus_vote_participation",
+        "Fraction of validators whose votes were seen in the round"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_CONSENSUS_HEARTBEAT_ACK_RATIO: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_consensus_heartbeat_ack_ratio",
+        "Fraction of sent heartbeats that were acknowledged"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_CONSENSUS_STATUS_TIME_MS: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_consensus_status_time",
+        "Time of the last node status line in milliseconds since Unix epoch"
+    )
+    .unwrap()
+});
+
+fn init_metrics() {
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_ROUND);
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_ROUNDS_PER_SECOND);
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_ROUND_AGE_SECONDS);
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_VOTE_PARTICIPATION);
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_HEARTBEAT_ACK_RATIO);
+    LazyLock::force(&GAUGE_HL_NODE_CONSENSUS_STATUS_TIME_MS);
+}
+
+/// Status hl-node logs periodically. Which fields are present differs between node versions and roles, missing ones
+/// leave their metrics unset
+#[derive(Debug, Default, Deserialize)]
+struct ConsensusStatus {
+    #[serde(alias = "current_round")]
+    round: Option<u64>,
+    /// Validators whose votes were seen in the round
+    #[serde(alias = "n_votes")]
+    votes: Option<u64>,
+    #[serde(alias = "n_validators")]
+    validators: Option<u64>,
+    #[serde(alias = "n_heartbeats")]
+    heartbeats: Option<u64>,
+    #[serde(alias = "n_heartbeat_acks")]
+    heartbeat_acks: Option<u64>,
+}
+
+impl ConsensusStatus {
+    fn vote_participation(&self) -> Option<f64> {
+        ratio(self.votes?, self.validators?)
+    }
+
+    fn heartbeat_ack_ratio(&self) -> Option<f64> {
+        ratio(self.heartbeat_acks?, self.heartbeats?)
+    }
+}
+
+fn ratio(part: u64, total: u64) -> Option<f64> {
+    (total > 0).then(|| part as f64 / total as f64)
+}
+
+/// Status lines are `[time, status]` pairs, bare status objects are accepted as well
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StatusLine {
+    Timed(String, ConsensusStatus),
+    Bare(ConsensusStatus),
+}
+
+fn parse_status_line(line: &[u8]) -> Option<(Option<i64>, ConsensusStatus)> {
+    match serde_json::from_slice(line).ok()? {
+        StatusLine::Timed(time, status) => Some((parse_node_time(&time), status)),
+        StatusLine::Bare(status) => Some((None, status)),
+    }
+}
+
+/// Newest line of hl/data/node_logs/status/hourly/<date>/<hour>
+fn last_status(status_logs: &Path) -> Option<(Option<i64>, ConsensusStatus)> {
+    let path = newest_file(status_logs)?;
+    let line = last_lines(&path, 1)
+        .map_err(|err| trace!(?err, ?path, "failed to read node status"))
+        .ok()?
+        .pop()?;
+    parse_status_line(&line)
+}
+
+/// Round progression between polls
+#[derive(Debug, Default)]
+struct RoundProgress {
+    /// Last round seen & when it was first seen
+    last: Option<(u64, Instant)>,
+}
+
+impl RoundProgress {
+    /// Rounds per second since the previous round change, when it progressed, and time since the last change
+    fn update(&mut self, now: Instant, round: u64) -> (Option<f64>, Duration) {
+        match self.last {
+            Some((last_round, since)) if round <= last_round => (None, now - since),
+            Some((last_round, since)) => {
+                self.last = Some((round, now));
+                let elapsed = (now - since).as_secs_f64();
+                let rate = (elapsed > 0.0).then(|| (round - last_round) as f64 / elapsed);
+                (rate, Duration::ZERO)
+            }
+            None => {
+                self.last = Some((round, now));
+                (None, Duration::ZERO)
+            }
+        }
+    }
+}
+
+pub async fn poll_consensus_status(data_directory: PathBuf, poll_interval: Duration) {
+    init_metrics();
+
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let status_logs = data_directory.join("hl/data/node_logs/status/hourly");
+    let mut progress = RoundProgress::default();
+    info!(
+        ?status_logs,
+        "polling consensus status from node status logs"
+    );
+    loop {
+        interval.tick().await;
+
+        let status_logs = status_logs.clone();
+        let Ok(Some((time_ms, status))) =
+            tokio::task::spawn_blocking(move || last_status(&status_logs)).await
+        else {
+            continue;
+        };
+        trace!(?time_ms, ?status, "consensus status");
+
+        if let Some(time_ms) = time_ms {
+            GAUGE_HL_NODE_CONSENSUS_STATUS_TIME_MS.set(time_ms as f64);
+        }
+        if let Some(round) = status.round {
+            GAUGE_HL_NODE_CONSENSUS_ROUND.set(round as i64);
+            let (rate, age) = progress.update(Instant::now(), round);
+            if let Some(rate) = rate {
+                GAUGE_HL_NODE_CONSENSUS_ROUNDS_PER_SECOND.set(rate);
+            }
+            GAUGE_HL_NODE_CONSENSUS_ROUND_AGE_SECONDS.set(age.as_secs_f64());
+        }
+        if let Some(participation) = status.vote_participation() {
+            GAUGE_HL_NODE_CONSENSUS_VOTE_PARTICIPATION.set(participation);
+        }
+        if let Some(ack_ratio) = status.heartbeat_ack_ratio() {
+            GAUGE_HL_NODE_CONSENSUS_HEARTBEAT_ACK_RATIO.set(ack_ratio);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_status_line() {
+        let (time_ms, status) = parse_status_line(
+            br#"["2025-06-15T15:06:40.123456789",{"current_round":628178000,"n_votes":18,"n_validators":24,"n_heartbeats":10,"n_heartbeat_acks":9,"other":[1]}]"#,
+        )
+        .unwrap();
+        assert_eq!(time_ms, Some(1750000000123));
+        assert_eq!(status.round, Some(628178000));
+        assert_eq!(status.vote_participation(), Some(0.75));
+        assert_eq!(status.heartbeat_ack_ratio(), Some(0.9));
+
+        let (time_ms, status) = parse_status_line(br#"{"round":5}"#).unwrap();
+        assert_eq!(time_ms, None);
+        assert_eq!(status.round, Some(5));
+        assert_eq!(status.vote_participation(), None);
+
+        assert!(parse_status_line(b"not json").is_none());
+    }
+
+    #[test]
+    fn test_round_progress() {
+        let start = Instant::now();
+        let mut progress = RoundProgress::default();
+        assert_eq!(progress.update(start, 100), (None, Duration::ZERO));
+        assert_eq!(
+            progress.update(start + Duration::from_secs(2), 110),
+            (Some(5.0), Duration::ZERO)
+        );
+        assert_eq!(
+            progress.update(start + Duration::from_secs(5), 110),
+            (None, Duration::from_secs(3))
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use std::{ffi::OsString, sync::LazyLock, time::Duration};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, ContextCompat, bail};
+use prometheus::{IntGauge, register_int_gauge};
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info};
+
+use crate::{
+    hl_gossip_config::HyperliquidChain,
+    monitor::{CLIENT, INFO_PORT, NETWORK_CLIENT},
+};
+
+pub static GAUGE_HL_NODE_EVM_BLOCK_NUMBER: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_node_evm_block_number",
+        "Latest block number reported by the local EVM RPC"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_EVM_REFERENCE_BLOCK_NUMBER: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_node_evm_reference_block_number",
+        "Latest block number reported by the reference EVM RPC"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_EVM_RESPONDING: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_node_evm_responding",
+        "Whether the local EVM RPC is responding to eth_blockNumber"
+    )
+    .unwrap()
+});
+
+fn init_metrics() {
+    LazyLock::force(&GAUGE_HL_NODE_EVM_BLOCK_NUMBER);
+    LazyLock::force(&GAUGE_HL_NODE_EVM_REFERENCE_BLOCK_NUMBER);
+    LazyLock::force(&GAUGE_HL_NODE_EVM_RESPONDING);
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct EvmArgs {
+    /// EVM JSON-RPC endpoint to compare the local EVM RPC's block number against, when hl-node runs with
+    /// --serve-eth-rpc. Defaults to the public Hyperliquid RPC of the network
+    #[arg(long, env = "HL_BOOTSTRAP_EVM_REFERENCE_RPC_URL")]
+    pub evm_reference_rpc_url: Option<String>,
+
+    /// How many blocks the local EVM RPC may be behind the reference to be considered synced (/ready, evm check)
+    #[arg(long, env = "HL_BOOTSTRAP_EVM_MAX_LAG_BLOCKS", default_value_t = 10)]
+    pub evm_max_lag_blocks: u64,
+
+    /// How often should eth_blockNumber be requested for EVM block number metrics
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_METRICS_EVM_POLL_INTERVAL",
+        default_value = "5s"
+    )]
+    pub metrics_evm_poll_interval: DurationString,
+}
+
+impl EvmArgs {
+    /// Sync settings, if hl-node is going to serve the EVM RPC with the hl-visor args
+    pub fn sync(&self, chain: HyperliquidChain, child_args: &[OsString]) -> Option<EvmSync> {
+        if !child_args.iter().any(|arg| arg == "--serve-eth-rpc") {
+            return None;
+        }
+        let reference_url = self.evm_reference_rpc_url.clone().unwrap_or_else(|| {
+            match chain {
+                HyperliquidChain::Mainnet => "https://rpc.hyperliquid.xyz/evm",
+                HyperliquidChain::Testnet => "https://rpc.hyperliquid-testnet.xyz/evm",
+            }
+            .to_string()
+        });
+        Some(EvmSync {
+            reference_url,
+            max_lag_blocks: self.evm_max_lag_blocks,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EvmSync {
+    pub reference_url: String,
+    pub max_lag_blocks: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EvmLag {
+    pub local: u64,
+    pub reference: u64,
+}
+
+impl EvmLag {
+    pub fn blocks(&self) -> u64 {
+        self.reference.saturating_sub(self.local)
+    }
+}
+
+impl EvmSync {
+    /// Block numbers of the local & reference EVM RPC
+    pub async fn lag(&self) -> eyre::Result<EvmLag> {
+        let local_url = local_url();
+        let (local, reference) = tokio::join!(
+            request_block_number(&CLIENT, &local_url),
+            request_block_number(&NETWORK_CLIENT, &self.reference_url)
+        );
+        Ok(EvmLag {
+            local: local.wrap_err("failed to request eth_blockNumber from hl-node")?,
+            reference: reference.wrap_err("failed to request eth_blockNumber from reference")?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<String>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+fn local_url() -> String {
+    format!("http://127.0.0.1:{INFO_PORT}/evm")
+}
+
+async fn request_block_number(client: &Client, url: &str) -> eyre::Result<u64> {
+    let response = client
+        .post(url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_blockNumber",
+            "params": [],
+        }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<RpcResponse>()
+        .await?;
+    parse_block_number(response)
+}
+
+fn parse_block_number(response: RpcResponse) -> eyre::Result<u64> {
+    if let Some(err) = response.error {
+        bail!("rpc error {}: {}", err.code, err.message);
+    }
+    let result = response.result.wrap_err("response has no result")?;
+    let digits = result
+        .strip_prefix("0x")
+        .wrap_err_with(|| format!("block number {result:?} is not hex"))?;
+    u64::from_str_radix(digits, 16).wrap_err_with(|| format!("invalid block number {result:?}"))
+}
+
+pub async fn poll_evm(sync: EvmSync, poll_interval: Duration) {
+    init_metrics();
+
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let local_url = local_url();
+    info!(
+        reference_url = sync.reference_url,
+        "polling EVM block numbers"
+    );
+    loop {
+        interval.tick().await;
+
+        let (local, reference) = tokio::join!(
+            request_block_number(&CLIENT, &local_url),
+            request_block_number(&NETWORK_CLIENT, &sync.reference_url)
+        );
+        match local {
+            Ok(block_number) => {
+                GAUGE_HL_NODE_EVM_RESPONDING.set(1);
+                GAUGE_HL_NODE_EVM_BLOCK_NUMBER.set(block_number as i64);
+            }
+            Err(err) => {
+                debug!(?err, "local EVM RPC is not responding");
+                GAUGE_HL_NODE_EVM_RESPONDING.set(0);
+            }
+        }
+        match reference {
+            Ok(block_number) => GAUGE_HL_NODE_EVM_REFERENCE_BLOCK_NUMBER.set(block_number as i64),
+            Err(err) => debug!(?err, "failed to request reference EVM block number"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_block_number() {
+        let response: RpcResponse =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","id":1,"result":"0x86a0f1"}"#).unwrap();
+        assert_eq!(parse_block_number(response).unwrap(), 8823025);
+
+        let response: RpcResponse = serde_json::from_str(
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32603,"message":"internal error"}}"#,
+        )
+        .unwrap();
+        assert!(parse_block_number(response).is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    fs,
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::LazyLock,
+    time::{Duration, SystemTime},
+};
+
+use prometheus::{IntGaugeVec, register_int_gauge_vec};
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, warn};
+
+use crate::{
+    alerts::{AlertEvent, alert},
+    status,
+};
+
+/// Ports used by hl-node for gossip
+pub const GOSSIP_PORTS: RangeInclusive<u16> = 4000..=4010;
+
+/// TCP_ESTABLISHED in include/net/tcp_states.h
+const TCP_ESTABLISHED: u8 = 0x01;
+
+pub static GAUGE_HL_GOSSIP_CONNECTIONS: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "hl_bootstrap_gossip_connections",
+        "Established TCP connections on the gossip ports",
+        &["direction"]
+    )
+    .unwrap()
+});
+
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct GossipConnections {
+    pub inbound: i64,
+    pub outbound: i64,
+}
+
+impl GossipConnections {
+    pub fn total(&self) -> i64 {
+        self.inbound + self.outbound
+    }
+
+    /// Counts established connections from /proc/net/tcp & /proc/net/tcp6 contents
+    fn count(&mut self, proc_net_tcp: &str) {
+        for line in proc_net_tcp.lines().skip(1) {
+            let mut fields = line.split_whitespace().skip(1);
+            let (Some(local), Some(remote), Some(state)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            if u8::from_str_radix(state, 16) != Ok(TCP_ESTABLISHED) {
+                continue;
+            }
+
+            if parse_port(local).is_some_and(|port| GOSSIP_PORTS.contains(&port)) {
+                self.inbound += 1;
+            } else if parse_port(remote).is_some_and(|port| GOSSIP_PORTS.contains(&port)) {
+                self.outbound += 1;
+            }
+        }
+    }
+}
+
+/// Address is in `<hex address>:<hex port>` format
+fn parse_port(address: &str) -> Option<u16> {
+    let (_, port) = address.rsplit_once(':')?;
+    u16::from_str_radix(port, 16).ok()
+}
+
+pub fn gossip_connections() -> GossipConnections {
+    let mut connections = GossipConnections::default();
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        if let Ok(contents) = fs::read_to_string(path) {
+            connections.count(&contents);
+        }
+    }
+    connections
+}
+
+pub async fn poll_gossip_connections(poll_interval: Duration) {
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut had_peers = false;
+
+    info!(ports = ?GOSSIP_PORTS, "monitoring gossip connections");
+    loop {
+        interval.tick().await;
+
+        let connections = gossip_connections();
+        GAUGE_HL_GOSSIP_CONNECTIONS
+            .with_label_values(&["inbound"])
+            .set(connections.inbound);
+        GAUGE_HL_GOSSIP_CONNECTIONS
+            .with_label_values(&["outbound"])
+            .set(connections.outbound);
+
+        // Only flag the drop, node has no peers for a while after starting up
+        let has_peers = connections.total() > 0;
+        if had_peers && !has_peers {
+            warn!("node has no gossip connections");
+        } else if !had_peers && has_peers {
+            info!(?connections, "node has gossip connections");
+        }
+        had_peers = has_peers;
+    }
+}
+
+/// Tracks override_gossip_config.json age from its mtime, so refreshes done by other processes are picked up as well,
+/// and flags when it stays stale for too long
+pub async fn gossip_config_staleness_task(path: PathBuf, stale_after: Duration) {
+    let mut interval = interval(Duration::from_secs(60));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut stale = false;
+
+    info!(?path, ?stale_after, "monitoring gossip config staleness");
+    loop {
+        interval.tick().await;
+
+        let mtime = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+            Ok(mtime) => mtime,
+            Err(err) => {
+                debug!(?err, ?path, "failed to read gossip config mtime");
+                continue;
+            }
+        };
+        let written_at_ms = mtime
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        status::update(|status| status.gossip_config_written_at_ms = Some(written_at_ms));
+
+        let age = mtime.elapsed().unwrap_or_default();
+        if age > stale_after && !stale {
+            warn!(?age, ?stale_after, ?path, "gossip config is stale");
+            alert(
+                AlertEvent::GossipConfigStale,
+                format!(
+                    "override_gossip_config.json was last written {}s ago",
+                    age.as_secs()
+                ),
+            );
+        } else if age <= stale_after && stale {
+            info!(?age, ?path, "gossip config was refreshed");
+        }
+        stale = age > stale_after;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_connections() {
+        let proc_net_tcp = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0FA1 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 1 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:0FA1 0A000001:D431 01 00000000:00000000 00:00000000 00000000  1000        0 2 1 0000000000000000 20 4 30 10 -1
+   2: 0A000002:C350 0B000001:0FA2 01 00000000:00000000 00:00000000 00000000  1000        0 3 1 0000000000000000 20 4 30 10 -1
+   3: 0A000002:C351 0B000001:0FA3 06 00000000:00000000 00:00000000 00000000  1000        0 4 1 0000000000000000 20 4 30 10 -1
+   4: 0100007F:0BB9 0100007F:D432 01 00000000:00000000 00:00000000 00000000  1000        0 5 1 0000000000000000 20 4 30 10 -1
+";
+        let mut connections = GossipConnections::default();
+        connections.count(proc_net_tcp);
+        assert_eq!(
+            connections,
+            GossipConnections {
+                inbound: 1,
+                outbound: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use prometheus::{Gauge, IntGaugeVec, register_gauge, register_int_gauge_vec};
+use serde::{Deserialize, Serialize};
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{info, trace};
+
+use crate::{data_api::parse_node_time, replica_stream::newest_file};
+
+pub static GAUGE_HL_NODE_DATA_BLOCK_HEIGHT: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "hl_node_data_block_height",
+        "Block height derived from hl/data, by source (replica_cmds, periodic_abci_states)",
+        &["source"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_NODE_DATA_BLOCK_TIME_MS: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_node_data_block_time",
+        "Time of the last block in replica_cmds in milliseconds since Unix epoch"
+    )
+    .unwrap()
+});
+
+fn init_metrics() {
+    LazyLock::force(&GAUGE_HL_NODE_DATA_BLOCK_HEIGHT);
+    LazyLock::force(&GAUGE_HL_NODE_DATA_BLOCK_TIME_MS);
+}
+
+/// Longest replica_cmds line looked for, blocks can be several MiB
+const MAX_LINE_LENGTH: u64 = 64 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HeightSource {
+    /// Last block hl-node wrote into the newest replica_cmds file
+    ReplicaCmds,
+    /// Height in the name of the newest periodic ABCI state, only written every few thousand blocks
+    PeriodicAbciStates,
+}
+
+impl HeightSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReplicaCmds => "replica_cmds",
+            Self::PeriodicAbciStates => "periodic_abci_states",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LocalHeight {
+    pub height: u64,
+    pub source: HeightSource,
+    /// Block time, only known from replica_cmds
+    pub time_ms: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ReplicaBlock {
+    abci_block: AbciBlock,
+}
+
+#[derive(Deserialize)]
+struct AbciBlock {
+    round: u64,
+    time: String,
+}
+
+/// Node's height as seen in hl/data, without going through the info endpoint. Highest of the sources
+pub fn local_height(data_directory: &Path) -> Option<LocalHeight> {
+    local_heights(data_directory)
+        .into_iter()
+        .max_by_key(|height| height.height)
+}
+
+/// Height per source found in hl/data
+pub fn local_heights(data_directory: &Path) -> Vec<LocalHeight> {
+    let data = data_directory.join("hl/data");
+    [
+        replica_cmds_height(&data.join("replica_cmds")),
+        abci_states_height(&data.join("periodic_abci_states")),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Files are named after their first block, which is used until hl-node finished writing a line into it
+fn replica_cmds_height(replica_cmds: &Path) -> Option<LocalHeight> {
+    let path = newest_file(replica_cmds)?;
+    let block = last_lines(&path, 1)
+        .map_err(|err| trace!(?err, ?path, "failed to read replica_cmds"))
+        .ok()
+        .and_then(|mut lines| lines.pop())
+        .and_then(|line| serde_json::from_slice::<ReplicaBlock>(&line).ok());
+    match block {
+        Some(block) => Some(LocalHeight {
+            height: block.abci_block.round,
+            source: HeightSource::ReplicaCmds,
+            time_ms: parse_node_time(&block.abci_block.time),
+        }),
+        None => Some(LocalHeight {
+            height: file_height(&path)?,
+            source: HeightSource::ReplicaCmds,
+            time_ms: None,
+        }),
+    }
+}
+
+/// periodic_abci_states/<date>/<height>.rmp
+fn abci_states_height(abci_states: &Path) -> Option<LocalHeight> {
+    Some(LocalHeight {
+        height: file_height(&newest_file(abci_states)?)?,
+        source: HeightSource::PeriodicAbciStates,
+        time_ms: None,
+    })
+}
+
+fn file_height(path: &Path) -> Option<u64> {
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Last newline terminated lines, oldest first, read backwards so the rest of the file is skipped. Lines longer than
+/// the limit end the search
+pub fn last_lines(path: &Path, count: usize) -> io::Result<Vec<Vec<u8>>> {
+    const CHUNK: u64 = 64 * 1024;
+
+    let mut file = File::open(path)?;
+    let length = file.metadata()?.len();
+    let mut lines = Vec::new();
+    let mut tail = Vec::new();
+    let mut position = length;
+    // The partial line hl-node is still writing, if any, comes after the last newline
+    let mut skipped_partial = false;
+    while position > 0 && lines.len() < count && (tail.len() as u64) < MAX_LINE_LENGTH {
+        let chunk_start = position.saturating_sub(CHUNK);
+        let mut chunk = vec![0; (position - chunk_start) as usize];
+        file.seek(SeekFrom::Start(chunk_start))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+        position = chunk_start;
+
+        while lines.len() < count {
+            let Some(end) = tail.iter().rposition(|&byte| byte == b'\n') else {
+                break;
+            };
+            let line = tail.split_off(end + 1);
+            tail.truncate(end);
+            if skipped_partial && !line.is_empty() {
+                lines.push(line);
+            }
+            skipped_partial = true;
+        }
+    }
+    if position == 0 && skipped_partial && lines.len() < count && !tail.is_empty() {
+        lines.push(tail);
+    }
+    lines.reverse();
+    Ok(lines)
+}
+
+pub async fn poll_local_height(data_directory: PathBuf, poll_interval: Duration) {
+    init_metrics();
+
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(?data_directory, "polling block height from hl/data");
+    loop {
+        interval.tick().await;
+
+        let data_directory = data_directory.clone();
+        let Ok(heights) = tokio::task::spawn_blocking(move || local_heights(&data_directory)).await
+        else {
+            continue;
+        };
+        for height in heights {
+            trace!(?height, "local block height");
+            GAUGE_HL_NODE_DATA_BLOCK_HEIGHT
+                .with_label_values(&[height.source.as_str()])
+                .set(height.height as i64);
+            if let Some(time_ms) = height.time_ms {
+                GAUGE_HL_NODE_DATA_BLOCK_TIME_MS.set(time_ms as f64);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replica_block() {
+        let line = br#"{"abci_block":{"time":"2025-06-15T15:06:40.123456789","round":628178000,"signed_action_bundles":[]},"resps":null}"#;
+        let block: ReplicaBlock = serde_json::from_slice(line).unwrap();
+        assert_eq!(block.abci_block.round, 628178000);
+        assert_eq!(parse_node_time(&block.abci_block.time), Some(1750000000123));
+    }
+
+    #[test]
+    fn test_file_height() {
+        assert_eq!(
+            file_height(Path::new("periodic_abci_states/20250615/628170000.rmp")),
+            Some(628170000)
+        );
+        assert_eq!(
+            file_height(Path::new("replica_cmds/x/20250615/628160000")),
+            Some(628160000)
+        );
+        assert_eq!(file_height(Path::new("20250615/latest.rmp")), None);
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     time::{Duration, SystemTime},
 };
 
+use eyre::Context;
 use prometheus::{
     Gauge, Histogram, IntGauge, exponential_buckets, histogram_opts, register_gauge,
     register_histogram, register_int_gauge,
@@ -12,7 +13,20 @@ use serde::Deserialize;
 use tokio::time::{MissedTickBehavior, interval};
 use tracing::{trace, warn};
 
+use crate::hl_gossip_config::HyperliquidChain;
+
+pub mod abci_state;
+pub mod activity;
+pub mod consensus;
+pub mod evm;
+pub mod gossip;
+pub mod height;
+pub mod peers;
 pub mod server;
+pub mod validator;
+
+/// Port hl-node serves the info server & EVM RPC on
+pub const INFO_PORT: u16 = 3001;
 
 pub static GAUGE_HL_NODE_SYSTEM_TIME_MS: LazyLock<Gauge> = LazyLock::new(|| {
     register_gauge!(
@@ -47,6 +61,14 @@ pub static HISTOGRAM_HL_NODE_TIME_DRIFT_MS: LazyLock<Histogram> = LazyLock::new(
     .unwrap()
 });
 
+pub static GAUGE_HL_CHILD_RSS_BYTES: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_child_rss_bytes",
+        "Resident memory of the child process tree in bytes"
+    )
+    .unwrap()
+});
+
 fn init_metrics() {
     LazyLock::force(&GAUGE_HL_NODE_SYSTEM_TIME_MS);
     LazyLock::force(&GAUGE_HL_NODE_TIME_MS);
@@ -61,12 +83,19 @@ static CLIENT: LazyLock<Client> = LazyLock::new(|| {
         .unwrap()
 });
 
-async fn request_exchange_time() -> Result<u64, reqwest::Error> {
-    #[derive(Deserialize)]
-    struct ExchangeStatus {
-        time: u64,
-    }
+pub static NETWORK_CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    ClientBuilder::new()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap()
+});
 
+#[derive(Deserialize)]
+struct ExchangeStatus {
+    time: u64,
+}
+
+pub async fn request_exchange_time() -> Result<u64, reqwest::Error> {
     let status = CLIENT
         .request(Method::POST, "http://127.0.0.1:3001/info")
         .body(r#"{"type":"exchangeStatus"}"#)
@@ -80,6 +109,41 @@ async fn request_exchange_time() -> Result<u64, reqwest::Error> {
     Ok(status.time)
 }
 
+/// Info endpoint of the public Hyperliquid API
+pub fn network_info_url(chain: HyperliquidChain) -> &'static str {
+    match chain {
+        HyperliquidChain::Mainnet => "https://api.hyperliquid.xyz/info",
+        HyperliquidChain::Testnet => "https://api.hyperliquid-testnet.xyz/info",
+    }
+}
+
+/// Requests exchange time from the public Hyperliquid API
+pub async fn request_network_exchange_time(chain: HyperliquidChain) -> Result<u64, reqwest::Error> {
+    let status = NETWORK_CLIENT
+        .request(Method::POST, network_info_url(chain))
+        .body(r#"{"type":"exchangeStatus"}"#)
+        .header(CONTENT_TYPE, "application/json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ExchangeStatus>()
+        .await?;
+
+    Ok(status.time)
+}
+
+/// How far local node's exchange time is behind the public Hyperliquid API
+pub async fn sync_lag(chain: HyperliquidChain) -> eyre::Result<Duration> {
+    let (local, network) = tokio::join!(
+        request_exchange_time(),
+        request_network_exchange_time(chain)
+    );
+    let local = local.wrap_err("failed to request exchange status from hl-node")?;
+    let network = network.wrap_err("failed to request exchange status from Hyperliquid API")?;
+
+    Ok(Duration::from_millis(network.saturating_sub(local)))
+}
+
 pub async fn poll_node(poll_interval: Duration) {
     init_metrics();
 
@@ -0,0 +1,117 @@
+use std::{collections::HashSet, path::PathBuf, sync::LazyLock, time::Duration};
+
+use prometheus::{GaugeVec, IntGaugeVec, register_gauge_vec, register_int_gauge_vec};
+use tokio::{
+    sync::watch,
+    task::JoinSet,
+    time::{MissedTickBehavior, interval},
+};
+use tracing::{debug, info, trace};
+
+use crate::{
+    hl_gossip_config::OverrideGossipConfig,
+    reload::Tunables,
+    speedtest::{GOSSIP_PORT, measure_node_latency},
+};
+
+pub static GAUGE_HL_PEER_LATENCY: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "hl_bootstrap_peer_latency_seconds",
+        "TCP connect latency to the selected root node",
+        &["ip"]
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_PEER_REACHABLE: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!(
+        "hl_bootstrap_peer_reachable",
+        "Whether the selected root node accepted a connection within the timeout",
+        &["ip"]
+    )
+    .unwrap()
+});
+
+/// Periodically measures latency to the root nodes currently in override_gossip_config.json.
+/// Timeout and ignored peers follow the seed peer settings, which can be changed on reload
+pub async fn poll_peer_latency(
+    gossip_config_path: PathBuf,
+    poll_interval: Duration,
+    tunables: watch::Receiver<Tunables>,
+) {
+    LazyLock::force(&GAUGE_HL_PEER_LATENCY);
+    LazyLock::force(&GAUGE_HL_PEER_REACHABLE);
+
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut known_peers = HashSet::new();
+
+    info!(?gossip_config_path, "monitoring peer latency");
+    loop {
+        interval.tick().await;
+
+        let config = match tokio::fs::read(&gossip_config_path)
+            .await
+            .map_err(eyre::Report::from)
+            .and_then(|contents| Ok(serde_json::from_slice::<OverrideGossipConfig>(&contents)?))
+        {
+            Ok(config) => config,
+            Err(err) => {
+                trace!(?err, "failed to read gossip config");
+                continue;
+            }
+        };
+
+        let (timeout_duration, ignored_peers) = {
+            let tunables = tunables.borrow();
+            (
+                tunables.seed_peers_max_latency,
+                tunables.seed_peers_ignored.clone(),
+            )
+        };
+        let peers: HashSet<_> = config
+            .root_node_ips
+            .iter()
+            .map(|peer| peer.ip)
+            .filter(|ip| !ignored_peers.contains(ip))
+            .collect();
+
+        // Peers no longer selected
+        for ip in known_peers.difference(&peers) {
+            let label = ip.to_string();
+            let _ = GAUGE_HL_PEER_LATENCY.remove_label_values(&[&label]);
+            let _ = GAUGE_HL_PEER_REACHABLE.remove_label_values(&[&label]);
+        }
+
+        let mut measurements = JoinSet::new();
+        for ip in peers.iter().copied() {
+            measurements.spawn(async move {
+                (
+                    ip,
+                    measure_node_latency(ip, GOSSIP_PORT, timeout_duration).await,
+                )
+            });
+        }
+
+        while let Some(Ok((ip, latency))) = measurements.join_next().await {
+            let label = ip.to_string();
+            match latency {
+                Ok(latency) => {
+                    trace!(%ip, ?latency, "peer latency");
+                    GAUGE_HL_PEER_LATENCY
+                        .with_label_values(&[&label])
+                        .set(latency.as_secs_f64());
+                    GAUGE_HL_PEER_REACHABLE.with_label_values(&[&label]).set(1);
+                }
+                Err(err) => {
+                    debug!(%ip, %err, "peer latency measurement failed");
+                    let _ = GAUGE_HL_PEER_LATENCY.remove_label_values(&[&label]);
+                    GAUGE_HL_PEER_REACHABLE.with_label_values(&[&label]).set(0);
+                }
+            }
+        }
+
+        known_peers = peers;
+    }
+}
@@ -1,29 +1,110 @@
-use std::sync::LazyLock;
-use std::{net::SocketAddr, ops::Sub, time::Duration};
+use std::sync::{Arc, LazyLock};
+use std::{net::SocketAddr, time::Duration};
 
 use axum::http::HeaderMap;
 use axum::http::header::CONTENT_TYPE;
 use axum::response::IntoResponse;
-use axum::routing::get;
-use axum::{Router, extract::State};
+use axum::routing::{get, post};
+use axum::{
+    Json, Router,
+    extract::{ConnectInfo, Path, State},
+};
 use prometheus::TextEncoder;
 use reqwest::StatusCode;
-use tokio::net::TcpListener;
+use serde::Serialize;
+use tokio::{net::TcpListener, sync::Mutex, time::Instant};
+use tracing::{debug, error};
 
-use crate::monitor::{
-    GAUGE_HL_NODE_RESPONDING, GAUGE_HL_NODE_SYSTEM_TIME_MS, GAUGE_HL_NODE_TIME_MS, as_ms_f64,
+use crate::{
+    auth::{self, Access, Scope},
+    health::{HealthChecks, Probes},
+    hl_gossip_config::HyperliquidChain,
+    http_server::{self, HttpServerArgs},
+    maintenance,
+    monitor::{evm::EvmSync, gossip::GOSSIP_PORTS, sync_lag},
+    reachability,
 };
 
+/// How long a sync lag measurement is reused, so frequent readiness probes don't each hit the Hyperliquid API
+const SYNC_LAG_TTL: Duration = Duration::from_secs(5);
+
 #[derive(Clone)]
 struct MonitorServer {
-    healthy_drift_threshold: Duration,
+    network: HyperliquidChain,
+    sync_max_lag: Duration,
+    evm: Option<EvmSync>,
+    sync_lag: Arc<Mutex<Option<SyncLagMeasurement>>>,
+}
+
+#[derive(Clone, Copy)]
+struct SyncLagMeasurement {
+    measured_at: Instant,
+    /// None when the lag couldn't be determined
+    lag: Option<Duration>,
 }
 
-fn router() -> Router<MonitorServer> {
-    Router::new()
-        .route("/metrics", get(metrics))
+impl MonitorServer {
+    /// Sync lag, measured at most once per [SYNC_LAG_TTL]. Concurrent probes wait for the same measurement
+    async fn sync_lag(&self) -> Option<Duration> {
+        let mut cached = self.sync_lag.lock().await;
+        if let Some(measurement) = *cached
+            && measurement.measured_at.elapsed() < SYNC_LAG_TTL
+        {
+            return measurement.lag;
+        }
+
+        let lag = match sync_lag(self.network).await {
+            Ok(lag) => Some(lag),
+            Err(err) => {
+                debug!(?err, "failed to determine sync lag");
+                None
+            }
+        };
+        *cached = Some(SyncLagMeasurement {
+            measured_at: Instant::now(),
+            lag,
+        });
+        lag
+    }
+}
+
+fn router(health_checks: HealthChecks, probes: Probes, access: &Access) -> Router<MonitorServer> {
+    let metrics = Router::new().route("/metrics", get(metrics));
+    let health = Router::new().route("/ready", get(ready));
+    // Keys are only required here with --api-keys-health, probes usually can't send them
+    let health_access = access.health();
+    health_router(health_checks, probes, access)
+        .merge(auth::require(&health_access, health, Scope::Health))
+        .merge(auth::require(&health_access, metrics, Scope::Metrics))
+}
+
+fn health_router<S: Clone + Send + Sync + 'static>(
+    health_checks: HealthChecks,
+    probes: Probes,
+    access: &Access,
+) -> Router<S> {
+    let probes = Router::new()
         .route("/livez", get(livez))
         .route("/readyz", get(readyz))
+        .route("/startupz", get(startupz))
+        .with_state(probes);
+    let router = Router::new()
+        .route("/healthz", get(healthz))
+        .with_state(health_checks)
+        .merge(probes)
+        .route("/maintenance", get(get_maintenance))
+        .route("/speedtest/history", get(speedtest_history))
+        .route("/reachability/{port}", get(reachability));
+    // Anyone who can reach the probes must not be able to take the node out of rotation
+    let maintenance = Router::new().route(
+        "/maintenance",
+        post(enable_maintenance).delete(disable_maintenance),
+    );
+    auth::require(&access.health(), router, Scope::Health).merge(auth::require_or_local(
+        access,
+        maintenance,
+        Scope::Health,
+    ))
 }
 
 async fn metrics() -> impl IntoResponse {
@@ -31,6 +112,7 @@ async fn metrics() -> impl IntoResponse {
         HeaderMap::from_iter([(CONTENT_TYPE, "text/plain;version=0.0.4".parse().unwrap())])
     });
 
+    crate::metrics::refresh();
     let metrics = prometheus::default_registry().gather();
 
     (
@@ -39,38 +121,153 @@ async fn metrics() -> impl IntoResponse {
     )
 }
 
-async fn livez() -> impl IntoResponse {
-    if GAUGE_HL_NODE_RESPONDING.get() == 1 {
+async fn speedtest_history() -> impl IntoResponse {
+    Json(crate::speedtest::history::runs())
+}
+
+/// Connects back to the caller's gossip port, so other nodes can verify their inbound reachability
+async fn reachability(
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Path(port): Path<u16>,
+) -> impl IntoResponse {
+    // Only gossip ports, so this can't be used for scanning arbitrary services of the caller
+    if !GOSSIP_PORTS.contains(&port) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Json(reachability::probe(peer.ip(), port).await))
+}
+
+fn probe_status(ok: bool) -> StatusCode {
+    if ok {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
     }
 }
 
-async fn readyz(State(state): State<MonitorServer>) -> impl IntoResponse {
-    if GAUGE_HL_NODE_RESPONDING.get() == 1
-        && GAUGE_HL_NODE_SYSTEM_TIME_MS
-            .get()
-            .sub(GAUGE_HL_NODE_TIME_MS.get())
-            .max(0.0)
-            < as_ms_f64(&state.healthy_drift_threshold)
-    {
+async fn livez(State(probes): State<Probes>) -> impl IntoResponse {
+    probe_status(probes.live())
+}
+
+async fn readyz(State(probes): State<Probes>) -> impl IntoResponse {
+    probe_status(probes.ready().await)
+}
+
+async fn startupz(State(probes): State<Probes>) -> impl IntoResponse {
+    probe_status(probes.started().await)
+}
+
+async fn healthz(State(health_checks): State<HealthChecks>) -> impl IntoResponse {
+    let report = health_checks.evaluate().await;
+    let code = if report.healthy {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (code, Json(report))
+}
+
+#[derive(Serialize)]
+struct MaintenanceStatus {
+    maintenance: bool,
+}
+
+async fn get_maintenance() -> impl IntoResponse {
+    maintenance_status()
+}
+
+async fn enable_maintenance() -> impl IntoResponse {
+    set_maintenance(true)
+}
+
+async fn disable_maintenance() -> impl IntoResponse {
+    set_maintenance(false)
+}
+
+fn set_maintenance(enabled: bool) -> impl IntoResponse {
+    match maintenance::set_enabled(enabled) {
+        Ok(()) => (StatusCode::OK, maintenance_status()),
+        Err(err) => {
+            error!(?err, "failed to change maintenance mode");
+            (StatusCode::INTERNAL_SERVER_ERROR, maintenance_status())
+        }
+    }
+}
+
+fn maintenance_status() -> Json<MaintenanceStatus> {
+    Json(MaintenanceStatus {
+        maintenance: maintenance::is_enabled(),
+    })
+}
+
+async fn ready(State(state): State<MonitorServer>) -> impl IntoResponse {
+    if maintenance::is_enabled() {
+        return StatusCode::SERVICE_UNAVAILABLE;
+    }
+
+    match state.sync_lag().await {
+        Some(lag) if lag < state.sync_max_lag => {}
+        Some(lag) => {
+            debug!(?lag, max_lag = ?state.sync_max_lag, "node is lagging behind the network");
+            return StatusCode::SERVICE_UNAVAILABLE;
+        }
+        None => return StatusCode::SERVICE_UNAVAILABLE,
+    }
+
+    let Some(evm) = &state.evm else {
+        return StatusCode::OK;
+    };
+    match evm.lag().await {
+        Ok(lag) if lag.blocks() <= evm.max_lag_blocks => StatusCode::OK,
+        Ok(lag) => {
+            debug!(
+                ?lag,
+                max_lag_blocks = evm.max_lag_blocks,
+                "EVM RPC is lagging behind the reference"
+            );
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+        Err(err) => {
+            debug!(?err, "failed to determine EVM block lag");
+            StatusCode::SERVICE_UNAVAILABLE
+        }
     }
 }
 
 pub async fn run_metrics_server(
     listen_address: SocketAddr,
-    healthy_drift_threshold: Duration,
+    network: HyperliquidChain,
+    sync_max_lag: Duration,
+    health_checks: HealthChecks,
+    probes: Probes,
+    access: Access,
+    http: HttpServerArgs,
 ) -> eyre::Result<()> {
     let state = MonitorServer {
-        healthy_drift_threshold,
+        network,
+        sync_max_lag,
+        evm: health_checks.evm.clone(),
+        sync_lag: Default::default(),
     };
 
     let listener = TcpListener::bind(listen_address).await?;
-    axum::serve(listener, router().with_state(state).into_make_service()).await?;
+    let router = auth::restrict(&access, router(health_checks, probes, &access)).with_state(state);
+    http_server::serve(listener, router, &http).await;
+
+    Ok(())
+}
+
+pub async fn run_health_server(
+    listen_address: SocketAddr,
+    health_checks: HealthChecks,
+    probes: Probes,
+    access: Access,
+    http: HttpServerArgs,
+) -> eyre::Result<()> {
+    let listener = TcpListener::bind(listen_address).await?;
+    let router = auth::restrict(&access, health_router(health_checks, probes, &access));
+    http_server::serve(listener, router, &http).await;
 
     Ok(())
 }
@@ -0,0 +1,279 @@
+use std::{sync::LazyLock, time::Duration};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, bail};
+use prometheus::{Gauge, IntGauge, register_gauge, register_int_gauge};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, warn};
+
+use crate::{
+    alerts::{AlertEvent, alert},
+    hl_gossip_config::HyperliquidChain,
+    monitor::{NETWORK_CLIENT, network_info_url},
+};
+
+pub static GAUGE_HL_VALIDATOR_JAILED: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_validator_jailed",
+        "Whether the validator is jailed according to the validator summaries"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_VALIDATOR_ACTIVE: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_validator_active",
+        "Whether the validator is in the active set according to the validator summaries"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_VALIDATOR_UPTIME_FRACTION: LazyLock<Gauge> = LazyLock::new(|| {
+    register_gauge!(
+        "hl_bootstrap_validator_uptime_fraction",
+        "Uptime fraction of the validator over the last day"
+    )
+    .unwrap()
+});
+
+pub static GAUGE_HL_VALIDATOR_RECENT_BLOCKS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "hl_bootstrap_validator_recent_blocks",
+        "Blocks recently proposed by the validator"
+    )
+    .unwrap()
+});
+
+fn init_metrics() {
+    LazyLock::force(&GAUGE_HL_VALIDATOR_JAILED);
+    LazyLock::force(&GAUGE_HL_VALIDATOR_ACTIVE);
+    LazyLock::force(&GAUGE_HL_VALIDATOR_UPTIME_FRACTION);
+    LazyLock::force(&GAUGE_HL_VALIDATOR_RECENT_BLOCKS);
+}
+
+#[derive(Clone, Debug, Args)]
+pub struct ValidatorMonitorArgs {
+    /// Address of the operator's validator (not the signer) to watch in the validator summaries of the network,
+    /// alerting when it's jailed or its uptime drops
+    #[arg(long, env = "HL_BOOTSTRAP_VALIDATOR_ADDRESS", value_parser = parse_address)]
+    pub validator_address: Option<String>,
+
+    /// Lowest uptime fraction over the last day before alerting (validator-uptime-low)
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_VALIDATOR_MIN_UPTIME",
+        default_value_t = 0.95
+    )]
+    pub validator_min_uptime: f64,
+
+    /// How often should the validator summaries be requested
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_VALIDATOR_POLL_INTERVAL",
+        default_value = "1m"
+    )]
+    pub validator_poll_interval: DurationString,
+}
+
+fn parse_address(value: &str) -> eyre::Result<String> {
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    if hex.len() != 40 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        bail!("expected a 20 byte hex address, e.g. 0x5ac99df645f3414876c816caa18b2d234024b487");
+    }
+    Ok(format!("0x{}", hex.to_ascii_lowercase()))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidatorSummary {
+    validator: String,
+    is_jailed: bool,
+    is_active: bool,
+    n_recent_blocks: i64,
+    /// Pairs of period (day, week, month) & its stats
+    stats: Vec<(String, ValidatorStats)>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidatorStats {
+    uptime_fraction: String,
+}
+
+impl ValidatorSummary {
+    fn day_uptime(&self) -> Option<f64> {
+        self.stats
+            .iter()
+            .find(|(period, _)| period == "day")
+            .and_then(|(_, stats)| stats.uptime_fraction.parse().ok())
+    }
+}
+
+async fn request_summary(
+    chain: HyperliquidChain,
+    address: &str,
+) -> eyre::Result<Option<ValidatorSummary>> {
+    let summaries: Vec<ValidatorSummary> = NETWORK_CLIENT
+        .post(network_info_url(chain))
+        .json(&json!({"type": "validatorSummaries"}))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .wrap_err("failed to request validator summaries")?
+        .json()
+        .await
+        .wrap_err("failed to parse validator summaries")?;
+
+    Ok(summaries
+        .into_iter()
+        .find(|summary| summary.validator.eq_ignore_ascii_case(address)))
+}
+
+/// Flags of the previous poll, alerts are only sent when they change
+#[derive(Debug, Default)]
+struct ValidatorState {
+    jailed: bool,
+    uptime_low: bool,
+}
+
+impl ValidatorState {
+    /// Alerts to send for the summary, updating the state
+    fn update(&mut self, summary: &ValidatorSummary, min_uptime: f64) -> Vec<(AlertEvent, String)> {
+        let mut alerts = Vec::new();
+
+        if summary.is_jailed && !self.jailed {
+            alerts.push((
+                AlertEvent::ValidatorJailed,
+                format!("validator {} is jailed", summary.validator),
+            ));
+        } else if !summary.is_jailed && self.jailed {
+            info!(validator = summary.validator, "validator was unjailed");
+        }
+        self.jailed = summary.is_jailed;
+
+        if let Some(uptime) = summary.day_uptime() {
+            let uptime_low = uptime < min_uptime;
+            if uptime_low && !self.uptime_low {
+                alerts.push((
+                    AlertEvent::ValidatorUptimeLow,
+                    format!(
+                        "validator {} uptime over the last day is {:.2}%, below {:.2}%",
+                        summary.validator,
+                        uptime * 100.0,
+                        min_uptime * 100.0
+                    ),
+                ));
+            } else if !uptime_low && self.uptime_low {
+                info!(
+                    validator = summary.validator,
+                    uptime, "validator uptime recovered"
+                );
+            }
+            self.uptime_low = uptime_low;
+        }
+
+        alerts
+    }
+}
+
+pub async fn validator_monitor_task(
+    chain: HyperliquidChain,
+    address: String,
+    min_uptime: f64,
+    poll_interval: Duration,
+) {
+    init_metrics();
+
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut state = ValidatorState::default();
+    let mut found = true;
+
+    info!(address, min_uptime, "monitoring validator status");
+    loop {
+        interval.tick().await;
+
+        let summary = match request_summary(chain, &address).await {
+            Ok(Some(summary)) => summary,
+            Ok(None) => {
+                if found {
+                    warn!(address, "validator is not in the validator summaries");
+                }
+                found = false;
+                continue;
+            }
+            Err(err) => {
+                debug!(?err, "failed to request validator summary");
+                continue;
+            }
+        };
+        found = true;
+
+        GAUGE_HL_VALIDATOR_JAILED.set(summary.is_jailed as i64);
+        GAUGE_HL_VALIDATOR_ACTIVE.set(summary.is_active as i64);
+        GAUGE_HL_VALIDATOR_RECENT_BLOCKS.set(summary.n_recent_blocks);
+        if let Some(uptime) = summary.day_uptime() {
+            GAUGE_HL_VALIDATOR_UPTIME_FRACTION.set(uptime);
+        }
+
+        for (event, message) in state.update(&summary, min_uptime) {
+            warn!(?event, message, "validator needs attention");
+            alert(event, message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validator_state() {
+        let mut summary: ValidatorSummary = serde_json::from_str(
+            r#"{
+                "validator": "0x5ac99df645f3414876c816caa18b2d234024b487",
+                "signer": "0x6e2b9a7c4f1bd3d0f5c8a1e9b0c7d6e5f4a3b2c1",
+                "name": "ASXN",
+                "stake": 100000000000000,
+                "isJailed": false,
+                "unjailableAfter": null,
+                "isActive": true,
+                "commission": "0.04",
+                "nRecentBlocks": 12,
+                "stats": [
+                    ["day", {"uptimeFraction": "0.998", "predictedApr": "0.021", "nSamples": 1440}],
+                    ["week", {"uptimeFraction": "0.9", "predictedApr": "0.021", "nSamples": 10080}]
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(summary.day_uptime(), Some(0.998));
+
+        let mut state = ValidatorState::default();
+        assert!(state.update(&summary, 0.95).is_empty());
+
+        summary.is_jailed = true;
+        summary.stats[0].1.uptime_fraction = "0.5".to_string();
+        let alerts: Vec<_> = state
+            .update(&summary, 0.95)
+            .into_iter()
+            .map(|(event, _)| event)
+            .collect();
+        assert_eq!(
+            alerts,
+            [AlertEvent::ValidatorJailed, AlertEvent::ValidatorUptimeLow]
+        );
+        // Not repeated while the validator stays jailed
+        assert!(state.update(&summary, 0.95).is_empty());
+
+        assert_eq!(
+            parse_address("0x5AC99DF645F3414876C816CAA18B2D234024B487").unwrap(),
+            "0x5ac99df645f3414876c816caa18b2d234024b487"
+        );
+        assert!(parse_address("0x1234").is_err());
+    }
+}
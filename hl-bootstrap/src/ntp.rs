@@ -0,0 +1,84 @@
+use std::time::{Duration, SystemTime};
+
+use eyre::{Context, bail};
+use tokio::{net::UdpSocket, time::timeout};
+
+use crate::reaper;
+
+/// Seconds between the NTP epoch (1900) and the unix epoch
+const NTP_UNIX_OFFSET_SECS: u64 = 2_208_988_800;
+
+/// Measures the local clock offset against an SNTP server (RFC 4330). Positive when the local clock is behind
+pub async fn query_clock_offset(server: &str, query_timeout: Duration) -> eyre::Result<f64> {
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .wrap_err("failed to bind NTP socket")?;
+    socket
+        .connect(server)
+        .await
+        .wrap_err_with(|| format!("failed to resolve NTP server {server}"))?;
+
+    // LI = 0, VN = 4, mode = 3 (client)
+    let mut request = [0_u8; 48];
+    request[0] = 0b00_100_011;
+
+    let sent_at = unix_secs_now();
+    socket.send(&request).await?;
+    let mut response = [0_u8; 48];
+    let received = timeout(query_timeout, socket.recv(&mut response))
+        .await
+        .wrap_err_with(|| format!("NTP server {server} did not respond in {query_timeout:?}"))??;
+    let received_at = unix_secs_now();
+
+    if received < response.len() {
+        bail!("short NTP response from {server}");
+    }
+    // Stratum 0 is a kiss-of-death packet
+    if response[1] == 0 {
+        bail!("NTP server {server} refused the request");
+    }
+
+    let server_received = ntp_timestamp(&response[32..40]);
+    let server_transmitted = ntp_timestamp(&response[40..48]);
+    Ok(((server_received - sent_at) + (server_transmitted - received_at)) / 2.0)
+}
+
+/// 64-bit NTP timestamp as unix seconds
+fn ntp_timestamp(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes(bytes[0..4].try_into().unwrap()) as u64;
+    let fraction = u32::from_be_bytes(bytes[4..8].try_into().unwrap()) as f64 / 2_f64.powi(32);
+    seconds.saturating_sub(NTP_UNIX_OFFSET_SECS) as f64 + fraction
+}
+
+fn unix_secs_now() -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+/// Whether the system clock is synchronized according to systemd-timedated, covering chrony & timesyncd
+pub fn is_clock_synchronized() -> Option<bool> {
+    let output = reaper::output(std::process::Command::new("timedatectl").args([
+        "show",
+        "--property=NTPSynchronized",
+        "--value",
+    ]))
+    .ok()
+    .filter(|output| output.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).trim() == "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntp_timestamp() {
+        // 2024-01-01T00:00:00.5Z
+        let seconds = (1_704_067_200 + NTP_UNIX_OFFSET_SECS) as u32;
+        let mut bytes = seconds.to_be_bytes().to_vec();
+        bytes.extend(0x8000_0000_u32.to_be_bytes());
+        assert_eq!(ntp_timestamp(&bytes), 1_704_067_200.5);
+    }
+}
@@ -0,0 +1,560 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::BufRead,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use arrow_array::{
+    ArrayRef, RecordBatch, StringArray, TimestampMillisecondArray, UInt32Array, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use clap::{Args, ValueEnum};
+use duration_string::DurationString;
+use eyre::Context;
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{Compression, ZstdLevel},
+    file::properties::WriterProperties,
+};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    compress::{self, uncompressed_name},
+    data_api::parse_node_time,
+    metrics::COUNTER_PARQUET_EXPORTS,
+    replica_stream::newest_file,
+    storage::Bucket,
+};
+
+/// Rows buffered before being written out as a row group
+const BATCH_ROWS: usize = 64 * 1024;
+
+#[derive(Clone, Debug, Args)]
+pub struct ParquetExportArgs {
+    /// How often to export completed replica_cmds files to Parquet. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_PARQUET_EXPORT_INTERVAL")]
+    pub parquet_export_interval: Option<DurationString>,
+
+    /// Directory to write Parquet files into, partitioned as `date=<YYYY-MM-DD>/<height>.parquet`
+    #[arg(long, env = "HL_BOOTSTRAP_PARQUET_EXPORT_DIRECTORY")]
+    pub parquet_export_directory: Option<PathBuf>,
+
+    /// Key prefix within the object storage (--s3-bucket or --storage-url, after its prefix) to upload Parquet files to, instead of writing them into
+    /// --parquet-export-directory, e.g. `replica_cmds/`
+    #[arg(long, env = "HL_BOOTSTRAP_PARQUET_EXPORT_S3_PREFIX")]
+    pub parquet_export_s3_prefix: Option<String>,
+
+    /// What a row is
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PARQUET_EXPORT_FLATTEN",
+        default_value = "actions"
+    )]
+    pub parquet_export_flatten: Flatten,
+
+    /// Also store the full block, or action & response, as JSON columns
+    #[arg(long, env = "HL_BOOTSTRAP_PARQUET_EXPORT_JSON")]
+    pub parquet_export_json: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum Flatten {
+    /// One row per block, with its action counts
+    Blocks,
+    /// One row per signed action, with the block it's in
+    Actions,
+}
+
+/// Where exported files go, keyed by `date=<YYYY-MM-DD>/<height>.parquet`
+enum Destination {
+    Directory(PathBuf),
+    Bucket { bucket: Box<Bucket>, prefix: String },
+}
+
+impl Destination {
+    /// Keys exported before, so restarts don't redo everything
+    async fn exported(&self) -> eyre::Result<HashSet<String>> {
+        match self {
+            Self::Directory(directory) => {
+                let mut exported = HashSet::new();
+                for partition in fs::read_dir(directory)?.flatten() {
+                    let Ok(files) = fs::read_dir(partition.path()) else {
+                        continue;
+                    };
+                    for file in files.flatten() {
+                        exported.insert(format!(
+                            "{}/{}",
+                            partition.file_name().to_string_lossy(),
+                            file.file_name().to_string_lossy()
+                        ));
+                    }
+                }
+                Ok(exported)
+            }
+            Self::Bucket { bucket, prefix } => Ok(bucket
+                .list(prefix)
+                .await?
+                .into_iter()
+                .filter_map(|object| Some(object.key.strip_prefix(prefix)?.to_string()))
+                .collect()),
+        }
+    }
+
+    async fn store(&self, key: &str, file: NamedTempFile) -> eyre::Result<()> {
+        match self {
+            Self::Directory(directory) => {
+                let path = directory.join(key);
+                if let Some(partition) = path.parent() {
+                    fs::create_dir_all(partition)?;
+                }
+                file.persist(&path)
+                    .wrap_err_with(|| format!("failed to move export to {path:?}"))?;
+            }
+            Self::Bucket { bucket, prefix } => {
+                bucket
+                    .upload_file(&format!("{prefix}{key}"), file.path())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Temporary files are created next to the destination, so they can be renamed into place
+    fn staging_directory<'a>(&'a self, data_directory: &'a Path) -> &'a Path {
+        match self {
+            Self::Directory(directory) => directory,
+            Self::Bucket { .. } => data_directory,
+        }
+    }
+}
+
+/// Worker task that converts every replica_cmds file hl-node moved on from into Parquet
+pub async fn parquet_export_task(
+    data_directory: PathBuf,
+    args: ParquetExportArgs,
+    bucket: Option<Bucket>,
+    export_interval: Duration,
+) {
+    let destination = match (bucket, &args.parquet_export_directory) {
+        (Some(bucket), _) => Destination::Bucket {
+            prefix: format!(
+                "{}{}",
+                bucket.prefix,
+                args.parquet_export_s3_prefix.as_deref().unwrap_or_default()
+            ),
+            bucket: Box::new(bucket),
+        },
+        (None, Some(directory)) => Destination::Directory(directory.clone()),
+        (None, None) => return,
+    };
+    if let Destination::Directory(directory) = &destination
+        && let Err(err) = fs::create_dir_all(directory)
+    {
+        warn!(?err, ?directory, "failed to create export directory");
+        return;
+    }
+
+    let replica_cmds = data_directory.join("hl/data/replica_cmds");
+    let mut exported = None;
+
+    let mut interval = interval(export_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        flatten = ?args.parquet_export_flatten,
+        json = args.parquet_export_json,
+        "exporting replica_cmds to Parquet"
+    );
+    loop {
+        interval.tick().await;
+
+        let exported = match &mut exported {
+            Some(exported) => exported,
+            None => match destination.exported().await {
+                Ok(keys) => exported.insert(keys),
+                Err(err) => {
+                    warn!(?err, "failed to list exported files");
+                    continue;
+                }
+            },
+        };
+
+        // The newest file is still being written
+        let newest = newest_file(&replica_cmds);
+        for (key, source) in replica_files(&replica_cmds) {
+            if exported.contains(&key) || Some(&source) == newest.as_ref() {
+                continue;
+            }
+
+            match export(&source, &key, &destination, &data_directory, &args).await {
+                Ok(rows) => {
+                    COUNTER_PARQUET_EXPORTS
+                        .with_label_values(&["success"])
+                        .inc();
+                    info!(key, rows, "exported replica_cmds file");
+                    exported.insert(key);
+                }
+                Err(err) => {
+                    COUNTER_PARQUET_EXPORTS.with_label_values(&["failed"]).inc();
+                    warn!(?err, ?source, "failed to export replica_cmds file");
+                    // Retried on the next tick, later files wait so the export stays in order
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the amount of rows written
+#[instrument(skip(destination, data_directory, args))]
+async fn export(
+    source: &Path,
+    key: &str,
+    destination: &Destination,
+    data_directory: &Path,
+    args: &ParquetExportArgs,
+) -> eyre::Result<usize> {
+    let file = NamedTempFile::with_prefix_in(
+        ".hl-parquet-",
+        destination.staging_directory(data_directory),
+    )
+    .wrap_err("failed to create export file")?;
+
+    let rows = tokio::task::spawn_blocking({
+        let source = source.to_path_buf();
+        let output = file.reopen()?;
+        let flatten = args.parquet_export_flatten;
+        let json = args.parquet_export_json;
+        move || convert(&source, output, flatten, json)
+    })
+    .await??;
+
+    destination.store(key, file).await?;
+    Ok(rows)
+}
+
+fn convert(source: &Path, output: File, flatten: Flatten, json: bool) -> eyre::Result<usize> {
+    let mut rows: Box<dyn Rows> = match flatten {
+        Flatten::Blocks => Box::<BlockRows>::default(),
+        Flatten::Actions => Box::<ActionRows>::default(),
+    };
+    let schema = rows.schema(json);
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(output, schema.clone(), Some(properties))?;
+
+    let mut written = 0;
+    let reader = compress::open(source).wrap_err_with(|| format!("failed to open {source:?}"))?;
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let block: Value = serde_json::from_str(&line)
+            .wrap_err_with(|| format!("invalid block on line {}", number + 1))?;
+        rows.push(&block, json);
+
+        if rows.len() >= BATCH_ROWS {
+            written += rows.len();
+            writer.write(&rows.take_batch(&schema, json)?)?;
+        }
+    }
+    written += rows.len();
+    writer.write(&rows.take_batch(&schema, json)?)?;
+    writer.close()?;
+
+    debug!(written, "converted replica_cmds file");
+    Ok(written)
+}
+
+/// replica_cmds/<session start>/<YYYYMMDD>/<height> files, compressed or not, as (export key, path)
+fn replica_files(replica_cmds: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    let entries = |path: &Path| {
+        fs::read_dir(path)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| {
+                (
+                    entry.file_name().to_string_lossy().into_owned(),
+                    entry.path(),
+                )
+            })
+    };
+    for (_, session) in entries(replica_cmds) {
+        for (date, day) in entries(&session) {
+            let Some(partition) = partition(&date) else {
+                continue;
+            };
+            for (name, path) in entries(&day) {
+                let height = uncompressed_name(&name);
+                if let Ok(number) = height.parse::<u64>() {
+                    files.push((number, format!("{partition}/{height}.parquet"), path));
+                }
+            }
+        }
+    }
+    files.sort_unstable();
+    // Both exist for a moment while a file is being compressed
+    files.dedup_by(|a, b| a.1 == b.1);
+    files
+        .into_iter()
+        .map(|(_, key, path)| (key, path))
+        .collect()
+}
+
+/// Hive style partition of a YYYYMMDD directory, understood by DuckDB, Athena & co
+fn partition(date: &str) -> Option<String> {
+    if date.len() != 8 || !date.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!(
+        "date={}-{}-{}",
+        &date[..4],
+        &date[4..6],
+        &date[6..]
+    ))
+}
+
+/// Columns being collected for the next record batch
+trait Rows {
+    fn schema(&self, json: bool) -> SchemaRef;
+    fn push(&mut self, block: &Value, json: bool);
+    fn len(&self) -> usize;
+    fn take_batch(&mut self, schema: &SchemaRef, json: bool) -> eyre::Result<RecordBatch>;
+}
+
+fn time_field() -> Field {
+    Field::new(
+        "time",
+        DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into())),
+        true,
+    )
+}
+
+fn time_array(times: Vec<Option<i64>>) -> ArrayRef {
+    Arc::new(TimestampMillisecondArray::from(times).with_timezone("UTC"))
+}
+
+/// Signed action bundles of a block, as (hash, bundle)
+fn bundles(block: &Value) -> impl Iterator<Item = (Option<&str>, &Value)> {
+    block["abci_block"]["signed_action_bundles"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|bundle| (bundle[0].as_str(), &bundle[1]))
+}
+
+#[derive(Default)]
+struct BlockRows {
+    round: Vec<Option<u64>>,
+    time: Vec<Option<i64>>,
+    proposer: Vec<Option<String>>,
+    bundles: Vec<u32>,
+    actions: Vec<u32>,
+    block: Vec<String>,
+}
+
+impl Rows for BlockRows {
+    fn schema(&self, json: bool) -> SchemaRef {
+        let mut fields = vec![
+            Field::new("round", DataType::UInt64, true),
+            time_field(),
+            Field::new("proposer", DataType::Utf8, true),
+            Field::new("bundles", DataType::UInt32, false),
+            Field::new("actions", DataType::UInt32, false),
+        ];
+        if json {
+            fields.push(Field::new("block", DataType::Utf8, false));
+        }
+        Arc::new(Schema::new(fields))
+    }
+
+    fn push(&mut self, block: &Value, json: bool) {
+        let abci_block = &block["abci_block"];
+        self.round.push(abci_block["round"].as_u64());
+        self.time
+            .push(abci_block["time"].as_str().and_then(parse_node_time));
+        self.proposer
+            .push(abci_block["proposer"].as_str().map(str::to_string));
+        self.bundles.push(bundles(block).count() as u32);
+        self.actions.push(
+            bundles(block)
+                .map(|(_, bundle)| bundle["signed_actions"].as_array().map_or(0, Vec::len))
+                .sum::<usize>() as u32,
+        );
+        if json {
+            self.block.push(block.to_string());
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.round.len()
+    }
+
+    fn take_batch(&mut self, schema: &SchemaRef, json: bool) -> eyre::Result<RecordBatch> {
+        let rows = std::mem::take(self);
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(rows.round)),
+            time_array(rows.time),
+            Arc::new(StringArray::from(rows.proposer)),
+            Arc::new(UInt32Array::from(rows.bundles)),
+            Arc::new(UInt32Array::from(rows.actions)),
+        ];
+        if json {
+            columns.push(Arc::new(StringArray::from(rows.block)));
+        }
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    }
+}
+
+#[derive(Default)]
+struct ActionRows {
+    round: Vec<Option<u64>>,
+    time: Vec<Option<i64>>,
+    bundle_hash: Vec<Option<String>>,
+    broadcaster: Vec<Option<String>>,
+    index: Vec<u32>,
+    action_type: Vec<Option<String>>,
+    user: Vec<Option<String>>,
+    nonce: Vec<Option<u64>>,
+    vault_address: Vec<Option<String>>,
+    action: Vec<String>,
+    response: Vec<Option<String>>,
+}
+
+impl Rows for ActionRows {
+    fn schema(&self, json: bool) -> SchemaRef {
+        let mut fields = vec![
+            Field::new("round", DataType::UInt64, true),
+            time_field(),
+            Field::new("bundle_hash", DataType::Utf8, true),
+            Field::new("broadcaster", DataType::Utf8, true),
+            Field::new("index", DataType::UInt32, false),
+            Field::new("action_type", DataType::Utf8, true),
+            Field::new("user", DataType::Utf8, true),
+            Field::new("nonce", DataType::UInt64, true),
+            Field::new("vault_address", DataType::Utf8, true),
+        ];
+        if json {
+            fields.push(Field::new("action", DataType::Utf8, false));
+            fields.push(Field::new("response", DataType::Utf8, true));
+        }
+        Arc::new(Schema::new(fields))
+    }
+
+    /// Users are only known from the responses, which are written with `--replica-cmds-style actions-and-responses`
+    fn push(&mut self, block: &Value, json: bool) {
+        let abci_block = &block["abci_block"];
+        let round = abci_block["round"].as_u64();
+        let time = abci_block["time"].as_str().and_then(parse_node_time);
+        let responses = block["resps"]["Full"].as_array();
+
+        for (bundle_index, (hash, bundle)) in bundles(block).enumerate() {
+            let bundle_responses = responses
+                .and_then(|responses| responses.get(bundle_index))
+                .map(|responses| &responses[1]);
+            let actions = bundle["signed_actions"].as_array().into_iter().flatten();
+            for (index, signed_action) in actions.enumerate() {
+                let response = bundle_responses.and_then(|responses| responses.get(index));
+                let action = &signed_action["action"];
+
+                self.round.push(round);
+                self.time.push(time);
+                self.bundle_hash.push(hash.map(str::to_string));
+                self.broadcaster
+                    .push(bundle["broadcaster"].as_str().map(str::to_string));
+                self.index.push(index as u32);
+                self.action_type
+                    .push(action["type"].as_str().map(str::to_string));
+                self.user.push(
+                    response
+                        .and_then(|response| response["user"].as_str())
+                        .map(str::to_string),
+                );
+                self.nonce.push(signed_action["nonce"].as_u64());
+                self.vault_address
+                    .push(signed_action["vaultAddress"].as_str().map(str::to_string));
+                if json {
+                    self.action.push(action.to_string());
+                    self.response
+                        .push(response.map(|response| response["res"].to_string()));
+                }
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.round.len()
+    }
+
+    fn take_batch(&mut self, schema: &SchemaRef, json: bool) -> eyre::Result<RecordBatch> {
+        let rows = std::mem::take(self);
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(UInt64Array::from(rows.round)),
+            time_array(rows.time),
+            Arc::new(StringArray::from(rows.bundle_hash)),
+            Arc::new(StringArray::from(rows.broadcaster)),
+            Arc::new(UInt32Array::from(rows.index)),
+            Arc::new(StringArray::from(rows.action_type)),
+            Arc::new(StringArray::from(rows.user)),
+            Arc::new(UInt64Array::from(rows.nonce)),
+            Arc::new(StringArray::from(rows.vault_address)),
+        ];
+        if json {
+            columns.push(Arc::new(StringArray::from(rows.action)));
+            columns.push(Arc::new(StringArray::from(rows.response)));
+        }
+        Ok(RecordBatch::try_new(schema.clone(), columns)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_rows() {
+        let block = serde_json::json!({
+            "abci_block": {
+                "time": "2025-06-15T15:06:40.123456789",
+                "round": 42,
+                "proposer": "0xproposer",
+                "signed_action_bundles": [
+                    ["0xhash", {
+                        "signed_actions": [
+                            {"action": {"type": "order"}, "nonce": 1},
+                            {"action": {"type": "cancel"}, "nonce": 2, "vaultAddress": "0xvault"},
+                        ],
+                        "broadcaster": "0xbroadcaster",
+                    }],
+                ],
+            },
+            "resps": {"Full": [["0xhash", [{"user": "0xa", "res": {}}, {"user": "0xb", "res": {}}]]]},
+        });
+
+        let mut rows = ActionRows::default();
+        rows.push(&block, true);
+        let schema = rows.schema(true);
+        let batch = rows.take_batch(&schema, true).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+        assert_eq!(rows.len(), 0);
+
+        let mut rows = ActionRows::default();
+        rows.push(&block, false);
+        assert_eq!(rows.time, [Some(1750000000123); 2]);
+        assert_eq!(rows.user, [Some("0xa".into()), Some("0xb".into())]);
+        assert_eq!(rows.vault_address, [None, Some("0xvault".into())]);
+    }
+
+    #[test]
+    fn test_partition() {
+        assert_eq!(partition("20250615").as_deref(), Some("date=2025-06-15"));
+        assert_eq!(partition("2025061"), None);
+    }
+}
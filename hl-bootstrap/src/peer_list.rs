@@ -0,0 +1,248 @@
+use std::{
+    collections::HashSet,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use clap::Args;
+use ed25519_dalek::{Signature, VerifyingKey};
+use eyre::{Context, bail, eyre};
+use tokio::process::Command;
+use tracing::{debug, info};
+
+use crate::{hl_gossip_config::HyperliquidSeedPeer, monitor::NETWORK_CLIENT, reaper};
+
+#[derive(Clone, Debug, Args)]
+pub struct PeerListArgs {
+    /// URL or file of extra seed peers, one IPv4 address per line optionally followed by the operator name, `#`
+    /// starts a comment
+    #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_LIST")]
+    pub seed_peers_list: Option<String>,
+
+    /// URL or file of the peer list signature. Defaults to the list location with `.sig` appended, `.asc` for PGP
+    #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_LIST_SIGNATURE")]
+    pub seed_peers_list_signature: Option<String>,
+
+    /// Ed25519 public key the peer list must be signed with, base64 or hex. The signature is the 64 byte signature,
+    /// raw, base64 or hex
+    #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_LIST_ED25519_KEY", value_parser = parse_ed25519_key)]
+    pub seed_peers_list_ed25519_key: Option<VerifyingKey>,
+
+    /// OpenPGP keyring (`gpg --export <key id> > keyring.gpg`) the peer list must be signed with, as a detached
+    /// signature. Verified with gpgv, which must be installed
+    #[arg(long, env = "HL_BOOTSTRAP_SEED_PEERS_LIST_PGP_KEYRING")]
+    pub seed_peers_list_pgp_keyring: Option<PathBuf>,
+}
+
+impl PeerListArgs {
+    fn signature_location(&self, list: &str) -> String {
+        self.seed_peers_list_signature.clone().unwrap_or_else(|| {
+            match self.seed_peers_list_pgp_keyring {
+                Some(_) => format!("{list}.asc"),
+                None => format!("{list}.sig"),
+            }
+        })
+    }
+}
+
+fn parse_ed25519_key(value: &str) -> eyre::Result<VerifyingKey> {
+    let bytes: [u8; 32] = decode(value.trim())?
+        .try_into()
+        .map_err(|_| eyre!("expected a 32 byte key"))?;
+    VerifyingKey::from_bytes(&bytes).wrap_err("invalid ed25519 public key")
+}
+
+/// Hex or base64
+fn decode(value: &str) -> eyre::Result<Vec<u8>> {
+    if value.len() % 2 == 0 && value.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Ok((0..value.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&value[index..index + 2], 16).unwrap())
+            .collect());
+    }
+    BASE64_STANDARD
+        .decode(value)
+        .wrap_err("expected hex or base64")
+}
+
+/// Seed peers of the operator provided list, unset when there's none. With a key configured the list is only
+/// trusted when its signature checks out
+pub async fn fetch_peer_list(
+    args: &PeerListArgs,
+    ignored_peers: &HashSet<Ipv4Addr>,
+) -> eyre::Result<Option<Vec<HyperliquidSeedPeer>>> {
+    let Some(list) = &args.seed_peers_list else {
+        return Ok(None);
+    };
+    let contents = read(list).await?;
+
+    let signature_location = args.signature_location(list);
+    let failed = || format!("peer list {list:?} failed signature verification");
+    if let Some(key) = &args.seed_peers_list_ed25519_key {
+        let signature = read(&signature_location).await?;
+        verify_ed25519(key, &contents, &signature).wrap_err_with(failed)?;
+        info!(
+            list,
+            signature = signature_location,
+            "verified peer list signature"
+        );
+    } else if let Some(keyring) = &args.seed_peers_list_pgp_keyring {
+        let signature = read(&signature_location).await?;
+        verify_pgp(keyring, &contents, &signature)
+            .await
+            .wrap_err_with(failed)?;
+        info!(
+            list,
+            signature = signature_location,
+            "verified peer list signature"
+        );
+    }
+
+    let text = std::str::from_utf8(&contents).wrap_err("peer list is not UTF-8")?;
+    let peers = parse_peer_list(text)
+        .wrap_err_with(|| format!("invalid peer list {list:?}"))?
+        .into_iter()
+        .filter(|peer| {
+            let ignored = ignored_peers.contains(&peer.ip);
+            if ignored {
+                debug!(ip = ?peer.ip, "skipping ignored seed node");
+            }
+            !ignored
+        })
+        .collect();
+    Ok(Some(peers))
+}
+
+async fn read(location: &str) -> eyre::Result<Vec<u8>> {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let bytes = NETWORK_CLIENT
+            .get(location)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .wrap_err_with(|| format!("failed to fetch {location}"))?
+            .bytes()
+            .await
+            .wrap_err_with(|| format!("failed to fetch {location}"))?;
+        return Ok(bytes.to_vec());
+    }
+    tokio::fs::read(location)
+        .await
+        .wrap_err_with(|| format!("failed to read {location:?}"))
+}
+
+fn verify_ed25519(key: &VerifyingKey, contents: &[u8], signature: &[u8]) -> eyre::Result<()> {
+    let signature = match <[u8; 64]>::try_from(signature) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            let text = std::str::from_utf8(signature).wrap_err("signature is not 64 bytes")?;
+            decode(text.trim())?
+                .try_into()
+                .map_err(|_| eyre!("expected a 64 byte signature"))?
+        }
+    };
+    key.verify_strict(contents, &Signature::from_bytes(&signature))
+        .wrap_err("invalid ed25519 signature")
+}
+
+async fn verify_pgp(keyring: &Path, contents: &[u8], signature: &[u8]) -> eyre::Result<()> {
+    let directory = tempfile::tempdir()?;
+    let list_path = directory.path().join("peers");
+    let signature_path = directory.path().join("peers.sig");
+    tokio::fs::write(&list_path, contents).await?;
+    tokio::fs::write(&signature_path, signature).await?;
+    // gpgv looks up relative keyrings in ~/.gnupg
+    let keyring = std::path::absolute(keyring)?;
+
+    let output = reaper::output_async(
+        Command::new("gpgv")
+            .arg("--keyring")
+            .arg(&keyring)
+            .arg(&signature_path)
+            .arg(&list_path),
+    )
+    .await
+    .wrap_err("failed to run gpgv")?;
+    if !output.status.success() {
+        bail!(
+            "invalid PGP signature: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn parse_peer_list(text: &str) -> eyre::Result<Vec<HyperliquidSeedPeer>> {
+    let mut peers = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (ip, operator_name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let ip = ip
+            .parse()
+            .wrap_err_with(|| format!("line {}: invalid IPv4 address {ip:?}", index + 1))?;
+        let operator_name = match operator_name.trim() {
+            "" => "peer list",
+            operator_name => operator_name,
+        };
+        peers.push(HyperliquidSeedPeer {
+            operator_name: operator_name.to_string(),
+            ip,
+        });
+    }
+    if peers.is_empty() {
+        bail!("peer list has no peers");
+    }
+
+    Ok(peers)
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+    use crate::storage::hex;
+
+    #[test]
+    fn test_parse_peer_list() {
+        let peers = parse_peer_list(
+            "# operator seeds\n\
+             203.0.113.7 ASXN\n\
+             \n\
+             198.51.100.1 # fallback\n",
+        )
+        .unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0].ip, Ipv4Addr::new(203, 0, 113, 7));
+        assert_eq!(peers[0].operator_name, "ASXN");
+        assert_eq!(peers[1].operator_name, "peer list");
+
+        assert!(parse_peer_list("203.0.113.300").is_err());
+        assert!(parse_peer_list("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn test_verify_ed25519() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let key = signing_key.verifying_key();
+        let contents = b"203.0.113.7\n";
+        let signature = signing_key.sign(contents).to_bytes();
+
+        assert!(verify_ed25519(&key, contents, &signature).is_ok());
+        assert!(
+            verify_ed25519(&key, contents, BASE64_STANDARD.encode(signature).as_bytes()).is_ok()
+        );
+        assert!(verify_ed25519(&key, b"6.6.6.6\n", &signature).is_err());
+
+        assert_eq!(parse_ed25519_key(&hex(key.as_bytes())).unwrap(), key);
+        assert_eq!(
+            parse_ed25519_key(&BASE64_STANDARD.encode(key.as_bytes())).unwrap(),
+            key
+        );
+    }
+}
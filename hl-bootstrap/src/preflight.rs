@@ -0,0 +1,777 @@
+use std::{
+    ffi::OsString,
+    io::{ErrorKind, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    path::Path,
+    thread::available_parallelism,
+    time::{Duration, Instant},
+};
+
+use bytesize::ByteSize;
+use clap::{Args, ValueEnum};
+use duration_string::DurationString;
+use eyre::{Context, bail, eyre};
+use nix::sys::statvfs::statvfs;
+use reqwest::Url;
+use tempfile::NamedTempFile;
+use tracing::{info, warn};
+
+use crate::{
+    alerts::{AlertEvent, send_alert},
+    monitor::{INFO_PORT, gossip::GOSSIP_PORTS},
+    ntp::{is_clock_synchronized, query_clock_offset},
+    procfs,
+    reachability::check_reachability,
+    sysctl::{read_sysctl, set_transparent_hugepages, transparent_hugepages, write_sysctl},
+};
+
+/// Checks run right before starting the node
+#[derive(Clone, Debug, Args)]
+pub struct PreflightArgs {
+    /// Minimum free space on the data directory filesystem to start the node
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_MIN_FREE",
+        default_value = "20GiB"
+    )]
+    pub preflight_disk_min_free: ByteSize,
+
+    /// Minimum free inodes on the data directory filesystem to start the node. hl-node writes lots of small files
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_MIN_INODES",
+        default_value_t = 1_000_000
+    )]
+    pub preflight_disk_min_inodes: u64,
+
+    /// What to do when the disk check fails
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_disk_action: PreflightAction,
+
+    /// How much the disk benchmark writes sequentially into the data directory
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_BENCH_SIZE",
+        default_value = "256MiB"
+    )]
+    pub preflight_disk_bench_size: ByteSize,
+
+    /// Minimum sequential write throughput per second, flushing to disk included. hl-node writes several GiB of
+    /// blocks & outputs per hour on mainnet, with bursts while catching up
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_MIN_WRITE_SPEED",
+        default_value = "200MiB"
+    )]
+    pub preflight_disk_min_write_speed: ByteSize,
+
+    /// Minimum small writes each followed by fdatasync per second. hl-node syncs state constantly, network block
+    /// storage with few IOPS falls behind mainnet even when its throughput is fine
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_MIN_FSYNCS",
+        default_value_t = 500
+    )]
+    pub preflight_disk_min_fsyncs: u32,
+
+    /// What to do when the disk benchmark fails. It writes --preflight-disk-bench-size on every start, so it's
+    /// skipped by default; `hl-bootstrap bench-disk` runs it once
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_DISK_BENCH_ACTION",
+        default_value = "ignore"
+    )]
+    pub preflight_disk_bench_action: PreflightAction,
+
+    /// What to do when the data directory is on a network or FUSE filesystem, or mounted with synchronous writes
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_FILESYSTEM_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_filesystem_action: PreflightAction,
+
+    /// NTP server to measure the clock skew against
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_NTP_SERVER",
+        default_value = "pool.ntp.org:123"
+    )]
+    pub preflight_ntp_server: String,
+
+    /// Maximum clock skew against the NTP server. Skewed clocks cause subtle consensus & gossip problems
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_CLOCK_MAX_SKEW",
+        default_value = "500ms"
+    )]
+    pub preflight_clock_max_skew: DurationString,
+
+    /// What to do when the clock check fails
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_CLOCK_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_clock_action: PreflightAction,
+
+    /// Minimum amount of CPUs available to the node, honoring affinity & cgroup quotas
+    #[arg(long, env = "HL_BOOTSTRAP_PREFLIGHT_MIN_CPUS", default_value_t = 16)]
+    pub preflight_min_cpus: usize,
+
+    /// Minimum memory available to the node, honoring the cgroup memory limit
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_MIN_MEMORY",
+        default_value = "64GiB"
+    )]
+    pub preflight_min_memory: ByteSize,
+
+    /// CPU feature flags the host has to support, as listed in /proc/cpuinfo
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_CPU_FEATURES",
+        value_delimiter = ',',
+        default_value = DEFAULT_CPU_FEATURES
+    )]
+    pub preflight_cpu_features: Vec<String>,
+
+    /// What to do when the CPU & memory check fails
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_RESOURCES_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_resources_action: PreflightAction,
+
+    /// What to do when CPUs aren't running with the performance governor. Latency sensitive block application benefits
+    /// from it measurably, the validator role warns by default
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_CPU_GOVERNOR_ACTION",
+        default_value = "ignore"
+    )]
+    pub preflight_cpu_governor_action: PreflightAction,
+
+    /// Maximum vm.swappiness. A swapping node falls behind consensus long before the OOM killer steps in
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_MAX_SWAPPINESS",
+        default_value_t = 10
+    )]
+    pub preflight_max_swappiness: u32,
+
+    /// Lower vm.swappiness to the maximum when above it. Active swap is left alone, turning it off could run the host
+    /// out of memory
+    #[arg(long, env = "HL_BOOTSTRAP_PREFLIGHT_SWAP_REMEDIATE")]
+    pub preflight_swap_remediate: bool,
+
+    /// What to do when swap is active or vm.swappiness is above the maximum
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_SWAP_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_swap_action: PreflightAction,
+
+    /// What to do when a port hl-node listens on is taken by another process
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_PORTS_ACTION",
+        default_value = "fail"
+    )]
+    pub preflight_ports_action: PreflightAction,
+
+    /// Switch transparent hugepages from always to madvise
+    #[arg(long, env = "HL_BOOTSTRAP_PREFLIGHT_THP_REMEDIATE")]
+    pub preflight_thp_remediate: bool,
+
+    /// What to do when transparent hugepages are set to always, which causes latency spikes from compaction &
+    /// memory bloat
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_THP_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_thp_action: PreflightAction,
+
+    /// Reachability endpoint of another node's hl-bootstrap (e.g. `http://10.0.0.2:2112/reachability`), asked to
+    /// connect back to the gossip ports to verify they're reachable from the outside
+    #[arg(long, env = "HL_BOOTSTRAP_PREFLIGHT_REACHABILITY_URL")]
+    pub preflight_reachability_url: Option<Url>,
+
+    /// What to do when the gossip ports are not reachable
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PREFLIGHT_REACHABILITY_ACTION",
+        default_value = "warn"
+    )]
+    pub preflight_reachability_action: PreflightAction,
+}
+
+const DEFAULT_CPU_FEATURES: &str = if cfg!(target_arch = "x86_64") {
+    "avx2"
+} else {
+    ""
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum PreflightAction {
+    /// Refuse to start the node
+    Fail,
+    /// Log a warning and send an alert, but start anyway
+    Warn,
+    /// Skip the check
+    Ignore,
+}
+
+pub async fn run_preflight(
+    args: &PreflightArgs,
+    data_directory: &Path,
+    child_args: &[OsString],
+) -> eyre::Result<()> {
+    if args.preflight_disk_action != PreflightAction::Ignore {
+        let result = check_disk(
+            data_directory,
+            args.preflight_disk_min_free,
+            args.preflight_disk_min_inodes,
+        );
+        enforce("disk", args.preflight_disk_action, result).await?;
+    }
+
+    if args.preflight_filesystem_action != PreflightAction::Ignore {
+        let result = check_filesystem(data_directory);
+        enforce("filesystem", args.preflight_filesystem_action, result).await?;
+    }
+
+    if args.preflight_disk_bench_action != PreflightAction::Ignore {
+        let path = data_directory.to_path_buf();
+        let bench_args = args.clone();
+        let result =
+            tokio::task::spawn_blocking(move || check_disk_speed(&path, &bench_args)).await?;
+        enforce("disk-bench", args.preflight_disk_bench_action, result).await?;
+    }
+
+    if args.preflight_clock_action != PreflightAction::Ignore {
+        let result = check_clock(
+            &args.preflight_ntp_server,
+            args.preflight_clock_max_skew.into(),
+        )
+        .await;
+        enforce("clock", args.preflight_clock_action, result).await?;
+    }
+
+    if args.preflight_resources_action != PreflightAction::Ignore {
+        let result = check_resources(
+            args.preflight_min_cpus,
+            args.preflight_min_memory,
+            &args.preflight_cpu_features,
+        );
+        enforce("resources", args.preflight_resources_action, result).await?;
+    }
+
+    if args.preflight_cpu_governor_action != PreflightAction::Ignore {
+        let result = check_cpu_governor();
+        enforce("cpu-governor", args.preflight_cpu_governor_action, result).await?;
+    }
+
+    if args.preflight_swap_action != PreflightAction::Ignore {
+        let result = check_swap(args.preflight_max_swappiness, args.preflight_swap_remediate);
+        enforce("swap", args.preflight_swap_action, result).await?;
+    }
+
+    if args.preflight_thp_action != PreflightAction::Ignore {
+        let result = check_transparent_hugepages(args.preflight_thp_remediate);
+        enforce("thp", args.preflight_thp_action, result).await?;
+    }
+
+    if args.preflight_ports_action != PreflightAction::Ignore {
+        let result = check_ports(&node_ports(child_args));
+        enforce("ports", args.preflight_ports_action, result).await?;
+    }
+
+    if let Some(url) = &args.preflight_reachability_url
+        && args.preflight_reachability_action != PreflightAction::Ignore
+    {
+        let result = check_reachability(url).await;
+        enforce("reachability", args.preflight_reachability_action, result).await?;
+    }
+
+    Ok(())
+}
+
+/// Fails or warns about a failed check according to the action
+async fn enforce(
+    name: &str,
+    action: PreflightAction,
+    result: eyre::Result<String>,
+) -> eyre::Result<()> {
+    match (result, action) {
+        (Ok(detail), _) => info!(check = name, detail, "preflight check passed"),
+        (Err(_), PreflightAction::Ignore) => {}
+        (Err(err), PreflightAction::Warn) => {
+            warn!(check = name, "preflight check failed: {err:#}");
+            send_alert(
+                AlertEvent::PreflightFailed,
+                &format!("preflight check {name} failed: {err:#}"),
+            )
+            .await;
+        }
+        (Err(err), PreflightAction::Fail) => {
+            return Err(err.wrap_err(format!("preflight check {name} failed")));
+        }
+    }
+    Ok(())
+}
+
+/// Clock skew against the NTP server. Without NTP connectivity, falls back to the synchronization state reported by
+/// timedatectl
+pub async fn check_clock(ntp_server: &str, max_skew: Duration) -> eyre::Result<String> {
+    let offset = match query_clock_offset(ntp_server, Duration::from_secs(2)).await {
+        Ok(offset) => offset,
+        Err(err) => {
+            return match is_clock_synchronized() {
+                Some(true) => Ok(format!(
+                    "NTP query failed ({err:#}), but the system clock is synchronized"
+                )),
+                Some(false) => bail!(
+                    "NTP query failed ({err:#}) and the system clock is not synchronized. Enable time \
+                     synchronization, e.g. chrony or systemd-timesyncd"
+                ),
+                None => Err(err.wrap_err("failed to measure clock skew")),
+            };
+        }
+    };
+
+    let skew = Duration::from_secs_f64(offset.abs());
+    if skew > max_skew {
+        bail!(
+            "clock is {skew:?} {} {ntp_server}, above the maximum of {max_skew:?}. Enable time synchronization, \
+             e.g. chrony or systemd-timesyncd",
+            if offset > 0.0 { "behind" } else { "ahead of" }
+        );
+    }
+    Ok(format!("clock is {skew:?} off {ntp_server}"))
+}
+
+/// CPU count, CPU features & memory against the minimums, reporting all shortfalls
+pub fn check_resources(
+    min_cpus: usize,
+    min_memory: ByteSize,
+    cpu_features: &[String],
+) -> eyre::Result<String> {
+    let cpus = available_parallelism().map_or(0, usize::from);
+    let memory = procfs::memory_total().map(ByteSize::b);
+    let flags = procfs::cpu_flags();
+    let missing_features: Vec<_> = cpu_features
+        .iter()
+        .filter(|feature| !feature.is_empty() && !flags.contains(feature))
+        .map(String::as_str)
+        .collect();
+
+    let mut problems = Vec::new();
+    if cpus < min_cpus {
+        problems.push(format!("{cpus} CPUs available, {min_cpus} required"));
+    }
+    match memory {
+        Some(memory) if memory < min_memory => {
+            problems.push(format!("{memory} memory available, {min_memory} required"))
+        }
+        Some(_) => {}
+        None => problems.push("failed to determine available memory".to_string()),
+    }
+    if !missing_features.is_empty() {
+        problems.push(format!(
+            "CPU lacks required features: {}",
+            missing_features.join(", ")
+        ));
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "machine is undersized: {}. Use a bigger machine, or lower the minimums with --preflight-min-cpus & \
+             --preflight-min-memory",
+            problems.join("; ")
+        );
+    }
+    Ok(format!(
+        "{cpus} CPUs and {} memory available",
+        memory.unwrap_or_default()
+    ))
+}
+
+/// CPU frequency scaling governors, which should be `performance`
+pub fn check_cpu_governor() -> eyre::Result<String> {
+    let governors = procfs::cpu_governors();
+    if governors.is_empty() {
+        return Ok("CPU frequency scaling is not exposed".to_string());
+    }
+
+    let scaling: Vec<_> = governors
+        .iter()
+        .filter(|governor| *governor != "performance")
+        .map(String::as_str)
+        .collect();
+    if !scaling.is_empty() {
+        bail!(
+            "CPUs run with the {} governor, which scales frequency down and slows block application. Switch to \
+             performance, e.g. `cpupower frequency-set -g performance`",
+            scaling.join(", ")
+        );
+    }
+    Ok("CPUs run with the performance governor".to_string())
+}
+
+/// Transparent hugepages not being set to always, optionally switching them to madvise first
+pub fn check_transparent_hugepages(remediate: bool) -> eyre::Result<String> {
+    let Some(mut mode) = transparent_hugepages() else {
+        return Ok("transparent hugepages are not supported".to_string());
+    };
+    if remediate && mode == "always" {
+        match set_transparent_hugepages("madvise") {
+            Ok(()) => {
+                info!(
+                    before = mode,
+                    after = "madvise",
+                    "changed transparent hugepages"
+                );
+                mode = "madvise".to_string();
+            }
+            Err(err) => warn!(?err, "failed to change transparent hugepages"),
+        }
+    }
+
+    if mode == "always" {
+        bail!(
+            "transparent hugepages are set to always. Set them to madvise with `echo madvise > \
+             /sys/kernel/mm/transparent_hugepage/enabled`, or run with --preflight-thp-remediate"
+        );
+    }
+    Ok(format!("transparent hugepages are set to {mode}"))
+}
+
+/// Ports hl-node is going to listen on with the hl-visor args
+pub fn node_ports(child_args: &[OsString]) -> Vec<u16> {
+    let mut ports: Vec<_> = GOSSIP_PORTS.collect();
+    if child_args
+        .iter()
+        .any(|arg| arg == "--serve-info" || arg == "--serve-eth-rpc")
+    {
+        ports.push(INFO_PORT);
+    }
+    ports
+}
+
+/// Ports being free to listen on, naming the processes holding the taken ones
+pub fn check_ports(ports: &[u16]) -> eyre::Result<String> {
+    let taken: Vec<_> = ports
+        .iter()
+        .filter(|&&port| {
+            matches!(
+                TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))),
+                Err(err) if err.kind() == ErrorKind::AddrInUse
+            )
+        })
+        .map(|&port| match procfs::listening_process(port) {
+            Some((pid, comm)) => format!("{port} by {comm} (pid {pid})"),
+            None => format!("{port} by an unknown process"),
+        })
+        .collect();
+
+    if !taken.is_empty() {
+        bail!(
+            "ports are already in use: {}. Stop the other process, which may be a previous hl-node still running",
+            taken.join(", ")
+        );
+    }
+    Ok(format!("ports {ports:?} are free"))
+}
+
+/// Filesystem types hl-node's small, frequent writes are painfully slow on
+const SLOW_FILESYSTEMS: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "fuse"];
+
+/// Mount options forcing synchronous writes
+const SYNC_MOUNT_OPTIONS: &[&str] = &["sync", "dirsync", "data=journal"];
+
+/// Filesystem type & mount options of the path
+pub fn check_filesystem(path: &Path) -> eyre::Result<String> {
+    let canonical = path
+        .canonicalize()
+        .wrap_err_with(|| format!("failed to resolve {}", path.display()))?;
+    let Some(mount) = procfs::mount_of(&canonical) else {
+        bail!("failed to find the mount of {}", canonical.display());
+    };
+
+    let mut problems = Vec::new();
+    // FUSE mounts are reported as fuse.<name>, e.g. fuse.sshfs
+    let base_type = mount.fs_type.split('.').next().unwrap_or_default();
+    if SLOW_FILESYSTEMS.contains(&base_type) {
+        problems.push(format!(
+            "it is on {} ({}), a network or FUSE filesystem",
+            mount.fs_type, mount.source
+        ));
+    }
+    let sync_options: Vec<_> = mount
+        .options
+        .iter()
+        .filter(|option| SYNC_MOUNT_OPTIONS.contains(&option.as_str()))
+        .map(String::as_str)
+        .collect();
+    if !sync_options.is_empty() {
+        problems.push(format!("it is mounted with {}", sync_options.join(",")));
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "data directory {} is unsuitable: {}. Use a local SSD with ext4 or xfs and default mount options",
+            path.display(),
+            problems.join("; ")
+        );
+    }
+    Ok(format!(
+        "{} on {} mounted at {}",
+        mount.fs_type,
+        mount.source,
+        mount.mount_point.display()
+    ))
+}
+
+/// Active swap & vm.swappiness, optionally lowering the swappiness first
+pub fn check_swap(max_swappiness: u32, remediate: bool) -> eyre::Result<String> {
+    let mut swappiness = read_sysctl("vm.swappiness")?
+        .parse::<u32>()
+        .wrap_err("failed to parse vm.swappiness")?;
+    if remediate && swappiness > max_swappiness {
+        match write_sysctl("vm.swappiness", &max_swappiness.to_string()) {
+            Ok(()) => {
+                info!(
+                    before = swappiness,
+                    after = max_swappiness,
+                    "lowered vm.swappiness"
+                );
+                swappiness = max_swappiness;
+            }
+            Err(err) => warn!(?err, "failed to lower vm.swappiness"),
+        }
+    }
+
+    let (total, used) = procfs::swap_usage().unwrap_or_default();
+    let mut problems = Vec::new();
+    if total > 0 {
+        problems.push(format!(
+            "swap is active ({} of {} used)",
+            ByteSize::b(used),
+            ByteSize::b(total)
+        ));
+    }
+    if swappiness > max_swappiness {
+        problems.push(format!(
+            "vm.swappiness is {swappiness}, above the maximum of {max_swappiness}"
+        ));
+    }
+
+    if !problems.is_empty() {
+        bail!(
+            "{}. Disable swap with `swapoff -a` and lower vm.swappiness, or run with --preflight-swap-remediate",
+            problems.join("; ")
+        );
+    }
+    Ok(format!("swap is off, vm.swappiness is {swappiness}"))
+}
+
+/// Free space & inodes on the filesystem of the path
+pub fn check_disk(path: &Path, min_free: ByteSize, min_inodes: u64) -> eyre::Result<String> {
+    let stat = statvfs(path).map_err(|err| eyre!("failed to stat {path:?}: {err}"))?;
+    let available = ByteSize::b(stat.blocks_available() * stat.fragment_size());
+    // Filesystems without inode limits (e.g. btrfs) report 0 total inodes
+    let inodes = (stat.files() > 0).then(|| stat.files_available());
+
+    if available < min_free {
+        bail!(
+            "{available} available in {}, below the minimum of {min_free}. Free up space or enable pruning with \
+             --prune-data-interval",
+            path.display()
+        );
+    }
+    if let Some(inodes) = inodes
+        && inodes < min_inodes
+    {
+        bail!(
+            "{inodes} inodes available in {}, below the minimum of {min_inodes}. Remove old files or enable \
+             pruning with --prune-data-interval",
+            path.display()
+        );
+    }
+
+    Ok(match inodes {
+        Some(inodes) => format!("{available} and {inodes} inodes available"),
+        None => format!("{available} available"),
+    })
+}
+
+/// Size of the writes in the fsync benchmark, a page
+const FSYNC_WRITE_SIZE: usize = 4096;
+
+/// The fsync benchmark stops after this many writes or the duration, whichever comes first, so slow disks don't hold
+/// up the start for long
+const FSYNC_WRITES: u32 = 2000;
+const FSYNC_MAX_DURATION: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug)]
+struct DiskSpeed {
+    /// Bytes per second
+    write_speed: ByteSize,
+    fsyncs_per_second: f64,
+}
+
+/// Sequential & fsync-heavy write performance of the filesystem of the path, against the minimums
+pub fn check_disk_speed(path: &Path, args: &PreflightArgs) -> eyre::Result<String> {
+    let speed = measure_disk_speed(path, args.preflight_disk_bench_size)?;
+    let detail = format!(
+        "{}/s sequential writes and {:.0} fsyncs per second",
+        speed.write_speed, speed.fsyncs_per_second
+    );
+
+    let mut problems = Vec::new();
+    if speed.write_speed < args.preflight_disk_min_write_speed {
+        problems.push(format!(
+            "sequential writes at {}/s, {}/s required",
+            speed.write_speed, args.preflight_disk_min_write_speed
+        ));
+    }
+    if speed.fsyncs_per_second < args.preflight_disk_min_fsyncs as f64 {
+        problems.push(format!(
+            "{:.0} fsyncs per second, {} required",
+            speed.fsyncs_per_second, args.preflight_disk_min_fsyncs
+        ));
+    }
+    if !problems.is_empty() {
+        bail!(
+            "{} is too slow to keep up with mainnet: {}. Use a local NVMe SSD, or provision more IOPS & throughput \
+             for network block storage",
+            path.display(),
+            problems.join("; ")
+        );
+    }
+    Ok(detail)
+}
+
+/// Writes into temporary files in the directory, which are removed afterwards
+fn measure_disk_speed(directory: &Path, size: ByteSize) -> eyre::Result<DiskSpeed> {
+    let failed = || format!("failed to benchmark writes in {}", directory.display());
+
+    // Not zeros, compressing & deduplicating storage would skip writing those
+    let mut chunk = vec![0; 1024 * 1024];
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut fill = |chunk: &mut [u8]| {
+        for bytes in chunk.chunks_mut(8) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            bytes.copy_from_slice(&state.to_le_bytes()[..bytes.len()]);
+        }
+    };
+
+    let mut file = NamedTempFile::new_in(directory).wrap_err_with(failed)?;
+    let start = Instant::now();
+    let mut written = 0;
+    while written < size.as_u64() {
+        fill(&mut chunk);
+        file.write_all(&chunk).wrap_err_with(failed)?;
+        written += chunk.len() as u64;
+    }
+    file.as_file().sync_all().wrap_err_with(failed)?;
+    let write_speed = ByteSize::b((written as f64 / start.elapsed().as_secs_f64()) as u64);
+    drop(file);
+
+    let mut file = NamedTempFile::new_in(directory).wrap_err_with(failed)?;
+    let start = Instant::now();
+    let mut fsyncs = 0;
+    while fsyncs < FSYNC_WRITES && start.elapsed() < FSYNC_MAX_DURATION {
+        fill(&mut chunk[..FSYNC_WRITE_SIZE]);
+        file.write_all(&chunk[..FSYNC_WRITE_SIZE])
+            .wrap_err_with(failed)?;
+        file.as_file().sync_data().wrap_err_with(failed)?;
+        fsyncs += 1;
+    }
+    let fsyncs_per_second = fsyncs as f64 / start.elapsed().as_secs_f64();
+
+    Ok(DiskSpeed {
+        write_speed,
+        fsyncs_per_second,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        preflight: PreflightArgs,
+    }
+
+    #[test]
+    fn test_default_disk_action() {
+        let args = TestCli::parse_from(["test"]).preflight;
+        assert_eq!(args.preflight_disk_action, PreflightAction::Warn);
+    }
+
+    #[test]
+    fn test_check_disk() {
+        let directory = tempfile::tempdir().unwrap();
+        assert!(check_disk(directory.path(), ByteSize::b(0), 0).is_ok());
+
+        let err = check_disk(directory.path(), ByteSize::pib(1024), 0).unwrap_err();
+        assert!(err.to_string().contains("below the minimum"), "{err}");
+        assert!(check_disk(&directory.path().join("missing"), ByteSize::b(0), 0).is_err());
+    }
+
+    #[test]
+    fn test_node_ports() {
+        let gossip: Vec<_> = GOSSIP_PORTS.collect();
+        assert_eq!(node_ports(&["run-non-validator".into()]), gossip);
+
+        let ports = node_ports(&["run-non-validator".into(), "--serve-info".into()]);
+        assert_eq!(ports[..gossip.len()], gossip);
+        assert_eq!(ports.last(), Some(&INFO_PORT));
+    }
+
+    #[test]
+    fn test_check_ports() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let err = check_ports(&[port]).unwrap_err();
+        assert!(err.to_string().contains(&format!("{port} by ")), "{err}");
+
+        drop(listener);
+        assert!(check_ports(&[port]).is_ok());
+    }
+
+    #[test]
+    fn test_check_disk_speed() {
+        let directory = tempfile::tempdir().unwrap();
+        let mut args = TestCli::parse_from([
+            "test",
+            "--preflight-disk-bench-size=1MiB",
+            "--preflight-disk-min-write-speed=0B",
+            "--preflight-disk-min-fsyncs=0",
+        ])
+        .preflight;
+        assert!(check_disk_speed(directory.path(), &args).is_ok());
+        // Temporary files are removed afterwards
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 0);
+
+        args.preflight_disk_min_write_speed = ByteSize::pib(1024);
+        let err = check_disk_speed(directory.path(), &args).unwrap_err();
+        assert!(err.to_string().contains("too slow"), "{err}");
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use eyre::Context;
+
+/// Subset of /proc/<pid>/stat
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProcessStat {
+    pub pid: u32,
+    pub state: char,
+    pub ppid: u32,
+}
+
+/// Lists all processes visible in /proc. Processes exiting during the scan are skipped
+pub fn processes() -> Vec<ProcessStat> {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            let stat = fs::read_to_string(entry.path().join("stat")).ok()?;
+            let (state, ppid) = parse_stat(&stat)?;
+
+            Some(ProcessStat { pid, state, ppid })
+        })
+        .collect()
+}
+
+/// Pid and all of its descendants
+pub fn process_tree(pid: u32) -> Vec<u32> {
+    let processes = processes();
+    let mut tree = vec![pid];
+    let mut i = 0;
+    while let Some(parent) = tree.get(i).copied() {
+        tree.extend(
+            processes
+                .iter()
+                .filter(|process| process.ppid == parent)
+                .map(|process| process.pid),
+        );
+        i += 1;
+    }
+
+    tree
+}
+
+/// Command name of a process, truncated to 15 characters by the kernel
+pub fn comm(pid: u32) -> Option<String> {
+    fs::read_to_string(format!("/proc/{pid}/comm"))
+        .ok()
+        .map(|comm| comm.trim().to_string())
+}
+
+/// Resident set size of a process in bytes, from VmRSS in /proc/<pid>/status
+pub fn rss_bytes(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kib: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(kib * 1024)
+}
+
+/// Memory available to hl-bootstrap and its children in bytes: MemTotal, capped by the cgroup v2 memory.max
+pub fn memory_total() -> Option<u64> {
+    let total = meminfo_bytes("MemTotal")?;
+
+    let cgroup_max = fs::read_to_string("/proc/self/cgroup")
+        .ok()
+        .and_then(|cgroup| {
+            cgroup
+                .lines()
+                .find_map(|line| line.strip_prefix("0::").map(str::to_string))
+        })
+        .and_then(|cgroup| {
+            fs::read_to_string(format!(
+                "/sys/fs/cgroup/{}/memory.max",
+                cgroup.trim_start_matches('/')
+            ))
+            .ok()
+        })
+        // "max" when unlimited
+        .and_then(|max| max.trim().parse::<u64>().ok());
+
+    Some(cgroup_max.map_or(total, |max| max.min(total)))
+}
+
+/// Swap in use in bytes as (total, used), (0, 0) without active swap
+pub fn swap_usage() -> Option<(u64, u64)> {
+    let total = meminfo_bytes("SwapTotal")?;
+    let free = meminfo_bytes("SwapFree")?;
+    Some((total, total.saturating_sub(free)))
+}
+
+/// Field of /proc/meminfo in bytes
+fn meminfo_bytes(key: &str) -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    let kib = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
+
+/// TCP_LISTEN in include/net/tcp_states.h
+const TCP_LISTEN: u8 = 0x0a;
+
+/// Process listening on the TCP port as (pid, command name). Pid is only found for processes whose file descriptors
+/// are readable, which for other users' processes needs root
+pub fn listening_process(port: u16) -> Option<(u32, String)> {
+    let inodes: Vec<_> = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .into_iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|table| listening_inodes(&table, port))
+        .collect();
+    if inodes.is_empty() {
+        return None;
+    }
+    let sockets: Vec<_> = inodes
+        .iter()
+        .map(|inode| PathBuf::from(format!("socket:[{inode}]")))
+        .collect();
+
+    processes().into_iter().find_map(|process| {
+        let fds = fs::read_dir(format!("/proc/{}/fd", process.pid)).ok()?;
+        fds.flatten()
+            .filter_map(|fd| fs::read_link(fd.path()).ok())
+            .any(|target| sockets.contains(&target))
+            .then(|| (process.pid, comm(process.pid).unwrap_or_default()))
+    })
+}
+
+/// Socket inodes listening on the port in /proc/net/tcp or /proc/net/tcp6 contents
+fn listening_inodes(table: &str, port: u16) -> Vec<u64> {
+    table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            let (_, local_port) = fields.get(1)?.rsplit_once(':')?;
+            let state = u8::from_str_radix(fields.get(3)?, 16).ok()?;
+            (u16::from_str_radix(local_port, 16).ok()? == port && state == TCP_LISTEN)
+                .then_some(fields.get(9)?.parse().ok()?)
+        })
+        .collect()
+}
+
+/// Mount from /proc/self/mountinfo
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+    /// Per-mount & superblock options combined
+    pub options: Vec<String>,
+}
+
+/// Mount the path resides on. The path has to be canonical
+pub fn mount_of(path: &Path) -> Option<Mount> {
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+    parse_mountinfo(&mountinfo, path)
+}
+
+/// Innermost mount containing the path. Later entries win ties, as they're mounted on top
+fn parse_mountinfo(mountinfo: &str, path: &Path) -> Option<Mount> {
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let (mount, filesystem) = line.split_once(" - ")?;
+            let mut mount = mount.split(' ').skip(4);
+            let mount_point = PathBuf::from(unescape_mountinfo(mount.next()?));
+            let mount_options = mount.next()?;
+            let mut filesystem = filesystem.split(' ');
+            let fs_type = filesystem.next()?.to_string();
+            let source = unescape_mountinfo(filesystem.next()?);
+            let super_options = filesystem.next().unwrap_or_default();
+
+            Some(Mount {
+                mount_point,
+                fs_type,
+                source,
+                options: mount_options
+                    .split(',')
+                    .chain(super_options.split(','))
+                    .map(str::to_string)
+                    .collect(),
+            })
+        })
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .max_by_key(|mount| mount.mount_point.components().count())
+}
+
+/// Spaces, tabs, newlines & backslashes are octal escaped in mountinfo
+fn unescape_mountinfo(field: &str) -> String {
+    field
+        .replace("\\040", " ")
+        .replace("\\011", "\t")
+        .replace("\\012", "\n")
+        .replace("\\134", "\\")
+}
+
+/// CPU feature flags of the first CPU in /proc/cpuinfo (`flags` on x86, `Features` on arm)
+pub fn cpu_flags() -> Vec<String> {
+    let Ok(cpuinfo) = fs::read_to_string("/proc/cpuinfo") else {
+        return Vec::new();
+    };
+
+    cpuinfo
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            matches!(key.trim(), "flags" | "Features").then_some(value)
+        })
+        .map(|flags| flags.split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+/// Distinct cpufreq scaling governors across CPUs, empty when frequency scaling isn't exposed (e.g. in most VMs)
+pub fn cpu_governors() -> Vec<String> {
+    let Ok(entries) = fs::read_dir("/sys/devices/system/cpu") else {
+        return Vec::new();
+    };
+
+    let mut governors: Vec<_> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("cpu"))
+                .is_some_and(|index| index.parse::<usize>().is_ok())
+        })
+        .filter_map(|entry| fs::read_to_string(entry.path().join("cpufreq/scaling_governor")).ok())
+        .map(|governor| governor.trim().to_string())
+        .collect();
+    governors.sort_unstable();
+    governors.dedup();
+    governors
+}
+
+pub fn hostname() -> eyre::Result<String> {
+    let hostname =
+        fs::read_to_string("/proc/sys/kernel/hostname").wrap_err("failed to read hostname")?;
+    Ok(hostname.trim().to_string())
+}
+
+/// Extracts process state and parent pid from /proc/<pid>/stat contents.
+/// Process name is in parentheses and may contain spaces and parentheses itself.
+fn parse_stat(stat: &str) -> Option<(char, u32)> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    let mut fields = rest.split_whitespace();
+    let state = fields.next()?.chars().next()?;
+    let ppid = fields.next()?.parse().ok()?;
+
+    Some((state, ppid))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_stat() {
+        assert_eq!(
+            parse_stat("1234 (hl-node) Z 1 1234 1234 0 -1 4194564 0 0"),
+            Some(('Z', 1))
+        );
+        assert_eq!(
+            parse_stat("42 (weird) name)) S 7 42 42 0 -1"),
+            Some(('S', 7))
+        );
+        assert_eq!(parse_stat("garbage"), None);
+    }
+
+    #[test]
+    fn test_listening_inodes() {
+        let table = "\
+  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:0FA0 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1234 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:0BB9 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 5678 1 0000000000000000 100 0 0 10 0
+   2: 0A000001:0FA0 0A000002:D431 01 00000000:00000000 00:00000000 00000000     0        0 9012 1 0000000000000000 20 4 30 10 -1";
+
+        assert_eq!(listening_inodes(table, 4000), [1234]);
+        assert_eq!(listening_inodes(table, 3001), [5678]);
+        assert!(listening_inodes(table, 4001).is_empty());
+    }
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let mountinfo = "\
+22 1 259:1 / / rw,relatime - ext4 /dev/nvme0n1p1 rw
+35 22 0:40 / /mnt/hl rw,relatime - nfs4 10.0.0.5:/export rw,vers=4.2
+36 22 0:41 / /mnt/hl\\040data rw,sync - xfs /dev/nvme1n1 rw
+37 35 259:2 / /mnt/hl/data rw - ext4 /dev/nvme2n1 rw,data=journal
+38 22 0:42 / /mnt/hl rw - ext4 /dev/nvme3n1 rw";
+
+        let mount = parse_mountinfo(mountinfo, Path::new("/root/hl")).unwrap();
+        assert_eq!(mount.mount_point, Path::new("/"));
+        assert_eq!(mount.fs_type, "ext4");
+
+        // Shadowed by the later mount on the same mount point
+        let mount = parse_mountinfo(mountinfo, Path::new("/mnt/hl/other")).unwrap();
+        assert_eq!(mount.source, "/dev/nvme3n1");
+
+        let mount = parse_mountinfo(mountinfo, Path::new("/mnt/hl/data/hl")).unwrap();
+        assert_eq!(mount.options, ["rw", "rw", "data=journal"]);
+
+        let mount = parse_mountinfo(mountinfo, Path::new("/mnt/hl data")).unwrap();
+        assert_eq!(mount.fs_type, "xfs");
+        assert!(mount.options.contains(&"sync".to_string()));
+    }
+}
@@ -1,36 +1,85 @@
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
-use tokio::time::{MissedTickBehavior, interval};
-use tracing::{info, trace, warn};
-
-/// Worker task that periodically cleans up old files in ${base}/hl/data
+use tokio::{
+    sync::watch,
+    time::{Instant, MissedTickBehavior, interval_at},
+};
+use tracing::{info, instrument, trace, warn};
+
+use crate::{
+    alerts::{AlertEvent, alert},
+    metrics::{COUNTER_PRUNE_FILES, COUNTER_PRUNE_RUNS},
+    reload::Tunables,
+    status::{self, PruneStatus},
+};
+
+/// Worker task that periodically cleans up old files in ${base}/hl/data, following prune settings changes on reload
 /// Equivalent to: find ${base}/hl/data -mindepth 1 -depth -mmin +240 -type f -not -name "visor_child_stderr"
 pub async fn prune_worker_task<P: AsRef<Path>>(
     base_path: P,
-    prune_interval: Duration,
-    prune_older_than: Duration,
+    mut tunables: watch::Receiver<Tunables>,
 ) {
     let base_path = base_path.as_ref().join("hl/data");
-
-    let mut interval = interval(prune_interval);
-    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
-    interval.tick().await; // will complete immediately, as per interval API
-
-    info!(?base_path, ?prune_older_than, "pruning node data directory");
-    if let Err(err) = run_cleanup(&base_path, prune_older_than).await {
-        warn!(?err, "initial node data prune failed");
-    }
+    let mut initial = true;
 
     loop {
-        interval.tick().await;
+        let (prune_interval, prune_older_than) = {
+            let tunables = tunables.borrow_and_update();
+            (tunables.prune_data_interval, tunables.prune_data_older_than)
+        };
 
-        if let Err(err) = run_cleanup(&base_path, prune_older_than).await {
-            warn!(?err, ?prune_older_than, "scheduled node data prune failed");
+        if let Some(prune_interval) = prune_interval {
+            // Prune right away on startup, but not on every reload
+            let start = if initial {
+                Instant::now()
+            } else {
+                Instant::now() + prune_interval
+            };
+            let mut interval = interval_at(start, prune_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            info!(
+                ?base_path,
+                ?prune_interval,
+                ?prune_older_than,
+                "pruning node data directory"
+            );
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    changed = tunables.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                        break;
+                    }
+                }
+
+                if let Err(err) = run_cleanup(&base_path, prune_older_than).await {
+                    warn!(?err, ?prune_older_than, initial, "node data prune failed");
+                    alert(
+                        AlertEvent::PruneFailed,
+                        format!("data prune failed: {err:#}"),
+                    );
+                }
+                initial = false;
+            }
+        } else if tunables.changed().await.is_err() {
+            return;
         }
+        initial = false;
     }
 }
 
+/// Prunes ${base}/hl/data once
+pub async fn prune_data(base_path: &Path, prune_older_than: Duration) -> eyre::Result<()> {
+    let base_path = base_path.join("hl/data");
+    info!(?base_path, ?prune_older_than, "pruning node data directory");
+    run_cleanup(&base_path, prune_older_than).await
+}
+
+#[instrument(skip_all)]
 async fn run_cleanup<P: AsRef<Path>>(data_path: P, prune_older_than: Duration) -> eyre::Result<()> {
     let data_path = data_path.as_ref();
     let now = SystemTime::now();
@@ -64,6 +113,26 @@ async fn run_cleanup<P: AsRef<Path>>(data_path: P, prune_older_than: Duration) -
     }
 
     info!(removed, failed, "prune complete",);
+    if failed > 0 {
+        alert(
+            AlertEvent::PruneFailed,
+            format!("failed to remove {failed} files during data prune"),
+        );
+    }
+    COUNTER_PRUNE_RUNS.inc();
+    COUNTER_PRUNE_FILES
+        .with_label_values(&["removed"])
+        .inc_by(removed as u64);
+    COUNTER_PRUNE_FILES
+        .with_label_values(&["failed"])
+        .inc_by(failed as u64);
+    status::update(|status| {
+        status.last_prune = Some(PruneStatus {
+            finished_at_ms: status::unix_ms_now(),
+            removed,
+            failed,
+        })
+    });
 
     Ok(())
 }
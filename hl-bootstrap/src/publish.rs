@@ -0,0 +1,491 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fs, io,
+    io::BufRead,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use async_nats::{
+    HeaderMap, HeaderValue, header::NATS_MESSAGE_ID, jetstream::Context as JetStream,
+};
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, ContextCompat};
+use rdkafka::{
+    ClientConfig,
+    producer::{FutureProducer, FutureRecord},
+};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tokio::{
+    sync::mpsc,
+    time::{MissedTickBehavior, interval},
+};
+use tracing::{debug, info, instrument, warn};
+
+use crate::{
+    compress::{self, uncompressed_name},
+    metrics::{COUNTER_PUBLISH_FAILURES, COUNTER_PUBLISHED_RECORDS},
+};
+
+/// Lines sent before waiting for acknowledgements & checkpointing
+const BATCH_LINES: usize = 1000;
+
+/// replica_cmds lines can be several MiB, so batches are cut by size too
+const BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Clone, Debug, Args)]
+pub struct PublishArgs {
+    /// NATS server to publish lines of completed data files to through JetStream, e.g. `nats://nats:4222`. A stream
+    /// has to capture the subjects
+    #[arg(long, env = "HL_BOOTSTRAP_PUBLISH_NATS_URL")]
+    pub publish_nats_url: Option<String>,
+
+    /// Kafka bootstrap servers to publish lines of completed data files to, e.g. `kafka-1:9092,kafka-2:9092`
+    #[arg(long, env = "HL_BOOTSTRAP_PUBLISH_KAFKA_BROKERS")]
+    pub publish_kafka_brokers: Option<String>,
+
+    /// Extra librdkafka producer properties, e.g. `message.max.bytes=16777216;security.protocol=SASL_SSL`
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PUBLISH_KAFKA_CONFIG",
+        value_delimiter = ';',
+        value_parser = parse_property
+    )]
+    pub publish_kafka_config: Vec<(String, String)>,
+
+    /// NATS subject or Kafka topic to publish to, `{dataset}` is replaced with the hl/data subdirectory
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PUBLISH_TOPIC",
+        default_value = "hyperliquid.{dataset}"
+    )]
+    pub publish_topic: String,
+
+    /// Subdirectories of hl/data to publish, one message per line
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PUBLISH_DATASETS",
+        value_delimiter = ',',
+        default_value = "replica_cmds,node_fills,node_fills_by_block"
+    )]
+    pub publish_datasets: Vec<String>,
+
+    /// How often to look for newly completed data files
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PUBLISH_POLL_INTERVAL",
+        default_value = "10s"
+    )]
+    pub publish_poll_interval: DurationString,
+
+    /// File keeping the position of the last acknowledged line per dataset, relative to the data directory
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PUBLISH_CHECKPOINT_FILE",
+        default_value = "hl-bootstrap-publish.json"
+    )]
+    pub publish_checkpoint_file: PathBuf,
+}
+
+impl PublishArgs {
+    pub fn enabled(&self) -> bool {
+        self.publish_nats_url.is_some() || self.publish_kafka_brokers.is_some()
+    }
+}
+
+fn parse_property(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or_else(|| format!("invalid property '{value}', expected key=value"))?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+enum Publisher {
+    Nats(JetStream),
+    Kafka(FutureProducer),
+}
+
+impl Publisher {
+    async fn connect(args: &PublishArgs) -> eyre::Result<Self> {
+        if let Some(url) = &args.publish_nats_url {
+            let client = async_nats::ConnectOptions::new()
+                .retry_on_initial_connect()
+                .connect(url.as_str())
+                .await
+                .wrap_err("failed to connect to nats")?;
+            return Ok(Self::Nats(async_nats::jetstream::new(client)));
+        }
+
+        let brokers = args
+            .publish_kafka_brokers
+            .as_ref()
+            .wrap_err("neither nats nor kafka is configured")?;
+        let mut config = ClientConfig::new();
+        config
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("acks", "all");
+        for (key, value) in &args.publish_kafka_config {
+            config.set(key, value);
+        }
+        let producer = config
+            .create()
+            .wrap_err("failed to create kafka producer")?;
+        Ok(Self::Kafka(producer))
+    }
+
+    /// Sends the lines in order and waits until all of them are acknowledged. Kafka messages are keyed by dataset so
+    /// they land on one partition, NATS messages carry their position as message id for JetStream deduplication
+    async fn publish(
+        &self,
+        topic: &str,
+        dataset: &str,
+        position: &Position,
+        lines: Vec<Vec<u8>>,
+    ) -> eyre::Result<()> {
+        match self {
+            Self::Nats(jetstream) => {
+                let mut acks = Vec::with_capacity(lines.len());
+                for (index, line) in lines.into_iter().enumerate() {
+                    let id = format!("{dataset}/{}/{}", position.file, position.lines + index);
+                    let mut headers = HeaderMap::new();
+                    headers.insert(NATS_MESSAGE_ID, HeaderValue::from(id.as_str()));
+                    acks.push(
+                        jetstream
+                            .publish_with_headers(topic.to_string(), headers, line.into())
+                            .await?,
+                    );
+                }
+                for ack in acks {
+                    ack.await?;
+                }
+            }
+            Self::Kafka(producer) => {
+                let mut deliveries = Vec::with_capacity(lines.len());
+                for line in &lines {
+                    let record = FutureRecord::to(topic).key(dataset).payload(line);
+                    deliveries.push(producer.send_result(record).map_err(|(err, _)| err)?);
+                }
+                for delivery in deliveries {
+                    delivery.await?.map_err(|(err, _)| err)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Line position within a dataset. Files are relative to the dataset directory, without compression suffix
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+struct Position {
+    file: String,
+    /// Lines of the file acknowledged so far
+    lines: usize,
+    complete: bool,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Checkpoint {
+    datasets: BTreeMap<String, Position>,
+}
+
+impl Checkpoint {
+    fn load(path: &Path) -> eyre::Result<Self> {
+        match fs::read(path) {
+            Ok(contents) => {
+                serde_json::from_slice(&contents).wrap_err("failed to parse publish checkpoint")
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).wrap_err("failed to read publish checkpoint"),
+        }
+    }
+
+    fn persist(&self, path: &Path) -> eyre::Result<()> {
+        let directory = path
+            .parent()
+            .wrap_err("failed to determine publish checkpoint directory")?;
+        let mut new_file = NamedTempFile::new_in(directory)?;
+        serde_json::to_writer(&mut new_file, self)
+            .wrap_err("failed to write publish checkpoint")?;
+        new_file
+            .persist(path)
+            .wrap_err("failed to replace publish checkpoint")?;
+        Ok(())
+    }
+
+    fn update(&mut self, path: &Path, dataset: &str, position: Position) -> eyre::Result<()> {
+        self.datasets.insert(dataset.to_string(), position);
+        self.persist(path)
+    }
+}
+
+/// Worker task that publishes every line of data files hl-node moved on from, checkpointing after each acknowledged
+/// batch. Delivery is at-least-once: a batch interrupted before its checkpoint is sent again
+pub async fn publish_task(data_directory: PathBuf, args: PublishArgs, poll_interval: Duration) {
+    let checkpoint_path = data_directory.join(&args.publish_checkpoint_file);
+    let mut checkpoint = match Checkpoint::load(&checkpoint_path) {
+        Ok(checkpoint) => checkpoint,
+        Err(err) => {
+            warn!(?err, ?checkpoint_path, "not publishing data files");
+            return;
+        }
+    };
+    let publisher = match Publisher::connect(&args).await {
+        Ok(publisher) => publisher,
+        Err(err) => {
+            warn!(?err, "not publishing data files");
+            return;
+        }
+    };
+
+    let data_path = data_directory.join("hl/data");
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        datasets = ?args.publish_datasets,
+        topic = args.publish_topic,
+        "publishing completed data files"
+    );
+    loop {
+        interval.tick().await;
+
+        for dataset in &args.publish_datasets {
+            let topic = args.publish_topic.replace("{dataset}", dataset);
+            let result = publish_dataset(
+                &publisher,
+                &data_path.join(dataset),
+                dataset,
+                &topic,
+                &mut checkpoint,
+                &checkpoint_path,
+            )
+            .await;
+            if let Err(err) = result {
+                COUNTER_PUBLISH_FAILURES.inc();
+                warn!(?err, dataset, "failed to publish data files");
+            }
+        }
+    }
+}
+
+async fn publish_dataset(
+    publisher: &Publisher,
+    root: &Path,
+    dataset: &str,
+    topic: &str,
+    checkpoint: &mut Checkpoint,
+    checkpoint_path: &Path,
+) -> eyre::Result<()> {
+    let files = {
+        let root = root.to_path_buf();
+        tokio::task::spawn_blocking(move || completed_files(&root)).await?
+    };
+
+    let Some(start) = checkpoint.datasets.get(dataset).cloned() else {
+        // Only files completed from now on are published, not everything hl-node wrote before
+        let position = Position {
+            file: files
+                .last()
+                .map(|(file, _)| file.clone())
+                .unwrap_or_default(),
+            lines: 0,
+            complete: true,
+        };
+        info!(
+            dataset,
+            file = position.file,
+            "starting to publish after file"
+        );
+        return checkpoint.update(checkpoint_path, dataset, position);
+    };
+
+    for (file, path) in files {
+        let skip = match compare_files(&file, &start.file) {
+            Ordering::Less => continue,
+            Ordering::Equal if start.complete => continue,
+            Ordering::Equal => start.lines,
+            Ordering::Greater => 0,
+        };
+        publish_file(
+            publisher,
+            topic,
+            dataset,
+            Position {
+                file,
+                lines: skip,
+                complete: false,
+            },
+            path,
+            checkpoint,
+            checkpoint_path,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[instrument(skip(publisher, topic, position, checkpoint, checkpoint_path), fields(file = position.file))]
+async fn publish_file(
+    publisher: &Publisher,
+    topic: &str,
+    dataset: &str,
+    mut position: Position,
+    path: PathBuf,
+    checkpoint: &mut Checkpoint,
+    checkpoint_path: &Path,
+) -> eyre::Result<()> {
+    debug!(skip = position.lines, "publishing data file");
+    let (sender, mut receiver) = mpsc::channel(2);
+    let skip = position.lines;
+    let reader = tokio::task::spawn_blocking(move || read_batches(&path, skip, &sender));
+
+    while let Some(batch) = receiver.recv().await {
+        let batch = batch.wrap_err("failed to read data file")?;
+        let count = batch.len();
+        publisher.publish(topic, dataset, &position, batch).await?;
+
+        position.lines += count;
+        COUNTER_PUBLISHED_RECORDS
+            .with_label_values(&[dataset])
+            .inc_by(count as u64);
+        checkpoint.update(checkpoint_path, dataset, position.clone())?;
+    }
+    reader.await?;
+
+    info!(lines = position.lines, "published data file");
+    position.complete = true;
+    checkpoint.update(checkpoint_path, dataset, position)
+}
+
+/// Sends the lines after the first `skip` ones in batches, until the receiver hangs up. A last line without newline
+/// was cut short by an unclean shutdown and isn't sent
+fn read_batches(path: &Path, skip: usize, sender: &mpsc::Sender<io::Result<Vec<Vec<u8>>>>) {
+    let result = (|| {
+        let mut reader = compress::open(path)?;
+        let (mut batch, mut batch_bytes) = (Vec::new(), 0);
+        let mut number = 0;
+        loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            if line.pop() != Some(b'\n') {
+                warn!(?path, "skipping truncated last line");
+                break;
+            }
+            number += 1;
+            if number <= skip {
+                continue;
+            }
+
+            batch_bytes += line.len();
+            batch.push(line);
+            if batch.len() == BATCH_LINES || batch_bytes >= BATCH_BYTES {
+                batch_bytes = 0;
+                if sender
+                    .blocking_send(Ok(std::mem::take(&mut batch)))
+                    .is_err()
+                {
+                    return Ok(());
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let _ = sender.blocking_send(Ok(batch));
+        }
+        io::Result::Ok(())
+    })();
+    if let Err(err) = result {
+        let _ = sender.blocking_send(Err(err));
+    }
+}
+
+/// Every file of the dataset except the newest, which hl-node is still writing, oldest first
+fn completed_files(root: &Path) -> Vec<(String, PathBuf)> {
+    let mut files = Vec::new();
+    let mut pending = vec![(String::new(), root.to_path_buf())];
+    while let Some((prefix, directory)) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                pending.push((format!("{prefix}{name}/"), entry.path()));
+            } else if file_type.is_file() {
+                files.push((
+                    format!("{prefix}{}", uncompressed_name(&name)),
+                    entry.path(),
+                ));
+            }
+        }
+    }
+    files.sort_by(|a, b| compare_files(&a.0, &b.0));
+    // Both exist for a moment while a file is being compressed
+    files.dedup_by(|a, b| a.0 == b.0);
+    files.pop();
+    files
+}
+
+/// Orders relative paths by component, numbers by length first so heights & hours sort numerically
+fn compare_files(a: &str, b: &str) -> Ordering {
+    let key = |file: &str| {
+        file.split('/')
+            .map(|component| (component.len(), component.to_string()))
+            .collect::<Vec<_>>()
+    };
+    key(a).cmp(&key(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_files() {
+        let mut files = vec![
+            "20250615/10",
+            "20250614/23",
+            "20250615/9",
+            "20250615/11",
+            "",
+        ];
+        files.sort_by(|a, b| compare_files(a, b));
+        assert_eq!(
+            files,
+            [
+                "",
+                "20250614/23",
+                "20250615/9",
+                "20250615/10",
+                "20250615/11"
+            ]
+        );
+        assert_eq!(
+            compare_files(
+                "20250615_100000/20250615/990000000",
+                "20250615_100000/20250615/1000000000"
+            ),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_parse_property() {
+        assert_eq!(
+            parse_property("sasl.password=a=b"),
+            Ok(("sasl.password".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_property("acks").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use clap::Args;
+use eyre::{Context, ContextCompat};
+use prometheus::{Encoder, TextEncoder};
+use reqwest::{Client, Url};
+use tracing::{info, instrument, warn};
+
+use crate::procfs;
+
+#[derive(Clone, Debug, Args)]
+pub struct PushgatewayArgs {
+    /// Prometheus Pushgateway to push the final metrics of one-shot runs to (setup without a child, `prune`), e.g.
+    /// `http://pushgateway:9091`. Long-running instances are scraped on --metrics-listen-address instead
+    #[arg(long, env = "HL_BOOTSTRAP_PUSHGATEWAY_URL")]
+    pub pushgateway_url: Option<Url>,
+
+    /// Job label metrics are grouped under
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_PUSHGATEWAY_JOB",
+        default_value = "hl-bootstrap"
+    )]
+    pub pushgateway_job: String,
+
+    /// Instance label metrics are grouped under, defaults to the hostname
+    #[arg(long, env = "HL_BOOTSTRAP_PUSHGATEWAY_INSTANCE")]
+    pub pushgateway_instance: Option<String>,
+}
+
+/// Pushes the default registry to the Pushgateway when configured, replacing the previous push of this job &
+/// instance. Failures are only logged, they shouldn't fail the run they're reporting on
+pub async fn push_metrics(args: &PushgatewayArgs) {
+    let Some(url) = &args.pushgateway_url else {
+        return;
+    };
+
+    if let Err(err) = push(url, args).await {
+        warn!(?err, %url, "failed to push metrics to Pushgateway");
+    }
+}
+
+#[instrument(level = "debug", skip(args))]
+async fn push(url: &Url, args: &PushgatewayArgs) -> eyre::Result<()> {
+    let instance = match &args.pushgateway_instance {
+        Some(instance) => instance.clone(),
+        None => procfs::hostname()?,
+    };
+    let push_url = group_url(url, &args.pushgateway_job, &instance)?;
+
+    crate::metrics::refresh();
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&prometheus::default_registry().gather(), &mut body)
+        .wrap_err("failed to encode metrics")?;
+
+    Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?
+        .put(push_url)
+        .header(reqwest::header::CONTENT_TYPE, encoder.format_type())
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+
+    info!(
+        job = args.pushgateway_job,
+        instance, "pushed metrics to Pushgateway"
+    );
+    Ok(())
+}
+
+/// `<url>/metrics/job/<job>/instance/<instance>`
+fn group_url(url: &Url, job: &str, instance: &str) -> eyre::Result<Url> {
+    let mut url = url.clone();
+    url.path_segments_mut()
+        .ok()
+        .wrap_err("Pushgateway URL can't be a base")?
+        .pop_if_empty()
+        .extend(["metrics", "job", job, "instance", instance]);
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_url() {
+        let url = Url::parse("http://pushgateway:9091/").unwrap();
+        assert_eq!(
+            group_url(&url, "hl-bootstrap", "node-1").unwrap().as_str(),
+            "http://pushgateway:9091/metrics/job/hl-bootstrap/instance/node-1"
+        );
+
+        let url = Url::parse("https://example.com/pushgateway").unwrap();
+        assert_eq!(
+            group_url(&url, "hl bootstrap", "node-1").unwrap().as_str(),
+            "https://example.com/pushgateway/metrics/job/hl%20bootstrap/instance/node-1"
+        );
+    }
+}
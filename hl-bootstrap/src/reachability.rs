@@ -0,0 +1,121 @@
+use std::{
+    io::ErrorKind,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use eyre::{Context, bail};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::{net::TcpListener, task::JoinSet};
+use tracing::debug;
+
+use crate::{
+    monitor::{NETWORK_CLIENT, gossip::GOSSIP_PORTS},
+    speedtest::measure_node_latency,
+};
+
+/// Result of connecting back to a host, served by the /reachability/{port} endpoint
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProbeResult {
+    pub reachable: bool,
+    pub error: Option<String>,
+}
+
+/// Connects to the gossip port of the host, on behalf of a node checking its own inbound reachability
+pub async fn probe(ip: IpAddr, port: u16) -> ProbeResult {
+    let IpAddr::V4(ip) = ip else {
+        return ProbeResult {
+            reachable: false,
+            error: Some("only IPv4 is supported".to_string()),
+        };
+    };
+
+    match measure_node_latency(ip, port, Duration::from_secs(3)).await {
+        Ok(_) => ProbeResult {
+            reachable: true,
+            error: None,
+        },
+        Err(err) => ProbeResult {
+            reachable: false,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+/// Asks the reflector (another hl-bootstrap's /reachability endpoint, or anything serving the same API) to connect
+/// back to every gossip port. Listens on the ports meanwhile, as the node isn't running yet
+pub async fn check_reachability(reflector_url: &Url) -> eyre::Result<String> {
+    let mut listeners = JoinSet::new();
+    for port in GOSSIP_PORTS {
+        match TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))).await {
+            Ok(listener) => {
+                listeners.spawn(async move {
+                    // Connection succeeding is all that matters, drop it right away
+                    while listener.accept().await.is_ok() {}
+                });
+            }
+            // Something is listening already, which is just as good for the test
+            Err(err) if err.kind() == ErrorKind::AddrInUse => {
+                debug!(port, "gossip port already bound, probing it as is")
+            }
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("failed to listen on port {port}"));
+            }
+        }
+    }
+
+    let mut probes = JoinSet::new();
+    for port in GOSSIP_PORTS {
+        let url = format!("{}/{port}", reflector_url.as_str().trim_end_matches('/'));
+        probes.spawn(async move {
+            let result: eyre::Result<ProbeResult> = async {
+                Ok(NETWORK_CLIENT
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?)
+            }
+            .await;
+            (port, result)
+        });
+    }
+
+    let mut unreachable = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        let (port, result) = result?;
+        match result {
+            Ok(ProbeResult {
+                reachable: true, ..
+            }) => {}
+            Ok(ProbeResult { error, .. }) => {
+                debug!(port, ?error, "gossip port is not reachable");
+                unreachable.push(port);
+            }
+            Err(err) => {
+                return Err(err)
+                    .wrap_err_with(|| format!("failed to query reflector {reflector_url}"));
+            }
+        }
+    }
+    listeners.abort_all();
+
+    if !unreachable.is_empty() {
+        unreachable.sort_unstable();
+        bail!(
+            "gossip ports {unreachable:?} are not reachable from {}. Allow inbound TCP {}-{} in the firewall or \
+             security group, and forward the ports when behind NAT",
+            reflector_url.host_str().unwrap_or_default(),
+            GOSSIP_PORTS.start(),
+            GOSSIP_PORTS.end()
+        );
+    }
+    Ok(format!(
+        "gossip ports {}-{} are reachable from {}",
+        GOSSIP_PORTS.start(),
+        GOSSIP_PORTS.end(),
+        reflector_url.host_str().unwrap_or_default()
+    ))
+}
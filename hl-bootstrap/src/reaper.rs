@@ -0,0 +1,160 @@
+use std::{
+    collections::BTreeSet,
+    io,
+    process::{Output, Stdio},
+    sync::Mutex,
+    time::Duration,
+};
+
+use eyre::Context;
+use nix::{
+    sys::{
+        prctl::set_child_subreaper,
+        wait::{WaitPidFlag, waitpid},
+    },
+    unistd::Pid,
+};
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    time::{MissedTickBehavior, interval},
+};
+use tracing::{debug, info, warn};
+
+use crate::procfs;
+
+/// Children spawned and waited on by hl-bootstrap itself, which the reaper must not touch. Every child has to be
+/// started through [spawn] or [output], otherwise the reaper takes its exit status once it exits
+static MANAGED_PIDS: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Spawns a child hl-bootstrap waits on itself. The lock is held while spawning, so the reaper can't see the child
+/// exit before it's managed. Call [unmanage] once it's waited on
+pub fn spawn(command: &mut tokio::process::Command) -> io::Result<tokio::process::Child> {
+    let mut pids = MANAGED_PIDS.lock().unwrap();
+    let child = command.spawn()?;
+    if let Some(pid) = child.id() {
+        pids.insert(pid);
+    }
+    Ok(child)
+}
+
+/// Runs the command to completion collecting its output, like [std::process::Command::output]
+pub fn output(command: &mut std::process::Command) -> io::Result<Output> {
+    let child = {
+        let mut pids = MANAGED_PIDS.lock().unwrap();
+        let child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        pids.insert(child.id());
+        child
+    };
+    let pid = child.id();
+    let output = child.wait_with_output();
+    unmanage(pid);
+    output
+}
+
+/// Async version of [output], like [tokio::process::Command::output]
+pub async fn output_async(command: &mut tokio::process::Command) -> io::Result<Output> {
+    let child = spawn(
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped()),
+    )?;
+    let pid = child.id();
+    let output = child.wait_with_output().await;
+    if let Some(pid) = pid {
+        unmanage(pid);
+    }
+    output
+}
+
+pub fn unmanage(pid: u32) {
+    MANAGED_PIDS.lock().unwrap().remove(&pid);
+}
+
+/// Whether orphaned processes get reparented to hl-bootstrap
+pub fn is_init() -> bool {
+    std::process::id() == 1
+}
+
+pub fn enable_subreaper() -> eyre::Result<()> {
+    set_child_subreaper(true).wrap_err("failed to set PR_SET_CHILD_SUBREAPER")?;
+    Ok(())
+}
+
+/// Reaps zombie processes reparented to hl-bootstrap (e.g. hl-node after hl-visor died)
+pub async fn reaper_task() {
+    let mut sigchld = match signal(SignalKind::child()) {
+        Ok(sigchld) => sigchld,
+        Err(err) => {
+            warn!(
+                ?err,
+                "failed to set up SIGCHLD handler, orphans won't be reaped"
+            );
+            return;
+        }
+    };
+
+    // SIGCHLD can be coalesced, rescan periodically as well
+    let mut interval = interval(Duration::from_secs(10));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!("reaping orphaned processes");
+    loop {
+        tokio::select! {
+            _ = sigchld.recv() => {}
+            _ = interval.tick() => {}
+        }
+
+        reap_orphans();
+    }
+}
+
+fn reap_orphans() {
+    let own_pid = std::process::id();
+    for process in procfs::processes() {
+        if process.ppid != own_pid
+            || process.state != 'Z'
+            || MANAGED_PIDS.lock().unwrap().contains(&process.pid)
+        {
+            continue;
+        }
+
+        let pid = process.pid;
+        match waitpid(Pid::from_raw(pid as i32), Some(WaitPidFlag::WNOHANG)) {
+            Ok(status) => debug!(pid, ?status, "reaped orphaned process"),
+            Err(err) => debug!(pid, ?err, "failed to reap orphaned process"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reap_orphans_skips_managed() {
+        let mut child = spawn(&mut tokio::process::Command::new("true")).unwrap();
+        let pid = child.id().unwrap();
+        while !procfs::processes()
+            .iter()
+            .any(|process| process.pid == pid && process.state == 'Z')
+        {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        reap_orphans();
+        assert!(child.wait().await.unwrap().success());
+        unmanage(pid);
+    }
+
+    #[test]
+    fn test_output() {
+        let output = output(std::process::Command::new("echo").arg("hello")).unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello\n");
+    }
+}
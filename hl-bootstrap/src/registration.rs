@@ -0,0 +1,414 @@
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use clap::{Args, ValueEnum};
+use duration_string::DurationString;
+use eyre::{Context, bail};
+use reqwest::{Client, ClientBuilder, RequestBuilder, Url};
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+use tracing::{debug, info, warn};
+
+use crate::{
+    health::local_address, hl_gossip_config::HyperliquidChain, monitor::INFO_PORT, procfs,
+    role::NodeRole,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct RegistrationArgs {
+    /// Consul agent to register the node with as a service while hl-bootstrap runs, e.g. `http://127.0.0.1:8500`.
+    /// An ACL token is taken from CONSUL_HTTP_TOKEN
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_REGISTER_CONSUL_URL",
+        conflicts_with = "register_etcd_url"
+    )]
+    pub register_consul_url: Option<Url>,
+
+    /// etcd (v3 JSON API) to register the node in while hl-bootstrap runs, e.g. `http://127.0.0.1:2379`. The key is
+    /// `<--register-etcd-prefix><service name>/<service id>`, attached to a lease kept alive meanwhile
+    #[arg(long, env = "HL_BOOTSTRAP_REGISTER_ETCD_URL")]
+    pub register_etcd_url: Option<Url>,
+
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_REGISTER_ETCD_PREFIX",
+        default_value = "/services/"
+    )]
+    pub register_etcd_prefix: String,
+
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_REGISTER_SERVICE_NAME",
+        default_value = "hyperliquid-node"
+    )]
+    pub register_service_name: String,
+
+    /// Defaults to `<service name>-<hostname>`
+    #[arg(long, env = "HL_BOOTSTRAP_REGISTER_SERVICE_ID")]
+    pub register_service_id: Option<String>,
+
+    /// Address clients reach the node on, defaults to the hostname
+    #[arg(long, env = "HL_BOOTSTRAP_REGISTER_ADDRESS")]
+    pub register_address: Option<String>,
+
+    /// Port clients reach the node on, the info endpoint by default
+    #[arg(long, env = "HL_BOOTSTRAP_REGISTER_PORT", default_value_t = INFO_PORT)]
+    pub register_port: u16,
+
+    /// Tags in addition to the network & node role
+    #[arg(long, env = "HL_BOOTSTRAP_REGISTER_TAGS", value_delimiter = ',')]
+    pub register_tags: Vec<String>,
+
+    /// How often Consul checks the health endpoint, and the TTL of the etcd lease
+    #[arg(long, env = "HL_BOOTSTRAP_REGISTER_TTL", default_value = "30s")]
+    pub register_ttl: DurationString,
+}
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    ClientBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap()
+});
+
+#[derive(Clone, Debug)]
+enum RegistryBackend {
+    Consul(Url),
+    Etcd { url: Url, prefix: String },
+}
+
+/// The node as announced to the registry
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct Service {
+    id: String,
+    name: String,
+    address: String,
+    port: u16,
+    tags: Vec<String>,
+    meta: BTreeMap<String, String>,
+    /// hl-bootstrap's /healthz, unset without a health or metrics address
+    health_url: Option<String>,
+}
+
+/// Registration of the node in a service registry, so fleets can discover it. Removed again on shutdown; Consul
+/// additionally tracks health through the health endpoint, etcd only while the lease is kept alive
+#[derive(Debug)]
+pub struct Registration {
+    backend: RegistryBackend,
+    service: Service,
+    ttl: Duration,
+    /// etcd lease the key is attached to
+    lease: Mutex<Option<i64>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsulService<'a> {
+    #[serde(rename = "ID")]
+    id: &'a str,
+    name: &'a str,
+    address: &'a str,
+    port: u16,
+    tags: &'a [String],
+    meta: &'a BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    check: Option<ConsulCheck>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct ConsulCheck {
+    #[serde(rename = "HTTP")]
+    http: String,
+    interval: String,
+    timeout: String,
+    deregister_critical_service_after: String,
+}
+
+/// etcd's JSON API encodes 64 bit integers as strings
+#[derive(Deserialize)]
+struct LeaseGrant {
+    #[serde(rename = "ID")]
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct KeepAlive {
+    result: Option<KeepAliveResult>,
+}
+
+#[derive(Deserialize)]
+struct KeepAliveResult {
+    #[serde(rename = "TTL")]
+    ttl: Option<String>,
+}
+
+impl KeepAlive {
+    /// Expired leases are answered without a TTL
+    fn alive(&self) -> bool {
+        self.result
+            .as_ref()
+            .and_then(|result| result.ttl.as_deref())
+            .is_some_and(|ttl| ttl != "0")
+    }
+}
+
+impl Registration {
+    pub fn from_args(
+        args: &RegistrationArgs,
+        network: HyperliquidChain,
+        role: Option<NodeRole>,
+        health_address: Option<SocketAddr>,
+    ) -> eyre::Result<Option<Self>> {
+        let backend = match (&args.register_consul_url, &args.register_etcd_url) {
+            (Some(url), _) => RegistryBackend::Consul(url.clone()),
+            (None, Some(url)) => RegistryBackend::Etcd {
+                url: url.clone(),
+                prefix: args.register_etcd_prefix.clone(),
+            },
+            (None, None) => return Ok(None),
+        };
+
+        let hostname = procfs::hostname()?;
+        let address = args.register_address.clone().unwrap_or(hostname.clone());
+        let health_url = health_address.map(|health_address| {
+            // Listening on all interfaces, the health endpoint is reachable on the advertised address too
+            let host = if health_address.ip().is_unspecified() {
+                address.clone()
+            } else {
+                local_address(health_address).ip().to_string()
+            };
+            format!("http://{host}:{}/healthz", health_address.port())
+        });
+
+        Ok(Some(Self {
+            backend,
+            service: service(args, network, role, &hostname, address, health_url),
+            ttl: args.register_ttl.into(),
+            lease: Mutex::new(None),
+        }))
+    }
+
+    /// Retries until registered, then keeps an etcd registration alive
+    pub async fn run(&self) {
+        let retry_interval = self.ttl / 3;
+        while let Err(err) = self.register().await {
+            warn!(?err, "failed to register service, retrying");
+            sleep(retry_interval).await;
+        }
+        info!(id = self.service.id, backend = ?self.backend, "registered service");
+
+        let RegistryBackend::Etcd { url, .. } = &self.backend else {
+            return;
+        };
+        loop {
+            sleep(retry_interval).await;
+            let Some(lease) = *self.lease.lock().unwrap() else {
+                return;
+            };
+            let alive = match etcd_lease_request(url, "v3/lease/keepalive", lease).await {
+                Ok(response) => response
+                    .json::<KeepAlive>()
+                    .await
+                    .is_ok_and(|keep_alive| keep_alive.alive()),
+                Err(err) => {
+                    warn!(?err, "failed to keep etcd lease alive");
+                    false
+                }
+            };
+            // Once the lease expired, the key is gone and has to be put again
+            if !alive && let Err(err) = self.register().await {
+                warn!(?err, "failed to register service");
+            }
+        }
+    }
+
+    pub async fn deregister(&self) {
+        match self.try_deregister().await {
+            Ok(()) => info!(id = self.service.id, "deregistered service"),
+            Err(err) => warn!(?err, "failed to deregister service"),
+        }
+    }
+
+    async fn try_deregister(&self) -> eyre::Result<()> {
+        match &self.backend {
+            RegistryBackend::Consul(url) => {
+                let url = url.join(&format!("v1/agent/service/deregister/{}", self.service.id))?;
+                consul_request(CLIENT.put(url)).await?;
+            }
+            RegistryBackend::Etcd { url, .. } => {
+                // Revoking the lease deletes the key
+                let lease = self.lease.lock().unwrap().take();
+                if let Some(lease) = lease {
+                    etcd_lease_request(url, "v3/lease/revoke", lease).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn register(&self) -> eyre::Result<()> {
+        match &self.backend {
+            RegistryBackend::Consul(url) => {
+                let check = self.service.health_url.clone().map(|http| ConsulCheck {
+                    http,
+                    interval: format!("{}s", self.ttl.as_secs().max(1)),
+                    timeout: "5s".to_string(),
+                    deregister_critical_service_after: "30m".to_string(),
+                });
+                let body = ConsulService {
+                    id: &self.service.id,
+                    name: &self.service.name,
+                    address: &self.service.address,
+                    port: self.service.port,
+                    tags: &self.service.tags,
+                    meta: &self.service.meta,
+                    check,
+                };
+                consul_request(
+                    CLIENT
+                        .put(url.join("v1/agent/service/register")?)
+                        .json(&body),
+                )
+                .await?;
+            }
+            RegistryBackend::Etcd { url, prefix } => {
+                let grant: LeaseGrant = etcd_request(
+                    CLIENT
+                        .post(url.join("v3/lease/grant")?)
+                        .json(&serde_json::json!({ "TTL": self.ttl.as_secs().max(5) })),
+                )
+                .await?
+                .json()
+                .await
+                .wrap_err("failed to parse etcd lease grant")?;
+                let lease: i64 = grant.id.parse().wrap_err("invalid etcd lease id")?;
+
+                let key = format!("{prefix}{}/{}", self.service.name, self.service.id);
+                debug!(key, lease, "putting etcd key");
+                etcd_request(
+                    CLIENT
+                        .post(url.join("v3/kv/put")?)
+                        .json(&serde_json::json!({
+                            "key": STANDARD.encode(&key),
+                            "value": STANDARD.encode(serde_json::to_vec(&self.service)?),
+                            "lease": lease.to_string(),
+                        })),
+                )
+                .await?;
+                *self.lease.lock().unwrap() = Some(lease);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn service(
+    args: &RegistrationArgs,
+    network: HyperliquidChain,
+    role: Option<NodeRole>,
+    hostname: &str,
+    address: String,
+    health_url: Option<String>,
+) -> Service {
+    let network = network.to_string().to_lowercase();
+    let role = role.and_then(|role| {
+        role.to_possible_value()
+            .map(|value| value.get_name().to_string())
+    });
+
+    let mut tags = vec![network.clone()];
+    tags.extend(role.clone());
+    tags.extend(args.register_tags.iter().cloned());
+    let mut meta = BTreeMap::from([("network".to_string(), network)]);
+    if let Some(role) = role {
+        meta.insert("role".to_string(), role);
+    }
+
+    Service {
+        id: args
+            .register_service_id
+            .clone()
+            .unwrap_or_else(|| format!("{}-{hostname}", args.register_service_name)),
+        name: args.register_service_name.clone(),
+        address,
+        port: args.register_port,
+        tags,
+        meta,
+        health_url,
+    }
+}
+
+async fn consul_request(request: RequestBuilder) -> eyre::Result<reqwest::Response> {
+    let request = match std::env::var("CONSUL_HTTP_TOKEN") {
+        Ok(token) => request.header("x-consul-token", token),
+        Err(_) => request,
+    };
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!(
+            "consul responded with {status}: {}",
+            response.text().await.unwrap_or_default()
+        );
+    }
+    Ok(response)
+}
+
+async fn etcd_lease_request(url: &Url, path: &str, lease: i64) -> eyre::Result<reqwest::Response> {
+    etcd_request(
+        CLIENT
+            .post(url.join(path)?)
+            .json(&serde_json::json!({ "ID": lease.to_string() })),
+    )
+    .await
+}
+
+async fn etcd_request(request: RequestBuilder) -> eyre::Result<reqwest::Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        bail!(
+            "etcd responded with {status}: {}",
+            response.text().await.unwrap_or_default()
+        );
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service() {
+        let args = RegistrationArgs {
+            register_consul_url: None,
+            register_etcd_url: None,
+            register_etcd_prefix: "/services/".to_string(),
+            register_service_name: "hyperliquid-node".to_string(),
+            register_service_id: None,
+            register_address: None,
+            register_port: INFO_PORT,
+            register_tags: vec!["eu".to_string()],
+            register_ttl: Duration::from_secs(30).into(),
+        };
+        let service = service(
+            &args,
+            HyperliquidChain::Mainnet,
+            Some(NodeRole::NonValidator),
+            "node-1",
+            "10.0.0.1".to_string(),
+            None,
+        );
+        assert_eq!(service.id, "hyperliquid-node-node-1");
+        assert_eq!(service.tags, ["mainnet", "non-validator", "eu"]);
+        assert_eq!(service.meta["role"], "non-validator");
+    }
+}
@@ -0,0 +1,153 @@
+use std::{collections::HashSet, net::Ipv4Addr, sync::OnceLock, time::Duration};
+
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::watch,
+};
+use tracing::{info, warn};
+use tracing_subscriber::{EnvFilter, Registry, reload::Handle};
+
+/// Settings which can be changed on SIGHUP without restarting the child
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tunables {
+    pub prune_data_interval: Option<Duration>,
+    pub prune_data_older_than: Duration,
+    pub seed_peers_max_latency: Duration,
+    pub seed_peers_ignored: HashSet<Ipv4Addr>,
+    pub log_level: Option<String>,
+}
+
+static TUNABLES: OnceLock<watch::Sender<Tunables>> = OnceLock::new();
+
+pub fn init(tunables: Tunables) {
+    let _ = TUNABLES.set(watch::Sender::new(tunables));
+}
+
+pub fn subscribe() -> watch::Receiver<Tunables> {
+    TUNABLES
+        .get()
+        .expect("tunables are initialized on startup")
+        .subscribe()
+}
+
+/// Log filter from hl-bootstrap log level directives, falling back to RUST_LOG
+pub fn log_filter(log_level: Option<&str>) -> EnvFilter {
+    let builder = EnvFilter::builder().with_default_directive(tracing::Level::INFO.into());
+    match log_level {
+        Some(directives) => builder.parse_lossy(directives),
+        None => builder.from_env_lossy(),
+    }
+}
+
+/// Re-reads configuration on SIGHUP and publishes the changed tunables
+pub async fn reload_task(
+    load: impl Fn() -> eyre::Result<Tunables>,
+    log_filter_handle: Handle<EnvFilter, Registry>,
+) {
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(sighup) => sighup,
+        Err(err) => {
+            warn!(
+                ?err,
+                "failed to set up SIGHUP handler, configuration won't be reloaded"
+            );
+            return;
+        }
+    };
+    let sender = TUNABLES.get().expect("tunables are initialized on startup");
+
+    while sighup.recv().await.is_some() {
+        let tunables = match load() {
+            Ok(tunables) => tunables,
+            Err(err) => {
+                warn!(
+                    ?err,
+                    "failed to reload configuration, keeping the current one"
+                );
+                continue;
+            }
+        };
+
+        if tunables.log_level != sender.borrow().log_level
+            && let Err(err) = log_filter_handle.reload(log_filter(tunables.log_level.as_deref()))
+        {
+            warn!(?err, "failed to reload log filter");
+        }
+
+        let changed = sender.send_if_modified(|current| {
+            if *current == tunables {
+                return false;
+            }
+            *current = tunables.clone();
+            true
+        });
+        info!(changed, ?tunables, "reloaded configuration");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use nix::sys::signal::{Signal, raise};
+
+    use super::*;
+
+    fn tunables(prune_data_older_than: Duration) -> Tunables {
+        Tunables {
+            prune_data_interval: None,
+            prune_data_older_than,
+            seed_peers_max_latency: Duration::from_millis(100),
+            seed_peers_ignored: HashSet::new(),
+            log_level: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_task() {
+        // Installs the handler right away, so an early SIGHUP doesn't terminate the test process
+        let _sighup = signal(SignalKind::hangup()).unwrap();
+        let initial = tunables(Duration::from_secs(60));
+        init(initial.clone());
+        let mut receiver = subscribe();
+
+        let next = Arc::new(Mutex::new(initial.clone()));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let (_layer, handle) = tracing_subscriber::reload::Layer::new(log_filter(None));
+        tokio::spawn(reload_task(
+            {
+                let next = next.clone();
+                let loads = loads.clone();
+                move || {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    Ok(next.lock().unwrap().clone())
+                }
+            },
+            handle,
+        ));
+
+        // Keeps raising SIGHUP until the task has reloaded, as it subscribes to the signal asynchronously
+        let reload = async || {
+            let target = loads.load(Ordering::SeqCst) + 1;
+            while loads.load(Ordering::SeqCst) < target {
+                raise(Signal::SIGHUP).unwrap();
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+
+        reload().await;
+        assert!(!receiver.has_changed().unwrap());
+
+        let changed = tunables(Duration::from_secs(120));
+        *next.lock().unwrap() = changed.clone();
+        reload().await;
+        assert!(receiver.has_changed().unwrap());
+        assert_eq!(*receiver.borrow_and_update(), changed);
+
+        reload().await;
+        assert!(!receiver.has_changed().unwrap());
+    }
+}
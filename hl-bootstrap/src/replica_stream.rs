@@ -0,0 +1,320 @@
+use std::{
+    cmp::Ordering,
+    ffi::OsString,
+    fs,
+    io::SeekFrom,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::{
+    Json, Router,
+    extract::{
+        ConnectInfo, State, WebSocketUpgrade,
+        ws::{CloseFrame, Message, WebSocket, close_code},
+    },
+    response::IntoResponse,
+    routing::get,
+};
+use clap::Args;
+use duration_string::DurationString;
+use serde::Serialize;
+use tokio::{
+    io::{AsyncReadExt, AsyncSeekExt},
+    net::TcpListener,
+    sync::{
+        broadcast::{self, error::RecvError},
+        watch,
+    },
+    time::{MissedTickBehavior, interval},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    auth::{self, Access, Scope},
+    compress::COMPRESSED_SUFFIX,
+    http_server::{self, HttpServerArgs},
+    metrics::{COUNTER_REPLICA_STREAM_LINES, GAUGE_REPLICA_STREAM_SUBSCRIBERS},
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct ReplicaStreamArgs {
+    /// Address to stream new replica_cmds lines on, as WebSocket text messages on /replica_cmds. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_REPLICA_STREAM_LISTEN_ADDRESS")]
+    pub replica_stream_listen_address: Option<SocketAddr>,
+
+    /// How often to check the newest replica_cmds file for new lines
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_REPLICA_STREAM_POLL_INTERVAL",
+        default_value = "100ms"
+    )]
+    pub replica_stream_poll_interval: DurationString,
+
+    /// How many lines a subscriber may fall behind before it's disconnected
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_REPLICA_STREAM_BUFFER",
+        default_value_t = 1024
+    )]
+    pub replica_stream_buffer: usize,
+}
+
+#[derive(Clone)]
+struct ReplicaStream {
+    lines: broadcast::Sender<Arc<str>>,
+    current_file: watch::Receiver<Option<PathBuf>>,
+}
+
+#[derive(Serialize)]
+struct StreamStatus {
+    file: Option<PathBuf>,
+    subscribers: usize,
+}
+
+/// Tails the newest replica_cmds file and serves its new lines to every subscriber
+pub async fn run_replica_stream_server(
+    listen_address: SocketAddr,
+    data_directory: PathBuf,
+    args: ReplicaStreamArgs,
+    access: Access,
+    http: HttpServerArgs,
+) -> eyre::Result<()> {
+    let listener = TcpListener::bind(listen_address).await?;
+    let (lines, _) = broadcast::channel(args.replica_stream_buffer);
+    let (current_file_tx, current_file) = watch::channel(None);
+
+    let _tail_task = tokio::spawn(tail_task(
+        data_directory.join("hl/data/replica_cmds"),
+        args.replica_stream_poll_interval.into(),
+        lines.clone(),
+        current_file_tx,
+    ));
+
+    let router = Router::new()
+        .route("/replica_cmds", get(subscribe))
+        .route("/replica_cmds/status", get(status))
+        .with_state(ReplicaStream {
+            lines,
+            current_file,
+        });
+    let router = auth::restrict(&access, auth::require(&access, router, Scope::Stream));
+
+    info!(%listen_address, "streaming replica_cmds");
+    http_server::serve(listener, router, &http).await;
+
+    Ok(())
+}
+
+async fn status(State(stream): State<ReplicaStream>) -> impl IntoResponse {
+    Json(StreamStatus {
+        file: stream.current_file.borrow().clone(),
+        subscribers: stream.lines.receiver_count(),
+    })
+}
+
+async fn subscribe(
+    ws: WebSocketUpgrade,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    State(stream): State<ReplicaStream>,
+) -> impl IntoResponse {
+    let lines = stream.lines.subscribe();
+    ws.on_upgrade(move |socket| send_lines(socket, lines, peer))
+}
+
+async fn send_lines(
+    mut socket: WebSocket,
+    mut lines: broadcast::Receiver<Arc<str>>,
+    peer: SocketAddr,
+) {
+    debug!(%peer, "replica_cmds subscriber connected");
+    GAUGE_REPLICA_STREAM_SUBSCRIBERS.inc();
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Ok(line) => {
+                    if socket.send(Message::Text(line.as_ref().into())).await.is_err() {
+                        break;
+                    }
+                }
+                // Silently skipping lines would hand out an inconsistent view, the client has to reconnect instead
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(%peer, skipped, "replica_cmds subscriber fell behind, disconnecting");
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "fell behind".into(),
+                        })))
+                        .await;
+                    break;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            message = socket.recv() => match message {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+    GAUGE_REPLICA_STREAM_SUBSCRIBERS.dec();
+    debug!(%peer, "replica_cmds subscriber disconnected");
+}
+
+/// Position within the replica_cmds file being tailed
+struct Tail {
+    path: PathBuf,
+    offset: u64,
+    partial: Vec<u8>,
+}
+
+impl Tail {
+    /// Broadcasts the lines appended since the last read, returning the amount of bytes read
+    async fn read_new(&mut self, lines: &broadcast::Sender<Arc<str>>) -> std::io::Result<u64> {
+        let mut file = tokio::fs::File::open(&self.path).await?;
+        let length = file.metadata().await?.len();
+        if length <= self.offset {
+            return Ok(0);
+        }
+
+        file.seek(SeekFrom::Start(self.offset)).await?;
+        let mut chunk = Vec::new();
+        file.take(length - self.offset)
+            .read_to_end(&mut chunk)
+            .await?;
+        self.offset += chunk.len() as u64;
+
+        for line in split_lines(&mut self.partial, &chunk) {
+            COUNTER_REPLICA_STREAM_LINES.inc();
+            // Fails only without subscribers
+            let _ = lines.send(line.into());
+        }
+        Ok(chunk.len() as u64)
+    }
+}
+
+async fn tail_task(
+    replica_cmds: PathBuf,
+    poll_interval: Duration,
+    lines: broadcast::Sender<Arc<str>>,
+    current_file: watch::Sender<Option<PathBuf>>,
+) {
+    let mut interval = interval(poll_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut tail: Option<Tail> = None;
+    loop {
+        interval.tick().await;
+
+        // hl-node only moves on to a new file once done with the current one, so it's drained first
+        if let Some(current) = &mut tail {
+            match current.read_new(&lines).await {
+                Ok(0) => {}
+                Ok(_) => continue,
+                Err(err) => {
+                    warn!(?err, path = ?current.path, "failed to read replica_cmds file");
+                    tail = None;
+                }
+            }
+        }
+
+        let Some(newest) = newest_file(&replica_cmds) else {
+            continue;
+        };
+        if tail.as_ref().is_some_and(|current| current.path == newest) {
+            continue;
+        }
+
+        // Only new lines are streamed, so the first file is tailed from its end while later ones are read whole
+        let offset = match tail {
+            Some(_) => 0,
+            None => fs::metadata(&newest).map_or(0, |metadata| metadata.len()),
+        };
+        info!(path = ?newest, offset, "tailing replica_cmds file");
+        current_file.send_replace(Some(newest.clone()));
+        tail = Some(Tail {
+            path: newest,
+            offset,
+            partial: Vec::new(),
+        });
+    }
+}
+
+/// Files are laid out as replica_cmds/<session start>/<date>/<height>, so the newest one has the greatest name on every
+/// level
+pub fn newest_file(replica_cmds: &Path) -> Option<PathBuf> {
+    let mut path = replica_cmds.to_path_buf();
+    loop {
+        let names = fs::read_dir(&path)
+            .ok()?
+            .flatten()
+            .map(|entry| entry.file_name());
+        path.push(newest_name(names)?);
+        if !path.is_dir() {
+            return Some(path);
+        }
+    }
+}
+
+/// Heights are compared numerically, by their length first. Compressed files are done being written
+fn newest_name(names: impl Iterator<Item = OsString>) -> Option<OsString> {
+    names
+        .filter(|name| {
+            let name = name.as_encoded_bytes();
+            !name.starts_with(b".") && !name.ends_with(COMPRESSED_SUFFIX.as_bytes())
+        })
+        .max_by(|a, b| match a.len().cmp(&b.len()) {
+            Ordering::Equal => a.cmp(b),
+            ordering => ordering,
+        })
+}
+
+/// Appends the chunk to the partial line left over from the previous one, returning the complete lines
+pub fn split_lines(partial: &mut Vec<u8>, chunk: &[u8]) -> Vec<String> {
+    partial.extend_from_slice(chunk);
+    let Some(end) = partial.iter().rposition(|&byte| byte == b'\n') else {
+        return Vec::new();
+    };
+    let rest = partial.split_off(end + 1);
+    let complete = std::mem::replace(partial, rest);
+
+    complete
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| String::from_utf8_lossy(line).into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newest_name() {
+        let names = ["9990000", "10000000", "9999000", ".tmp", "10010000.zst"].map(OsString::from);
+        assert_eq!(
+            newest_name(names.into_iter()),
+            Some(OsString::from("10000000"))
+        );
+
+        let names = ["20260101", "20251231"].map(OsString::from);
+        assert_eq!(
+            newest_name(names.into_iter()),
+            Some(OsString::from("20260101"))
+        );
+    }
+
+    #[test]
+    fn test_split_lines() {
+        let mut partial = Vec::new();
+        assert_eq!(
+            split_lines(&mut partial, b"{\"a\":1}\n{\"b\""),
+            ["{\"a\":1}"]
+        );
+        assert_eq!(partial, b"{\"b\"");
+        assert!(split_lines(&mut partial, b":2").is_empty());
+        assert_eq!(split_lines(&mut partial, b"}\n\n"), ["{\"b\":2}"]);
+        assert!(partial.is_empty());
+    }
+}
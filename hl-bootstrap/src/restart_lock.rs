@@ -0,0 +1,332 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, bail};
+use nix::{
+    errno::Errno,
+    fcntl::{Flock, FlockArg},
+};
+use reqwest::{Client, ClientBuilder, StatusCode, Url};
+use serde::Serialize;
+use tempfile::NamedTempFile;
+use tokio::time::{Instant, sleep};
+use tracing::{debug, info, warn};
+
+use crate::{monitor::request_exchange_time, procfs, status::unix_ms_now};
+
+#[derive(Clone, Debug, Args)]
+pub struct RestartLockArgs {
+    /// Lock file on shared storage which has to be acquired before restarting the child on a restart request.
+    /// Prevents the whole fleet from restarting at once
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_RESTART_LOCK_FILE",
+        conflicts_with = "restart_lock_url"
+    )]
+    pub restart_lock_file: Option<PathBuf>,
+
+    /// HTTP lock service URL, acquired with PUT and released with DELETE. 2xx means acquired, 409/423 held by another node
+    #[arg(long, env = "HL_BOOTSTRAP_RESTART_LOCK_URL")]
+    pub restart_lock_url: Option<Url>,
+
+    /// Lock holder name, defaults to the hostname
+    #[arg(long, env = "HL_BOOTSTRAP_RESTART_LOCK_HOLDER")]
+    pub restart_lock_holder: Option<String>,
+
+    /// How long the lock may be held before it's considered stale. The lock is released earlier once the restarted
+    /// node responds again
+    #[arg(long, env = "HL_BOOTSTRAP_RESTART_LOCK_TTL", default_value = "10m")]
+    pub restart_lock_ttl: DurationString,
+
+    /// How often to retry acquiring the lock while it's held by another node
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_RESTART_LOCK_RETRY_INTERVAL",
+        default_value = "30s"
+    )]
+    pub restart_lock_retry_interval: DurationString,
+}
+
+#[derive(Clone, Debug)]
+enum LockBackend {
+    File(PathBuf),
+    Http(Url),
+}
+
+/// Fleet-wide lock serializing restarts triggered by hl-bootstrap itself (watchdogs).
+/// Both backends are best effort: a stale lock expires after the TTL, so a crashed holder can't block the fleet forever.
+#[derive(Clone, Debug)]
+pub struct RestartLock {
+    backend: LockBackend,
+    holder: String,
+    ttl: Duration,
+    retry_interval: Duration,
+}
+
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    ClientBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .unwrap()
+});
+
+#[derive(Serialize)]
+struct LockRequest<'a> {
+    holder: &'a str,
+    ttl_seconds: u64,
+}
+
+impl RestartLock {
+    pub fn from_args(args: &RestartLockArgs) -> eyre::Result<Option<Self>> {
+        let backend = match (&args.restart_lock_file, &args.restart_lock_url) {
+            (Some(path), _) => LockBackend::File(path.clone()),
+            (None, Some(url)) => LockBackend::Http(url.clone()),
+            (None, None) => return Ok(None),
+        };
+
+        let holder = match &args.restart_lock_holder {
+            Some(holder) => holder.clone(),
+            None => procfs::hostname()?,
+        };
+
+        Ok(Some(Self {
+            backend,
+            holder,
+            ttl: args.restart_lock_ttl.into(),
+            retry_interval: args.restart_lock_retry_interval.into(),
+        }))
+    }
+
+    /// Waits until the lock is acquired
+    pub async fn acquire(&self) {
+        loop {
+            match self.try_acquire().await {
+                Ok(true) => {
+                    info!(holder = self.holder, backend = ?self.backend, "acquired restart lock");
+                    return;
+                }
+                Ok(false) => {
+                    info!(retry_interval = ?self.retry_interval, "restart lock is held by another node, waiting")
+                }
+                Err(err) => warn!(?err, "failed to acquire restart lock, retrying"),
+            }
+            sleep(self.retry_interval).await;
+        }
+    }
+
+    /// Keeps the lock until the restarted node responds on the info endpoint again, or the TTL runs out
+    pub async fn release_after_recovery(self) {
+        let deadline = Instant::now() + self.ttl;
+        while Instant::now() < deadline && request_exchange_time().await.is_err() {
+            sleep(Duration::from_secs(5)).await;
+        }
+
+        match self.release().await {
+            Ok(()) => info!("released restart lock"),
+            Err(err) => warn!(?err, "failed to release restart lock"),
+        }
+    }
+
+    async fn try_acquire(&self) -> eyre::Result<bool> {
+        match &self.backend {
+            LockBackend::File(path) => self.try_acquire_file(path),
+            LockBackend::Http(url) => {
+                let response = CLIENT
+                    .put(url.clone())
+                    .json(&LockRequest {
+                        holder: &self.holder,
+                        ttl_seconds: self.ttl.as_secs(),
+                    })
+                    .send()
+                    .await?;
+
+                match response.status() {
+                    status if status.is_success() => Ok(true),
+                    StatusCode::CONFLICT | StatusCode::LOCKED => Ok(false),
+                    status => bail!("unexpected lock service response {status}"),
+                }
+            }
+        }
+    }
+
+    fn try_acquire_file(&self, path: &Path) -> eyre::Result<bool> {
+        let Some(_guard) = FileGuard::lock(path)? else {
+            debug!("restart lock is being acquired by another node");
+            return Ok(false);
+        };
+
+        match fs::read_to_string(path) {
+            Ok(existing) => {
+                let Some((holder, expires_at_ms)) = parse_lock_file(&existing) else {
+                    bail!("malformed restart lock file {path:?}");
+                };
+                if holder != self.holder {
+                    if expires_at_ms > unix_ms_now() {
+                        debug!(holder, expires_at_ms, "restart lock is held");
+                        return Ok(false);
+                    }
+                    warn!(holder, expires_at_ms, "taking over stale restart lock");
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err).wrap_err_with(|| format!("failed to read restart lock {path:?}"));
+            }
+        }
+
+        // Renamed into place, so other nodes never see a missing or partially written lock during a takeover
+        let directory = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let mut file = NamedTempFile::new_in(directory)?;
+        let expires_at_ms = unix_ms_now() + self.ttl.as_millis() as u64;
+        writeln!(file, "{} {expires_at_ms}", self.holder)?;
+        file.as_file().sync_all()?;
+        file.persist(path)
+            .wrap_err_with(|| format!("failed to write restart lock {path:?}"))?;
+
+        Ok(true)
+    }
+
+    async fn release(&self) -> eyre::Result<()> {
+        match &self.backend {
+            LockBackend::File(path) => {
+                // Another node acquiring the lock right now retries later
+                let Some(_guard) = FileGuard::lock(path)? else {
+                    bail!("restart lock {path:?} is being acquired by another node");
+                };
+                // Don't remove the lock if it expired and was taken over by another node
+                let existing = fs::read_to_string(path).unwrap_or_default();
+                if parse_lock_file(&existing).is_some_and(|(holder, _)| holder == self.holder) {
+                    fs::remove_file(path)
+                        .wrap_err_with(|| format!("failed to remove restart lock {path:?}"))?;
+                }
+            }
+            LockBackend::Http(url) => {
+                CLIENT
+                    .delete(url.clone())
+                    .json(&LockRequest {
+                        holder: &self.holder,
+                        ttl_seconds: 0,
+                    })
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// flock(2) of a file next to the lock file, held while checking & replacing the lock, so two nodes can't both
+/// take over the same stale lock. Never removed, as removing it would let nodes lock different files
+struct FileGuard {
+    _file: Flock<File>,
+}
+
+impl FileGuard {
+    /// None when another node holds the guard
+    fn lock(path: &Path) -> eyre::Result<Option<Self>> {
+        let mut guard_path = path.as_os_str().to_owned();
+        guard_path.push(".flock");
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&guard_path)
+            .wrap_err_with(|| format!("failed to open restart lock guard {guard_path:?}"))?;
+
+        match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+            Ok(file) => Ok(Some(Self { _file: file })),
+            Err((_, Errno::EWOULDBLOCK)) => Ok(None),
+            Err((_, errno)) => Err(errno)
+                .wrap_err_with(|| format!("failed to lock restart lock guard {guard_path:?}")),
+        }
+    }
+}
+
+/// Lock file contains `<holder> <expires at unix ms>`
+fn parse_lock_file(contents: &str) -> Option<(&str, u64)> {
+    let (holder, expires_at_ms) = contents.trim().rsplit_once(' ')?;
+    Some((holder, expires_at_ms.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_lock(path: &Path, holder: &str, ttl: Duration) -> RestartLock {
+        RestartLock {
+            backend: LockBackend::File(path.to_path_buf()),
+            holder: holder.to_string(),
+            ttl,
+            retry_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_lock_contention() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("restart.lock");
+        let first = file_lock(&path, "first", Duration::from_secs(600));
+        let second = file_lock(&path, "second", Duration::from_secs(600));
+
+        assert!(first.try_acquire().await.unwrap());
+        assert!(!second.try_acquire().await.unwrap());
+        // Held by the same holder, e.g. after a restart of hl-bootstrap
+        assert!(first.try_acquire().await.unwrap());
+
+        first.release().await.unwrap();
+        assert!(!path.exists());
+        assert!(second.try_acquire().await.unwrap());
+        assert!(!first.try_acquire().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_file_lock_stale_takeover() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("restart.lock");
+        let stale = file_lock(&path, "stale", Duration::ZERO);
+        let second = file_lock(&path, "second", Duration::from_secs(600));
+
+        assert!(stale.try_acquire().await.unwrap());
+        assert!(second.try_acquire().await.unwrap());
+        assert!(fs::read_to_string(&path).unwrap().starts_with("second "));
+
+        // Releasing the taken over lock leaves it to the new holder
+        stale.release().await.unwrap();
+        assert!(!stale.try_acquire().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_file_lock_guard() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("restart.lock");
+        let lock = file_lock(&path, "first", Duration::from_secs(600));
+
+        // Another node is in the middle of acquiring the lock
+        let guard = FileGuard::lock(&path).unwrap().unwrap();
+        assert!(!lock.try_acquire().await.unwrap());
+        drop(guard);
+        assert!(lock.try_acquire().await.unwrap());
+    }
+
+    #[test]
+    fn test_malformed_lock_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("restart.lock");
+        fs::write(&path, "garbage").unwrap();
+
+        let lock = file_lock(&path, "first", Duration::from_secs(600));
+        assert!(lock.try_acquire_file(&path).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use clap::ValueEnum;
+
+/// Kind of node being run, bundling defaults so operators don't have to find every knob
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum NodeRole {
+    /// Consensus participant, health is tied to staying in sync
+    Validator,
+    /// Regular non-validating node serving data, with pruning enabled
+    NonValidator,
+    /// Non-validating node keeping all data and writing every output
+    Archive,
+}
+
+impl NodeRole {
+    /// Argument defaults of the role as (argument id, values), including the minimum machine size. These sit below the
+    /// configuration file, environment variables and flags, so anything set explicitly wins
+    pub fn defaults(&self) -> &'static [(&'static str, &'static [&'static str])] {
+        match self {
+            Self::Validator => &[
+                ("seed_peers_amount", &["8"]),
+                ("preflight_min_cpus", &["32"]),
+                ("preflight_min_memory", &["128GiB"]),
+                ("preflight_cpu_governor_action", &["warn"]),
+                ("prune_data_interval", &["1h"]),
+                ("prune_data_older_than", &["4h"]),
+                ("health_required_checks", &["child", "sync", "disk"]),
+            ],
+            Self::NonValidator => &[
+                ("prune_data_interval", &["1h"]),
+                ("prune_data_older_than", &["4h"]),
+                ("health_required_checks", &["child", "info"]),
+            ],
+            Self::Archive => &[
+                ("health_required_checks", &["child", "info", "disk"]),
+                ("health_disk_min_free", &["100GiB"]),
+                ("preflight_disk_min_free", &["100GiB"]),
+            ],
+        }
+    }
+
+    /// hl-visor args used when none are given
+    pub fn child_args(&self) -> &'static [&'static str] {
+        match self {
+            Self::Validator => &["run-validator"],
+            Self::NonValidator => &["run-non-validator", "--serve-info"],
+            Self::Archive => &[
+                "run-non-validator",
+                "--serve-info",
+                "--replica-cmds-style",
+                "actions-and-responses",
+                "--write-trades",
+                "--write-fills",
+                "--write-order-statuses",
+                "--write-misc-events",
+            ],
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::ffi::OsString;
+
+use clap::{Args, ValueEnum};
+use eyre::bail;
+
+const RUN_NON_VALIDATOR: &str = "run-non-validator";
+
+#[derive(Clone, Debug, Args)]
+pub struct RunFlagsArgs {
+    /// Serve the info endpoint on port 3001 (hl-node --serve-info)
+    #[arg(long, env = "HL_BOOTSTRAP_SERVE_INFO")]
+    pub serve_info: bool,
+
+    /// Serve the EVM JSON-RPC on port 3001 (hl-node --serve-eth-rpc)
+    #[arg(long, env = "HL_BOOTSTRAP_SERVE_ETH_RPC")]
+    pub serve_eth_rpc: bool,
+
+    /// What replica_cmds files contain (hl-node --replica-cmds-style)
+    #[arg(long, env = "HL_BOOTSTRAP_REPLICA_CMDS_STYLE")]
+    pub replica_cmds_style: Option<ReplicaCmdsStyle>,
+
+    /// Write trades to hl/data/node_trades (hl-node --write-trades)
+    #[arg(long, env = "HL_BOOTSTRAP_WRITE_TRADES")]
+    pub write_trades: bool,
+
+    /// Write fills to hl/data/node_fills (hl-node --write-fills)
+    #[arg(long, env = "HL_BOOTSTRAP_WRITE_FILLS")]
+    pub write_fills: bool,
+
+    /// Write order status updates to hl/data/node_order_statuses (hl-node --write-order-statuses)
+    #[arg(long, env = "HL_BOOTSTRAP_WRITE_ORDER_STATUSES")]
+    pub write_order_statuses: bool,
+
+    /// Write ledger updates, funding & other events to hl/data/misc_events (hl-node --write-misc-events)
+    #[arg(long, env = "HL_BOOTSTRAP_WRITE_MISC_EVENTS")]
+    pub write_misc_events: bool,
+
+    /// Write order book diffs to hl/data/node_raw_book_diffs (hl-node --write-raw-book-diffs)
+    #[arg(long, env = "HL_BOOTSTRAP_WRITE_RAW_BOOK_DIFFS")]
+    pub write_raw_book_diffs: bool,
+
+    /// Group the written outputs by block instead of by event (hl-node --batch-by-block)
+    #[arg(long, env = "HL_BOOTSTRAP_BATCH_BY_BLOCK")]
+    pub batch_by_block: bool,
+
+    /// Flush the written outputs after every block (hl-node --disable-output-file-buffering)
+    #[arg(long, env = "HL_BOOTSTRAP_DISABLE_OUTPUT_FILE_BUFFERING")]
+    pub disable_output_file_buffering: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
+pub enum ReplicaCmdsStyle {
+    /// Signed actions only
+    Actions,
+    /// Signed actions with the responses hl-node gave, which include the users
+    ActionsAndResponses,
+    /// Only the most recent actions, keeping the files small
+    RecentActions,
+}
+
+impl RunFlagsArgs {
+    /// hl-node flags of the switches that are set
+    fn flags(&self) -> Vec<&'static str> {
+        [
+            (self.serve_info, "--serve-info"),
+            (self.serve_eth_rpc, "--serve-eth-rpc"),
+            (self.write_trades, "--write-trades"),
+            (self.write_fills, "--write-fills"),
+            (self.write_order_statuses, "--write-order-statuses"),
+            (self.write_misc_events, "--write-misc-events"),
+            (self.write_raw_book_diffs, "--write-raw-book-diffs"),
+            (self.batch_by_block, "--batch-by-block"),
+            (
+                self.disable_output_file_buffering,
+                "--disable-output-file-buffering",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.flags().is_empty() && self.replica_cmds_style.is_none()
+    }
+
+    /// Adds the flags to the hl-visor args, starting `run-non-validator` when there are none. Flags already present
+    /// aren't repeated, and the typed --replica-cmds-style replaces one given in the args
+    pub fn apply(&self, mut args: Vec<OsString>) -> eyre::Result<Vec<OsString>> {
+        if self.is_empty() {
+            return Ok(args);
+        }
+        if args.is_empty() {
+            args.push(RUN_NON_VALIDATOR.into());
+        }
+        if args[0] != RUN_NON_VALIDATOR {
+            bail!(
+                "{} only apply to {RUN_NON_VALIDATOR}, but the args start with {:?}",
+                self.names().join(", "),
+                args[0]
+            );
+        }
+
+        for flag in self.flags() {
+            if !args.iter().any(|arg| arg == flag) {
+                args.push(flag.into());
+            }
+        }
+        if let Some(style) = self.replica_cmds_style {
+            let style = style
+                .to_possible_value()
+                .expect("no skipped variants")
+                .get_name()
+                .to_string();
+            match args.iter().position(|arg| arg == "--replica-cmds-style") {
+                Some(index) if index + 1 < args.len() => args[index + 1] = style.into(),
+                Some(_) => args.push(style.into()),
+                None => args.extend(["--replica-cmds-style".into(), style.into()]),
+            }
+        }
+        Ok(args)
+    }
+
+    /// Typed flags that are set, for messages
+    fn names(&self) -> Vec<&'static str> {
+        let mut names = self.flags();
+        if self.replica_cmds_style.is_some() {
+            names.push("--replica-cmds-style");
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        let flags = RunFlagsArgs {
+            serve_info: true,
+            serve_eth_rpc: false,
+            replica_cmds_style: Some(ReplicaCmdsStyle::ActionsAndResponses),
+            write_trades: false,
+            write_fills: true,
+            write_order_statuses: false,
+            write_misc_events: false,
+            write_raw_book_diffs: false,
+            batch_by_block: false,
+            disable_output_file_buffering: false,
+        };
+
+        assert_eq!(
+            flags.apply(Vec::new()).unwrap(),
+            args(&[
+                "run-non-validator",
+                "--serve-info",
+                "--write-fills",
+                "--replica-cmds-style",
+                "actions-and-responses"
+            ])
+        );
+        assert_eq!(
+            flags
+                .apply(args(&[
+                    "run-non-validator",
+                    "--serve-info",
+                    "--replica-cmds-style",
+                    "actions"
+                ]))
+                .unwrap(),
+            args(&[
+                "run-non-validator",
+                "--serve-info",
+                "--replica-cmds-style",
+                "actions-and-responses",
+                "--write-fills"
+            ])
+        );
+        assert!(flags.apply(args(&["run-validator"])).is_err());
+        assert_eq!(
+            RunFlagsArgs {
+                serve_info: false,
+                replica_cmds_style: None,
+                write_fills: false,
+                ..flags
+            }
+            .apply(args(&["run-validator"]))
+            .unwrap(),
+            args(&["run-validator"])
+        );
+    }
+}
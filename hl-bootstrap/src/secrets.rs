@@ -0,0 +1,270 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::PathBuf,
+    time::Duration,
+};
+
+use eyre::{Context, ContextCompat, bail};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tokio::runtime::Builder;
+use tracing::debug;
+
+use crate::aws;
+
+const VAULT_SCHEME: &str = "vault://";
+const AWS_SECRETS_MANAGER_SCHEME: &str = "aws-sm://";
+
+/// Reference to a secret, given in place of a flag value
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum SecretRef {
+    /// `vault://<path>#<field>`, read from `$VAULT_ADDR/v1/<path>`. KV v2 paths include `data/`, e.g.
+    /// `vault://secret/data/hl-node#s3_secret_access_key`
+    Vault { path: String, field: String },
+    /// `aws-sm://<secret id>[#<field>]`, the secret string or a field of it when it's a JSON object. The secret id
+    /// is a name or an ARN
+    AwsSecretsManager {
+        secret_id: String,
+        field: Option<String>,
+    },
+}
+
+impl SecretRef {
+    /// Parses a reference, values without a secret scheme aren't one
+    pub fn parse(value: &str) -> eyre::Result<Option<Self>> {
+        if let Some(reference) = value.strip_prefix(VAULT_SCHEME) {
+            let (path, field) = reference
+                .split_once('#')
+                .wrap_err_with(|| format!("{value:?} has no #<field>"))?;
+            if path.is_empty() || field.is_empty() {
+                bail!("{value:?} must look like vault://<path>#<field>");
+            }
+            return Ok(Some(Self::Vault {
+                path: path.trim_matches('/').to_string(),
+                field: field.to_string(),
+            }));
+        }
+
+        if let Some(reference) = value.strip_prefix(AWS_SECRETS_MANAGER_SCHEME) {
+            let (secret_id, field) = match reference.split_once('#') {
+                Some((secret_id, field)) => (secret_id, Some(field.to_string())),
+                None => (reference, None),
+            };
+            if secret_id.is_empty() || field.as_deref() == Some("") {
+                bail!("{value:?} must look like aws-sm://<secret id>[#<field>]");
+            }
+            return Ok(Some(Self::AwsSecretsManager {
+                secret_id: secret_id.to_string(),
+                field,
+            }));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Fetches the referenced secrets, keyed by the reference as it was given. Runs on its own thread, so it can be
+/// called both before the runtime is started and from within it when reloading
+pub fn resolve(references: BTreeSet<String>) -> eyre::Result<HashMap<String, String>> {
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                Builder::new_current_thread()
+                    .enable_all()
+                    .build()?
+                    .block_on(resolve_all(references))
+            })
+            .join()
+            .map_err(|_| eyre::eyre!("secret resolution panicked"))?
+    })
+}
+
+async fn resolve_all(references: BTreeSet<String>) -> eyre::Result<HashMap<String, String>> {
+    let client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .wrap_err("failed to build secrets client")?;
+
+    // Several fields of the same secret are fetched once
+    let mut documents: HashMap<String, Value> = HashMap::new();
+    let mut secrets = HashMap::new();
+    for reference in references {
+        let secret_ref = SecretRef::parse(&reference)?
+            .wrap_err_with(|| format!("{reference:?} is not a secret reference"))?;
+        let secret = match &secret_ref {
+            SecretRef::Vault { path, field } => {
+                if !documents.contains_key(path) {
+                    documents.insert(path.clone(), read_vault(&client, path).await?);
+                }
+                field_value(&documents[path], field)
+            }
+            SecretRef::AwsSecretsManager { secret_id, field } => {
+                if !documents.contains_key(secret_id) {
+                    documents.insert(
+                        secret_id.clone(),
+                        read_aws_secret(&client, secret_id).await?.into(),
+                    );
+                }
+                let document = &documents[secret_id];
+                match field {
+                    Some(field) => {
+                        let object: Value =
+                            serde_json::from_str(document.as_str().unwrap_or_default())
+                                .wrap_err_with(|| {
+                                    format!("secret {secret_id:?} is not a JSON object")
+                                })?;
+                        field_value(&object, field)
+                    }
+                    None => document.as_str().map(str::to_string),
+                }
+            }
+        }
+        .wrap_err_with(|| format!("{reference:?} points at no string value"))?;
+
+        debug!(reference, "resolved secret");
+        secrets.insert(reference, secret);
+    }
+    Ok(secrets)
+}
+
+/// String field of a JSON object, numbers & booleans are taken as their text
+fn field_value(document: &Value, field: &str) -> Option<String> {
+    match document.get(field)? {
+        Value::String(value) => Some(value.clone()),
+        value @ (Value::Number(_) | Value::Bool(_)) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+struct VaultResponse {
+    data: Value,
+}
+
+/// Fields of the secret at the path, from KV v2 (`data.data`) or any engine returning them as `data`
+async fn read_vault(client: &Client, path: &str) -> eyre::Result<Value> {
+    let address = std::env::var("VAULT_ADDR").wrap_err("VAULT_ADDR is not set")?;
+    let token = match std::env::var("VAULT_TOKEN") {
+        Ok(token) => token,
+        Err(_) => {
+            let path = PathBuf::from(std::env::var_os("HOME").unwrap_or_else(|| "/root".into()))
+                .join(".vault-token");
+            std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("VAULT_TOKEN is not set and {path:?} can't be read"))?
+                .trim()
+                .to_string()
+        }
+    };
+
+    let mut request = client
+        .get(format!("{}/v1/{path}", address.trim_end_matches('/')))
+        .header("x-vault-token", token);
+    if let Ok(namespace) = std::env::var("VAULT_NAMESPACE") {
+        request = request.header("x-vault-namespace", namespace);
+    }
+    let response: VaultResponse = request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .wrap_err_with(|| format!("failed to read {path:?} from Vault"))?
+        .json()
+        .await
+        .wrap_err_with(|| format!("failed to parse Vault response for {path:?}"))?;
+
+    // KV v2 nests the fields next to their metadata
+    if response.data.get("metadata").is_some()
+        && let Some(fields) = response
+            .data
+            .get("data")
+            .filter(|fields| fields.is_object())
+    {
+        return Ok(fields.clone());
+    }
+    Ok(response.data)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GetSecretValueResponse {
+    secret_string: Option<String>,
+}
+
+async fn read_aws_secret(client: &Client, secret_id: &str) -> eyre::Result<String> {
+    // ARNs carry the region, arn:aws:secretsmanager:<region>:<account>:secret:<name>
+    let region = match secret_id.strip_prefix("arn:") {
+        Some(arn) => arn.split(':').nth(2).map(str::to_string),
+        None => aws::env_region(),
+    }
+    .wrap_err_with(|| {
+        format!("no region for secret {secret_id:?}, set AWS_REGION or use its ARN")
+    })?;
+
+    let service = aws::Service {
+        client: client.clone(),
+        credentials: aws::credentials(&region)?,
+        name: "secretsmanager",
+        endpoint: format!("https://secretsmanager.{region}.amazonaws.com/").parse()?,
+        region,
+    };
+    let headers = vec![
+        ("content-type", "application/x-amz-json-1.1".to_string()),
+        ("x-amz-target", "secretsmanager.GetSecretValue".to_string()),
+    ];
+    let response: GetSecretValueResponse = service
+        .post(headers, json!({ "SecretId": secret_id }).to_string())
+        .await
+        .wrap_err_with(|| format!("failed to read secret {secret_id:?} from Secrets Manager"))?
+        .json()
+        .await
+        .wrap_err("failed to parse Secrets Manager response")?;
+
+    response.secret_string.wrap_err_with(|| {
+        format!("secret {secret_id:?} is binary, only string secrets are supported")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            SecretRef::parse("vault://secret/data/hl-node#s3_secret_access_key").unwrap(),
+            Some(SecretRef::Vault {
+                path: "secret/data/hl-node".to_string(),
+                field: "s3_secret_access_key".to_string(),
+            })
+        );
+        assert_eq!(
+            SecretRef::parse("aws-sm://hl-node/alerts#telegram_bot_token").unwrap(),
+            Some(SecretRef::AwsSecretsManager {
+                secret_id: "hl-node/alerts".to_string(),
+                field: Some("telegram_bot_token".to_string()),
+            })
+        );
+        assert_eq!(
+            SecretRef::parse(
+                "aws-sm://arn:aws:secretsmanager:ap-northeast-1:123456789012:secret:hl-node"
+            )
+            .unwrap(),
+            Some(SecretRef::AwsSecretsManager {
+                secret_id: "arn:aws:secretsmanager:ap-northeast-1:123456789012:secret:hl-node"
+                    .to_string(),
+                field: None,
+            })
+        );
+        assert_eq!(SecretRef::parse("https://example.com").unwrap(), None);
+        assert!(SecretRef::parse("vault://secret/data/hl-node").is_err());
+    }
+
+    #[test]
+    fn test_field_value() {
+        let document = json!({ "token": "abc", "port": 587, "nested": {} });
+        assert_eq!(field_value(&document, "token").as_deref(), Some("abc"));
+        assert_eq!(field_value(&document, "port").as_deref(), Some("587"));
+        assert_eq!(field_value(&document, "nested"), None);
+        assert_eq!(field_value(&document, "missing"), None);
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    env::{self, consts::ARCH},
+    fs::{File, Permissions},
+    io::{Read, Write},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use clap::Args;
+use ed25519_dalek::{Signature, VerifyingKey};
+use eyre::{Context, ContextCompat, eyre};
+use hmac_sha256::Hash;
+use reqwest::Client;
+use serde::Deserialize;
+use tempfile::NamedTempFile;
+use tracing::info;
+
+const RELEASES_URL: &str =
+    "https://api.github.com/repos/BuckshotCapital/hyperliquid-node/releases/latest";
+
+/// Hex encoded public ed25519 key release artifacts are signed with, baked in when building releases. Binaries built
+/// without it refuse to update themselves
+const RELEASE_KEY: Option<&str> = option_env!("HL_BOOTSTRAP_RELEASE_KEY");
+
+#[derive(Clone, Debug, Args)]
+pub struct SelfUpdateArgs {
+    /// Only report whether a newer release is available
+    #[arg(long)]
+    pub check: bool,
+
+    /// Install the latest release even when it's not newer than the running binary
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// Release artifact name for the host, e.g. `hl-bootstrap-x86_64-linux`
+fn artifact_name() -> String {
+    format!("{}-{ARCH}-linux", env!("CARGO_PKG_NAME"))
+}
+
+/// Numeric components of a `v1.2.3` style version, for ordering releases
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['.', '-', '+'])
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// Replaces the running binary with the latest release artifact after verifying its signature against the release
+/// key. Returns whether the binary was replaced
+pub async fn self_update(args: &SelfUpdateArgs) -> eyre::Result<bool> {
+    let client = Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .build()?;
+
+    let release: Release = client
+        .get(RELEASES_URL)
+        .send()
+        .await?
+        .error_for_status()
+        .wrap_err("failed to fetch the latest release")?
+        .json()
+        .await
+        .wrap_err("failed to parse the latest release")?;
+
+    let current = env!("CARGO_PKG_VERSION");
+    let newer = version_parts(&release.tag_name) > version_parts(current);
+    println!(
+        "current version: {current}, latest release: {}",
+        release.tag_name
+    );
+    if args.check || (!newer && !args.force) {
+        if !newer {
+            println!("already up to date");
+        }
+        return Ok(false);
+    }
+
+    let release_key = RELEASE_KEY
+        .wrap_err("this binary was built without a release key, download the release manually")
+        .and_then(parse_release_key)
+        .wrap_err("can't verify release artifacts")?;
+
+    let name = artifact_name();
+    let find_asset = |name: &str| {
+        release
+            .assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .wrap_err_with(|| format!("release {} has no {name} artifact", release.tag_name))
+    };
+    let binary_asset = find_asset(&name)?;
+    let signature_asset = find_asset(&format!("{name}.sig"))?;
+
+    let current_exe = env::current_exe().wrap_err("failed to locate the running binary")?;
+    let directory = current_exe
+        .parent()
+        .wrap_err("failed to determine the binary directory")?;
+
+    info!(tag = release.tag_name, name, "downloading release");
+    let mut new_binary = NamedTempFile::new_in(directory)
+        .wrap_err_with(|| format!("failed to create a temporary file in {directory:?}"))?;
+    let binary = client
+        .get(&binary_asset.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+        .wrap_err_with(|| format!("failed to download {name}"))?;
+
+    let signature = client
+        .get(&signature_asset.browser_download_url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+        .wrap_err_with(|| format!("failed to download {name}.sig"))?;
+    let signature: [u8; 64] = signature[..]
+        .try_into()
+        .map_err(|_| eyre!("expected a 64 byte signature in {name}.sig"))?;
+    release_key
+        .verify_strict(&binary, &Signature::from_bytes(&signature))
+        .wrap_err_with(|| {
+            format!(
+                "{name} of release {} failed signature verification",
+                release.tag_name
+            )
+        })?;
+    info!(tag = release.tag_name, name, "verified release signature");
+
+    new_binary.write_all(&binary)?;
+    new_binary.flush()?;
+
+    new_binary
+        .as_file()
+        .set_permissions(Permissions::from_mode(0o755))?;
+    // Renaming over the running binary is atomic, the running process keeps its inode
+    new_binary
+        .persist(&current_exe)
+        .wrap_err_with(|| format!("failed to replace {current_exe:?}"))?;
+
+    info!(tag = release.tag_name, path = ?current_exe, "hl-bootstrap updated");
+    println!("updated {} to {}", current_exe.display(), release.tag_name);
+    Ok(true)
+}
+
+/// Hex encoded sha256 of the file, the same as `sha256sum` prints
+pub fn sha256sum(path: &Path) -> eyre::Result<String> {
+    let mut file = File::open(path).wrap_err_with(|| format!("failed to open {path:?}"))?;
+    let mut hash = Hash::new();
+    let mut buffer = vec![0; 1 << 20];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .wrap_err_with(|| format!("failed to read {path:?}"))?;
+        if read == 0 {
+            break;
+        }
+        hash.update(&buffer[..read]);
+    }
+    Ok(hash
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect())
+}
+
+fn parse_release_key(key: &str) -> eyre::Result<VerifyingKey> {
+    let key = key.trim();
+    let bytes: [u8; 32] = (0..key.len())
+        .step_by(2)
+        .map(|index| {
+            key.get(index..index + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<Vec<u8>>>()
+        .and_then(|bytes| bytes.try_into().ok())
+        .wrap_err("expected a hex encoded 32 byte key")?;
+    VerifyingKey::from_bytes(&bytes).wrap_err("invalid ed25519 public key")
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    #[test]
+    fn test_release_key() {
+        let signing_key = SigningKey::from_bytes(&[7; 32]);
+        let hex: String = signing_key
+            .verifying_key()
+            .as_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let key = parse_release_key(&hex).unwrap();
+
+        let signature = signing_key.sign(b"binary");
+        assert!(key.verify_strict(b"binary", &signature).is_ok());
+        assert!(key.verify_strict(b"tampered", &signature).is_err());
+        assert!(parse_release_key("abcd").is_err());
+        assert!(parse_release_key(&hex[1..]).is_err());
+    }
+
+    #[test]
+    fn test_sha256sum() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"hello\n").unwrap();
+        assert_eq!(
+            sha256sum(file.path()).unwrap(),
+            "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+        );
+    }
+}
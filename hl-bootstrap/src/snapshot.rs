@@ -0,0 +1,286 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use bytesize::ByteSize;
+use clap::Args;
+use eyre::{Context, ContextCompat, bail};
+use flate2::read::GzDecoder;
+use http::{StatusCode, header::RANGE};
+use nix::sys::statvfs::statvfs;
+use reqwest::{Client, Url};
+use tempfile::TempDir;
+use tracing::{debug, info, instrument, warn};
+
+use crate::{self_update::sha256sum, supervisor::privileges::RunAs};
+
+/// How often to log download progress
+const PROGRESS_EVERY: u64 = 1024 * 1024 * 1024;
+
+#[derive(Clone, Debug, Args)]
+pub struct SnapshotArgs {
+    /// State snapshot to fast-sync from when the data directory has no hl/ yet, e.g. a public or presigned S3 object
+    /// URL. A tar archive (optionally .gz or .zst compressed) with hl/ at the top level
+    #[arg(long, env = "HL_BOOTSTRAP_SNAPSHOT_URL")]
+    pub snapshot_url: Option<Url>,
+
+    /// Expected sha256 of the snapshot. Defaults to the contents of `<snapshot url>.sha256`
+    #[arg(long, env = "HL_BOOTSTRAP_SNAPSHOT_SHA256")]
+    pub snapshot_sha256: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    fn from_name(name: &str) -> eyre::Result<Self> {
+        if name.ends_with(".tar") {
+            Ok(Self::None)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Ok(Self::Gzip)
+        } else if name.ends_with(".tar.zst") || name.ends_with(".tzst") {
+            Ok(Self::Zstd)
+        } else {
+            bail!("unsupported snapshot format {name}, expected .tar, .tar.gz or .tar.zst")
+        }
+    }
+}
+
+/// Downloads, verifies and unpacks the snapshot into the data directory. Interrupted downloads are resumed on the
+/// next start, and hl/ only appears once fully unpacked
+#[instrument(skip_all, fields(%url))]
+pub async fn restore_snapshot(
+    url: &Url,
+    expected_sha256: Option<&str>,
+    data_directory: &Path,
+    owner: Option<&RunAs>,
+) -> eyre::Result<()> {
+    let name = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .wrap_err("snapshot URL has no file name")?
+        .to_string();
+    let compression = Compression::from_name(&name)?;
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(10))
+        .build()?;
+
+    let expected = match expected_sha256 {
+        Some(expected) => expected.to_string(),
+        None => {
+            let checksum_url = format!("{url}.sha256");
+            client
+                .get(&checksum_url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+                .wrap_err_with(|| {
+                    format!("failed to download {checksum_url}, or pass --snapshot-sha256")
+                })?
+                .split_whitespace()
+                .next()
+                .wrap_err("empty snapshot checksum file")?
+                .to_string()
+        }
+    };
+
+    let archive = data_directory.join(format!(".{name}.part"));
+    download_resumable(&client, url, &archive).await?;
+
+    let actual = sha256sum(&archive)?;
+    debug!(actual, expected, "comparing checksums");
+    if !actual.eq_ignore_ascii_case(&expected) {
+        // Restart from scratch next time, the snapshot may have been replaced while resuming
+        let _ = fs::remove_file(&archive);
+        bail!("checksum mismatch for snapshot {name}, expected {expected}, got {actual}");
+    }
+
+    info!(?archive, "unpacking snapshot");
+    let data_directory = data_directory.to_path_buf();
+    let owner = owner.cloned();
+    tokio::task::spawn_blocking(move || {
+        unpack(&archive, compression, &data_directory, owner.as_ref())?;
+        fs::remove_file(&archive).wrap_err("failed to remove snapshot archive")
+    })
+    .await??;
+
+    info!("snapshot restored");
+    Ok(())
+}
+
+async fn download_resumable(client: &Client, url: &Url, path: &Path) -> eyre::Result<()> {
+    let mut offset = fs::metadata(path).map_or(0, |metadata| metadata.len());
+    let mut request = client.get(url.clone());
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+    let response = request.send().await?;
+    // Nothing left to download, the start before was interrupted after the download finished. A stale archive fails
+    // the checksum and is removed
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        info!(size = %ByteSize::b(offset), "snapshot is already downloaded");
+        return Ok(());
+    }
+    let mut response = response
+        .error_for_status()
+        .wrap_err_with(|| format!("failed to download snapshot {url}"))?;
+
+    let resuming = response.status() == StatusCode::PARTIAL_CONTENT;
+    if offset > 0 && !resuming {
+        warn!(
+            offset,
+            "server does not support resuming, downloading from scratch"
+        );
+        offset = 0;
+    }
+    if let Some(remaining) = response.content_length() {
+        // The unpacked snapshot is at least as large as the archive, which is only removed after unpacking
+        let directory = path.parent().unwrap_or(Path::new("."));
+        let size = offset + remaining;
+        check_free_space(directory, remaining + size)?;
+    }
+    info!(
+        offset = %ByteSize::b(offset),
+        size = ?response.content_length().map(|remaining| ByteSize::b(offset + remaining)),
+        "downloading snapshot"
+    );
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(resuming)
+        .write(true)
+        .truncate(!resuming)
+        .open(path)
+        .wrap_err_with(|| format!("failed to open {path:?}"))?;
+    let mut downloaded = offset;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .wrap_err("snapshot download interrupted, it resumes on the next start")?
+    {
+        file.write_all(&chunk)?;
+        let before = downloaded;
+        downloaded += chunk.len() as u64;
+        if before / PROGRESS_EVERY != downloaded / PROGRESS_EVERY {
+            info!(downloaded = %ByteSize::b(downloaded), "downloading snapshot");
+        }
+    }
+    file.flush()?;
+
+    Ok(())
+}
+
+fn check_free_space(directory: &Path, needed: u64) -> eyre::Result<()> {
+    let stat = statvfs(directory).wrap_err_with(|| format!("failed to stat {directory:?}"))?;
+    let available = stat.blocks_available() * stat.fragment_size();
+    if needed > available {
+        bail!(
+            "snapshot needs {} more, only {} available in {}",
+            ByteSize::b(needed),
+            ByteSize::b(available),
+            directory.display()
+        );
+    }
+    Ok(())
+}
+
+fn reader(archive: &Path, compression: Compression) -> eyre::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(archive)?);
+    Ok(match compression {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(GzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+    })
+}
+
+/// Total size of the files in the archive. Compressed archives have to be decompressed once for it, a plain tar is
+/// about as large as its contents
+fn unpacked_size(archive: &Path, compression: Compression) -> eyre::Result<u64> {
+    if compression == Compression::None {
+        return Ok(fs::metadata(archive)?.len());
+    }
+
+    let mut size = 0;
+    for entry in tar::Archive::new(reader(archive, compression)?).entries()? {
+        size += entry?.header().size()?;
+    }
+    Ok(size)
+}
+
+/// Unpacks into a temporary directory next to hl/ first, so a partially unpacked snapshot is never mistaken for a
+/// data directory
+fn unpack(
+    archive: &Path,
+    compression: Compression,
+    data_directory: &Path,
+    owner: Option<&RunAs>,
+) -> eyre::Result<()> {
+    let size = unpacked_size(archive, compression).wrap_err("failed to read snapshot")?;
+    check_free_space(data_directory, size)?;
+
+    let staging = TempDir::with_prefix_in(".hl-snapshot-", data_directory)
+        .wrap_err("failed to create snapshot staging directory")?;
+    tar::Archive::new(reader(archive, compression)?)
+        .unpack(staging.path())
+        .wrap_err("failed to unpack snapshot")?;
+
+    let hl: PathBuf = staging.path().join("hl");
+    if !hl.is_dir() {
+        bail!("snapshot has no hl/ directory at the top level");
+    }
+    if let Some(owner) = owner {
+        owner.chown_recursive(&hl)?;
+    }
+    fs::rename(&hl, data_directory.join("hl")).wrap_err("failed to move hl/ into place")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_from_name() {
+        assert_eq!(
+            Compression::from_name("hl-20260101.tar.zst").unwrap(),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::from_name("hl.tgz").unwrap(), Compression::Gzip);
+        assert_eq!(Compression::from_name("hl.tar").unwrap(), Compression::None);
+        assert!(Compression::from_name("hl.zip").is_err());
+    }
+
+    #[test]
+    fn test_unpacked_size() {
+        let directory = tempfile::tempdir().unwrap();
+        let archive = directory.path().join("hl.tar.zst");
+        let mut builder = tar::Builder::new(
+            zstd::Encoder::new(File::create(&archive).unwrap(), 0)
+                .unwrap()
+                .auto_finish(),
+        );
+        for (name, size) in [("hl/a", 1000), ("hl/b", 2345)] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(size);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, &vec![0; size as usize][..])
+                .unwrap();
+        }
+        builder.into_inner().unwrap();
+
+        assert_eq!(unpacked_size(&archive, Compression::Zstd).unwrap(), 3345);
+    }
+}
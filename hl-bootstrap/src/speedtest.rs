@@ -10,12 +10,18 @@ use tokio::{
     sync::Semaphore,
     time::{Instant, timeout},
 };
-use tracing::{Level, debug, info, trace};
+use tracing::{Instrument, Level, debug, info, instrument, trace};
 
-use crate::hl_gossip_config::HyperliquidSeedPeer;
+use crate::{
+    hl_gossip_config::HyperliquidSeedPeer,
+    speedtest::history::{SpeedtestResult, SpeedtestRun},
+    status::unix_ms_now,
+};
+
+pub mod history;
 
 #[derive(Debug)]
-enum MeasureError {
+pub enum MeasureError {
     Timeout,
     IOError(std::io::Error),
 }
@@ -29,13 +35,24 @@ impl fmt::Display for MeasureError {
     }
 }
 
+/// Gossip port is 4001 as of 2025-07-23, could change in the future
+pub const GOSSIP_PORT: u16 = 4001;
+
 // TODO: return failure reason for debugging
-async fn measure_node_latency(
+#[instrument(level = "debug", skip(timeout_duration))]
+pub async fn measure_node_latency(
     ip: Ipv4Addr,
     port: u16,
     timeout_duration: Duration,
 ) -> Result<Duration, MeasureError> {
-    let addr = SocketAddr::new(ip.into(), port);
+    measure_latency(SocketAddr::new(ip.into(), port), timeout_duration).await
+}
+
+/// Time to establish a TCP connection
+pub async fn measure_latency(
+    addr: SocketAddr,
+    timeout_duration: Duration,
+) -> Result<Duration, MeasureError> {
     let start = Instant::now();
 
     match timeout(timeout_duration, TcpStream::connect(addr)).await {
@@ -45,14 +62,15 @@ async fn measure_node_latency(
     }
 }
 
+#[instrument(skip_all, fields(candidates = candidates.len()))]
 pub async fn speedtest_nodes(
     candidates: Vec<HyperliquidSeedPeer>,
     n: usize,
     timeout_duration: Duration,
 ) -> eyre::Result<Vec<HyperliquidSeedPeer>> {
-    // NOTE: Gossip port is 4001 as of 2025-07-23, could change in the future
-    let port = 4001;
+    let port = GOSSIP_PORT;
     let concurrency = 64;
+    let started_at_ms = unix_ms_now();
 
     info!(
         candidates = candidates.len(),
@@ -67,17 +85,20 @@ pub async fn speedtest_nodes(
         let ip = node.ip;
         let sem = semaphore.clone();
 
-        let task = tokio::spawn(async move {
-            let _permit = sem.acquire().await.unwrap();
-            let latency = measure_node_latency(ip, port, timeout_duration).await;
-            (idx, latency)
-        });
+        let task = tokio::spawn(
+            async move {
+                let _permit = sem.acquire().await.unwrap();
+                let latency = measure_node_latency(ip, port, timeout_duration).await;
+                (idx, latency)
+            }
+            .in_current_span(),
+        );
 
         tasks.push(task);
     }
 
     let mut successful_nodes = Vec::new();
-    let mut failed = 0;
+    let mut failed_nodes = Vec::new();
 
     for task in tasks {
         let (idx, latency) = task.await?;
@@ -90,14 +111,14 @@ pub async fn speedtest_nodes(
             }
             Err(err) => {
                 trace!(%err, ?node, "latency test failed");
-                failed += 1;
+                failed_nodes.push((idx, err));
             }
         }
     }
 
     info!(
         successful = successful_nodes.len(),
-        failed = failed,
+        failed = failed_nodes.len(),
         "latency test complete"
     );
 
@@ -108,6 +129,29 @@ pub async fn speedtest_nodes(
 
     // Return the n lowest latency nodes
     let to_take = n.min(successful_nodes.len());
+
+    history::record(SpeedtestRun {
+        started_at_ms,
+        timeout_ms: timeout_duration.as_millis() as u64,
+        results: successful_nodes
+            .iter()
+            .enumerate()
+            .map(|(rank, (idx, latency))| SpeedtestResult {
+                ip: candidates[*idx].ip,
+                operator_name: candidates[*idx].operator_name.clone(),
+                latency_ms: Some(latency.as_secs_f64() * 1e3),
+                error: None,
+                selected: rank < to_take,
+            })
+            .chain(failed_nodes.iter().map(|(idx, err)| SpeedtestResult {
+                ip: candidates[*idx].ip,
+                operator_name: candidates[*idx].operator_name.clone(),
+                latency_ms: None,
+                error: Some(err.to_string()),
+                selected: false,
+            }))
+            .collect(),
+    });
     let result: Vec<_> = successful_nodes
         .into_iter()
         .map(|(idx, latency)| (candidates[idx].clone(), latency)) // TODO: too lazy to remove this clone
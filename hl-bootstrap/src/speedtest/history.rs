@@ -0,0 +1,159 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use eyre::{Context, ContextCompat};
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+use tracing::{debug, warn};
+
+/// Speedtest run, kept for diagnosing why certain seed peers were picked
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpeedtestRun {
+    pub started_at_ms: u64,
+    pub timeout_ms: u64,
+    pub results: Vec<SpeedtestResult>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpeedtestResult {
+    pub ip: Ipv4Addr,
+    pub operator_name: String,
+    /// None when the connection failed
+    pub latency_ms: Option<f64>,
+    pub error: Option<String>,
+    pub selected: bool,
+}
+
+struct History {
+    capacity: usize,
+    file: Option<PathBuf>,
+    runs: VecDeque<SpeedtestRun>,
+}
+
+static HISTORY: Mutex<History> = Mutex::new(History {
+    capacity: 0,
+    file: None,
+    runs: VecDeque::new(),
+});
+
+/// Sets up history retention, loading runs persisted by previous processes from `file`
+pub fn configure(capacity: usize, file: Option<PathBuf>) {
+    let mut history = HISTORY.lock().unwrap();
+    history.capacity = capacity;
+
+    if let Some(file) = &file {
+        match load(file) {
+            Ok(runs) => history.runs = runs,
+            Err(err) => debug!(?err, ?file, "failed to load speedtest history"),
+        }
+    }
+    history.file = file;
+
+    while history.runs.len() > capacity {
+        history.runs.pop_front();
+    }
+}
+
+pub fn record(run: SpeedtestRun) {
+    let mut history = HISTORY.lock().unwrap();
+    if history.capacity == 0 {
+        return;
+    }
+    if history.runs.len() >= history.capacity {
+        history.runs.pop_front();
+    }
+    history.runs.push_back(run);
+
+    if let Some(file) = &history.file
+        && let Err(err) = persist(file, &history.runs)
+    {
+        warn!(?err, ?file, "failed to persist speedtest history");
+    }
+}
+
+/// Past runs, oldest first
+pub fn runs() -> Vec<SpeedtestRun> {
+    HISTORY.lock().unwrap().runs.iter().cloned().collect()
+}
+
+fn load(file: &Path) -> eyre::Result<VecDeque<SpeedtestRun>> {
+    let contents = fs::read(file)?;
+    serde_json::from_slice(&contents).wrap_err("failed to parse speedtest history")
+}
+
+fn persist(file: &Path, runs: &VecDeque<SpeedtestRun>) -> eyre::Result<()> {
+    let directory = file
+        .parent()
+        .wrap_err("failed to determine speedtest history directory")?;
+    let mut new_file = NamedTempFile::new_in(directory)?;
+    serde_json::to_writer(&mut new_file, runs).wrap_err("failed to write speedtest history")?;
+    new_file
+        .persist(file)
+        .wrap_err("failed to replace speedtest history")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(started_at_ms: u64) -> SpeedtestRun {
+        SpeedtestRun {
+            started_at_ms,
+            timeout_ms: 1000,
+            results: vec![SpeedtestResult {
+                ip: Ipv4Addr::new(1, 2, 3, 4),
+                operator_name: "operator".to_string(),
+                latency_ms: Some(12.5),
+                error: None,
+                selected: true,
+            }],
+        }
+    }
+
+    fn started_at(runs: &[SpeedtestRun]) -> Vec<u64> {
+        runs.iter().map(|run| run.started_at_ms).collect()
+    }
+
+    #[test]
+    fn test_persist_round_trip() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("speedtest_history.json");
+        persist(&file, &VecDeque::from([run(1), run(2)])).unwrap();
+
+        let runs = Vec::from(load(&file).unwrap());
+        assert_eq!(started_at(&runs), [1, 2]);
+        assert_eq!(runs[0].results[0].ip, Ipv4Addr::new(1, 2, 3, 4));
+        assert_eq!(runs[0].results[0].latency_ms, Some(12.5));
+        assert!(load(&directory.path().join("missing.json")).is_err());
+    }
+
+    // HISTORY is global, so configure, record and runs are tested in one go
+    #[test]
+    fn test_history() {
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("speedtest_history.json");
+
+        configure(0, None);
+        record(run(1));
+        assert!(runs().is_empty());
+
+        configure(2, Some(file.clone()));
+        for started_at_ms in 1..=3 {
+            record(run(started_at_ms));
+        }
+        assert_eq!(started_at(&runs()), [2, 3]);
+        assert_eq!(started_at(&Vec::from(load(&file).unwrap())), [2, 3]);
+
+        // A new process loads the persisted runs, trimmed to its capacity
+        configure(1, Some(file.clone()));
+        assert_eq!(started_at(&runs()), [3]);
+
+        configure(0, None);
+    }
+}
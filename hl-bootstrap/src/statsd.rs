@@ -0,0 +1,233 @@
+use std::{collections::HashMap, fmt::Write as _};
+
+use clap::Args;
+use duration_string::DurationString;
+use eyre::Context;
+use prometheus::proto::{Metric, MetricFamily, MetricType};
+use tokio::{
+    net::UdpSocket,
+    time::{MissedTickBehavior, interval},
+};
+use tracing::{debug, info, warn};
+
+/// Keep datagrams below the common 1500 byte MTU
+const MAX_PACKET_SIZE: usize = 1432;
+
+#[derive(Clone, Debug, Args)]
+pub struct StatsdArgs {
+    /// StatsD server address to emit metrics to, e.g. `127.0.0.1:8125`. Emits the same metrics as /metrics
+    #[arg(long, env = "HL_BOOTSTRAP_STATSD_ADDRESS")]
+    pub statsd_address: Option<String>,
+
+    /// How often metrics are emitted to StatsD
+    #[arg(long, env = "HL_BOOTSTRAP_STATSD_INTERVAL", default_value = "10s")]
+    pub statsd_interval: DurationString,
+
+    /// Emit labels as DogStatsD tags instead of appending label values to metric names
+    #[arg(long, env = "HL_BOOTSTRAP_STATSD_DOGSTATSD")]
+    pub statsd_dogstatsd: bool,
+
+    /// Extra DogStatsD tags added to every metric, e.g. `env:prod`
+    #[arg(long, env = "HL_BOOTSTRAP_STATSD_TAGS", value_delimiter = ',')]
+    pub statsd_tags: Vec<String>,
+}
+
+/// Worker task periodically emitting the Prometheus registry to StatsD.
+/// Counters are sent as deltas since the previous emit, everything else as gauges.
+pub async fn statsd_task(address: String, args: StatsdArgs) {
+    let socket = match connect(&address).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!(
+                ?err,
+                address, "failed to set up StatsD socket, not emitting metrics"
+            );
+            return;
+        }
+    };
+
+    let mut encoder = Encoder {
+        dogstatsd: args.statsd_dogstatsd,
+        tags: args.statsd_tags,
+        counters: HashMap::new(),
+    };
+
+    let mut interval = interval(args.statsd_interval.into());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(
+        address,
+        dogstatsd = encoder.dogstatsd,
+        "emitting metrics to StatsD"
+    );
+    loop {
+        interval.tick().await;
+
+        crate::metrics::refresh();
+        let lines = encoder.encode(&prometheus::default_registry().gather());
+
+        for packet in packets(&lines) {
+            if let Err(err) = socket.send(packet.as_bytes()).await {
+                debug!(?err, "failed to send StatsD packet");
+            }
+        }
+    }
+}
+
+async fn connect(address: &str) -> eyre::Result<UdpSocket> {
+    let socket = UdpSocket::bind("0.0.0.0:0")
+        .await
+        .wrap_err("failed to bind StatsD socket")?;
+    socket
+        .connect(address)
+        .await
+        .wrap_err_with(|| format!("failed to resolve StatsD address {address}"))?;
+    Ok(socket)
+}
+
+/// Joins lines into newline separated packets
+fn packets(lines: &[String]) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut packet = String::new();
+    for line in lines {
+        if !packet.is_empty() && packet.len() + 1 + line.len() > MAX_PACKET_SIZE {
+            packets.push(std::mem::take(&mut packet));
+        }
+        if !packet.is_empty() {
+            packet.push('\n');
+        }
+        packet.push_str(line);
+    }
+    if !packet.is_empty() {
+        packets.push(packet);
+    }
+    packets
+}
+
+struct Encoder {
+    dogstatsd: bool,
+    tags: Vec<String>,
+    /// Last seen counter values, keyed by encoded metric name & tags
+    counters: HashMap<String, f64>,
+}
+
+impl Encoder {
+    fn encode(&mut self, families: &[MetricFamily]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for family in families {
+            let name = family.name();
+            for metric in family.get_metric() {
+                match family.get_field_type() {
+                    MetricType::COUNTER => {
+                        let key = self.key(name, metric);
+                        let value = metric.get_counter().get_value();
+                        let previous = self.counters.insert(key.clone(), value).unwrap_or(0.0);
+                        // Counter was reset, e.g. metric got removed and recreated
+                        let delta = if value >= previous {
+                            value - previous
+                        } else {
+                            value
+                        };
+                        lines.push(self.line(&key, delta, "c"));
+                    }
+                    MetricType::GAUGE => {
+                        let key = self.key(name, metric);
+                        lines.push(self.line(&key, metric.get_gauge().get_value(), "g"));
+                    }
+                    MetricType::HISTOGRAM => {
+                        let histogram = metric.get_histogram();
+                        let key = self.key(&format!("{name}_sum"), metric);
+                        lines.push(self.line(&key, histogram.get_sample_sum(), "g"));
+                        let key = self.key(&format!("{name}_count"), metric);
+                        lines.push(self.line(&key, histogram.get_sample_count() as f64, "g"));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        lines
+    }
+
+    /// `name|#tag:value` for DogStatsD, `name.label_value` for plain StatsD
+    fn key(&self, name: &str, metric: &Metric) -> String {
+        let mut key = name.to_string();
+        if self.dogstatsd {
+            let tags: Vec<_> = metric
+                .get_label()
+                .iter()
+                .map(|label| format!("{}:{}", label.name(), label.value()))
+                .chain(self.tags.iter().cloned())
+                .collect();
+            if !tags.is_empty() {
+                let _ = write!(key, "|#{}", tags.join(","));
+            }
+        } else {
+            for label in metric.get_label() {
+                key.push('.');
+                key.extend(label.value().chars().map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                        c
+                    } else {
+                        '_'
+                    }
+                }));
+            }
+        }
+        key
+    }
+
+    fn line(&self, key: &str, value: f64, kind: &str) -> String {
+        match key.split_once('|') {
+            Some((name, tags)) => format!("{name}:{value}|{kind}|{tags}"),
+            None => format!("{key}:{value}|{kind}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prometheus::{IntCounterVec, IntGauge, Opts, Registry};
+
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let registry = Registry::new();
+        let counter = IntCounterVec::new(Opts::new("test_runs", "runs"), &["result"]).unwrap();
+        let gauge = IntGauge::new("test_peers", "peers").unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(gauge.clone())).unwrap();
+
+        counter.with_label_values(&["ok"]).inc_by(3);
+        gauge.set(7);
+
+        let mut dogstatsd = Encoder {
+            dogstatsd: true,
+            tags: vec!["env:test".to_string()],
+            counters: HashMap::new(),
+        };
+        let mut statsd = Encoder {
+            dogstatsd: false,
+            tags: Vec::new(),
+            counters: HashMap::new(),
+        };
+
+        assert_eq!(
+            dogstatsd.encode(&registry.gather()),
+            [
+                "test_peers:7|g|#env:test",
+                "test_runs:3|c|#result:ok,env:test"
+            ]
+        );
+        assert_eq!(
+            statsd.encode(&registry.gather()),
+            ["test_peers:7|g", "test_runs.ok:3|c"]
+        );
+
+        counter.with_label_values(&["ok"]).inc();
+        assert_eq!(
+            statsd.encode(&registry.gather()),
+            ["test_peers:7|g", "test_runs.ok:1|c"]
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::maintenance;
+
+pub mod report;
+pub mod version;
+
+/// Current state of hl-bootstrap, shared between setup, supervisor and background tasks
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BootstrapStatus {
+    pub phase: Phase,
+    pub gossip_config_written_at_ms: Option<u64>,
+    pub child: ChildStatus,
+    pub last_prune: Option<PruneStatus>,
+    pub maintenance: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    #[default]
+    Starting,
+    DownloadingVisor,
+    RestoringSnapshot,
+    ConfiguringPeers,
+    SetupDone,
+    Stopping,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ChildStatus {
+    pub pid: Option<u32>,
+    pub state: ChildState,
+    pub restarts: u32,
+    pub last_exit_code: Option<i32>,
+    /// Result of the liveness probe against the hl-node info endpoint, unset until the node has responded once
+    pub node_responding: Option<bool>,
+    /// Unix milliseconds since which the child isn't running, unset while it is
+    pub down_since_ms: Option<u64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildState {
+    #[default]
+    NotStarted,
+    Running,
+    Restarting,
+    Exited,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct PruneStatus {
+    pub finished_at_ms: u64,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Starting => "starting",
+            Self::DownloadingVisor => "downloading_visor",
+            Self::RestoringSnapshot => "restoring_snapshot",
+            Self::ConfiguringPeers => "configuring_peers",
+            Self::SetupDone => "setup_done",
+            Self::Stopping => "stopping",
+        }
+    }
+}
+
+impl BootstrapStatus {
+    /// Setup still being in progress is considered healthy, as is running child after setup
+    pub fn is_healthy(&self) -> bool {
+        match self.phase {
+            Phase::Starting
+            | Phase::DownloadingVisor
+            | Phase::RestoringSnapshot
+            | Phase::ConfiguringPeers => true,
+            Phase::SetupDone => {
+                matches!(
+                    self.child.state,
+                    ChildState::NotStarted | ChildState::Running
+                ) && self.child.node_responding != Some(false)
+            }
+            Phase::Stopping => false,
+        }
+    }
+}
+
+static STATUS: LazyLock<Mutex<BootstrapStatus>> = LazyLock::new(Default::default);
+
+pub fn update(f: impl FnOnce(&mut BootstrapStatus)) {
+    f(&mut STATUS.lock().unwrap());
+}
+
+pub fn set_phase(phase: Phase) {
+    update(|status| status.phase = phase);
+}
+
+pub fn snapshot() -> BootstrapStatus {
+    let mut status = STATUS.lock().unwrap().clone();
+    status.maintenance = maintenance::is_enabled();
+    status
+}
+
+pub fn unix_ms_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
@@ -0,0 +1,185 @@
+use std::{fmt::Write, fs, net::SocketAddr, path::PathBuf, time::Duration};
+
+use nix::sys::statvfs::statvfs;
+
+use crate::{
+    disk_usage::directory_size,
+    health::local_address,
+    hl_gossip_config::{HyperliquidChain, OverrideGossipConfig},
+    monitor::{NETWORK_CLIENT, abci_state::AbciState, sync_lag},
+    status::{BootstrapStatus, unix_ms_now},
+};
+
+/// Everything needed to describe an hl-bootstrap instance from the outside
+pub struct StatusReport {
+    pub network: HyperliquidChain,
+    pub data_directory: PathBuf,
+    pub visor_binary_directory: Option<PathBuf>,
+    pub gossip_config_path: PathBuf,
+    pub pid_file: PathBuf,
+    pub disk_usage_directories: Vec<PathBuf>,
+    /// Health or metrics server of the running instance, serving /healthz
+    pub health_address: Option<SocketAddr>,
+    /// API key for /healthz, when the health server requires one
+    pub health_api_key: Option<String>,
+}
+
+impl StatusReport {
+    pub async fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write(&mut out).await;
+        out
+    }
+
+    async fn write(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "network: {}", self.network.to_string())?;
+        writeln!(out, "data directory: {}", self.data_directory.display())?;
+
+        writeln!(out, "\n[gossip config]")?;
+        match fs::metadata(&self.gossip_config_path).and_then(|metadata| metadata.modified()) {
+            Ok(mtime) => {
+                let age = mtime.elapsed().unwrap_or_default();
+                writeln!(out, "path: {}", self.gossip_config_path.display())?;
+                writeln!(out, "age: {}", format_duration(age))?;
+            }
+            Err(err) => writeln!(out, "unavailable: {err}")?,
+        }
+        if let Some(config) = fs::read(&self.gossip_config_path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<OverrideGossipConfig>(&contents).ok())
+        {
+            let peers: Vec<_> = config
+                .root_node_ips
+                .iter()
+                .map(|peer| peer.ip.to_string())
+                .collect();
+            writeln!(out, "peers ({}): {}", peers.len(), peers.join(", "))?;
+            writeln!(out, "try new peers: {}", config.try_new_peers)?;
+        }
+
+        writeln!(out, "\n[binaries]")?;
+        match &self.visor_binary_directory {
+            Some(visor_binary_directory) => {
+                for name in ["hl-visor", "hl-node"] {
+                    let etag_path = visor_binary_directory.join(format!(".{name}.etag"));
+                    match fs::read_to_string(&etag_path) {
+                        Ok(etag) => writeln!(out, "{name} etag: {}", etag.trim())?,
+                        Err(_) => writeln!(out, "{name}: not downloaded by hl-bootstrap")?,
+                    }
+                }
+            }
+            None => writeln!(out, "unknown, --visor-binary-directory is not set")?,
+        }
+
+        writeln!(out, "\n[process]")?;
+        match fs::read_to_string(&self.pid_file) {
+            Ok(pid) if !pid.trim().is_empty() => {
+                let pid = pid.trim();
+                let alive = fs::metadata(format!("/proc/{pid}")).is_ok();
+                writeln!(out, "hl-bootstrap pid: {pid} (running: {alive})")?;
+            }
+            _ => writeln!(out, "hl-bootstrap: not running")?,
+        }
+        match self.fetch_health().await {
+            Some(status) => {
+                writeln!(out, "phase: {:?}", status.phase)?;
+                writeln!(out, "healthy: {}", status.is_healthy())?;
+                writeln!(out, "maintenance: {}", status.maintenance)?;
+                writeln!(
+                    out,
+                    "child: {:?}, pid: {}, restarts: {}, last exit code: {}",
+                    status.child.state,
+                    display_option(status.child.pid),
+                    status.child.restarts,
+                    display_option(status.child.last_exit_code),
+                )?;
+                match status.last_prune {
+                    Some(prune) => writeln!(
+                        out,
+                        "last prune: {} ago, removed {}, failed {}",
+                        format_duration(Duration::from_millis(
+                            unix_ms_now().saturating_sub(prune.finished_at_ms)
+                        )),
+                        prune.removed,
+                        prune.failed,
+                    )?,
+                    None => writeln!(out, "last prune: never")?,
+                }
+            }
+            None => writeln!(
+                out,
+                "child status unavailable (health server not configured or not reachable)"
+            )?,
+        }
+
+        writeln!(out, "\n[disk]")?;
+        match statvfs(&self.data_directory) {
+            Ok(stat) => {
+                let fragment_size = stat.fragment_size();
+                writeln!(
+                    out,
+                    "available: {} of {}",
+                    bytesize::ByteSize::b(stat.blocks_available() * fragment_size),
+                    bytesize::ByteSize::b(stat.blocks() * fragment_size),
+                )?;
+            }
+            Err(err) => writeln!(out, "unavailable: {err}")?,
+        }
+        let hl_data = self.data_directory.join("hl/data");
+        for directory in &self.disk_usage_directories {
+            let size = directory_size(&hl_data.join(directory));
+            writeln!(
+                out,
+                "{}: {}",
+                directory.display(),
+                bytesize::ByteSize::b(size)
+            )?;
+        }
+
+        writeln!(out, "\n[sync]")?;
+        if let Some(state) = fs::read(AbciState::path(&self.data_directory))
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<AbciState>(&contents).ok())
+        {
+            writeln!(
+                out,
+                "height: {}, consensus time: {}",
+                state.height, state.consensus_time
+            )?;
+        }
+        match sync_lag(self.network).await {
+            Ok(lag) => writeln!(out, "lag: {}", format_duration(lag))?,
+            Err(err) => writeln!(out, "lag: unknown ({err})")?,
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_health(&self) -> Option<BootstrapStatus> {
+        let address = local_address(self.health_address?);
+        let mut request = NETWORK_CLIENT.get(format!("http://{address}/healthz"));
+        if let Some(api_key) = &self.health_api_key {
+            request = request.bearer_auth(api_key);
+        }
+        request.send().await.ok()?.json().await.ok()
+    }
+}
+
+fn display_option<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "-".to_string(), |value| value.to_string())
+}
+
+/// Human friendly duration, e.g. `1h 2m 5s`. Sub-second precision is only shown for short durations
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{secs}s"),
+        (0, _) => format!("{minutes}m {secs}s"),
+        _ => format!("{hours}h {minutes}m {secs}s"),
+    }
+}
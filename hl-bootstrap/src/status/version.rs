@@ -0,0 +1,71 @@
+use std::{env::consts::ARCH, fmt::Write, fs, path::PathBuf};
+
+use crate::{
+    hl_gossip_config::HyperliquidChain,
+    hl_visor::download::{binary_url, fetch_etag},
+    self_update::sha256sum,
+};
+
+pub const VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (commit ",
+    env!("HL_BOOTSTRAP_COMMIT"),
+    ")"
+);
+
+/// Version information worth including in support requests
+pub struct VersionReport {
+    pub network: HyperliquidChain,
+    /// Local binaries are only reported when set
+    pub visor_binary_directory: Option<PathBuf>,
+}
+
+impl VersionReport {
+    pub async fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write(&mut out).await;
+        out
+    }
+
+    async fn write(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "hl-bootstrap: {VERSION}, {ARCH}-linux")?;
+        writeln!(out, "network: {}", self.network.to_string())?;
+
+        for name in ["hl-visor", "hl-node"] {
+            writeln!(out, "\n[{name}]")?;
+
+            let mut local_etag = None;
+            if let Some(visor_binary_directory) = &self.visor_binary_directory {
+                let binary_path = visor_binary_directory.join(name);
+                local_etag =
+                    fs::read_to_string(visor_binary_directory.join(format!(".{name}.etag")))
+                        .ok()
+                        .map(|etag| etag.trim().to_string());
+                match &local_etag {
+                    Some(etag) => writeln!(out, "local etag: {etag}")?,
+                    None => writeln!(out, "local etag: not downloaded by hl-bootstrap")?,
+                }
+                if binary_path.exists() {
+                    match sha256sum(&binary_path) {
+                        Ok(hash) => writeln!(out, "sha256: {hash}")?,
+                        Err(err) => writeln!(out, "sha256: unavailable ({err})")?,
+                    }
+                }
+            }
+
+            match fetch_etag(&binary_url(self.network, name)).await {
+                Ok(upstream_etag) if self.visor_binary_directory.is_some() => {
+                    let up_to_date = local_etag.as_deref() == Some(upstream_etag.as_str());
+                    writeln!(
+                        out,
+                        "upstream etag: {upstream_etag} (up to date: {up_to_date})"
+                    )?;
+                }
+                Ok(upstream_etag) => writeln!(out, "upstream etag: {upstream_etag}")?,
+                Err(err) => writeln!(out, "upstream etag: unavailable ({err:#})")?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,313 @@
+use std::{fmt::Write, path::Path as FsPath, sync::Arc};
+
+use clap::Args;
+use eyre::{Context, ContextCompat, bail};
+use futures::{StreamExt, TryStreamExt};
+use hmac_sha256::Hash;
+use object_store::{
+    ObjectStore, ObjectStoreScheme, aws::AmazonS3Builder, azure::MicrosoftAzureBuilder,
+    buffered::BufWriter, gcp::GoogleCloudStorageBuilder, local::LocalFileSystem, path::Path,
+};
+use reqwest::Url;
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, copy},
+};
+use tracing::instrument;
+
+/// Objects above this are uploaded in parts of this size, S3 requires parts of at least 5MiB
+const MULTIPART_PART_SIZE: usize = 64 * 1024 * 1024;
+
+/// Object storage for backups, exports & migrations: S3-compatible storage (AWS, MinIO, R2, ...) configured through
+/// the --s3-* flags, or any supported backend through --storage-url
+#[derive(Clone, Debug, Args)]
+pub struct StorageArgs {
+    /// Object storage to use instead of the --s3-* flags, e.g. `s3://bucket/prefix/`, `gs://bucket/prefix/`,
+    /// `az://container/prefix/` or `file:///mnt/backups/`. Credentials are taken from the environment (AWS_*, GOOGLE_*,
+    /// AZURE_*) or the instance metadata service
+    #[arg(long, env = "HL_BOOTSTRAP_STORAGE_URL")]
+    pub storage_url: Option<Url>,
+
+    /// Object storage endpoint, e.g. `https://s3.eu-central-1.amazonaws.com` or a MinIO/R2 URL
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_S3_ENDPOINT",
+        default_value = "https://s3.amazonaws.com"
+    )]
+    pub s3_endpoint: Url,
+
+    /// Region the bucket is in, `auto` for R2
+    #[arg(long, env = "HL_BOOTSTRAP_S3_REGION", default_value = "us-east-1")]
+    pub s3_region: String,
+
+    /// Bucket to store backups & exports in
+    #[arg(long, env = "HL_BOOTSTRAP_S3_BUCKET")]
+    pub s3_bucket: Option<String>,
+
+    /// Key prefix within the bucket, e.g. `mainnet/node-1/`
+    #[arg(long, env = "HL_BOOTSTRAP_S3_PREFIX", default_value = "")]
+    pub s3_prefix: String,
+
+    /// Defaults to AWS_ACCESS_KEY_ID, or instance credentials when that's unset too
+    #[arg(long, env = "HL_BOOTSTRAP_S3_ACCESS_KEY_ID")]
+    pub s3_access_key_id: Option<String>,
+
+    #[arg(long, env = "HL_BOOTSTRAP_S3_SECRET_ACCESS_KEY")]
+    pub s3_secret_access_key: Option<String>,
+}
+
+impl StorageArgs {
+    pub fn is_configured(&self) -> bool {
+        self.storage_url.is_some() || self.s3_bucket.is_some()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Object {
+    pub key: String,
+    pub size: u64,
+    /// RFC 3339 in UTC, like S3 listings have it
+    pub last_modified: String,
+}
+
+/// Bucket (or container) of any object storage backend, with keys relative to its root
+#[derive(Clone, Debug)]
+pub struct Bucket {
+    store: Arc<dyn ObjectStore>,
+    pub prefix: String,
+}
+
+impl Bucket {
+    pub fn from_args(args: &StorageArgs) -> eyre::Result<Self> {
+        Self::connect(args, false)
+    }
+
+    /// Charges transfers to the account of the credentials rather than the bucket owner, only supported by S3.
+    /// Buckets with requester pays enabled reject requests without it
+    pub fn requester_pays(args: &StorageArgs) -> eyre::Result<Self> {
+        Self::connect(args, true)
+    }
+
+    fn connect(args: &StorageArgs, requester_pays: bool) -> eyre::Result<Self> {
+        let Some(url) = &args.storage_url else {
+            let name = args.s3_bucket.clone().wrap_err("--s3-bucket is not set")?;
+            let mut builder = AmazonS3Builder::from_env()
+                .with_endpoint(args.s3_endpoint.as_str().trim_end_matches('/'))
+                .with_allow_http(args.s3_endpoint.scheme() == "http")
+                .with_region(&args.s3_region)
+                .with_bucket_name(name)
+                .with_request_payer(requester_pays);
+            if let (Some(access_key_id), Some(secret_access_key)) =
+                (&args.s3_access_key_id, &args.s3_secret_access_key)
+            {
+                builder = builder
+                    .with_access_key_id(access_key_id)
+                    .with_secret_access_key(secret_access_key);
+            }
+            return Ok(Self {
+                store: Arc::new(builder.build().wrap_err("invalid S3 configuration")?),
+                prefix: args.s3_prefix.clone(),
+            });
+        };
+
+        let (scheme, path) = ObjectStoreScheme::parse(url)
+            .wrap_err_with(|| format!("unsupported --storage-url {url}"))?;
+        let store: Arc<dyn ObjectStore> = match scheme {
+            ObjectStoreScheme::AmazonS3 => Arc::new(
+                AmazonS3Builder::from_env()
+                    .with_url(url.as_str())
+                    .with_request_payer(requester_pays)
+                    .build()?,
+            ),
+            ObjectStoreScheme::GoogleCloudStorage => Arc::new(
+                GoogleCloudStorageBuilder::from_env()
+                    .with_url(url.as_str())
+                    .build()?,
+            ),
+            ObjectStoreScheme::MicrosoftAzure => Arc::new(
+                MicrosoftAzureBuilder::from_env()
+                    .with_url(url.as_str())
+                    .build()?,
+            ),
+            // The path is kept as the prefix, so keys look the same as in a bucket
+            ObjectStoreScheme::Local => Arc::new(LocalFileSystem::new()),
+            _ => bail!("unsupported --storage-url {url}, expected s3://, gs://, az:// or file://"),
+        };
+        if requester_pays && scheme != ObjectStoreScheme::AmazonS3 {
+            bail!("requester pays is only supported by S3");
+        }
+
+        let mut prefix = path.to_string();
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+        Ok(Self { store, prefix })
+    }
+
+    /// Lists objects whose key starts with the prefix, which is relative to the bucket rather than --s3-prefix
+    #[instrument(skip(self))]
+    pub async fn list(&self, prefix: &str) -> eyre::Result<Vec<Object>> {
+        // Object stores list by directory, the rest of the prefix is matched here
+        let directory = prefix
+            .rsplit_once('/')
+            .map_or("", |(directory, _)| directory);
+        let directory = (!directory.is_empty())
+            .then(|| Path::parse(directory))
+            .transpose()?;
+        let objects: Vec<_> = self
+            .store
+            .list(directory.as_ref())
+            .try_collect()
+            .await
+            .wrap_err_with(|| format!("failed to list {prefix}"))?;
+
+        Ok(objects
+            .into_iter()
+            .map(|meta| Object {
+                key: meta.location.to_string(),
+                size: meta.size,
+                last_modified: meta
+                    .last_modified
+                    .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                    .to_string(),
+            })
+            .filter(|object| object.key.starts_with(prefix))
+            .collect())
+    }
+
+    pub async fn put(&self, key: &str, body: Vec<u8>) -> eyre::Result<()> {
+        self.store
+            .put(&Path::parse(key)?, body.into())
+            .await
+            .wrap_err_with(|| format!("failed to upload {key}"))?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, key: &str) -> eyre::Result<()> {
+        self.store
+            .delete(&Path::parse(key)?)
+            .await
+            .wrap_err_with(|| format!("failed to delete {key}"))
+    }
+
+    pub async fn get_text(&self, key: &str) -> eyre::Result<String> {
+        let bytes = self
+            .store
+            .get(&Path::parse(key)?)
+            .await
+            .wrap_err_with(|| format!("failed to download {key}"))?
+            .bytes()
+            .await?;
+        Ok(String::from_utf8(bytes.to_vec())?)
+    }
+
+    /// Uploads the file in parts, without holding more than a few parts in memory
+    #[instrument(skip(self))]
+    pub async fn upload_file(&self, key: &str, path: &FsPath) -> eyre::Result<()> {
+        let mut file = File::open(path)
+            .await
+            .wrap_err_with(|| format!("failed to open {path:?}"))?;
+        let mut writer = BufWriter::with_capacity(
+            Arc::clone(&self.store),
+            Path::parse(key)?,
+            MULTIPART_PART_SIZE,
+        );
+        let result = async {
+            copy(&mut file, &mut writer).await?;
+            writer.shutdown().await
+        }
+        .await;
+
+        if let Err(err) = result {
+            // Parts of abandoned uploads are stored (and billed) until aborted
+            let _ = writer.abort().await;
+            return Err(err).wrap_err_with(|| format!("failed to upload {key}"));
+        }
+        Ok(())
+    }
+
+    /// Hex encoded sha256 of the object, streamed rather than downloaded
+    #[instrument(skip(self))]
+    pub async fn sha256(&self, key: &str) -> eyre::Result<String> {
+        let mut stream = self
+            .store
+            .get(&Path::parse(key)?)
+            .await
+            .wrap_err_with(|| format!("failed to download {key}"))?
+            .into_stream();
+        let mut hash = Hash::new();
+        while let Some(chunk) = stream.next().await {
+            hash.update(&chunk?);
+        }
+        Ok(hex(&hash.finalize()))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn download_file(&self, key: &str, path: &FsPath) -> eyre::Result<()> {
+        let mut stream = self
+            .store
+            .get(&Path::parse(key)?)
+            .await
+            .wrap_err_with(|| format!("failed to download {key}"))?
+            .into_stream();
+        let mut file = File::create(path)
+            .await
+            .wrap_err_with(|| format!("failed to create {path:?}"))?;
+        while let Some(chunk) = stream.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{byte:02x}");
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_storage() {
+        let directory = tempfile::tempdir().unwrap();
+        let url = Url::from_directory_path(directory.path()).unwrap();
+        let bucket = Bucket::from_args(&StorageArgs {
+            storage_url: Some(url),
+            s3_endpoint: "https://s3.amazonaws.com".parse().unwrap(),
+            s3_region: "us-east-1".to_string(),
+            s3_bucket: None,
+            s3_prefix: String::new(),
+            s3_access_key_id: None,
+            s3_secret_access_key: None,
+        })
+        .unwrap();
+
+        let backup = format!("{}backups/hl-backup-1.tar.zst", bucket.prefix);
+        bucket.put(&backup, b"backup".to_vec()).await.unwrap();
+        bucket
+            .put(&format!("{}backups/other", bucket.prefix), Vec::new())
+            .await
+            .unwrap();
+
+        // Prefixes don't have to end at a directory
+        let listed = bucket
+            .list(&format!("{}backups/hl-backup-", bucket.prefix))
+            .await
+            .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].key, backup);
+        assert_eq!(listed[0].size, 6);
+        assert_eq!(
+            bucket.sha256(&backup).await.unwrap(),
+            hex(&Hash::hash(b"backup"))
+        );
+
+        bucket.delete(&backup).await.unwrap();
+        assert!(bucket.get_text(&backup).await.is_err());
+    }
+}
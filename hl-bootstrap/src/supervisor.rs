@@ -0,0 +1,382 @@
+use std::{
+    os::unix::process::ExitStatusExt,
+    path::PathBuf,
+    process::{ExitStatus, Stdio},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use bytesize::ByteSize;
+use clap::Args;
+use duration_string::DurationString;
+use eyre::{Context, ContextCompat};
+use nix::{
+    sys::signal::{Signal, kill},
+    unistd::Pid,
+};
+use sd_notify::NotifyState;
+use tokio::{
+    process::{Child, Command},
+    signal::unix::{SignalKind, signal},
+    sync::Notify,
+    time::{Instant, sleep, timeout},
+};
+use tracing::{info, warn};
+
+use crate::{
+    alerts::{AlertEvent, alert, send_alert},
+    diagnostics::CrashDiagnostics,
+    hooks::Hooks,
+    maintenance, reaper,
+    restart_lock::RestartLock,
+    status::{self, ChildState, Phase},
+    supervisor::output::capture_output,
+    systemd,
+};
+
+pub mod limits;
+pub mod log_file;
+pub mod output;
+pub mod privileges;
+
+#[derive(Clone, Debug, Args)]
+pub struct SupervisorArgs {
+    /// How long to wait for the child to exit after forwarding SIGTERM/SIGINT before killing it
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_SHUTDOWN_TIMEOUT",
+        default_value = "30s"
+    )]
+    pub child_shutdown_timeout: DurationString,
+
+    /// How many times to restart the child after it exits with a failure. 0 disables restarts
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_RESTART_MAX_RETRIES",
+        default_value_t = 0
+    )]
+    pub child_restart_max_retries: u32,
+
+    /// Delay before the first restart, doubled on every consecutive restart
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_RESTART_BACKOFF", default_value = "1s")]
+    pub child_restart_backoff: DurationString,
+
+    /// Upper bound for the restart delay
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_RESTART_MAX_BACKOFF",
+        default_value = "5m"
+    )]
+    pub child_restart_max_backoff: DurationString,
+
+    /// If the child ran at least this long before failing, retry counter and backoff are reset
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_RESTART_RESET_AFTER",
+        default_value = "10m"
+    )]
+    pub child_restart_reset_after: DurationString,
+
+    /// Capture child stdout/stderr and re-emit it through hl-bootstrap logging instead of inheriting stdio
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_CAPTURE_OUTPUT",
+        default_value_t = false
+    )]
+    pub child_capture_output: bool,
+
+    /// Write captured child output into this file instead of hl-bootstrap logging. Implies --child-capture-output
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_LOG_FILE")]
+    pub child_log_file: Option<PathBuf>,
+
+    /// Rotate the child log file once it reaches this size
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_LOG_MAX_SIZE",
+        default_value = "100MiB"
+    )]
+    pub child_log_max_size: ByteSize,
+
+    /// Rotate the child log file after this long, regardless of its size
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_LOG_ROTATE_INTERVAL")]
+    pub child_log_rotate_interval: Option<DurationString>,
+
+    /// How many rotated child log files to keep
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_LOG_RETENTION", default_value_t = 10)]
+    pub child_log_retention: usize,
+
+    /// Become a child subreaper and reap orphaned processes (e.g. hl-node left behind by hl-visor).
+    /// Always enabled when running as PID 1
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_SUBREAPER", default_value_t = false)]
+    pub child_subreaper: bool,
+}
+
+static RESTART_REQUESTED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// Asks the supervisor to gracefully stop the running child and start it again, alerting the event of the caller.
+/// Returns false if the request was ignored due to maintenance mode.
+pub fn request_restart(event: AlertEvent, reason: &str) -> bool {
+    if maintenance::is_enabled() {
+        warn!(
+            reason,
+            "child restart requested, but ignored due to maintenance mode"
+        );
+        return false;
+    }
+
+    warn!(reason, "child restart requested");
+    alert(event, format!("child restart requested: {reason}"));
+    RESTART_REQUESTED.notify_one();
+    true
+}
+
+impl SupervisorArgs {
+    pub fn capture_output(&self) -> bool {
+        self.child_capture_output || self.child_log_file.is_some()
+    }
+
+    fn restart_delay(&self, retry: u32) -> Duration {
+        let backoff: Duration = self.child_restart_backoff.into();
+        backoff
+            .saturating_mul(2_u32.saturating_pow(retry))
+            .min(self.child_restart_max_backoff.into())
+    }
+}
+
+/// Spawns the child and waits for it to exit, restarting it on failures according to the restart policy.
+/// SIGTERM/SIGINT are forwarded to the child, and if it does not exit within the shutdown timeout, it gets SIGKILL.
+pub async fn supervise(
+    mut command: Command,
+    args: &SupervisorArgs,
+    hooks: &Hooks,
+    diagnostics: Option<&CrashDiagnostics>,
+    restart_lock: Option<&RestartLock>,
+) -> eyre::Result<ExitStatus> {
+    let mut sigterm =
+        signal(SignalKind::terminate()).wrap_err("failed to set up SIGTERM handler")?;
+    let mut sigint = signal(SignalKind::interrupt()).wrap_err("failed to set up SIGINT handler")?;
+
+    if args.capture_output() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let mut retries = 0;
+    let mut held_restart_lock = None;
+    loop {
+        let started_at = Instant::now();
+        let mut child = reaper::spawn(&mut command).wrap_err("failed to spawn child")?;
+        let pid = child.id().wrap_err("spawned child has no pid")?;
+        info!(pid, "child started");
+        status::update(|status| {
+            status.child.pid = Some(pid);
+            status.child.state = ChildState::Running;
+            status.child.node_responding = None;
+            status.child.down_since_ms = None;
+        });
+        systemd::notify(&[
+            NotifyState::Ready,
+            NotifyState::Status(&format!("hl-visor running (pid {pid})")),
+        ]);
+
+        if args.capture_output() {
+            capture_output(&mut child);
+        }
+
+        if let Some(restart_lock) = held_restart_lock.take() {
+            tokio::spawn(RestartLock::release_after_recovery(restart_lock));
+        }
+
+        // Waiting for the restart lock is part of the restart request, so the child exiting or a signal received
+        // meanwhile is handled as usual. A signal stops the child instead of restarting it
+        let restart = async {
+            RESTART_REQUESTED.notified().await;
+            if let Some(restart_lock) = restart_lock {
+                restart_lock.acquire().await;
+            }
+        };
+
+        let signal = tokio::select! {
+            status = child.wait() => {
+                let status = status.wrap_err("failed to wait for child")?;
+                mark_child_exited(status);
+                hooks.post_exit(pid, status).await;
+                if status.success() {
+                    return Ok(status);
+                }
+
+                if let Some(diagnostics) = diagnostics {
+                    diagnostics.collect(pid, status).await;
+                }
+
+                if started_at.elapsed() >= args.child_restart_reset_after {
+                    retries = 0;
+                }
+                if retries >= args.child_restart_max_retries {
+                    send_alert(
+                        AlertEvent::ChildCrashed,
+                        &format!("child (pid {pid}) failed with {status}, not restarting"),
+                    )
+                    .await;
+                    return Ok(status);
+                }
+                status::update(|status| {
+                    status.child.state = ChildState::Restarting;
+                    status.child.restarts += 1;
+                    status
+                        .child
+                        .down_since_ms
+                        .get_or_insert_with(status::unix_ms_now);
+                });
+
+                let delay = args.restart_delay(retries);
+                retries += 1;
+                warn!(
+                    %status,
+                    retries,
+                    max_retries = args.child_restart_max_retries,
+                    ?delay,
+                    "child failed, restarting"
+                );
+                alert(
+                    AlertEvent::ChildCrashed,
+                    format!(
+                        "child (pid {pid}) failed with {status}, restarting in {delay:?} (retry {retries}/{})",
+                        args.child_restart_max_retries
+                    ),
+                );
+
+                tokio::select! {
+                    _ = sleep(delay) => continue,
+                    _ = sigterm.recv() => return Ok(status),
+                    _ = sigint.recv() => return Ok(status),
+                }
+            }
+            _ = restart => {
+                held_restart_lock = restart_lock.cloned();
+                status::update(|status| {
+                    status.child.state = ChildState::Restarting;
+                    status.child.restarts += 1;
+                    status
+                        .child
+                        .down_since_ms
+                        .get_or_insert_with(status::unix_ms_now);
+                });
+                let status = shutdown_child(
+                    &mut child,
+                    Signal::SIGTERM,
+                    args.child_shutdown_timeout.into(),
+                )
+                .await?;
+                info!(%status, "child stopped for restart");
+                alert(
+                    AlertEvent::ChildRestarted,
+                    format!("child (pid {pid}) stopped with {status} for restart"),
+                );
+                reaper::unmanage(pid);
+                hooks.post_exit(pid, status).await;
+                continue;
+            }
+            _ = sigterm.recv() => Signal::SIGTERM,
+            _ = sigint.recv() => Signal::SIGINT,
+        };
+
+        status::set_phase(Phase::Stopping);
+        systemd::notify(&[NotifyState::Stopping]);
+        let status = shutdown_child(&mut child, signal, args.child_shutdown_timeout.into()).await?;
+        mark_child_exited(status);
+        hooks.post_exit(pid, status).await;
+
+        return Ok(status);
+    }
+}
+
+/// Exit code to propagate for the child exit status, following the shell convention of 128+N for signal deaths
+pub fn exit_code(status: ExitStatus) -> i32 {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => code,
+        (None, Some(signal)) => 128 + signal,
+        (None, None) => 1,
+    }
+}
+
+fn mark_child_exited(exit_status: ExitStatus) {
+    status::update(|status| {
+        if let Some(pid) = status.child.pid {
+            reaper::unmanage(pid);
+        }
+        status.child.pid = None;
+        status.child.state = ChildState::Exited;
+        status.child.node_responding = None;
+        status
+            .child
+            .down_since_ms
+            .get_or_insert_with(status::unix_ms_now);
+        status.child.last_exit_code = exit_status.code();
+    });
+}
+
+async fn shutdown_child(
+    child: &mut Child,
+    signal: Signal,
+    shutdown_timeout: Duration,
+) -> eyre::Result<ExitStatus> {
+    // Child could have exited on its own in the meantime
+    let Some(pid) = child.id() else {
+        return child.wait().await.wrap_err("failed to wait for child");
+    };
+
+    info!(pid, %signal, ?shutdown_timeout, "forwarding signal to child");
+    if let Err(err) = kill(Pid::from_raw(pid as i32), signal) {
+        warn!(?err, pid, %signal, "failed to forward signal to child");
+    }
+
+    match timeout(shutdown_timeout, child.wait()).await {
+        Ok(status) => status.wrap_err("failed to wait for child"),
+        Err(_) => {
+            warn!(
+                pid,
+                ?shutdown_timeout,
+                "child did not exit in time, killing"
+            );
+            child.kill().await.wrap_err("failed to kill child")?;
+            child.wait().await.wrap_err("failed to wait for child")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct TestCli {
+        #[command(flatten)]
+        supervisor: SupervisorArgs,
+    }
+
+    #[test]
+    fn test_restart_delay() {
+        let args = TestCli::parse_from([
+            "test",
+            "--child-restart-backoff=1s",
+            "--child-restart-max-backoff=10s",
+        ])
+        .supervisor;
+
+        assert_eq!(args.restart_delay(0), Duration::from_secs(1));
+        assert_eq!(args.restart_delay(1), Duration::from_secs(2));
+        assert_eq!(args.restart_delay(3), Duration::from_secs(8));
+        assert_eq!(args.restart_delay(4), Duration::from_secs(10));
+        assert_eq!(args.restart_delay(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(exit_code(ExitStatus::from_raw(0)), 0);
+        assert_eq!(exit_code(ExitStatus::from_raw(3 << 8)), 3);
+        assert_eq!(exit_code(ExitStatus::from_raw(libc::SIGKILL)), 137);
+        assert_eq!(exit_code(ExitStatus::from_raw(libc::SIGTERM)), 143);
+    }
+}
@@ -0,0 +1,318 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use bytesize::ByteSize;
+use clap::{Args, ValueEnum};
+use eyre::{Context, ContextCompat, bail};
+use nix::sys::resource::{Resource, getrlimit, setrlimit};
+use tracing::{debug, info, warn};
+
+#[derive(Clone, Debug, Args)]
+pub struct ResourceArgs {
+    /// Niceness of the child process, from -20 (highest priority) to 19 (lowest priority)
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_NICE",
+        allow_hyphen_values = true,
+        value_parser = clap::value_parser!(i32).range(-20..=19)
+    )]
+    pub child_nice: Option<i32>,
+
+    /// I/O scheduling class of the child process
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_IONICE_CLASS")]
+    pub child_ionice_class: Option<IoniceClass>,
+
+    /// I/O scheduling priority within the class, from 0 (highest) to 7 (lowest)
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_CHILD_IONICE_LEVEL",
+        default_value_t = 4,
+        value_parser = clap::value_parser!(u8).range(0..=7)
+    )]
+    pub child_ionice_level: u8,
+
+    /// CPUs the child is allowed to run on, e.g. `0-7,16`
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_CPU_AFFINITY")]
+    pub child_cpu_affinity: Option<CpuList>,
+
+    /// cgroup v2 memory limit (memory.max) for the child, e.g. `48GiB`
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_MEMORY_MAX")]
+    pub child_memory_max: Option<ByteSize>,
+
+    /// cgroup v2 CPU limit (cpu.max) for the child in cores, e.g. `7.5`
+    #[arg(long, env = "HL_BOOTSTRAP_CHILD_CPU_MAX")]
+    pub child_cpu_max: Option<f64>,
+
+    /// Open files limit (RLIMIT_NOFILE) to raise to before starting the child, which inherits it. The soft limit can
+    /// go up to the hard limit, raising the hard limit needs CAP_SYS_RESOURCE
+    #[arg(long, env = "HL_BOOTSTRAP_OPEN_FILES_LIMIT", default_value_t = 1048576)]
+    pub open_files_limit: u64,
+
+    /// Refuse to start the child when the open files limit can't be raised to at least this
+    #[arg(long, env = "HL_BOOTSTRAP_OPEN_FILES_MIN", default_value_t = 65536)]
+    pub open_files_min: u64,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum IoniceClass {
+    Realtime,
+    BestEffort,
+    Idle,
+}
+
+impl IoniceClass {
+    /// IOPRIO_CLASS_* values from linux/ioprio.h
+    fn as_raw(&self) -> libc::c_int {
+        match self {
+            Self::Realtime => 1,
+            Self::BestEffort => 2,
+            Self::Idle => 3,
+        }
+    }
+}
+
+/// CPU list in the format used by taskset(1) and /sys/devices/system/cpu/online
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CpuList(pub Vec<usize>);
+
+impl FromStr for CpuList {
+    type Err = eyre::ErrReport;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut cpus = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+            match part.split_once('-') {
+                Some((start, end)) => {
+                    let start: usize = start.parse().wrap_err("invalid cpu range start")?;
+                    let end: usize = end.parse().wrap_err("invalid cpu range end")?;
+                    if start > end {
+                        bail!("invalid cpu range '{part}'");
+                    }
+                    cpus.extend(start..=end);
+                }
+                None => cpus.push(part.parse().wrap_err("invalid cpu number")?),
+            }
+        }
+
+        if cpus.is_empty() {
+            bail!("cpu list is empty");
+        }
+        if let Some(cpu) = cpus.iter().find(|cpu| **cpu >= libc::CPU_SETSIZE as usize) {
+            bail!("cpu {cpu} is out of range");
+        }
+
+        Ok(Self(cpus))
+    }
+}
+
+/// Resource limits prepared in the parent process, applied to the child between fork and exec
+#[derive(Clone, Debug, Default)]
+pub struct ChildLimits {
+    nice: Option<i32>,
+    ioprio: Option<libc::c_int>,
+    cpu_affinity: Option<Vec<usize>>,
+    cgroup_procs: Option<CString>,
+}
+
+impl ChildLimits {
+    pub fn prepare(args: &ResourceArgs) -> eyre::Result<Self> {
+        let cgroup_procs = if args.child_memory_max.is_some() || args.child_cpu_max.is_some() {
+            let cgroup = prepare_child_cgroup(args)?;
+            Some(CString::new(
+                cgroup.join("cgroup.procs").as_os_str().as_bytes(),
+            )?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            nice: args.child_nice,
+            ioprio: args
+                .child_ionice_class
+                .map(|class| (class.as_raw() << 13) | args.child_ionice_level as libc::c_int),
+            cpu_affinity: args.child_cpu_affinity.clone().map(|cpus| cpus.0),
+            cgroup_procs,
+        })
+    }
+
+    pub fn apply_to(self, command: &mut Command) {
+        if self.nice.is_none()
+            && self.ioprio.is_none()
+            && self.cpu_affinity.is_none()
+            && self.cgroup_procs.is_none()
+        {
+            return;
+        }
+
+        // SAFETY: closure only does raw syscalls, no allocations or locking happen after the fork
+        unsafe {
+            command.pre_exec(move || self.apply_in_child());
+        }
+    }
+
+    fn apply_in_child(&self) -> io::Result<()> {
+        // Join the cgroup first, so everything spawned by the child ends up there as well
+        if let Some(cgroup_procs) = &self.cgroup_procs {
+            let fd = unsafe { libc::open(cgroup_procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // "0" refers to the writing process
+            let written = unsafe { libc::write(fd, b"0\n".as_ptr().cast(), 2) };
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(fd) };
+            if written < 0 {
+                return Err(err);
+            }
+        }
+
+        if let Some(nice) = self.nice
+            && unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        if let Some(ioprio) = self.ioprio {
+            const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+            if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ioprio) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        if let Some(cpus) = &self.cpu_affinity {
+            let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+            for cpu in cpus {
+                unsafe { libc::CPU_SET(*cpu, &mut set) };
+            }
+            if unsafe { libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Raises RLIMIT_NOFILE of hl-bootstrap towards the target, failing when it ends up below the minimum
+pub fn raise_open_files_limit(target: u64, min: u64) -> eyre::Result<()> {
+    let (soft, hard) =
+        getrlimit(Resource::RLIMIT_NOFILE).wrap_err("failed to get open files limit")?;
+    if soft >= target {
+        debug!(
+            soft,
+            hard, target, "open files limit is already at the target"
+        );
+        return Ok(());
+    }
+
+    // Raising the hard limit only works with CAP_SYS_RESOURCE and up to fs.nr_open
+    let limit = if hard < target && setrlimit(Resource::RLIMIT_NOFILE, target, target).is_ok() {
+        target
+    } else {
+        let limit = target.min(hard);
+        setrlimit(Resource::RLIMIT_NOFILE, limit, hard)
+            .wrap_err("failed to raise open files limit")?;
+        limit
+    };
+    info!(
+        before = soft,
+        after = limit,
+        hard,
+        target,
+        "raised open files limit"
+    );
+
+    if limit < min {
+        bail!(
+            "open files limit {limit} is below the required {min} as the hard limit is {hard}, raise it with \
+             LimitNOFILE= in the systemd unit, --ulimit nofile=1048576 for containers or limits.conf"
+        );
+    }
+    if limit < target {
+        warn!(
+            limit,
+            hard, target, "open files limit is capped by the hard limit"
+        );
+    }
+
+    Ok(())
+}
+
+/// Creates a cgroup for the child next to the current cgroup and sets up the limits.
+///
+/// cgroup v2 does not allow processes in cgroups which delegate controllers to their children, so hl-bootstrap moves
+/// itself into a `hl-bootstrap` leaf first and the child gets its own `hl-visor` leaf.
+fn prepare_child_cgroup(args: &ResourceArgs) -> eyre::Result<PathBuf> {
+    let own_cgroup = fs::read_to_string("/proc/self/cgroup")
+        .wrap_err("failed to read /proc/self/cgroup")?
+        .lines()
+        .find_map(|line| line.strip_prefix("0::").map(str::to_string))
+        .wrap_err("cgroup v2 is not available")?;
+
+    let base = Path::new("/sys/fs/cgroup").join(own_cgroup.trim_start_matches('/'));
+    let bootstrap_cgroup = base.join("hl-bootstrap");
+    let child_cgroup = base.join("hl-visor");
+    debug!(?base, "setting up child cgroup");
+
+    fs::create_dir_all(&bootstrap_cgroup)
+        .wrap_err_with(|| format!("failed to create cgroup {bootstrap_cgroup:?}"))?;
+    fs::write(bootstrap_cgroup.join("cgroup.procs"), "0\n")
+        .wrap_err("failed to move hl-bootstrap into its own cgroup")?;
+
+    let mut controllers = Vec::new();
+    if args.child_memory_max.is_some() {
+        controllers.push("+memory");
+    }
+    if args.child_cpu_max.is_some() {
+        controllers.push("+cpu");
+    }
+    fs::write(base.join("cgroup.subtree_control"), controllers.join(" "))
+        .wrap_err("failed to enable cgroup controllers")?;
+
+    fs::create_dir_all(&child_cgroup)
+        .wrap_err_with(|| format!("failed to create cgroup {child_cgroup:?}"))?;
+
+    if let Some(memory_max) = args.child_memory_max {
+        fs::write(
+            child_cgroup.join("memory.max"),
+            memory_max.as_u64().to_string(),
+        )
+        .wrap_err("failed to set memory.max")?;
+    }
+    if let Some(cpu_max) = args.child_cpu_max {
+        let period = 100_000_u64;
+        let quota = (cpu_max * period as f64).round() as u64;
+        fs::write(child_cgroup.join("cpu.max"), format!("{quota} {period}"))
+            .wrap_err("failed to set cpu.max")?;
+    }
+
+    info!(
+        cgroup = ?child_cgroup,
+        memory_max = ?args.child_memory_max,
+        cpu_max = ?args.child_cpu_max,
+        "child cgroup set up"
+    );
+
+    Ok(child_cgroup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpu_list() -> eyre::Result<()> {
+        assert_eq!("0-3,8".parse::<CpuList>()?, CpuList(vec![0, 1, 2, 3, 8]));
+        assert_eq!("5".parse::<CpuList>()?, CpuList(vec![5]));
+        assert!("3-1".parse::<CpuList>().is_err());
+        assert!("".parse::<CpuList>().is_err());
+
+        Ok(())
+    }
+}
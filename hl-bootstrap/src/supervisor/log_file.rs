@@ -0,0 +1,176 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bytesize::ByteSize;
+use eyre::Context;
+use tokio::sync::mpsc::{Sender, channel};
+use tracing::{debug, warn};
+
+use crate::status::unix_ms_now;
+
+/// Lines queued for the writer thread before the child output readers wait, and with them the child
+const QUEUE_SIZE: usize = 1024;
+
+/// Captured child output is written here instead of being re-emitted through logging, when configured
+static CHILD_LOG: Mutex<Option<Sender<String>>> = Mutex::new(None);
+
+/// Hands the log to a dedicated writer thread, so writing & rotating it never blocks the runtime
+pub fn set_child_log(mut log: RotatingLog) {
+    let (sender, mut receiver) = channel::<String>(QUEUE_SIZE);
+    std::thread::spawn(move || {
+        while let Some(line) = receiver.blocking_recv() {
+            if let Err(err) = log.write_line(&line) {
+                warn!(?err, path = ?log.path, "failed to write child log");
+            }
+        }
+    });
+    *CHILD_LOG.lock().unwrap() = Some(sender);
+}
+
+/// Queues the line for the child log file. Returns false if the child log file is not configured
+pub async fn write_child_log(line: String) -> bool {
+    let Some(sender) = CHILD_LOG.lock().unwrap().clone() else {
+        return false;
+    };
+
+    // Only fails if the writer thread panicked
+    let _ = sender.send(line).await;
+    true
+}
+
+/// Log file rotated by size and optionally by age. Rotated files get the rotation time in ms as a suffix,
+/// e.g. `hl-visor.log.1752000000000`, bumped to stay unique within the same millisecond. Only the newest `retention`
+/// of them are kept.
+pub struct RotatingLog {
+    path: PathBuf,
+    max_size: ByteSize,
+    rotate_interval: Option<Duration>,
+    retention: usize,
+    file: File,
+    size: u64,
+    opened_at: Instant,
+    last_suffix: u64,
+}
+
+impl RotatingLog {
+    pub fn open(
+        path: PathBuf,
+        max_size: ByteSize,
+        rotate_interval: Option<Duration>,
+        retention: usize,
+    ) -> eyre::Result<Self> {
+        let file = open_append(&path)
+            .wrap_err_with(|| format!("failed to open child log file {path:?}"))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size,
+            rotate_interval,
+            retention,
+            file,
+            size,
+            opened_at: Instant::now(),
+            last_suffix: 0,
+        })
+    }
+
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let due_by_age = self
+            .rotate_interval
+            .is_some_and(|interval| self.opened_at.elapsed() >= interval);
+        if self.size > 0 && (self.size + line.len() as u64 >= self.max_size.as_u64() || due_by_age)
+        {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.file.write_all(b"\n")?;
+        self.size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.last_suffix = unix_ms_now().max(self.last_suffix + 1);
+        let rotated = suffixed(&self.path, &self.last_suffix.to_string());
+        debug!(path = ?self.path, ?rotated, "rotating child log");
+        fs::rename(&self.path, &rotated)?;
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+
+        self.remove_old()
+    }
+
+    fn remove_old(&self) -> io::Result<()> {
+        let (Some(dir), Some(file_name)) = (self.path.parent(), self.path.file_name()) else {
+            return Ok(());
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        let prefix = format!("{}.", file_name.to_string_lossy());
+
+        let mut rotated: Vec<(u64, PathBuf)> = fs::read_dir(dir)?
+            .flatten()
+            .filter_map(|entry| {
+                let name = entry.file_name();
+                let suffix = name.to_str()?.strip_prefix(&prefix)?;
+                Some((suffix.parse().ok()?, entry.path()))
+            })
+            .collect();
+        rotated.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+
+        for (_, path) in rotated.into_iter().skip(self.retention) {
+            debug!(?path, "removing old child log");
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    name.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_retention() -> eyre::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("child.log");
+        let mut log = RotatingLog::open(path.clone(), ByteSize::b(16), None, 2)?;
+
+        for i in 0..5 {
+            log.write_line(&format!("line number {i}"))?;
+        }
+
+        let mut files: Vec<_> = fs::read_dir(dir.path())?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<_, _>>()?;
+        files.sort();
+        assert_eq!(files.len(), 3, "{files:?}");
+        assert_eq!(fs::read_to_string(&path)?, "line number 4\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, BufReader},
+    process::Child,
+};
+use tracing::{info, warn};
+
+use crate::{status::unix_ms_now, supervisor::log_file::write_child_log};
+
+/// Last lines of captured child output, kept around for crash diagnostics
+struct RecentOutput {
+    capacity: usize,
+    lines: VecDeque<String>,
+}
+
+static RECENT_OUTPUT: Mutex<RecentOutput> = Mutex::new(RecentOutput {
+    capacity: 0,
+    lines: VecDeque::new(),
+});
+
+pub fn set_recent_output_capacity(capacity: usize) {
+    let mut recent = RECENT_OUTPUT.lock().unwrap();
+    recent.capacity = capacity;
+    while recent.lines.len() > capacity {
+        recent.lines.pop_front();
+    }
+}
+
+pub fn recent_output() -> Vec<String> {
+    RECENT_OUTPUT
+        .lock()
+        .unwrap()
+        .lines
+        .iter()
+        .cloned()
+        .collect()
+}
+
+fn remember_line(stream: OutputStream, line: &str) {
+    let mut recent = RECENT_OUTPUT.lock().unwrap();
+    if recent.capacity == 0 {
+        return;
+    }
+    if recent.lines.len() >= recent.capacity {
+        recent.lines.pop_front();
+    }
+    recent
+        .lines
+        .push_back(format!("{} {}: {line}", unix_ms_now(), stream.as_str()));
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        }
+    }
+}
+
+/// Takes piped stdout/stderr from the child and re-emits every line through tracing, or writes it to the child log file
+pub fn capture_output(child: &mut Child) {
+    let pid = child.id();
+
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_lines(stdout, OutputStream::Stdout, pid));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_lines(stderr, OutputStream::Stderr, pid));
+    }
+}
+
+async fn forward_lines(reader: impl AsyncRead + Unpin, stream: OutputStream, pid: Option<u32>) {
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf).await {
+            Ok(0) => break,
+            Ok(_) => {
+                // hl-visor & hl-node output is not guaranteed to be valid UTF-8
+                let line = String::from_utf8_lossy(&buf);
+                let line = line.trim_end_matches(['\n', '\r']);
+                let logged = write_child_log(format!(
+                    "{} {} {}: {line}",
+                    unix_ms_now(),
+                    stream.as_str(),
+                    pid.unwrap_or_default()
+                ))
+                .await;
+                if !logged {
+                    info!(target: "hl_visor", stream = stream.as_str(), pid, "{line}");
+                }
+                remember_line(stream, line);
+            }
+            Err(err) => {
+                warn!(
+                    ?err,
+                    stream = stream.as_str(),
+                    pid,
+                    "failed to read child output"
+                );
+                break;
+            }
+        }
+    }
+}
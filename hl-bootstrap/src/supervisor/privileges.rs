@@ -0,0 +1,110 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{
+        fs::{MetadataExt, lchown},
+        process::CommandExt,
+    },
+    path::Path,
+    process::Command,
+};
+
+use eyre::{Context, ContextCompat, bail};
+use nix::unistd::{Uid, User, getgrouplist};
+use tracing::info;
+
+/// Unprivileged user the child is started as, once hl-bootstrap is done with the setup needing root. Groups are
+/// resolved upfront, as nothing may be allocated between fork & exec
+#[derive(Clone, Debug)]
+pub struct RunAs {
+    user: User,
+    groups: Vec<libc::gid_t>,
+}
+
+impl RunAs {
+    pub fn resolve(name: &str) -> eyre::Result<Self> {
+        let user = User::from_name(name)
+            .wrap_err_with(|| format!("failed to look up user {name}"))?
+            .wrap_err_with(|| format!("user {name} does not exist"))?;
+        if user.uid.is_root() {
+            bail!("user {name} is root, pass an unprivileged user to --run-as-user");
+        }
+        if !Uid::effective().is_root() {
+            bail!("--run-as-user needs hl-bootstrap to be started as root");
+        }
+
+        let groups = getgrouplist(&CString::new(name)?, user.gid)
+            .wrap_err_with(|| format!("failed to get groups of user {name}"))?
+            .into_iter()
+            .map(|gid| gid.as_raw())
+            .collect();
+
+        Ok(Self { user, groups })
+    }
+
+    /// hl-node writes into the data directory and hl/ in it, which have to belong to the user. Not fixed up
+    /// automatically, as chowning an existing hl/ recursively can take a long time
+    pub fn check_ownership(&self, data_directory: &Path) -> eyre::Result<()> {
+        for path in [data_directory.to_path_buf(), data_directory.join("hl")] {
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            if metadata.uid() != self.user.uid.as_raw() {
+                bail!(
+                    "{} is not owned by {}, which the node runs as. Fix it with `chown -R {1}: {0}`",
+                    path.display(),
+                    self.user.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Hands over files hl-bootstrap created for the node, symlinks themselves rather than their targets
+    pub fn chown_recursive(&self, path: &Path) -> eyre::Result<()> {
+        self.chown(path)?;
+        if fs::symlink_metadata(path)?.is_dir() {
+            for entry in fs::read_dir(path)? {
+                self.chown_recursive(&entry?.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn chown(&self, path: &Path) -> eyre::Result<()> {
+        lchown(
+            path,
+            Some(self.user.uid.as_raw()),
+            Some(self.user.gid.as_raw()),
+        )
+        .wrap_err_with(|| format!("failed to chown {path:?}"))
+    }
+
+    pub fn apply_to(self, command: &mut Command) {
+        info!(user = self.user.name, uid = %self.user.uid, gid = %self.user.gid, "child runs unprivileged");
+        command
+            .env("HOME", &self.user.dir)
+            .env("USER", &self.user.name)
+            .env("LOGNAME", &self.user.name);
+
+        // SAFETY: closure only does raw syscalls, no allocations or locking happen after the fork
+        unsafe {
+            command.pre_exec(move || self.apply_in_child());
+        }
+    }
+
+    /// Runs after the resource limits were applied, as joining the cgroup & raising the priority need root
+    fn apply_in_child(&self) -> io::Result<()> {
+        // Groups first, changing them needs the privileges given up by setuid
+        if unsafe { libc::setgroups(self.groups.len(), self.groups.as_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::setgid(self.user.gid.as_raw()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::setuid(self.user.uid.as_raw()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,163 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use eyre::Context;
+use eyre::{Context, bail};
+use tracing::{info, warn};
+
+/// Sysctls hl-node runs best with, as (key, value, whether only raising the current value)
+pub const RECOMMENDED_SYSCTLS: &[(&str, &str, bool)] = &[
+    // hl-node picks up IPv6 addresses as its public IP and fails to parse them
+    ("net.ipv6.conf.all.disable_ipv6", "1", false),
+    ("net.ipv6.conf.default.disable_ipv6", "1", false),
+    // Gossip & RPC traffic is bursty
+    ("net.core.rmem_max", "16777216", true),
+    ("net.core.wmem_max", "16777216", true),
+    ("net.core.netdev_max_backlog", "16384", true),
+    ("net.core.somaxconn", "4096", true),
+    ("net.ipv4.tcp_max_syn_backlog", "8192", true),
+    // Every gossip peer & RPC client is a tracked connection when conntrack is loaded
+    ("net.netfilter.nf_conntrack_max", "262144", true),
+];
+
+const TRANSPARENT_HUGEPAGE_ENABLED: &str = "/sys/kernel/mm/transparent_hugepage/enabled";
+
+/// Connection tracking table usage above which new connections are at risk of being dropped
+const CONNTRACK_USAGE_WARN: f64 = 0.8;
+
+/// Network stack settings below the recommendations for a gossip heavy node, as human readable findings.
+/// IPv6 is checked separately
+pub fn network_tuning_findings() -> Vec<String> {
+    let mut findings: Vec<_> = pending_sysctls()
+        .into_iter()
+        .filter(|(key, _, _)| !key.starts_with("net.ipv6."))
+        .map(|(key, current, recommended)| format!("{key} = {current}, recommended {recommended}"))
+        .collect();
+
+    if let (Ok(count), Ok(max)) = (
+        read_sysctl("net.netfilter.nf_conntrack_count"),
+        read_sysctl("net.netfilter.nf_conntrack_max"),
+    ) && let (Ok(count), Ok(max)) = (count.parse::<u64>(), max.parse::<u64>())
+        && max > 0
+        && count as f64 / max as f64 > CONNTRACK_USAGE_WARN
+    {
+        findings.push(format!("conntrack table is {count}/{max} full"));
+    }
+
+    findings
+}
+
+fn sysctl_path(key: &str) -> PathBuf {
+    PathBuf::from("/proc/sys").join(key.replace('.', "/"))
+}
 
 pub fn read_sysctl(key: &str) -> eyre::Result<String> {
     let key_normalized = key.replace('/', ".");
-    let path = PathBuf::from("/proc/sys").join(key.replace('.', "/"));
+    let path = sysctl_path(key);
     let value = fs::read_to_string(path)
         .wrap_err_with(|| format!("failed to read sysctl {key_normalized}"))?;
 
     Ok(value.trim().to_string())
 }
+
+pub fn write_sysctl(key: &str, value: &str) -> eyre::Result<()> {
+    fs::write(sysctl_path(key), value).wrap_err_with(|| format!("failed to write sysctl {key}"))
+}
+
+/// Sets disable_ipv6 for all, default and every existing interface, then verifies that all of them took effect.
+/// Interfaces are handled through their paths, as names may contain dots (VLANs)
+pub fn disable_ipv6() -> eyre::Result<()> {
+    let conf = Path::new("/proc/sys/net/ipv6/conf");
+    let Ok(entries) = fs::read_dir(conf) else {
+        info!("IPv6 is not available, nothing to disable");
+        return Ok(());
+    };
+    // all first, so it applies to interfaces appearing in between as well
+    let mut paths = vec![
+        conf.join("all/disable_ipv6"),
+        conf.join("default/disable_ipv6"),
+    ];
+    paths.extend(
+        entries
+            .flatten()
+            .filter(|entry| entry.file_name() != "all" && entry.file_name() != "default")
+            .map(|entry| entry.path().join("disable_ipv6")),
+    );
+
+    for path in &paths {
+        let before = fs::read_to_string(path).unwrap_or_default();
+        if before.trim() == "1" {
+            continue;
+        }
+        fs::write(path, "1").wrap_err_with(|| {
+            format!("failed to write {path:?}, disabling IPv6 needs root or the net.ipv6 sysctls set for the container")
+        })?;
+        info!(?path, before = before.trim(), after = "1", "disabled IPv6");
+    }
+
+    let enabled: Vec<_> = paths
+        .iter()
+        .filter(|path| {
+            fs::read_to_string(path)
+                .map(|value| value.trim() != "1")
+                .unwrap_or(false)
+        })
+        .collect();
+    if !enabled.is_empty() {
+        bail!("IPv6 is still enabled after disabling it: {enabled:?}");
+    }
+
+    Ok(())
+}
+
+/// Transparent hugepage mode, the bracketed one in e.g. `always [madvise] never`. None without THP support
+pub fn transparent_hugepages() -> Option<String> {
+    let modes = fs::read_to_string(TRANSPARENT_HUGEPAGE_ENABLED).ok()?;
+    let (_, selected) = modes.split_once('[')?;
+    let (selected, _) = selected.split_once(']')?;
+    Some(selected.to_string())
+}
+
+pub fn set_transparent_hugepages(mode: &str) -> eyre::Result<()> {
+    fs::write(TRANSPARENT_HUGEPAGE_ENABLED, mode)
+        .wrap_err_with(|| format!("failed to write {TRANSPARENT_HUGEPAGE_ENABLED}"))
+}
+
+/// Recommended sysctls differing from the current values, as (key, current value, recommended value)
+pub fn pending_sysctls() -> Vec<(&'static str, String, &'static str)> {
+    RECOMMENDED_SYSCTLS
+        .iter()
+        .filter_map(|&(key, recommended, raise_only)| {
+            // Not available in this kernel or network namespace
+            let current = read_sysctl(key).ok()?;
+            let differs = if raise_only {
+                matches!(
+                    (current.parse::<u64>(), recommended.parse::<u64>()),
+                    (Ok(current), Ok(recommended)) if current < recommended
+                )
+            } else {
+                current != recommended
+            };
+            differs.then_some((key, current, recommended))
+        })
+        .collect()
+}
+
+/// Writes recommended sysctls. Needs root (or CAP_NET_ADMIN & writable /proc/sys), failures only warn
+pub fn apply_recommended_sysctls() {
+    for (key, before, value) in pending_sysctls() {
+        if let Err(err) = write_sysctl(key, value) {
+            warn!(
+                ?err,
+                key,
+                before,
+                value,
+                "failed to apply sysctl, set it on the host or with `--sysctl` for containers"
+            );
+            continue;
+        }
+        let after = read_sysctl(key).unwrap_or_default();
+        info!(key, before, after, "applied sysctl");
+    }
+}
@@ -0,0 +1,44 @@
+use std::{env, time::Duration};
+
+use sd_notify::NotifyState;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{debug, info, warn};
+
+use crate::status;
+
+/// Environment variables used by systemd to talk to the service, which must not leak to hl-visor
+pub const NOTIFY_ENV_VARS: [&str; 3] = ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"];
+
+/// Whether hl-bootstrap was started by systemd as Type=notify service
+pub fn notify_enabled() -> bool {
+    env::var_os("NOTIFY_SOCKET").is_some()
+}
+
+pub fn notify(state: &[NotifyState]) {
+    if let Err(err) = sd_notify::notify(state) {
+        warn!(?err, "failed to notify systemd");
+    }
+}
+
+/// Pings systemd watchdog for as long as the health checks pass
+pub async fn watchdog_task() {
+    let Some(watchdog_timeout) = sd_notify::watchdog_enabled() else {
+        return;
+    };
+
+    // Ping twice per timeout as recommended by sd_watchdog_enabled(3)
+    let mut interval = interval((watchdog_timeout / 2).max(Duration::from_millis(100)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    info!(?watchdog_timeout, "pinging systemd watchdog");
+    loop {
+        interval.tick().await;
+
+        let status = status::snapshot();
+        if status.is_healthy() {
+            notify(&[NotifyState::Watchdog]);
+        } else {
+            debug!(?status, "unhealthy, skipping systemd watchdog ping");
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use eyre::Context;
+use opentelemetry::trace::TracerProvider;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use tracing::{Subscriber, warn};
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+/// Exports tracing spans to an OTLP/HTTP collector. Standard OTEL_EXPORTER_OTLP_* variables are respected as well.
+/// Spans are flushed when this gets dropped.
+pub struct OtlpExporter {
+    provider: SdkTracerProvider,
+}
+
+impl OtlpExporter {
+    /// Must be called outside of a tokio runtime, as spans are exported from a dedicated thread with a blocking client
+    pub fn new(endpoint: &str) -> eyre::Result<Self> {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()
+            .wrap_err("failed to set up OTLP span exporter")?;
+
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(env!("CARGO_PKG_NAME"))
+                    .build(),
+            )
+            .build();
+
+        Ok(Self { provider })
+    }
+
+    pub fn layer<S>(&self) -> impl Layer<S> + use<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.provider.tracer(env!("CARGO_PKG_NAME")))
+    }
+
+    pub fn flush(&self) {
+        if let Err(err) = self.provider.force_flush() {
+            warn!(?err, "failed to flush OTLP spans");
+        }
+    }
+}
+
+impl Drop for OtlpExporter {
+    fn drop(&mut self) {
+        if let Err(err) = self.provider.shutdown() {
+            warn!(?err, "failed to flush OTLP spans");
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::serve::Listener;
+use eyre::{Context, ContextCompat};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::{Semaphore, mpsc},
+    time::timeout,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{RootCertStore, ServerConfig, crypto::ring, server::WebPkiClientVerifier},
+    server::TlsStream,
+};
+use tracing::debug;
+
+/// Connections that haven't completed the handshake by then are dropped
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most handshakes in progress at once, further clients wait in the listen backlog
+const MAX_HANDSHAKES: usize = 64;
+
+/// Server config for the PEM certificate chain & key. With a client CA, only clients presenting a certificate issued
+/// by it can connect
+pub fn server_config(
+    cert: &Path,
+    key: &Path,
+    client_ca: Option<&Path>,
+) -> eyre::Result<Arc<ServerConfig>> {
+    let provider = Arc::new(ring::default_provider());
+    let certs = rustls_pemfile::certs(&mut BufReader::new(open(cert)?))
+        .collect::<Result<Vec<_>, _>>()
+        .wrap_err_with(|| format!("failed to parse certificates in {cert:?}"))?;
+    let private_key = rustls_pemfile::private_key(&mut BufReader::new(open(key)?))
+        .wrap_err_with(|| format!("failed to parse private key in {key:?}"))?
+        .wrap_err_with(|| format!("no private key in {key:?}"))?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match client_ca {
+        Some(client_ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut BufReader::new(open(client_ca)?)) {
+                roots
+                    .add(cert.wrap_err_with(|| format!("failed to parse {client_ca:?}"))?)
+                    .wrap_err_with(|| format!("invalid CA certificate in {client_ca:?}"))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(roots.into(), provider)
+                .build()
+                .wrap_err("failed to set up client certificate verification")?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, private_key)
+        .wrap_err("invalid certificate or private key")?;
+    // Only HTTP/1 is served
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+fn open(path: &Path) -> eyre::Result<File> {
+    File::open(path).wrap_err_with(|| format!("failed to open {path:?}"))
+}
+
+/// TCP listener handing out connections once their TLS handshake is done. Handshakes run in their own tasks, so
+/// clients stalling in them don't hold up accepting others
+pub struct TlsListener {
+    streams: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        // Finished handshakes wait for the server to take them while holding their permit, so the server's
+        // connection limit bounds the handshakes as well
+        let (sender, streams) = mpsc::channel(1);
+        tokio::spawn(accept_task(listener, TlsAcceptor::from(config), sender));
+        Ok(Self {
+            streams,
+            local_addr,
+        })
+    }
+}
+
+async fn accept_task(
+    mut listener: TcpListener,
+    acceptor: TlsAcceptor,
+    sender: mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+) {
+    let handshakes = Arc::new(Semaphore::new(MAX_HANDSHAKES));
+    while !sender.is_closed() {
+        let permit = handshakes
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let (stream, peer) = Listener::accept(&mut listener).await;
+
+        let acceptor = acceptor.clone();
+        let sender = sender.clone();
+        tokio::spawn(async move {
+            match timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    let _ = sender.send((stream, peer)).await;
+                }
+                // Includes clients without an accepted certificate
+                Ok(Err(err)) => debug!(%peer, ?err, "TLS handshake failed"),
+                Err(_) => debug!(%peer, "TLS handshake timed out"),
+            }
+            drop(permit);
+        });
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        self.streams
+            .recv()
+            .await
+            .expect("accept task runs as long as the listener")
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::{
+        TlsConnector,
+        rustls::{ClientConfig, pki_types::ServerName},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stalled_handshake() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let directory = tempfile::tempdir().unwrap();
+        let cert_path = directory.path().join("cert.pem");
+        let key_path = directory.path().join("key.pem");
+        std::fs::write(&cert_path, cert.cert.pem()).unwrap();
+        std::fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+
+        let config = server_config(&cert_path, &key_path, None).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut listener = TlsListener::new(listener, config).unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // Connects without ever starting the handshake
+        let _stalled = TcpStream::connect(address).await.unwrap();
+
+        let mut roots = RootCertStore::empty();
+        roots.add(cert.cert.der().clone()).unwrap();
+        let client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let client = tokio::spawn(async move {
+            let stream = TcpStream::connect(address).await.unwrap();
+            let mut stream = TlsConnector::from(Arc::new(client_config))
+                .connect(ServerName::try_from("localhost").unwrap(), stream)
+                .await
+                .unwrap();
+            stream.write_all(b"ping").await.unwrap();
+        });
+
+        let (mut stream, _) = timeout(Duration::from_secs(2), listener.accept())
+            .await
+            .expect("stalled client held up the handshake of another");
+        let mut buffer = [0; 4];
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"ping");
+        client.await.unwrap();
+    }
+}
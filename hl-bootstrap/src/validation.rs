@@ -0,0 +1,65 @@
+use std::fmt::{self, Display};
+
+use eyre::eyre;
+
+/// Collects configuration problems, so all of them are reported at once instead of one per attempt
+#[derive(Debug, Default)]
+pub struct Problems(Vec<Problem>);
+
+#[derive(Debug)]
+struct Problem {
+    message: String,
+    fix: String,
+}
+
+impl Problems {
+    /// Records a problem unless `ok` holds
+    pub fn check(&mut self, ok: bool, message: impl Into<String>, fix: impl Into<String>) {
+        if !ok {
+            self.0.push(Problem {
+                message: message.into(),
+                fix: fix.into(),
+            });
+        }
+    }
+
+    pub fn into_result(self) -> eyre::Result<()> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(eyre!("{self}"))
+        }
+    }
+}
+
+impl Display for Problems {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration, {} problem(s):", self.0.len())?;
+        for problem in &self.0 {
+            write!(f, "\n  - {}\n    fix: {}", problem.message, problem.fix)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_problems() {
+        let mut problems = Problems::default();
+        problems.check(true, "fine", "nothing to do");
+        assert!(problems.into_result().is_ok());
+
+        let mut problems = Problems::default();
+        problems.check(true, "fine", "nothing to do");
+        problems.check(false, "--a is 0", "set --a to 1");
+        problems.check(false, "--b is 0", "set --b to 1");
+        let err = problems.into_result().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid configuration, 2 problem(s):\n  - --a is 0\n    fix: set --a to 1\n  - --b is 0\n    fix: set --b to 1"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    fs::{self, Permissions},
+    io::ErrorKind,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use eyre::{Context, ContextCompat, bail};
+use serde_json::{Map, Value};
+use tempfile::NamedTempFile;
+use tracing::{debug, info, warn};
+
+use crate::supervisor::privileges::RunAs;
+
+/// node_config.json holds the signer key, so only its owner may read it
+const MODE: u32 = 0o600;
+
+#[derive(Clone, Debug, Args)]
+pub struct ValidatorConfigArgs {
+    /// Signer private key of the validator, hex with optional 0x prefix, written into
+    /// hl/hyperliquid_data/node_config.json. Pass it as a secret reference or through
+    /// HL_BOOTSTRAP_VALIDATOR_SIGNER_KEY_FILE rather than as plain text
+    #[arg(long, env = "HL_BOOTSTRAP_VALIDATOR_SIGNER_KEY")]
+    pub validator_signer_key: Option<String>,
+
+    /// Further top level node_config.json fields as KEY=VALUE, e.g. ports. Values are parsed as JSON, falling back to
+    /// strings. Multiple entries in env var are separated by `;`
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_VALIDATOR_NODE_CONFIG",
+        value_delimiter = ';',
+        value_parser = parse_field
+    )]
+    pub validator_node_config: Vec<(String, Value)>,
+}
+
+impl ValidatorConfigArgs {
+    pub fn enabled(&self) -> bool {
+        self.validator_signer_key.is_some() || !self.validator_node_config.is_empty()
+    }
+}
+
+fn parse_field(value: &str) -> eyre::Result<(String, Value)> {
+    let (key, value) = value
+        .split_once('=')
+        .wrap_err("expected node config field in KEY=VALUE format")?;
+    if key.is_empty() {
+        bail!("node config field name can't be empty");
+    }
+    if key == "key" {
+        bail!("set the signer key with --validator-signer-key");
+    }
+
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((key.to_string(), value))
+}
+
+fn check_signer_key(key: &str) -> eyre::Result<()> {
+    let hex = key.strip_prefix("0x").unwrap_or(key);
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        bail!("signer key must be 32 bytes of hex, optionally prefixed with 0x");
+    }
+    Ok(())
+}
+
+pub fn node_config_path(data_directory: &Path) -> PathBuf {
+    data_directory.join("hl/hyperliquid_data/node_config.json")
+}
+
+/// Sets the configured fields in node_config.json, keeping the other fields of an existing file. The file is replaced
+/// atomically and only readable by its owner, the child user when running unprivileged
+pub fn write_node_config(
+    args: &ValidatorConfigArgs,
+    data_directory: &Path,
+    owner: Option<&RunAs>,
+    dry_run: bool,
+) -> eyre::Result<()> {
+    let path = node_config_path(data_directory);
+    let current: Option<Map<String, Value>> = match fs::read(&path) {
+        Ok(contents) => Some(
+            serde_json::from_slice(&contents)
+                .wrap_err_with(|| format!("failed to parse {path:?}"))?,
+        ),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err).wrap_err_with(|| format!("failed to read {path:?}")),
+    };
+
+    let mut config = current.clone().unwrap_or_default();
+    for (key, value) in &args.validator_node_config {
+        config.insert(key.clone(), value.clone());
+    }
+    if let Some(key) = &args.validator_signer_key {
+        let key = key.trim();
+        check_signer_key(key)?;
+        config.insert("key".to_string(), Value::String(key.to_string()));
+    }
+
+    if current.as_ref() == Some(&config) {
+        debug!(?path, "node_config.json is up to date");
+        let mode = fs::metadata(&path)?.permissions().mode() & 0o777;
+        if mode != MODE && !dry_run {
+            warn!(
+                ?path,
+                mode = format!("{mode:o}"),
+                "restricting node_config.json permissions"
+            );
+            fs::set_permissions(&path, Permissions::from_mode(MODE))?;
+        }
+        if let Some(owner) = owner
+            && !dry_run
+        {
+            owner.chown(&path)?;
+        }
+        return Ok(());
+    }
+    if dry_run {
+        // Not printing the contents, they include the signer key
+        let fields: Vec<_> = config.keys().collect();
+        println!("would write {} with fields {fields:?}", path.display());
+        return Ok(());
+    }
+
+    let directory = path.parent().expect("node_config.json has a parent");
+    create_directories(data_directory, directory, owner)?;
+    let mut file = NamedTempFile::new_in(directory)?;
+    file.as_file()
+        .set_permissions(Permissions::from_mode(MODE))?;
+    serde_json::to_writer_pretty(&mut file, &config)
+        .wrap_err("failed to write node_config.json")?;
+    file.as_file().sync_all()?;
+    if let Some(owner) = owner {
+        owner.chown(file.path())?;
+    }
+    file.persist(&path)
+        .wrap_err_with(|| format!("failed to replace {path:?}"))?;
+
+    info!(?path, "wrote node_config.json");
+    Ok(())
+}
+
+/// Creates the directories from the data directory down to `directory`, owned by the child user so they pass
+/// [RunAs::check_ownership]
+fn create_directories(
+    data_directory: &Path,
+    directory: &Path,
+    owner: Option<&RunAs>,
+) -> eyre::Result<()> {
+    let missing: Vec<_> = directory
+        .ancestors()
+        .take_while(|path| path.starts_with(data_directory) && !path.exists())
+        .collect();
+    fs::create_dir_all(directory).wrap_err_with(|| format!("failed to create {directory:?}"))?;
+    if let Some(owner) = owner {
+        for path in missing {
+            owner.chown(path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_write_node_config() {
+        let home = tempfile::tempdir().unwrap();
+        let path = node_config_path(home.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, r#"{"key": "0xold", "extra": true}"#).unwrap();
+        fs::set_permissions(&path, Permissions::from_mode(0o644)).unwrap();
+
+        let args = ValidatorConfigArgs {
+            validator_signer_key: Some(format!("0x{}", "ab".repeat(32))),
+            validator_node_config: vec![parse_field("port=4001").unwrap()],
+        };
+        write_node_config(&args, home.path(), None, false).unwrap();
+
+        let config: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(
+            config,
+            json!({ "key": format!("0x{}", "ab".repeat(32)), "extra": true, "port": 4001 })
+        );
+        assert_eq!(
+            fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+            MODE
+        );
+
+        let args = ValidatorConfigArgs {
+            validator_signer_key: Some("0x1234".to_string()),
+            validator_node_config: Vec::new(),
+        };
+        assert!(write_node_config(&args, home.path(), None, false).is_err());
+        assert!(parse_field("key=0xabc").is_err());
+        assert_eq!(
+            parse_field("name=validator-1").unwrap().1,
+            Value::String("validator-1".to_string())
+        );
+    }
+}
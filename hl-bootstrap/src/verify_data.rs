@@ -0,0 +1,370 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytesize::ByteSize;
+use chrono::{DateTime, NaiveDate};
+use clap::Args;
+use serde::de::IgnoredAny;
+use tracing::{debug, info};
+
+use crate::compress::{self, COMPRESSED_SUFFIX, uncompressed_name};
+
+/// Files modified this recently may still be written by hl-node, so a partial last line is expected
+const ACTIVE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+/// How far back to look for the start of the last line, replica_cmds blocks can be several MiB
+const MAX_LINE_LENGTH: u64 = 64 * 1024 * 1024;
+
+const HOUR_SECS: i64 = 60 * 60;
+
+#[derive(Clone, Debug, Args)]
+pub struct VerifyDataArgs {
+    /// Parse every JSON line instead of only the first & last line of each file. Reads all data, which takes a
+    /// while on archive nodes
+    #[arg(long)]
+    pub full: bool,
+
+    /// Subdirectories of hl/data to scan, all by default
+    #[arg(long, value_delimiter = ',')]
+    pub directories: Vec<PathBuf>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Issue {
+    Unreadable(String),
+    Empty,
+    /// Last line has no newline, the write was cut short
+    Truncated,
+    InvalidJson {
+        lines: usize,
+        first_line: usize,
+    },
+    /// Only the first & last lines are parsed without --full, the last line's number is unknown then
+    InvalidLastLine,
+    /// Hourly files missing between the first & last one, as (first missing, last missing) unix timestamps
+    MissingHours {
+        from: i64,
+        to: i64,
+    },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(err) => write!(f, "unreadable: {err}"),
+            Self::Empty => write!(f, "empty file"),
+            Self::Truncated => write!(f, "last line is truncated"),
+            Self::InvalidJson { lines, first_line } => {
+                write!(
+                    f,
+                    "{lines} unparseable JSON lines, first on line {first_line}"
+                )
+            }
+            Self::InvalidLastLine => write!(f, "last line is unparseable JSON"),
+            Self::MissingHours { from, to } => {
+                let hour = |timestamp: i64| {
+                    DateTime::from_timestamp(timestamp, 0)
+                        .map(|time| time.format("%Y%m%d/%-H").to_string())
+                        .unwrap_or_default()
+                };
+                if from == to {
+                    write!(f, "missing hour {}", hour(*from))
+                } else {
+                    write!(f, "missing hours {} to {}", hour(*from), hour(*to))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub files: u64,
+    pub bytes: u64,
+    /// Paths relative to hl/data
+    pub issues: Vec<(PathBuf, Issue)>,
+}
+
+impl Report {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "scanned {} files ({})",
+            self.files,
+            ByteSize::b(self.bytes)
+        );
+        for (path, issue) in &self.issues {
+            let _ = writeln!(out, "[FAIL] {}: {issue}", path.display());
+        }
+        if self.issues.is_empty() {
+            let _ = writeln!(out, "no issues found");
+        }
+        out
+    }
+}
+
+/// Scans hl/data for files corrupted by disk incidents or unclean shutdowns
+pub fn verify_data(data_directory: &Path, args: &VerifyDataArgs) -> Report {
+    let data = data_directory.join("hl/data");
+    let roots = match args.directories.as_slice() {
+        [] => vec![data.clone()],
+        directories => directories.iter().map(|path| data.join(path)).collect(),
+    };
+    info!(?roots, full = args.full, "verifying data");
+
+    let mut report = Report::default();
+    // Hours present per hourly/ directory
+    let mut hourly: BTreeMap<PathBuf, Vec<i64>> = BTreeMap::new();
+    let mut pending = roots;
+    while let Some(directory) = pending.pop() {
+        let relative = |path: &Path| path.strip_prefix(&data).unwrap_or(path).to_path_buf();
+        let entries = match fs::read_dir(&directory) {
+            Ok(entries) => entries,
+            Err(err) => {
+                report
+                    .issues
+                    .push((relative(&directory), Issue::Unreadable(err.to_string())));
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+
+            report.files += 1;
+            report.bytes += metadata.len();
+            if report.files % 10_000 == 0 {
+                info!(files = report.files, "verifying data");
+            }
+
+            if let Some((hourly_directory, hour)) = hourly_file(&path) {
+                hourly
+                    .entry(relative(hourly_directory))
+                    .or_default()
+                    .push(hour);
+            }
+
+            let active = metadata
+                .modified()
+                .ok()
+                .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                .is_none_or(|age| age < ACTIVE_WINDOW);
+            debug!(?path, active, "verifying file");
+            let issues = match check_file(&path, metadata.len(), active, args.full) {
+                Ok(issues) => issues,
+                Err(err) => vec![Issue::Unreadable(err.to_string())],
+            };
+            report
+                .issues
+                .extend(issues.into_iter().map(|issue| (relative(&path), issue)));
+        }
+    }
+
+    for (directory, hours) in hourly {
+        for (from, to) in hour_gaps(hours) {
+            report
+                .issues
+                .push((directory.clone(), Issue::MissingHours { from, to }));
+        }
+    }
+    report.issues.sort_by(|a, b| a.0.cmp(&b.0));
+    report
+}
+
+fn check_file(path: &Path, length: u64, active: bool, full: bool) -> io::Result<Vec<Issue>> {
+    if length == 0 {
+        return Ok(if active { vec![] } else { vec![Issue::Empty] });
+    }
+    if path.to_string_lossy().ends_with(COMPRESSED_SUFFIX) {
+        return check_compressed_file(path);
+    }
+
+    let mut file = File::open(path)?;
+    let mut first_line = Vec::new();
+    BufReader::new(&mut file)
+        .take(MAX_LINE_LENGTH)
+        .read_until(b'\n', &mut first_line)?;
+    // Only hl-node's JSON lines outputs are parsed, periodic ABCI states & co are binary
+    if !matches!(first_line.first(), Some(b'{' | b'[')) {
+        return Ok(vec![]);
+    }
+
+    let mut issues = Vec::new();
+    // A partial last line is expected while hl-node is still writing. It's never parsed, so it's only reported once
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    if last[0] != b'\n' && !active {
+        issues.push(Issue::Truncated);
+    }
+
+    if full {
+        file.rewind()?;
+        issues.extend(invalid_lines(BufReader::new(&mut file))?.0);
+    } else {
+        if first_line.last() == Some(&b'\n') && !is_valid_json(&first_line) {
+            issues.push(Issue::InvalidJson {
+                lines: 1,
+                first_line: 1,
+            });
+        }
+        if let Some(line) = last_line(&mut file, length)?
+            && !is_valid_json(&line)
+        {
+            issues.push(Issue::InvalidLastLine);
+        }
+    }
+    Ok(issues)
+}
+
+/// Compressed files can only be checked by decompressing them whole, which also catches damaged zstd frames. They
+/// were complete when compressed, so nothing is still being written
+fn check_compressed_file(path: &Path) -> io::Result<Vec<Issue>> {
+    let mut reader = compress::open(path)?;
+    if !matches!(reader.fill_buf()?.first(), Some(b'{' | b'[')) {
+        io::copy(&mut reader, &mut io::sink())?;
+        return Ok(vec![]);
+    }
+
+    let (invalid, complete) = invalid_lines(reader)?;
+    let mut issues: Vec<_> = invalid.into_iter().collect();
+    if !complete {
+        issues.push(Issue::Truncated);
+    }
+    Ok(issues)
+}
+
+/// Parses every complete line, also returning whether the last line was complete too
+fn invalid_lines(mut reader: impl BufRead) -> io::Result<(Option<Issue>, bool)> {
+    let mut line = Vec::new();
+    let (mut invalid, mut first_invalid) = (0, 0);
+    let mut complete = true;
+    for number in 1.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        if line.last() != Some(&b'\n') {
+            complete = false;
+            break;
+        }
+        if !is_valid_json(&line) {
+            invalid += 1;
+            if first_invalid == 0 {
+                first_invalid = number;
+            }
+        }
+    }
+    let issue = (invalid > 0).then_some(Issue::InvalidJson {
+        lines: invalid,
+        first_line: first_invalid,
+    });
+    Ok((issue, complete))
+}
+
+/// Blank lines aren't hl-node's doing either, but they don't lose data
+fn is_valid_json(line: &[u8]) -> bool {
+    line.trim_ascii().is_empty() || serde_json::from_slice::<IgnoredAny>(line).is_ok()
+}
+
+/// Last complete line, unless it's the first one. Read backwards, so the rest of the file is skipped
+fn last_line(file: &mut File, length: u64) -> io::Result<Option<Vec<u8>>> {
+    const CHUNK: u64 = 64 * 1024;
+
+    // Newline ending the last complete line
+    let mut end = None;
+    let mut position = length;
+    while position > 0 && length - position < MAX_LINE_LENGTH {
+        let chunk_start = position.saturating_sub(CHUNK);
+        let mut chunk = vec![0; (position - chunk_start) as usize];
+        file.seek(SeekFrom::Start(chunk_start))?;
+        file.read_exact(&mut chunk)?;
+
+        for (index, _) in chunk
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, byte)| **byte == b'\n')
+        {
+            let newline = chunk_start + index as u64;
+            let Some(end) = end else {
+                end = Some(newline);
+                continue;
+            };
+            let mut line = vec![0; (end - newline) as usize];
+            file.seek(SeekFrom::Start(newline + 1))?;
+            file.read_exact(&mut line)?;
+            return Ok(Some(line));
+        }
+        position = chunk_start;
+    }
+    Ok(None)
+}
+
+/// `<directory>/hourly/<YYYYMMDD>/<hour>` files, as the hourly directory & the hour's unix timestamp
+fn hourly_file(path: &Path) -> Option<(&Path, i64)> {
+    let day_directory = path.parent()?;
+    let hourly_directory = day_directory.parent()?;
+    if hourly_directory.file_name()? != "hourly" {
+        return None;
+    }
+    let hour: i64 = uncompressed_name(path.file_name()?.to_str()?)
+        .parse()
+        .ok()?;
+    let date = NaiveDate::parse_from_str(day_directory.file_name()?.to_str()?, "%Y%m%d").ok()?;
+    if !(0..24).contains(&hour) {
+        return None;
+    }
+    let day = date.and_time(Default::default()).and_utc().timestamp();
+    Some((hourly_directory, day + hour * HOUR_SECS))
+}
+
+/// Missing hours between the first & last present one, as (first missing, last missing)
+fn hour_gaps(mut hours: Vec<i64>) -> Vec<(i64, i64)> {
+    hours.sort_unstable();
+    hours.dedup();
+    hours
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > HOUR_SECS)
+        .map(|pair| (pair[0] + HOUR_SECS, pair[1] - HOUR_SECS))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hour_gaps() {
+        let hour = |h: i64| 1750000000 / HOUR_SECS * HOUR_SECS + h * HOUR_SECS;
+        assert_eq!(
+            hour_gaps(vec![hour(5), hour(0), hour(1), hour(6)]),
+            [(hour(2), hour(4))]
+        );
+        assert!(hour_gaps(vec![hour(1), hour(2)]).is_empty());
+    }
+
+    #[test]
+    fn test_hourly_file() {
+        let (directory, hour) =
+            hourly_file(Path::new("/hl/data/node_fills/hourly/20250615/15")).unwrap();
+        assert_eq!(directory, Path::new("/hl/data/node_fills/hourly"));
+        assert_eq!(hour, 1749999600);
+        assert!(hourly_file(Path::new("/hl/data/node_fills/hourly/20250615/15.zst")).is_some());
+        assert!(hourly_file(Path::new("/hl/data/replica_cmds/x/20250615/15")).is_none());
+    }
+}
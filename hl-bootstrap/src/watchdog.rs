@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use bytesize::ByteSize;
+use clap::{Args, ValueEnum};
+use duration_string::DurationString;
+use tokio::time::{Instant, MissedTickBehavior, interval};
+use tracing::{debug, info, warn};
+
+use crate::{
+    alerts::{AlertEvent, alert},
+    monitor::{GAUGE_HL_CHILD_RSS_BYTES, request_exchange_time},
+    procfs, status,
+    supervisor::request_restart,
+};
+
+#[derive(Clone, Debug, Args)]
+pub struct WatchdogArgs {
+    /// Consider the node stalled when HyperCore exchange time does not advance for this long. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_STALL_WATCHDOG_TIMEOUT")]
+    pub stall_watchdog_timeout: Option<DurationString>,
+
+    /// What to do when the node is stalled
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_STALL_WATCHDOG_ACTION",
+        default_value = "restart"
+    )]
+    pub stall_watchdog_action: WatchdogAction,
+
+    /// How often to probe the local hl-node info endpoint for liveness. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_LIVENESS_PROBE_INTERVAL")]
+    pub liveness_probe_interval: Option<DurationString>,
+
+    /// Consecutive failed liveness probes after which the node is considered unhealthy
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_LIVENESS_PROBE_FAILURE_THRESHOLD",
+        default_value_t = 3,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub liveness_probe_failure_threshold: u32,
+
+    /// Resident memory limit for the child process tree, e.g. `60GiB`. Disabled when unset
+    #[arg(long, env = "HL_BOOTSTRAP_MEMORY_WATCHDOG_LIMIT")]
+    pub memory_watchdog_limit: Option<ByteSize>,
+
+    /// How often to check the child process tree memory usage
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_MEMORY_WATCHDOG_INTERVAL",
+        default_value = "10s"
+    )]
+    pub memory_watchdog_interval: DurationString,
+
+    /// What to do when the child exceeds the memory limit
+    #[arg(
+        long,
+        env = "HL_BOOTSTRAP_MEMORY_WATCHDOG_ACTION",
+        default_value = "restart"
+    )]
+    pub memory_watchdog_action: WatchdogAction,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum WatchdogAction {
+    /// Gracefully restart hl-visor
+    Restart,
+    /// Only log a warning
+    Warn,
+}
+
+/// Watches exchange time reported by the local info endpoint, which only advances when the node applies blocks.
+/// The watchdog arms itself only after the node has responded once since the child was (re)started, as initial
+/// sync can take a long time.
+pub async fn stall_watchdog_task(stall_timeout: Duration, action: WatchdogAction) {
+    let mut interval = interval((stall_timeout / 10).max(Duration::from_secs(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut child_pid = None;
+    let mut last_exchange_time = None;
+    let mut last_progress = Instant::now();
+
+    info!(?stall_timeout, ?action, "starting stall watchdog");
+    loop {
+        interval.tick().await;
+
+        let current_pid = status::snapshot().child.pid;
+        if current_pid != child_pid {
+            debug!(?current_pid, "child changed, disarming stall watchdog");
+            child_pid = current_pid;
+            last_exchange_time = None;
+            last_progress = Instant::now();
+        }
+
+        if let Ok(time) = request_exchange_time().await
+            && last_exchange_time.is_none_or(|last| time > last)
+        {
+            if last_exchange_time.is_none() {
+                debug!(exchange_time = time, "stall watchdog armed");
+            }
+            last_exchange_time = Some(time);
+            last_progress = Instant::now();
+            continue;
+        }
+
+        // Not armed yet
+        let Some(exchange_time) = last_exchange_time else {
+            continue;
+        };
+
+        let stalled_for = last_progress.elapsed();
+        if stalled_for < stall_timeout {
+            continue;
+        }
+
+        warn!(?stalled_for, exchange_time, "node appears stalled");
+        if let WatchdogAction::Warn = action {
+            alert(
+                AlertEvent::WatchdogTriggered,
+                format!("node appears stalled for {stalled_for:?}"),
+            );
+        }
+        match action {
+            WatchdogAction::Restart => {
+                if request_restart(AlertEvent::WatchdogTriggered, "node stalled") {
+                    last_exchange_time = None;
+                }
+            }
+            WatchdogAction::Warn => {}
+        }
+        last_progress = Instant::now();
+    }
+}
+
+/// Probes the local info endpoint, as hl-visor can keep running while hl-node is wedged. Like the stall watchdog,
+/// the probe only starts counting failures after the node has responded once since the child was (re)started.
+pub async fn liveness_probe_task(probe_interval: Duration, failure_threshold: u32) {
+    let mut interval = interval(probe_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut child_pid = None;
+    let mut failures = 0;
+
+    info!(
+        ?probe_interval,
+        failure_threshold, "starting liveness probe"
+    );
+    loop {
+        interval.tick().await;
+
+        let snapshot = status::snapshot();
+        if snapshot.child.pid != child_pid {
+            child_pid = snapshot.child.pid;
+            failures = 0;
+        }
+
+        let responding = match request_exchange_time().await {
+            Ok(_) => {
+                if snapshot.child.node_responding != Some(true) {
+                    info!(pid = ?child_pid, "hl-node info endpoint is responding");
+                }
+                failures = 0;
+                Some(true)
+            }
+            // Not armed yet
+            Err(_) if snapshot.child.node_responding.is_none() => None,
+            Err(err) => {
+                failures += 1;
+                debug!(%err, failures, "liveness probe failed");
+                if failures < failure_threshold {
+                    continue;
+                }
+                if snapshot.child.node_responding == Some(true) {
+                    warn!(
+                        failures,
+                        "hl-node info endpoint is not responding, marking unhealthy"
+                    );
+                }
+                Some(false)
+            }
+        };
+
+        status::update(|status| {
+            // Child could have been restarted while the probe was in flight
+            if status.child.pid == child_pid {
+                status.child.node_responding = responding;
+            }
+        });
+    }
+}
+
+/// Sums up resident memory of the child and all of its descendants (hl-visor spawns hl-node), restarting the child
+/// before the kernel OOM killer picks a victim, possibly a co-located service.
+pub async fn memory_watchdog_task(
+    limit: ByteSize,
+    check_interval: Duration,
+    action: WatchdogAction,
+) {
+    let mut interval = interval(check_interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    // Child which already exceeded the limit, to act only once per child
+    let mut over_limit_pid = None;
+
+    info!(%limit, ?check_interval, ?action, "starting memory watchdog");
+    loop {
+        interval.tick().await;
+
+        let Some(pid) = status::snapshot().child.pid else {
+            continue;
+        };
+
+        let rss = ByteSize::b(
+            procfs::process_tree(pid)
+                .into_iter()
+                .filter_map(procfs::rss_bytes)
+                .sum(),
+        );
+        GAUGE_HL_CHILD_RSS_BYTES.set(rss.as_u64() as i64);
+        debug!(pid, %rss, "child memory usage");
+
+        if rss <= limit {
+            over_limit_pid = None;
+            continue;
+        }
+        if over_limit_pid == Some(pid) {
+            continue;
+        }
+
+        warn!(pid, %rss, %limit, "child exceeded memory limit");
+        let handled = match action {
+            WatchdogAction::Restart => {
+                request_restart(AlertEvent::WatchdogTriggered, "memory limit exceeded")
+            }
+            WatchdogAction::Warn => {
+                alert(
+                    AlertEvent::WatchdogTriggered,
+                    format!("child exceeded memory limit: {rss} > {limit}"),
+                );
+                true
+            }
+        };
+        if handled {
+            over_limit_pid = Some(pid);
+        }
+    }
+}